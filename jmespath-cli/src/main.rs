@@ -6,7 +6,7 @@ use std::rc::Rc;
 
 use clap::{App, Arg};
 use jmespath::Rcvar;
-use jmespath::{compile, Variable};
+use jmespath::{Runtime, Variable};
 
 macro_rules! die(
     ($msg:expr) => (
@@ -17,6 +17,17 @@ macro_rules! die(
     )
 );
 
+mod repl;
+
+/// Creates the runtime used to compile expressions: the builtin functions
+/// and `env()`, as expressions given to `jp` are trusted.
+fn runtime() -> Runtime {
+    let mut runtime = Runtime::new();
+    runtime.register_builtin_functions();
+    runtime.register_env_function();
+    runtime
+}
+
 fn main() {
     let matches = App::new("jp")
         .version(env!("CARGO_PKG_VERSION"))
@@ -35,6 +46,23 @@ fn main() {
                 .long("unquoted")
                 .multiple(false),
         )
+        .arg(
+            Arg::with_name("yaml")
+                .help("Print the result as YAML instead of JSON.")
+                .long("yaml")
+                .multiple(false),
+        )
+        .arg(
+            Arg::with_name("repl")
+                .help(
+                    "Load the input JSON once and evaluate expressions typed at a prompt. \
+                  Requires --filename.",
+                )
+                .long("repl")
+                .requires("filename")
+                .conflicts_with_all(&["expression", "expr-file", "ast"])
+                .multiple(false),
+        )
         .arg(
             Arg::with_name("ast")
                 .help(
@@ -51,49 +79,79 @@ fn main() {
                 .takes_value(true)
                 .long("expr-file")
                 .conflicts_with("expression")
-                .required(true),
+                .required_unless("repl"),
         )
         .arg(
             Arg::with_name("expression")
-                .help("JMESPath expression to evaluate")
+                .help("JMESPath expressions to evaluate, each printing its own result")
                 .index(1)
+                .multiple(true)
                 .conflicts_with("expr-file")
-                .required(true),
+                .required_unless("repl"),
         )
         .get_matches();
 
+    let runtime = runtime();
+
+    if matches.is_present("repl") {
+        let json = Rc::new(get_json(matches.value_of("filename")));
+        repl::run(
+            &runtime,
+            json,
+            matches.is_present("unquoted"),
+            matches.is_present("yaml"),
+        );
+        exit(0);
+    }
+
     let file_expression = matches
         .value_of("expr-file")
         .map(|f| read_file("expression", f));
 
-    let expr = if let Some(ref e) = file_expression {
-        compile(e)
+    let compile = |e: &str| runtime.compile(e).unwrap_or_else(|e| die!(e.to_string()));
+    let expressions: Vec<_> = if let Some(ref e) = file_expression {
+        vec![compile(e)]
     } else {
-        compile(matches.value_of("expression").unwrap())
-    }
-    .map_err(|e| die!(e.to_string()))
-    .unwrap();
+        matches
+            .values_of("expression")
+            .unwrap()
+            .map(compile)
+            .collect()
+    };
 
     if matches.is_present("ast") {
-        println!("{:#?}", expr.as_ast());
+        for expr in &expressions {
+            println!("{:#?}", expr.as_ast());
+        }
         exit(0);
     }
 
     let json = Rc::new(get_json(matches.value_of("filename")));
 
-    match expr.search(&json) {
-        Err(e) => die!(e.to_string()),
-        Ok(result) => show_result(result, matches.is_present("unquoted")),
+    for expr in &expressions {
+        match expr.search(&json) {
+            Err(e) => die!(e.to_string()),
+            Ok(result) => show_result(
+                result,
+                matches.is_present("unquoted"),
+                matches.is_present("yaml"),
+            ),
+        }
     }
 }
 
-fn show_result(result: Rcvar, unquoted: bool) {
+fn show_result(result: Rcvar, unquoted: bool, yaml: bool) {
     if unquoted && result.is_string() {
         println!("{}", result.as_string().unwrap());
+    } else if yaml {
+        match result.to_yaml_string() {
+            Ok(s) => print!("{}", s),
+            Err(e) => die!(format!("Error converting result to YAML: {}", e)),
+        }
     } else {
         let mut out = io::stdout();
         serde_json::to_writer_pretty(&mut out, &result)
-            .map(|_| out.write(&[b'\n']))
+            .map(|_| out.write(b"\n"))
             .map_err(|e| die!(format!("Error converting result to string: {}", e)))
             .ok();
     }
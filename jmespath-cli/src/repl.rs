@@ -0,0 +1,52 @@
+//! Interactive mode that searches a document loaded once with expressions
+//! typed at a prompt.
+
+use std::io::Write;
+use std::process::exit;
+
+use jmespath::{Rcvar, Runtime};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::show_result;
+
+const HELP: &str = "Type an expression to search the document, or one of:
+  :ast <expression>  print the AST of the parsed expression
+  :help              print this message
+  :quit              exit (as does Ctrl-D)";
+
+/// Reads expressions until the end of input and prints their results.
+pub fn run(runtime: &Runtime, json: Rcvar, unquoted: bool, yaml: bool) {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => die!(format!("Error starting the REPL: {}", e)),
+    };
+    loop {
+        let line = match editor.readline("jp> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => die!(format!("Error reading input: {}", e)),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line).ok();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            (":quit", _) | (":q", _) => break,
+            (":help", _) => println!("{}", HELP),
+            (":ast", expression) => match runtime.compile(expression.trim()) {
+                Ok(expr) => println!("{:#?}", expr.as_ast()),
+                Err(e) => println!("{}", e),
+            },
+            _ => match runtime.compile(line) {
+                Ok(expr) => match expr.search(&json) {
+                    Ok(result) => show_result(result, unquoted, yaml),
+                    Err(e) => println!("{}", e),
+                },
+                Err(e) => println!("{}", e),
+            },
+        }
+    }
+}
@@ -49,6 +49,17 @@ fn executes_query_against_files() {
     assert_eq!("\"bar\"\n", output);
 }
 
+#[test]
+fn reads_environment_variables() {
+    let output = get_output(vec![
+        "env('CARGO_PKG_NAME')",
+        "-f",
+        "tests/fixtures/valid-json",
+    ])
+    .unwrap();
+    assert_eq!("\"jmespath-cli\"\n", output);
+}
+
 #[test]
 fn allows_unquoted_strings() {
     let output = get_output(vec![
@@ -68,6 +79,43 @@ fn unquoted_does_nothing_for_non_strings() {
     assert_eq!("[\n  \"foo\"\n]\n", output);
 }
 
+#[test]
+fn prints_yaml() {
+    let output = get_output(vec![
+        "-f",
+        "tests/fixtures/valid-json",
+        "--yaml",
+        "`{\"a\": [1, \"b\"]}`",
+    ])
+    .unwrap();
+    assert_eq!("a:\n- 1\n- b\n", output);
+}
+
+#[test]
+fn evaluates_multiple_expressions() {
+    let output = get_output(vec![
+        "-f",
+        "tests/fixtures/valid-json",
+        "-u",
+        "foo.baz",
+        "`1`",
+    ])
+    .unwrap();
+    assert_eq!("bar\n1\n", output);
+}
+
+#[test]
+fn shows_runtime_errors_with_non_zero_rc() {
+    let output = get_output(vec![
+        "-f",
+        "tests/fixtures/valid-json",
+        "foo.baz",
+        "abs(foo)",
+    ])
+    .unwrap_err();
+    assert!(output.starts_with("Runtime error: abs() Argument 0 expects type number"));
+}
+
 #[test]
 fn validates_json_file_exists() {
     let output = get_output(vec![
@@ -137,9 +185,33 @@ fn can_read_json_from_stdin() {
         .stdin
         .as_mut()
         .unwrap()
-        .write("{\"foo\":\"bar\"}".as_bytes())
+        .write_all("{\"foo\":\"bar\"}".as_bytes())
         .ok();
     let output = child.wait_with_output().unwrap();
     let stdout = String::from_utf8(output.stdout).unwrap();
     assert_eq!("\"bar\"\n", stdout);
 }
+
+#[test]
+fn evaluates_expressions_in_the_repl() {
+    use std::io::prelude::*;
+    let mut child = Command::new(JPBIN)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .args(["--repl", "-f", "tests/fixtures/valid-json"])
+        .spawn()
+        .expect("Failed to spawn process");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(b"foo.baz\n\n:ast foo\nfoo{\n:quit\nfoo\n")
+        .ok();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "\"bar\"\nField {\n    offset: 0,\n    name: \"foo\",\n}\n\
+         Parse error: Unexpected led token -- found Lbrace (line 0, column 3)\nfoo{\n   ^\n\n",
+        String::from_utf8(output.stdout).unwrap()
+    );
+}
@@ -0,0 +1,59 @@
+//! Compile-time checked JMESPath expressions.
+//!
+//! The `jmespath!` macro parses an expression while the crate using it is
+//! being compiled, so a malformed static query is reported as a compile
+//! error instead of an `Err` at runtime. It expands to a
+//! `&'static jmespath::Expression<'static>` that is compiled the first time
+//! it is used.
+//!
+//! ```
+//! use jmespath_macros::jmespath;
+//!
+//! let data = jmespath::Variable::from_json(r#"{"foo": [{"bar": 2}]}"#).unwrap();
+//! let result = jmespath!("foo[?bar > `1`].bar").search(data).unwrap();
+//! assert_eq!("[2]", result.to_string());
+//! ```
+//!
+//! Expressions that fail to parse do not compile:
+//!
+//! ```compile_fail
+//! use jmespath_macros::jmespath;
+//!
+//! let expr = jmespath!("foo[?bar >");
+//! ```
+//!
+//! The expansion refers to the `jmespath` crate, which must also be a
+//! dependency of the crate using the macro. Expressions are checked against
+//! the grammar only: calls to functions that are not registered with the
+//! default runtime still fail when the expression is searched.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// Validates a JMESPath expression at compile time and expands to a lazily
+/// compiled `&'static jmespath::Expression<'static>`.
+///
+/// Each thread compiles the expression once, using `jmespath::compile`, the
+/// first time the expansion is evaluated on that thread.
+#[proc_macro]
+pub fn jmespath(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    if let Err(e) = jmespath::parse(&literal.value()) {
+        return syn::Error::new(literal.span(), e.to_string())
+            .to_compile_error()
+            .into();
+    }
+    quote!({
+        ::std::thread_local! {
+            static EXPRESSION: &'static ::jmespath::Expression<'static> =
+                ::std::boxed::Box::leak(::std::boxed::Box::new(
+                    ::jmespath::compile(#literal).expect("expression validated by jmespath!"),
+                ));
+        }
+        EXPRESSION.with(|expression| *expression)
+    })
+    .into()
+}
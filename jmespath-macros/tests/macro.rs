@@ -0,0 +1,26 @@
+use jmespath::Variable;
+use jmespath_macros::jmespath;
+
+fn first_name() -> &'static jmespath::Expression<'static> {
+    jmespath!("people[0].name")
+}
+
+#[test]
+fn expands_to_a_compiled_expression() {
+    let data = Variable::from_json(r#"{"people": [{"name": "a"}, {"name": "b"}]}"#).unwrap();
+    let expr = first_name();
+    assert_eq!("people[0].name", expr.as_str());
+    assert_eq!("\"a\"", expr.search(data).unwrap().to_string());
+}
+
+#[test]
+fn compiles_once_per_thread() {
+    assert!(std::ptr::eq(first_name(), first_name()));
+}
+
+#[test]
+fn accepts_raw_strings() {
+    let data = Variable::from_json(r#"{"a": "b"}"#).unwrap();
+    let result = jmespath!(r#"a == 'b'"#).search(data).unwrap();
+    assert!(result.as_boolean().unwrap());
+}
@@ -0,0 +1,87 @@
+//! Compares the tree-walking interpreter with the bytecode VM.
+//!
+//! Both are given an `Rcvar` directly so that converting the data into a
+//! `Variable` is not part of the measurements.
+
+use bencher::*;
+use jmespath::backend::{EvalBackend, TreeWalker};
+use jmespath::ir::Program;
+use jmespath::{parse, Context, Rcvar, Runtime, Variable};
+
+const EXPRESSIONS: &[&str] = &[
+    "people[*].name",
+    "people[?age > `40`].name",
+    "people[*].{n: name, t: tags[0]}",
+    "people[*].tags[]",
+    "length(people[?contains(tags, 't1')])",
+];
+
+fn people() -> Rcvar {
+    let people = (0..100)
+        .map(|i| {
+            format!(
+                r#"{{"name": "person{}", "age": {}, "tags": ["t{}", "t{}"]}}"#,
+                i,
+                20 + i % 40,
+                i % 3,
+                i % 5
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    Rcvar::new(Variable::from_json(&format!(r#"{{"people": [{}]}}"#, people)).unwrap())
+}
+
+fn runtime() -> Runtime {
+    let mut runtime = Runtime::new();
+    runtime.register_builtin_functions();
+    runtime
+}
+
+fn tree_walker(index: usize, b: &mut Bencher) {
+    let (data, runtime) = (people(), runtime());
+    let expression = EXPRESSIONS[index];
+    let ast = parse(expression).unwrap();
+    b.iter(|| {
+        TreeWalker
+            .evaluate(&ast, &data, &mut Context::new(expression, &runtime))
+            .ok()
+    });
+}
+
+fn vm(index: usize, b: &mut Bencher) {
+    let (data, runtime) = (people(), runtime());
+    let expression = EXPRESSIONS[index];
+    let program = Program::lower(&parse(expression).unwrap());
+    b.iter(|| {
+        program
+            .execute(&data, &mut Context::new(expression, &runtime))
+            .ok()
+    });
+}
+
+macro_rules! benches {
+    ($($index:expr => $walk:ident, $vm:ident;)*) => {
+        $(
+            fn $walk(b: &mut Bencher) {
+                tree_walker($index, b)
+            }
+
+            fn $vm(b: &mut Bencher) {
+                vm($index, b)
+            }
+        )*
+
+        benchmark_group!(benches, $($walk, $vm),*);
+    };
+}
+
+benches! {
+    0 => projection_tree_walker, projection_vm;
+    1 => filter_tree_walker, filter_vm;
+    2 => multi_hash_tree_walker, multi_hash_vm;
+    3 => flatten_tree_walker, flatten_vm;
+    4 => function_tree_walker, function_vm;
+}
+
+benchmark_main!(benches);
@@ -17,7 +17,7 @@ pub fn main() {
 
     let mut all_benches_test = vec![];
 
-    for (suite_num, &(ref filename, ref suite)) in suites.iter().enumerate() {
+    for (suite_num, (filename, suite)) in suites.iter().enumerate() {
         let suite_obj = suite.as_object().expect("Suite not object");
         let given = suite_obj.get("given").expect("No given value");
         let cases = suite_obj.get("cases").expect("No cases value");
@@ -14,15 +14,57 @@
 //!
 //! let ast = jmespath::parse("a || b && c").unwrap();
 //! ```
+//!
+//! Ast implements serde's `Serialize` and `Deserialize`, so an expression
+//! can be parsed ahead of time (e.g., in a build script), embedded in a
+//! binary, and turned back into an `Expression` with
+//! `Expression::from_parts` without parsing it again at startup.
+//!
+//! `AstBuilder` constructs the same trees the parser produces, without
+//! writing out nodes and offsets by hand:
+//!
+//! ```
+//! use jmespath::ast::AstBuilder;
+//! use jmespath::{Expression, Runtime, Variable};
+//!
+//! let mut runtime = Runtime::new();
+//! runtime.register_builtin_functions();
+//! // foo[0].bar[*].baz
+//! let ast = AstBuilder::field("foo")
+//!     .index(0)
+//!     .dot(AstBuilder::field("bar"))
+//!     .projection()
+//!     .dot(AstBuilder::field("baz"))
+//!     .build_checked(&runtime)
+//!     .unwrap();
+//! let expr = Expression::new("foo[0].bar[*].baz", ast, &runtime);
+//! let data = Variable::from_json(r#"{"foo": [{"bar": [{"baz": 1}, {"baz": 2}]}]}"#).unwrap();
+//! assert_eq!("[1,2]", expr.search(data).unwrap().to_string());
+//! ```
 
 use std::fmt;
+use std::ops;
+
+use serde::{Deserialize, Serialize};
 
 use crate::lexer::Token;
-use crate::Rcvar;
+use crate::lint::{self, Warning, WarningKind};
+use crate::{Rcvar, Runtime, Variable};
 
 /// A JMESPath expression abstract syntax tree.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Ast {
+    /// Applies an arithmetic operator to two numbers.
+    Arithmetic {
+        /// Approximate absolute position in the parsed expression.
+        offset: usize,
+        /// Operator applied to the two operands.
+        operator: ArithmeticOperator,
+        /// Left hand side of the operation.
+        lhs: Box<Ast>,
+        /// Right hand side of the operation.
+        rhs: Box<Ast>,
+    },
     /// Compares two nodes using a comparator, returning true/false.
     Comparison {
         /// Approximate absolute position in the parsed expression.
@@ -44,6 +86,11 @@ pub enum Ast {
         /// The node to traverse if the predicate is truthy.
         then: Box<Ast>,
     },
+    /// Returns the document originally provided to the search.
+    Root {
+        /// Approximate absolute position in the parsed expression.
+        offset: usize,
+    },
     /// Returns the current node.
     Identity {
         /// Approximate absolute position in the parsed expression.
@@ -72,6 +119,22 @@ pub enum Ast {
         /// Function arguments.
         args: Vec<Ast>,
     },
+    /// Binds variables, then evaluates `body` with the variables in scope.
+    Let {
+        /// Approximate absolute position in the parsed expression.
+        offset: usize,
+        /// Variables bound for the evaluation of `body`.
+        bindings: Vec<Binding>,
+        /// Expression evaluated with the bindings in scope.
+        body: Box<Ast>,
+    },
+    /// Resolves to the value bound to a variable.
+    VariableRef {
+        /// Approximate absolute position in the parsed expression.
+        offset: usize,
+        /// Variable name, without the leading `$`.
+        name: String,
+    },
     /// Extracts a key by name from a map.
     Field {
         /// Approximate absolute position in the parsed expression.
@@ -107,6 +170,15 @@ pub enum Ast {
         /// Elements of the hash
         elements: Vec<KeyValuePair>,
     },
+    /// Applies a unary arithmetic operator to a number.
+    Unary {
+        /// Approximate absolute position in the parsed expression.
+        offset: usize,
+        /// Operator applied to the operand.
+        operator: UnaryOperator,
+        /// Node to evaluate and apply the operator to.
+        node: Box<Ast>,
+    },
     /// Evaluates to true/false based on if the expression is not truthy.
     Not {
         /// Approximate absolute position in the parsed expression.
@@ -170,6 +242,154 @@ pub enum Ast {
     },
 }
 
+impl Ast {
+    /// Returns the offset of the node in the expression string.
+    pub fn offset(&self) -> usize {
+        match *self {
+            Ast::Arithmetic { offset, .. }
+            | Ast::Comparison { offset, .. }
+            | Ast::Condition { offset, .. }
+            | Ast::Identity { offset }
+            | Ast::Root { offset }
+            | Ast::Expref { offset, .. }
+            | Ast::Flatten { offset, .. }
+            | Ast::Function { offset, .. }
+            | Ast::Let { offset, .. }
+            | Ast::VariableRef { offset, .. }
+            | Ast::Field { offset, .. }
+            | Ast::Index { offset, .. }
+            | Ast::Literal { offset, .. }
+            | Ast::MultiList { offset, .. }
+            | Ast::MultiHash { offset, .. }
+            | Ast::Unary { offset, .. }
+            | Ast::Not { offset, .. }
+            | Ast::Projection { offset, .. }
+            | Ast::ObjectValues { offset, .. }
+            | Ast::And { offset, .. }
+            | Ast::Or { offset, .. }
+            | Ast::Slice { offset, .. }
+            | Ast::Subexpr { offset, .. } => offset,
+        }
+    }
+
+    /// Returns the direct child nodes of the node.
+    pub fn children(&self) -> Vec<&Ast> {
+        match *self {
+            Ast::Field { .. }
+            | Ast::Identity { .. }
+            | Ast::Root { .. }
+            | Ast::Index { .. }
+            | Ast::Literal { .. }
+            | Ast::Slice { .. }
+            | Ast::VariableRef { .. } => vec![],
+            Ast::Expref { ref ast, .. } => vec![ast],
+            Ast::Flatten { ref node, .. }
+            | Ast::Unary { ref node, .. }
+            | Ast::Not { ref node, .. }
+            | Ast::ObjectValues { ref node, .. } => vec![node],
+            Ast::Function { ref args, .. } => args.iter().collect(),
+            Ast::MultiList { ref elements, .. } => elements.iter().collect(),
+            Ast::MultiHash { ref elements, .. } => elements.iter().map(|kvp| &kvp.value).collect(),
+            Ast::Let {
+                ref bindings,
+                ref body,
+                ..
+            } => bindings
+                .iter()
+                .map(|binding| &binding.value)
+                .chain(Some(&**body))
+                .collect(),
+            Ast::Condition {
+                ref predicate,
+                ref then,
+                ..
+            } => vec![predicate, then],
+            Ast::Arithmetic {
+                ref lhs, ref rhs, ..
+            }
+            | Ast::Comparison {
+                ref lhs, ref rhs, ..
+            }
+            | Ast::Projection {
+                ref lhs, ref rhs, ..
+            }
+            | Ast::And {
+                ref lhs, ref rhs, ..
+            }
+            | Ast::Or {
+                ref lhs, ref rhs, ..
+            }
+            | Ast::Subexpr {
+                ref lhs, ref rhs, ..
+            } => vec![lhs, rhs],
+        }
+    }
+
+    /// Adds `by` to the offset of this node and every node below it.
+    pub(crate) fn shift_offsets(&mut self, by: usize) {
+        let (offset, children): (&mut usize, Vec<&mut Ast>) = match self {
+            Ast::Field { offset, .. }
+            | Ast::Identity { offset }
+            | Ast::Root { offset }
+            | Ast::Index { offset, .. }
+            | Ast::Literal { offset, .. }
+            | Ast::Slice { offset, .. }
+            | Ast::VariableRef { offset, .. } => (offset, vec![]),
+            Ast::Expref { offset, ast } => (offset, vec![ast]),
+            Ast::Flatten { offset, node }
+            | Ast::Unary { offset, node, .. }
+            | Ast::Not { offset, node }
+            | Ast::ObjectValues { offset, node } => (offset, vec![node]),
+            Ast::Function { offset, args, .. } => (offset, args.iter_mut().collect()),
+            Ast::MultiList { offset, elements } => (offset, elements.iter_mut().collect()),
+            Ast::MultiHash { offset, elements } => (
+                offset,
+                elements.iter_mut().map(|kvp| &mut kvp.value).collect(),
+            ),
+            Ast::Let {
+                offset,
+                bindings,
+                body,
+            } => (
+                offset,
+                bindings
+                    .iter_mut()
+                    .map(|binding| &mut binding.value)
+                    .chain(Some(&mut **body))
+                    .collect(),
+            ),
+            Ast::Condition {
+                offset,
+                predicate,
+                then,
+            } => (offset, vec![predicate, then]),
+            Ast::Arithmetic {
+                offset, lhs, rhs, ..
+            }
+            | Ast::Comparison {
+                offset, lhs, rhs, ..
+            }
+            | Ast::Projection { offset, lhs, rhs }
+            | Ast::And { offset, lhs, rhs }
+            | Ast::Or { offset, lhs, rhs }
+            | Ast::Subexpr { offset, lhs, rhs } => (offset, vec![lhs, rhs]),
+        };
+        *offset += by;
+        for child in children {
+            child.shift_offsets(by);
+        }
+    }
+
+    /// Returns the number of nodes in the tree, including this node.
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children()
+            .into_iter()
+            .map(Ast::node_count)
+            .sum::<usize>()
+    }
+}
+
 impl fmt::Display for Ast {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         write!(fmt, "{:#?}", self)
@@ -177,7 +397,7 @@ impl fmt::Display for Ast {
 }
 
 /// Represents a key value pair in a MultiHash.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct KeyValuePair {
     /// Key name.
     pub key: String,
@@ -185,8 +405,17 @@ pub struct KeyValuePair {
     pub value: Ast,
 }
 
+/// Represents a variable binding in a Let node.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Binding {
+    /// Variable name, without the leading `$`.
+    pub name: String,
+    /// Expression used to determine the value of the variable.
+    pub value: Ast,
+}
+
 /// Comparators used in Comparison nodes.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Comparator {
     Equal,
     NotEqual,
@@ -196,6 +425,60 @@ pub enum Comparator {
     GreaterThanEqual,
 }
 
+/// Operators used in Arithmetic nodes.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ArithmeticOperator {
+    /// Adds the operands (`+`).
+    Add,
+    /// Subtracts the right operand from the left (`-`).
+    Subtract,
+    /// Multiplies the operands (`*`).
+    Multiply,
+    /// Divides the left operand by the right (`/`).
+    Divide,
+    /// Divides and rounds the quotient toward negative infinity (`//`).
+    ///
+    /// This is not jq's alternative operator. A fallback for null values
+    /// is written as `not_null(a, b)`; `a || b` also replaces `false` and
+    /// empty strings, arrays and objects.
+    IntegerDivide,
+    /// Remainder of integer division, taking the sign of the divisor (`%`).
+    Modulo,
+}
+
+/// Displays the operator as it is written in an expression.
+impl fmt::Display for ArithmeticOperator {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            ArithmeticOperator::Add => write!(fmt, "+"),
+            ArithmeticOperator::Subtract => write!(fmt, "-"),
+            ArithmeticOperator::Multiply => write!(fmt, "*"),
+            ArithmeticOperator::Divide => write!(fmt, "/"),
+            ArithmeticOperator::IntegerDivide => write!(fmt, "//"),
+            ArithmeticOperator::Modulo => write!(fmt, "%"),
+        }
+    }
+}
+
+/// Operators used in Unary nodes.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    /// Returns the number unchanged (`+`).
+    Plus,
+    /// Negates the number (`-`).
+    Minus,
+}
+
+/// Displays the operator as it is written in an expression.
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            UnaryOperator::Plus => write!(fmt, "+"),
+            UnaryOperator::Minus => write!(fmt, "-"),
+        }
+    }
+}
+
 /// Creates a Comparator from a Token.
 ///
 /// Note: panics if the Token is invalid.
@@ -213,6 +496,368 @@ impl From<Token> for Comparator {
     }
 }
 
+/// Builds an `Ast` node by node, using the node shapes that the parser
+/// produces for the equivalent expression.
+///
+/// Projections behave as they do in expressions: `dot`, `index`, `slice`,
+/// `projection`, `values` and `filter` called on a projection apply to each
+/// projected element (as in `a[*].b[0]`), while other methods apply to the
+/// projected result (as in `a[*].b == c`). Use `pipe` to apply any of them
+/// to the result instead. Every node has an offset of 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AstBuilder {
+    ast: Ast,
+}
+
+impl AstBuilder {
+    /// The current node (`@`).
+    pub fn identity() -> AstBuilder {
+        AstBuilder::from(Ast::Identity { offset: 0 })
+    }
+
+    /// The root of the searched document (`$`).
+    pub fn root() -> AstBuilder {
+        AstBuilder::from(Ast::Root { offset: 0 })
+    }
+
+    /// A field of the current node (`name`).
+    pub fn field<S: Into<String>>(name: S) -> AstBuilder {
+        AstBuilder::from(Ast::Field {
+            offset: 0,
+            name: name.into(),
+        })
+    }
+
+    /// A literal value (`` `value` ``).
+    pub fn literal(value: Variable) -> AstBuilder {
+        AstBuilder::from(Ast::Literal {
+            offset: 0,
+            value: Rcvar::new(value),
+        })
+    }
+
+    /// A reference to a variable (`$name`).
+    pub fn variable<S: Into<String>>(name: S) -> AstBuilder {
+        AstBuilder::from(Ast::VariableRef {
+            offset: 0,
+            name: name.into(),
+        })
+    }
+
+    /// A function call (`name(args...)`).
+    pub fn function<S: Into<String>>(name: S, args: Vec<AstBuilder>) -> AstBuilder {
+        AstBuilder::from(Ast::Function {
+            offset: 0,
+            name: name.into(),
+            args: args.into_iter().map(AstBuilder::build).collect(),
+        })
+    }
+
+    /// An expression reference, used as a function argument (`&node`).
+    pub fn expref(node: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::Expref {
+            offset: 0,
+            ast: Box::new(node.ast),
+        })
+    }
+
+    /// A multi-select list (`[elements...]`).
+    pub fn multi_list(elements: Vec<AstBuilder>) -> AstBuilder {
+        AstBuilder::from(Ast::MultiList {
+            offset: 0,
+            elements: elements.into_iter().map(AstBuilder::build).collect(),
+        })
+    }
+
+    /// A multi-select hash (`{key: value, ...}`).
+    pub fn multi_hash<S: Into<String>>(elements: Vec<(S, AstBuilder)>) -> AstBuilder {
+        AstBuilder::from(Ast::MultiHash {
+            offset: 0,
+            elements: elements
+                .into_iter()
+                .map(|(key, value)| KeyValuePair {
+                    key: key.into(),
+                    value: value.ast,
+                })
+                .collect(),
+        })
+    }
+
+    /// Evaluates `rhs` against the result of this node (`self.rhs`).
+    pub fn dot(self, rhs: AstBuilder) -> AstBuilder {
+        self.extend(Continuation::Dot, |node, projected| {
+            subexpr(node, rhs.ast, projected)
+        })
+    }
+
+    /// Selects an element of the array (`self[idx]`); negative indexes
+    /// count from the end.
+    pub fn index(self, idx: i32) -> AstBuilder {
+        self.extend(Continuation::Bracket, |node, projected| {
+            subexpr(node, Ast::Index { offset: 0, idx }, projected)
+        })
+    }
+
+    /// Projects a slice of the array (`self[start:stop:step]`).
+    pub fn slice(self, start: Option<i32>, stop: Option<i32>, step: i32) -> AstBuilder {
+        let slice = Ast::Projection {
+            offset: 0,
+            lhs: Box::new(Ast::Slice {
+                offset: 0,
+                start,
+                stop,
+                step,
+            }),
+            rhs: Box::new(Ast::Identity { offset: 0 }),
+        };
+        self.extend(Continuation::Bracket, |node, projected| {
+            subexpr(node, slice, projected)
+        })
+    }
+
+    /// Projects the elements of the array (`self[*]`).
+    pub fn projection(self) -> AstBuilder {
+        self.extend(Continuation::Bracket, |node, _| Ast::Projection {
+            offset: 0,
+            lhs: Box::new(node),
+            rhs: Box::new(Ast::Identity { offset: 0 }),
+        })
+    }
+
+    /// Projects the values of the object (`self.*`).
+    pub fn values(self) -> AstBuilder {
+        self.extend(Continuation::Dot, |node, _| Ast::Projection {
+            offset: 0,
+            lhs: Box::new(Ast::ObjectValues {
+                offset: 0,
+                node: Box::new(node),
+            }),
+            rhs: Box::new(Ast::Identity { offset: 0 }),
+        })
+    }
+
+    /// Projects the elements of the array for which `predicate` is truthy
+    /// (`self[?predicate]`).
+    pub fn filter(self, predicate: AstBuilder) -> AstBuilder {
+        self.extend(Continuation::Filter, |node, _| Ast::Projection {
+            offset: 0,
+            lhs: Box::new(node),
+            rhs: Box::new(Ast::Condition {
+                offset: 0,
+                predicate: Box::new(predicate.ast),
+                then: Box::new(Ast::Identity { offset: 0 }),
+            }),
+        })
+    }
+
+    /// Flattens the array by one level and projects its elements
+    /// (`self[]`).
+    pub fn flatten(self) -> AstBuilder {
+        AstBuilder::from(Ast::Projection {
+            offset: 0,
+            lhs: Box::new(Ast::Flatten {
+                offset: 0,
+                node: Box::new(self.ast),
+            }),
+            rhs: Box::new(Ast::Identity { offset: 0 }),
+        })
+    }
+
+    /// Compares the result of this node with `rhs` (`self == rhs`, etc.).
+    pub fn compare(self, comparator: Comparator, rhs: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::Comparison {
+            offset: 0,
+            comparator,
+            lhs: Box::new(self.ast),
+            rhs: Box::new(rhs.ast),
+        })
+    }
+
+    /// Applies an arithmetic operator to this node and `rhs` (`self + rhs`,
+    /// etc.).
+    pub fn arithmetic(self, operator: ArithmeticOperator, rhs: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::Arithmetic {
+            offset: 0,
+            operator,
+            lhs: Box::new(self.ast),
+            rhs: Box::new(rhs.ast),
+        })
+    }
+
+    /// Returns this node if it is truthy, otherwise `rhs` (`self || rhs`).
+    pub fn or(self, rhs: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::Or {
+            offset: 0,
+            lhs: Box::new(self.ast),
+            rhs: Box::new(rhs.ast),
+        })
+    }
+
+    /// Returns this node if it is falsy, otherwise `rhs` (`self && rhs`).
+    pub fn and(self, rhs: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::And {
+            offset: 0,
+            lhs: Box::new(self.ast),
+            rhs: Box::new(rhs.ast),
+        })
+    }
+
+    /// Evaluates `rhs` against the result of this node, ending any
+    /// projection (`self | rhs`).
+    pub fn pipe(self, rhs: AstBuilder) -> AstBuilder {
+        AstBuilder::from(Ast::Subexpr {
+            offset: 0,
+            lhs: Box::new(self.ast),
+            rhs: Box::new(rhs.ast),
+        })
+    }
+
+    /// Returns the built AST without checking it.
+    pub fn build(self) -> Ast {
+        self.ast
+    }
+
+    /// Returns the built AST if it can be evaluated using `runtime`.
+    ///
+    /// Otherwise, the lint warnings for the problems that make every
+    /// evaluation fail are returned: calls to functions that are not
+    /// registered or with the wrong number of arguments, references to
+    /// unbound variables, and slices with a step of 0.
+    pub fn build_checked(self, runtime: &Runtime) -> Result<Ast, Vec<Warning>> {
+        let errors: Vec<Warning> = lint::lint(&self.ast, runtime)
+            .into_iter()
+            .filter(|warning| warning.kind != WarningKind::ConstantFalseFilter)
+            .collect();
+        if errors.is_empty() {
+            Ok(self.ast)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Applies `f` to the node that an operation of the given kind applies
+    /// to: the right hand side of the innermost projection, or the whole
+    /// node if it is not a projection. `f` is told whether the node it
+    /// receives is projected.
+    fn extend<F: FnOnce(Ast, bool) -> Ast>(self, kind: Continuation, f: F) -> AstBuilder {
+        AstBuilder::from(extend(self.ast, kind, f, false))
+    }
+}
+
+/// How an operation applied to a node continues the node's projections,
+/// following the binding powers used by the parser.
+#[derive(Clone, Copy, PartialEq)]
+enum Continuation {
+    /// A dot or `.*`, which continues projections (`a[*].b`).
+    Dot,
+    /// An index, slice or `[*]`, which also binds to the right hand side of
+    /// a subexpression (`a.b[0]` is `a.(b[0])`).
+    Bracket,
+    /// A filter, which is applied to a filter projection when the filter's
+    /// right hand side is neither empty nor a projection (`a[?b].c[?d]`).
+    Filter,
+}
+
+/// Applies `f` as described by `AstBuilder::extend`.
+fn extend<F: FnOnce(Ast, bool) -> Ast>(
+    node: Ast,
+    kind: Continuation,
+    f: F,
+    projected: bool,
+) -> Ast {
+    match node {
+        Ast::Projection { offset, lhs, rhs } => match *rhs {
+            Ast::Condition {
+                offset: condition_offset,
+                predicate,
+                then,
+            } => {
+                let then = if kind == Continuation::Filter
+                    && !is_open(&then)
+                    && !matches!(*then, Ast::Identity { .. })
+                {
+                    let rhs = Ast::Condition {
+                        offset: condition_offset,
+                        predicate,
+                        then,
+                    };
+                    let node = Ast::Projection {
+                        offset,
+                        lhs,
+                        rhs: Box::new(rhs),
+                    };
+                    return f(node, projected);
+                } else {
+                    extend(*then, kind, f, true)
+                };
+                Ast::Projection {
+                    offset,
+                    lhs,
+                    rhs: Box::new(Ast::Condition {
+                        offset: condition_offset,
+                        predicate,
+                        then: Box::new(then),
+                    }),
+                }
+            }
+            rhs => Ast::Projection {
+                offset,
+                lhs,
+                rhs: Box::new(extend(rhs, kind, f, true)),
+            },
+        },
+        Ast::Subexpr { offset, lhs, rhs } if kind == Continuation::Bracket || is_open(&rhs) => {
+            Ast::Subexpr {
+                offset,
+                lhs,
+                rhs: Box::new(extend(*rhs, kind, f, projected)),
+            }
+        }
+        node => f(node, projected),
+    }
+}
+
+/// Returns true if operations such as `dot` continue a projection of the
+/// node, as in `a[1:2].b` or `(a | b[*]).c`.
+fn is_open(node: &Ast) -> bool {
+    match *node {
+        Ast::Projection { .. } => true,
+        Ast::Subexpr { ref rhs, .. } => is_open(rhs),
+        _ => false,
+    }
+}
+
+impl From<Ast> for AstBuilder {
+    fn from(ast: Ast) -> AstBuilder {
+        AstBuilder { ast }
+    }
+}
+
+/// Negates the truthiness of the node (`!node`).
+impl ops::Not for AstBuilder {
+    type Output = AstBuilder;
+
+    fn not(self) -> AstBuilder {
+        AstBuilder::from(Ast::Not {
+            offset: 0,
+            node: Box::new(self.ast),
+        })
+    }
+}
+
+/// Creates the node for `lhs.rhs`. The parser omits the left hand side of
+/// a projection's right hand side.
+fn subexpr(lhs: Ast, rhs: Ast, projected: bool) -> Ast {
+    match lhs {
+        Ast::Identity { .. } if projected => rhs,
+        lhs => Ast::Subexpr {
+            offset: 0,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -228,4 +873,157 @@ mod test {
             format!("{}", node)
         );
     }
+
+    /// Parses an expression, setting every offset to 0.
+    fn parse_without_offsets(expression: &str) -> Ast {
+        fn clear(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    if let Some(offset) = map.get_mut("offset") {
+                        *offset = 0.into();
+                    }
+                    map.values_mut().for_each(clear);
+                }
+                serde_json::Value::Array(array) => array.iter_mut().for_each(clear),
+                _ => {}
+            }
+        }
+        let mut value = serde_json::to_value(crate::parse(expression).unwrap()).unwrap();
+        clear(&mut value);
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn builds_parsed_asts() {
+        let field = AstBuilder::field;
+        let cases = vec![
+            ("a.b[0]", field("a").dot(field("b")).index(0)),
+            (
+                "a[*].b[0]",
+                field("a").projection().dot(field("b")).index(0),
+            ),
+            ("a[*][0][1]", field("a").projection().index(0).index(1)),
+            (
+                "a.b[*].c",
+                field("a").dot(field("b")).projection().dot(field("c")),
+            ),
+            (
+                "a[1:2].b[0]",
+                field("a")
+                    .slice(Some(1), Some(2), 1)
+                    .dot(field("b"))
+                    .index(0),
+            ),
+            (
+                "a[?x].b[1:2][?y]",
+                field("a")
+                    .filter(field("x"))
+                    .dot(field("b"))
+                    .slice(Some(1), Some(2), 1)
+                    .filter(field("y")),
+            ),
+            (
+                "a[1:2].b",
+                field("a").slice(Some(1), Some(2), 1).dot(field("b")),
+            ),
+            ("a[::-1][*]", field("a").slice(None, None, -1).projection()),
+            (
+                "a.*.b[?c].d",
+                field("a")
+                    .values()
+                    .dot(field("b"))
+                    .filter(field("c"))
+                    .dot(field("d")),
+            ),
+            (
+                "a[?b][?c]",
+                field("a").filter(field("b")).filter(field("c")),
+            ),
+            (
+                "a[?b].c[?d]",
+                field("a")
+                    .filter(field("b"))
+                    .dot(field("c"))
+                    .filter(field("d")),
+            ),
+            (
+                "a[*].b[].c",
+                field("a")
+                    .projection()
+                    .dot(field("b"))
+                    .flatten()
+                    .dot(field("c")),
+            ),
+            (
+                "a[*].b == `1` || !c",
+                field("a")
+                    .projection()
+                    .dot(field("b"))
+                    .compare(
+                        Comparator::Equal,
+                        AstBuilder::literal(Variable::Number(1.into())),
+                    )
+                    .or(!field("c")),
+            ),
+            (
+                "a[*].b | @[0]",
+                field("a")
+                    .projection()
+                    .dot(field("b"))
+                    .pipe(AstBuilder::identity().index(0)),
+            ),
+            (
+                "{x: sort_by(@, &b), y: [$, $v]}.x",
+                AstBuilder::multi_hash(vec![
+                    (
+                        "x",
+                        AstBuilder::function(
+                            "sort_by",
+                            vec![AstBuilder::identity(), AstBuilder::expref(field("b"))],
+                        ),
+                    ),
+                    (
+                        "y",
+                        AstBuilder::multi_list(vec![AstBuilder::root(), AstBuilder::variable("v")]),
+                    ),
+                ])
+                .dot(field("x")),
+            ),
+        ];
+        for (expression, builder) in cases {
+            assert_eq!(
+                parse_without_offsets(expression),
+                builder.build(),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn checks_built_asts() {
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        let valid = AstBuilder::field("a").filter(AstBuilder::literal(Variable::Bool(false)));
+        assert!(valid.build_checked(&runtime).is_ok());
+        let invalid = AstBuilder::function("length", vec![])
+            .slice(None, None, 0)
+            .dot(AstBuilder::variable("x"));
+        let kinds: Vec<WarningKind> = invalid
+            .build_checked(&runtime)
+            .unwrap_err()
+            .into_iter()
+            .map(|warning| warning.kind)
+            .collect();
+        assert_eq!(3, kinds.len());
+        assert!(kinds.contains(&WarningKind::ZeroSliceStep));
+        assert!(kinds.contains(&WarningKind::UndefinedVariable("x".to_owned())));
+    }
+
+    #[test]
+    fn counts_nodes() {
+        let ast = crate::parse("foo[?a == `1`].{b: b, c: length(c)}").unwrap();
+        assert_eq!(2, ast.children().len());
+        assert_eq!(10, ast.node_count());
+    }
 }
@@ -0,0 +1,67 @@
+//! Pluggable strategies for evaluating compiled expressions.
+//!
+//! An `EvalBackend` evaluates the AST of a compiled `Expression`. The
+//! default backend is `TreeWalker`, which walks the AST recursively. Other
+//! backends can be selected when building an expression using
+//! `ExpressionBuilder::backend`, which makes it possible to experiment with
+//! other execution strategies (e.g., interpreting `ir::Program`s) without
+//! changing how expressions are compiled or searched.
+//!
+//! ```
+//! use jmespath::ast::Ast;
+//! use jmespath::backend::{EvalBackend, TreeWalker};
+//! use jmespath::{Context, ExpressionBuilder, Rcvar};
+//!
+//! /// Delegates to the tree-walking interpreter.
+//! struct Delegating;
+//!
+//! impl EvalBackend for Delegating {
+//!     fn evaluate(
+//!         &self,
+//!         ast: &Ast,
+//!         data: &Rcvar,
+//!         ctx: &mut Context<'_>,
+//!     ) -> Result<Rcvar, jmespath::JmespathError> {
+//!         TreeWalker.evaluate(ast, data, ctx)
+//!     }
+//! }
+//!
+//! static BACKEND: Delegating = Delegating;
+//!
+//! let expr = ExpressionBuilder::new("length(@)")
+//!     .backend(&BACKEND)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(Some(3.0), expr.search("abc").unwrap().as_number());
+//! ```
+
+use crate::ast::Ast;
+use crate::interpreter::{interpret, SearchResult};
+use crate::{Context, Rcvar};
+
+/// Evaluates the AST of an expression against data.
+///
+/// Expression references passed to functions are evaluated by the function
+/// itself (typically using `Context::evaluate`), so backends only control
+/// the evaluation of the expression as a whole.
+pub trait EvalBackend: Sync + Send {
+    /// Evaluates `ast` against `data`.
+    ///
+    /// The context holds the runtime used to call functions and the search
+    /// options that must be honored.
+    fn evaluate(&self, ast: &Ast, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult;
+}
+
+/// Evaluates expressions by recursively walking the AST.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeWalker;
+
+impl EvalBackend for TreeWalker {
+    #[inline]
+    fn evaluate(&self, ast: &Ast, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult {
+        interpret(data, ast, ctx)
+    }
+}
+
+/// The backend used when none is configured.
+pub(crate) static DEFAULT_BACKEND: TreeWalker = TreeWalker;
@@ -0,0 +1,114 @@
+//! Bounded cache of expressions compiled by `jmespath::compile`.
+//!
+//! With the `sync` feature the cache is shared by every thread behind a
+//! mutex. Otherwise expressions hold `Rc`s and cannot be sent between
+//! threads, so each thread keeps its own cache.
+
+use std::collections::HashMap;
+
+use crate::{Expression, JmespathError, Runtime};
+
+/// Number of expressions kept by the cache used by `jmespath::compile`.
+pub const COMPILE_CACHE_CAPACITY: usize = 256;
+
+/// Least recently used cache of compiled expressions keyed by their string.
+struct Cache {
+    expressions: HashMap<String, (Expression<'static>, u64)>,
+    clock: u64,
+}
+
+impl Cache {
+    fn new() -> Cache {
+        Cache {
+            expressions: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, expression: &str) -> Option<Expression<'static>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.expressions.get_mut(expression).map(|entry| {
+            entry.1 = clock;
+            entry.0.clone()
+        })
+    }
+
+    fn insert(&mut self, expr: Expression<'static>) {
+        if self.expressions.len() >= COMPILE_CACHE_CAPACITY {
+            let oldest = self
+                .expressions
+                .iter()
+                .min_by_key(|(_, entry)| entry.1)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.expressions.remove(&oldest);
+            }
+        }
+        self.clock += 1;
+        self.expressions
+            .insert(expr.as_str().to_owned(), (expr, self.clock));
+    }
+}
+
+#[cfg(feature = "sync")]
+static CACHE: once_cell::sync::Lazy<std::sync::Mutex<Cache>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(Cache::new()));
+
+#[cfg(not(feature = "sync"))]
+thread_local! {
+    static CACHE: std::cell::RefCell<Cache> = std::cell::RefCell::new(Cache::new());
+}
+
+fn with_cache<T>(f: impl FnOnce(&mut Cache) -> T) -> T {
+    #[cfg(feature = "sync")]
+    {
+        let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut cache)
+    }
+    #[cfg(not(feature = "sync"))]
+    CACHE.with(|cache| f(&mut cache.borrow_mut()))
+}
+
+/// Returns a cached copy of `expression` compiled with `runtime`, compiling
+/// and caching it if needed. Expressions that fail to compile are not
+/// cached.
+pub(crate) fn compile(
+    runtime: &'static Runtime,
+    expression: &str,
+) -> Result<Expression<'static>, JmespathError> {
+    if let Some(expr) = with_cache(|cache| cache.get(expression)) {
+        return Ok(expr);
+    }
+    let expr = runtime.compile(expression)?;
+    with_cache(|cache| cache.insert(expr.clone()));
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DEFAULT_RUNTIME;
+
+    #[test]
+    fn evicts_least_recently_used_expressions() {
+        let mut cache = Cache::new();
+        for i in 0..COMPILE_CACHE_CAPACITY {
+            cache.insert(DEFAULT_RUNTIME.compile(&format!("a{}", i)).unwrap());
+        }
+        assert!(cache.get("a0").is_some());
+        cache.insert(DEFAULT_RUNTIME.compile("b").unwrap());
+        assert_eq!(COMPILE_CACHE_CAPACITY, cache.expressions.len());
+        assert!(cache.get("a0").is_some());
+        assert!(cache.get("a1").is_none());
+        assert_eq!("b", cache.get("b").unwrap().as_str());
+    }
+
+    #[test]
+    fn does_not_cache_errors() {
+        assert!(compile(&DEFAULT_RUNTIME, "a[").is_err());
+        assert!(with_cache(|cache| cache.get("a[").is_none()));
+        assert_eq!("a.b", compile(&DEFAULT_RUNTIME, "a.b").unwrap().as_str());
+        assert!(with_cache(|cache| cache.get("a.b").is_some()));
+    }
+}
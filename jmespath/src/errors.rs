@@ -3,6 +3,8 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::functions::ArgumentType;
+use crate::variable::JmespathType;
 use crate::Context;
 
 /// JMESPath error.
@@ -106,6 +108,8 @@ pub enum ErrorReason {
     Parse(String),
     /// An error occurred while evaluating an expression.
     Runtime(RuntimeError),
+    /// An expression exceeded a limit set by `ParserOptions`.
+    ParseLimit(ParseLimit),
 }
 
 impl fmt::Display for ErrorReason {
@@ -113,6 +117,46 @@ impl fmt::Display for ErrorReason {
         match *self {
             ErrorReason::Parse(ref e) => write!(fmt, "Parse error: {}", e),
             ErrorReason::Runtime(ref e) => write!(fmt, "Runtime error: {}", e),
+            ErrorReason::ParseLimit(ref e) => write!(fmt, "Parse error: {}", e),
+        }
+    }
+}
+
+/// Parser limit that was exceeded by an expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseLimit {
+    /// The expression nests deeper than `ParserOptions::max_depth`.
+    Depth(usize),
+    /// The expression has more tokens than `ParserOptions::max_tokens`.
+    Tokens(usize),
+}
+
+impl fmt::Display for ParseLimit {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            ParseLimit::Depth(max) => write!(fmt, "Expression nests more than {} levels", max),
+            ParseLimit::Tokens(max) => write!(fmt, "Expression has more than {} tokens", max),
+        }
+    }
+}
+
+/// Operand of an operator that was given a value of the wrong type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// The left operand of a binary operator.
+    Left,
+    /// The right operand of a binary operator.
+    Right,
+    /// The operand of a unary operator.
+    Unary,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            Operand::Left => write!(fmt, "left operand"),
+            Operand::Right => write!(fmt, "right operand"),
+            Operand::Unary => write!(fmt, "operand"),
         }
     }
 }
@@ -138,26 +182,127 @@ pub enum RuntimeError {
     },
     /// Encountered when an unknown function is called.
     UnknownFunction(String),
+    /// Encountered when dividing by zero and `DivisionByZero::Error` is in effect.
+    DivisionByZero,
+    /// Encountered when an integer result overflows and `Overflow::Error`
+    /// is in effect.
+    Overflow,
+    /// Encountered when evaluating an expression nests more AST nodes than
+    /// `SearchOptions::max_depth` allows.
+    MaxDepthExceeded(usize),
+    /// Encountered when an intermediate step of a subexpression yields null
+    /// and `NullPropagation::Error` is in effect.
+    NullValue,
+    /// Encountered when a variable is referenced outside of any `let`
+    /// expression that binds it.
+    UndefinedVariable(String),
+    /// Encountered when an object does not have a field and
+    /// `SearchOptions::strict` is enabled.
+    MissingField(String),
+    /// Encountered when an index is past either end of an array and
+    /// `SearchOptions::strict` is enabled.
+    IndexOutOfBounds {
+        /// Index that was requested.
+        index: i32,
+        /// Length of the array.
+        length: usize,
+    },
+    /// Encountered when getting a field of a value that is not an object,
+    /// or an element of a value that is not an array, and
+    /// `SearchOptions::strict` is enabled.
+    InvalidAccess {
+        /// Type of value that supports the access.
+        expected: JmespathType,
+        /// Type of the accessed value.
+        actual: JmespathType,
+    },
     /// Encountered when a type of variable given to a function is invalid.
     InvalidType {
+        /// Name of the function that was called, when known.
+        function: Option<String>,
         /// Expected type.
-        expected: String,
-        /// Provided type.
-        actual: String,
+        expected: ArgumentType,
+        /// Type of the provided value.
+        actual: JmespathType,
         /// Argument position when calling the function.
         position: usize,
     },
     /// Encountered when an expression reference returns an invalid type.
     InvalidReturnType {
+        /// Name of the function that was called, when known.
+        function: Option<String>,
         /// Expected return type.
-        expected: String,
-        /// Actual return type.
-        actual: String,
+        expected: ArgumentType,
+        /// Type of the value returned by the expression reference.
+        actual: JmespathType,
         /// Argument position from which the expression reference was invoked.
         position: usize,
         /// Which invocation iteration of the expression reference failed.
         invocation: usize,
     },
+    /// Encountered when an argument has the expected type but a value the
+    /// function cannot accept, such as a negative count.
+    InvalidValue {
+        /// Name of the function that was called, when known.
+        function: Option<String>,
+        /// Argument position when calling the function.
+        position: usize,
+        /// Description of the problem with the value.
+        message: String,
+    },
+    /// Encountered when the VM executes an `ir::Program` that is not well
+    /// formed, such as one that pops more values than it pushed.
+    InvalidProgram {
+        /// Index of the offending instruction.
+        instruction: usize,
+        /// Description of the problem with the instruction.
+        message: String,
+    },
+    /// Encountered when an operand of an arithmetic or unary operator has
+    /// an invalid type.
+    InvalidOperand {
+        /// Operator as it is written in the expression, e.g. `+`.
+        operator: String,
+        /// Operand that has the invalid type.
+        operand: Operand,
+        /// Expected type.
+        expected: ArgumentType,
+        /// Type of the provided value.
+        actual: JmespathType,
+    },
+}
+
+impl RuntimeError {
+    /// Returns the name of the function that raised the error, if known.
+    pub fn function(&self) -> Option<&str> {
+        match *self {
+            RuntimeError::InvalidType { ref function, .. }
+            | RuntimeError::InvalidReturnType { ref function, .. }
+            | RuntimeError::InvalidValue { ref function, .. } => function.as_deref(),
+            RuntimeError::UnknownFunction(ref name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the expected return type of an expression reference.
+fn expref_return_type(expected: &ArgumentType) -> String {
+    match *expected {
+        ArgumentType::Union(ref types) => types
+            .iter()
+            .map(|t| format!("expression->{}", t))
+            .collect::<Vec<_>>()
+            .join("|"),
+        ref t => format!("expression->{}", t),
+    }
+}
+
+/// Formats the function name that prefixes a type error, if known.
+fn function_prefix(function: &Option<String>) -> String {
+    match *function {
+        Some(ref name) => format!("{}() ", name),
+        None => String::new(),
+    }
 }
 
 impl fmt::Display for RuntimeError {
@@ -182,24 +327,83 @@ impl fmt::Display for RuntimeError {
                 expected, actual
             ),
             InvalidType {
+                ref function,
                 ref expected,
                 ref actual,
                 ref position,
             } => write!(
                 fmt,
-                "Argument {} expects type {}, given {}",
-                position, expected, actual
+                "{}Argument {} expects type {}, given {}",
+                function_prefix(function),
+                position,
+                expected,
+                actual
             ),
             InvalidSlice => write!(fmt, "Invalid slice"),
+            DivisionByZero => write!(fmt, "Division by zero"),
+            Overflow => write!(fmt, "Numeric overflow"),
+            MaxDepthExceeded(ref max_depth) => {
+                write!(fmt, "Maximum evaluation depth of {} exceeded", max_depth)
+            }
+            NullValue => write!(fmt, "Intermediate value of subexpression is null"),
+            UndefinedVariable(ref name) => write!(fmt, "Reference to undefined variable ${}", name),
+            MissingField(ref name) => write!(fmt, "Object has no field {:?}", name),
+            IndexOutOfBounds { index, length } => write!(
+                fmt,
+                "Index {} is out of bounds for an array of length {}",
+                index, length
+            ),
+            InvalidAccess {
+                ref expected,
+                ref actual,
+            } => write!(
+                fmt,
+                "Cannot access a value of type {} as an {}",
+                actual, expected
+            ),
             InvalidReturnType {
+                ref function,
                 ref expected,
                 ref actual,
                 ref position,
                 ref invocation,
             } => write!(
                 fmt,
-                "Argument {} must return {} but invocation {} returned {}",
-                position, expected, invocation, actual
+                "{}Argument {} must return {} but invocation {} returned {}",
+                function_prefix(function),
+                position,
+                expref_return_type(expected),
+                invocation,
+                actual
+            ),
+            InvalidValue {
+                ref function,
+                position,
+                ref message,
+            } => write!(
+                fmt,
+                "{}Argument {} is invalid: {}",
+                function_prefix(function),
+                position,
+                message
+            ),
+            InvalidProgram {
+                instruction,
+                ref message,
+            } => write!(
+                fmt,
+                "Invalid program at instruction {}: {}",
+                instruction, message
+            ),
+            InvalidOperand {
+                ref operator,
+                operand,
+                ref expected,
+                ref actual,
+            } => write!(
+                fmt,
+                "The {} of `{}` expects type {}, given {}",
+                operand, operator, expected, actual
             ),
         }
     }
@@ -212,7 +416,7 @@ mod test {
     #[test]
     fn coordinates_can_be_created_from_string_with_new_lines() {
         let expr = "foo\n..bar";
-        let err = JmespathError::new(&expr, 5, ErrorReason::Parse("Test".to_owned()));
+        let err = JmespathError::new(expr, 5, ErrorReason::Parse("Test".to_owned()));
         assert_eq!(1, err.line);
         assert_eq!(1, err.column);
         assert_eq!(5, err.offset);
@@ -225,7 +429,7 @@ mod test {
     #[test]
     fn coordinates_can_be_created_from_string_with_new_lines_pointing_to_non_last() {
         let expr = "foo\n..bar\nbaz";
-        let err = JmespathError::new(&expr, 5, ErrorReason::Parse("Test".to_owned()));
+        let err = JmespathError::new(expr, 5, ErrorReason::Parse("Test".to_owned()));
         assert_eq!(1, err.line);
         assert_eq!(1, err.column);
         assert_eq!(5, err.offset);
@@ -238,7 +442,7 @@ mod test {
     #[test]
     fn coordinates_can_be_created_from_string_with_no_new_lines() {
         let expr = "foo..bar";
-        let err = JmespathError::new(&expr, 4, ErrorReason::Parse("Test".to_owned()));
+        let err = JmespathError::new(expr, 4, ErrorReason::Parse("Test".to_owned()));
         assert_eq!(0, err.line);
         assert_eq!(4, err.column);
         assert_eq!(4, err.offset);
@@ -266,8 +470,9 @@ mod test {
     #[test]
     fn displays_invalid_type_error() {
         let error = RuntimeError::InvalidType {
-            expected: "string".to_owned(),
-            actual: "boolean".to_owned(),
+            function: None,
+            expected: ArgumentType::String,
+            actual: JmespathType::Boolean,
             position: 0,
         };
         assert_eq!(
@@ -276,12 +481,57 @@ mod test {
         );
     }
 
+    #[test]
+    fn displays_invalid_type_error_with_function_name() {
+        let error = RuntimeError::InvalidType {
+            function: Some("abs".to_owned()),
+            expected: ArgumentType::Number,
+            actual: JmespathType::String,
+            position: 0,
+        };
+        assert_eq!(Some("abs"), error.function());
+        assert_eq!(
+            "abs() Argument 0 expects type number, given string",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn displays_invalid_operand_error() {
+        let error = RuntimeError::InvalidOperand {
+            operator: "%".to_owned(),
+            operand: Operand::Right,
+            expected: ArgumentType::Number,
+            actual: JmespathType::String,
+        };
+        assert_eq!(None, error.function());
+        assert_eq!(
+            "The right operand of `%` expects type number, given string",
+            error.to_string()
+        );
+    }
+
     #[test]
     fn displays_invalid_slice() {
         let error = RuntimeError::InvalidSlice;
         assert_eq!("Invalid slice", error.to_string());
     }
 
+    #[test]
+    fn displays_null_value() {
+        let error = RuntimeError::NullValue;
+        assert_eq!(
+            "Intermediate value of subexpression is null",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn displays_undefined_variable() {
+        let error = RuntimeError::UndefinedVariable("foo".to_owned());
+        assert_eq!("Reference to undefined variable $foo", error.to_string());
+    }
+
     #[test]
     fn displays_too_many_arguments_error() {
         let error = RuntimeError::TooManyArguments {
@@ -306,13 +556,15 @@ mod test {
     #[test]
     fn displays_invalid_return_type_error() {
         let error = RuntimeError::InvalidReturnType {
-            expected: "string".to_string(),
-            actual: "boolean".to_string(),
+            function: None,
+            expected: ArgumentType::Union(vec![ArgumentType::Number, ArgumentType::String]),
+            actual: JmespathType::Boolean,
             position: 0,
             invocation: 2,
         };
         assert_eq!(
-            "Argument 0 must return string but invocation 2 returned boolean",
+            "Argument 0 must return expression->number|expression->string \
+             but invocation 2 returned boolean",
             error.to_string()
         );
     }
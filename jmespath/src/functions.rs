@@ -4,19 +4,35 @@ use std::cmp::{max, min};
 use std::collections::BTreeMap;
 use std::fmt;
 
-use crate::interpreter::{interpret, SearchResult};
+use crate::interpreter::{
+    as_integer, check_float_overflow, check_overflow, integer_variable, interpret, Integer,
+    SearchResult,
+};
 use crate::variable::{JmespathType, Variable};
-use crate::{Context, ErrorReason, JmespathError, Rcvar, RuntimeError};
+use crate::{Context, ErrorReason, JmespathError, Overflow, Rcvar, RuntimeError};
 use serde_json::Number;
 
 /// Represents a JMESPath function.
 pub trait Function: Sync + Send {
     /// Evaluates the function against an in-memory variable.
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult;
+
+    /// Returns the signature of the function, if it has one.
+    ///
+    /// The signature is used to statically analyze expressions before they
+    /// are evaluated. Functions that validate their own arguments may
+    /// return `None`.
+    fn signature(&self) -> Option<&Signature> {
+        None
+    }
 }
 
 /// Function argument types used when validating.
+///
+/// Crate features add variants, so matches outside this crate need a
+/// wildcard arm.
 #[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
 pub enum ArgumentType {
     Any,
     Null,
@@ -26,6 +42,14 @@ pub enum ArgumentType {
     Object,
     Array,
     Expref,
+    /// Requires the `datetime` feature.
+    #[cfg(feature = "datetime")]
+    DateTime,
+    /// Requires the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    Bytes,
+    /// An external value with the given type name.
+    External(&'static str),
     /// Each element of the array must matched the provided type.
     TypedArray(Box<ArgumentType>),
     /// Accepts one of a number of `ArgumentType`s
@@ -45,6 +69,11 @@ impl ArgumentType {
             Bool if value.is_boolean() => true,
             Expref if value.is_expref() => true,
             Array if value.is_array() => true,
+            #[cfg(feature = "datetime")]
+            DateTime if value.is_datetime() => true,
+            #[cfg(feature = "bytes")]
+            Bytes if value.is_bytes() => true,
+            External(name) => value.get_type() == JmespathType::External(name),
             TypedArray(ref t) if value.is_array() => {
                 if let Some(array) = value.as_array() {
                     array.iter().all(|v| t.is_valid(v))
@@ -70,6 +99,11 @@ impl fmt::Display for ArgumentType {
             Object => write!(fmt, "object"),
             Null => write!(fmt, "null"),
             Expref => write!(fmt, "expref"),
+            #[cfg(feature = "datetime")]
+            DateTime => write!(fmt, "datetime"),
+            #[cfg(feature = "bytes")]
+            Bytes => write!(fmt, "bytes"),
+            External(name) => write!(fmt, "{}", name),
             TypedArray(ref t) => write!(fmt, "array[{}]", t),
             Union(ref types) => {
                 let str_value = types
@@ -83,6 +117,26 @@ impl fmt::Display for ArgumentType {
     }
 }
 
+/// Converts the type of a value into the argument type that accepts it.
+impl From<JmespathType> for ArgumentType {
+    fn from(t: JmespathType) -> Self {
+        match t {
+            JmespathType::Null => ArgumentType::Null,
+            JmespathType::String => ArgumentType::String,
+            JmespathType::Number => ArgumentType::Number,
+            JmespathType::Boolean => ArgumentType::Bool,
+            JmespathType::Array => ArgumentType::Array,
+            JmespathType::Object => ArgumentType::Object,
+            JmespathType::Expref => ArgumentType::Expref,
+            #[cfg(feature = "datetime")]
+            JmespathType::DateTime => ArgumentType::DateTime,
+            #[cfg(feature = "bytes")]
+            JmespathType::Bytes => ArgumentType::Bytes,
+            JmespathType::External(name) => ArgumentType::External(name),
+        }
+    }
+}
+
 macro_rules! arg {
     (any) => (ArgumentType::Any);
     (null) => (ArgumentType::Null);
@@ -94,23 +148,51 @@ macro_rules! arg {
     (array_number) => (ArgumentType::TypedArray(Box::new(ArgumentType::Number)));
     (array_string) => (ArgumentType::TypedArray(Box::new(ArgumentType::String)));
     (array) => (ArgumentType::Array);
+    (datetime) => (ArgumentType::DateTime);
+    (bytes) => (ArgumentType::Bytes);
     ($($x:ident) | *) => (ArgumentType::Union(vec![$(arg!($x)), *]));
 }
 
+/// Types that can be converted to a string by `to_string()` and `concat()`.
+fn stringable() -> ArgumentType {
+    #[allow(unused_mut)]
+    let mut types = vec![
+        arg!(object),
+        arg!(array),
+        arg!(bool),
+        arg!(number),
+        arg!(string),
+        arg!(null),
+    ];
+    #[cfg(feature = "datetime")]
+    types.push(arg!(datetime));
+    #[cfg(feature = "bytes")]
+    types.push(arg!(bytes));
+    ArgumentType::Union(types)
+}
+
+/// Types that have a length according to `length()`.
+fn sized() -> ArgumentType {
+    #[cfg(feature = "bytes")]
+    return arg!(array | object | string | bytes);
+    #[cfg(not(feature = "bytes"))]
+    return arg!(array | object | string);
+}
+
+/// Closure invoked by a `CustomFunction` once its signature is validated.
+pub type CustomFunctionFn = dyn Fn(&[Rcvar], &mut Context<'_>) -> SearchResult + Sync + Send;
+
 /// Custom function that allows the creation of runtime functions with signature validation.
 pub struct CustomFunction {
     /// Signature used to validate the function.
     signature: Signature,
     /// Function to invoke after validating the signature.
-    f: Box<dyn Fn(&[Rcvar], &mut Context<'_>) -> SearchResult + Sync + Send>,
+    f: Box<CustomFunctionFn>,
 }
 
 impl CustomFunction {
     /// Creates a new custom function.
-    pub fn new(
-        fn_signature: Signature,
-        f: Box<dyn Fn(&[Rcvar], &mut Context<'_>) -> SearchResult + Sync + Send>,
-    ) -> CustomFunction {
+    pub fn new(fn_signature: Signature, f: Box<CustomFunctionFn>) -> CustomFunction {
         CustomFunction {
             signature: fn_signature,
             f,
@@ -119,6 +201,10 @@ impl CustomFunction {
 }
 
 impl Function for CustomFunction {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         (self.f)(args, ctx)
@@ -139,56 +225,121 @@ where
     }
 }
 
+/// Types accepted by the trailing arguments of a variadic function.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Variadic {
+    /// Any number of additional arguments of the same type.
+    Repeat(ArgumentType),
+    /// Additional arguments whose types cycle through the given list
+    /// (e.g., string, any, string, any, ... for key/value pairs). Arguments
+    /// must be provided in complete groups. An empty list accepts no
+    /// additional arguments.
+    Cycle(Vec<ArgumentType>),
+}
+
+impl Variadic {
+    /// Returns the type expected at the given offset into the variadic tail.
+    pub fn type_at(&self, index: usize) -> Option<&ArgumentType> {
+        match *self {
+            Variadic::Repeat(ref t) => Some(t),
+            Variadic::Cycle(ref types) if types.is_empty() => None,
+            Variadic::Cycle(ref types) => types.get(index % types.len()),
+        }
+    }
+
+    /// Returns the number of arguments that make up a complete group.
+    fn group_len(&self) -> usize {
+        match *self {
+            Variadic::Repeat(_) => 1,
+            Variadic::Cycle(ref types) => types.len(),
+        }
+    }
+}
+
 /// Represents a function's signature.
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Signature {
     pub inputs: Vec<ArgumentType>,
-    pub variadic: Option<ArgumentType>,
+    pub variadic: Option<Variadic>,
 }
 
 impl Signature {
     /// Creates a new Signature struct.
+    ///
+    /// When `variadic` is provided, any number of additional arguments of
+    /// that type are accepted after `inputs`.
     pub fn new(inputs: Vec<ArgumentType>, variadic: Option<ArgumentType>) -> Signature {
-        Signature { inputs, variadic }
+        Signature {
+            inputs,
+            variadic: variadic.map(Variadic::Repeat),
+        }
     }
 
-    /// Validates the arity of a function. If the arity is invalid, a runtime
-    /// error is returned with the relative position of the error and the
-    /// expression that was being executed.
-    pub fn validate_arity(&self, actual: usize, ctx: &Context<'_>) -> Result<(), JmespathError> {
-        let expected = self.inputs.len();
-        if self.variadic.is_some() {
-            if actual >= expected {
+    /// Creates a new Signature struct that accepts a variadic tail.
+    pub fn with_variadic(inputs: Vec<ArgumentType>, variadic: Variadic) -> Signature {
+        Signature {
+            inputs,
+            variadic: Some(variadic),
+        }
+    }
+
+    /// Returns the type expected for the argument at the given position.
+    pub fn type_at(&self, position: usize) -> Option<&ArgumentType> {
+        match self.inputs.get(position) {
+            Some(t) => Some(t),
+            None => self
+                .variadic
+                .as_ref()
+                .and_then(|v| v.type_at(position - self.inputs.len())),
+        }
+    }
+
+    /// Checks the number of provided arguments against the signature,
+    /// returning the arity error that would be raised if it is invalid.
+    pub fn check_arity(&self, actual: usize) -> Result<(), RuntimeError> {
+        let inputs = self.inputs.len();
+        let group = self.variadic.as_ref().map_or(0, Variadic::group_len);
+        if actual < inputs {
+            Err(RuntimeError::NotEnoughArguments {
+                expected: inputs,
+                actual,
+            })
+        } else if group == 0 {
+            if actual == inputs {
                 Ok(())
             } else {
-                let reason =
-                    ErrorReason::Runtime(RuntimeError::NotEnoughArguments { expected, actual });
-                Err(JmespathError::from_ctx(ctx, reason))
+                Err(RuntimeError::TooManyArguments {
+                    expected: inputs,
+                    actual,
+                })
             }
-        } else if actual == expected {
-            Ok(())
-        } else if actual < expected {
-            let reason =
-                ErrorReason::Runtime(RuntimeError::NotEnoughArguments { expected, actual });
-            Err(JmespathError::from_ctx(ctx, reason))
         } else {
-            let reason = ErrorReason::Runtime(RuntimeError::TooManyArguments { expected, actual });
-            Err(JmespathError::from_ctx(ctx, reason))
+            // Variadic groups must be complete (e.g., no dangling keys).
+            match (actual - inputs) % group {
+                0 => Ok(()),
+                partial => Err(RuntimeError::NotEnoughArguments {
+                    expected: actual + group - partial,
+                    actual,
+                }),
+            }
         }
     }
 
+    /// Validates the arity of a function. If the arity is invalid, a runtime
+    /// error is returned with the relative position of the error and the
+    /// expression that was being executed.
+    pub fn validate_arity(&self, actual: usize, ctx: &Context<'_>) -> Result<(), JmespathError> {
+        self.check_arity(actual)
+            .map_err(|e| JmespathError::from_ctx(ctx, ErrorReason::Runtime(e)))
+    }
+
     /// Validates the provided function arguments against the signature.
     pub fn validate(&self, args: &[Rcvar], ctx: &Context<'_>) -> Result<(), JmespathError> {
         self.validate_arity(args.len(), ctx)?;
-        if let Some(ref variadic) = self.variadic {
-            for (k, v) in args.iter().enumerate() {
-                let validator = self.inputs.get(k).unwrap_or(variadic);
+        for (k, v) in args.iter().enumerate() {
+            if let Some(validator) = self.type_at(k) {
                 self.validate_arg(ctx, k, v, validator)?;
             }
-        } else {
-            for (k, v) in args.iter().enumerate() {
-                self.validate_arg(ctx, k, v, &self.inputs[k])?;
-            }
         }
         Ok(())
     }
@@ -204,8 +355,9 @@ impl Signature {
             Ok(())
         } else {
             let reason = ErrorReason::Runtime(RuntimeError::InvalidType {
-                expected: validator.to_string(),
-                actual: value.get_type().to_string(),
+                function: None,
+                expected: validator.clone(),
+                actual: value.get_type(),
                 position,
             });
             Err(JmespathError::from_ctx(ctx, reason))
@@ -236,6 +388,57 @@ macro_rules! defn {
     };
 }
 
+/// Creates the error returned when a validated argument has an unexpected shape.
+fn internal_error(message: &str) -> JmespathError {
+    JmespathError::new("", 0, ErrorReason::Parse(message.to_owned()))
+}
+
+/// Creates the error returned when the argument at `position` has a value
+/// the function cannot accept.
+fn invalid_value(ctx: &Context<'_>, position: usize, message: &str) -> JmespathError {
+    JmespathError::from_ctx(
+        ctx,
+        ErrorReason::Runtime(RuntimeError::InvalidValue {
+            function: None,
+            position,
+            message: message.to_owned(),
+        }),
+    )
+}
+
+/// Returns an error if more than `max` arguments were provided.
+fn check_max_arguments(args: &[Rcvar], max: usize, ctx: &Context<'_>) -> Result<(), JmespathError> {
+    if args.len() > max {
+        return Err(JmespathError::from_ctx(
+            ctx,
+            ErrorReason::Runtime(RuntimeError::TooManyArguments {
+                expected: max,
+                actual: args.len(),
+            }),
+        ));
+    }
+    Ok(())
+}
+
+/// Converts an optional count argument into a non-negative integer.
+fn count_arg(
+    args: &[Rcvar],
+    position: usize,
+    ctx: &Context<'_>,
+) -> Result<Option<usize>, JmespathError> {
+    match args.get(position) {
+        None => Ok(None),
+        Some(value) => match value.as_number() {
+            Some(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n as usize)),
+            _ => Err(invalid_value(
+                ctx,
+                position,
+                "Expected a non-negative integer",
+            )),
+        },
+    }
+}
+
 /// Macro used to implement max_by and min_by functions.
 macro_rules! min_and_max_by {
     ($ctx:expr, $operator:ident, $args:expr) => {{
@@ -264,8 +467,9 @@ macro_rules! min_and_max_by {
             return Err(JmespathError::from_ctx(
                 $ctx,
                 ErrorReason::Runtime(RuntimeError::InvalidReturnType {
-                    expected: "expression->number|expression->string".to_owned(),
-                    actual: entered_type.to_string(),
+                    function: None,
+                    expected: ArgumentType::Union(vec![ArgumentType::Number, ArgumentType::String]),
+                    actual: entered_type,
                     position: 1,
                     invocation: 1,
                 }),
@@ -279,8 +483,9 @@ macro_rules! min_and_max_by {
                 return Err(JmespathError::from_ctx(
                     $ctx,
                     ErrorReason::Runtime(RuntimeError::InvalidReturnType {
-                        expected: format!("expression->{}", entered_type),
-                        actual: mapped.get_type().to_string(),
+                        function: None,
+                        expected: ArgumentType::from(entered_type),
+                        actual: mapped.get_type(),
                         position: 1,
                         invocation,
                     }),
@@ -316,9 +521,81 @@ macro_rules! min_and_max {
     }};
 }
 
+/// Macro used to implement functions that transform the case of a string.
+macro_rules! case_fn {
+    ($name:ident, $transform:expr) => {
+        defn!($name, vec![arg!(string)], None);
+
+        impl Function for $name {
+            fn signature(&self) -> Option<&Signature> {
+                Some(&self.signature)
+            }
+
+            fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+                self.signature.validate(args, ctx)?;
+                let s = args[0]
+                    .as_string()
+                    .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+                let transform: fn(&str) -> String = $transform;
+                Ok(Rcvar::new(Variable::String(transform(s))))
+            }
+        }
+    };
+}
+
+/// Splits a string into words for `snake_case` and `camel_case`.
+///
+/// Words are separated by characters that are not alphanumeric, by an
+/// uppercase letter that follows a lowercase letter or a digit, and by the
+/// last uppercase letter of an acronym that is followed by a lowercase
+/// letter (e.g., `HTTPServer` is split into `HTTP` and `Server`).
+fn words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = vec![];
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() {
+            if let Some(prev) = current.chars().last() {
+                let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if prev.is_lowercase() || prev.is_numeric() || prev.is_uppercase() && next_is_lower
+                {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// Uppercases the first character of a string and lowercases the rest.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.as_str().to_lowercase().chars())
+            .collect(),
+        None => String::new(),
+    }
+}
+
 defn!(AbsFn, vec![arg!(number)], None);
 
 impl Function for AbsFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         match args[0].as_ref() {
@@ -342,14 +619,67 @@ impl Function for AbsFn {
                     )
                 })?,
             ))),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(d) => Ok(Rcvar::new(Variable::Decimal(d.abs()))),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(i) => Ok(Rcvar::new(Variable::from_i128(i.saturating_abs()))),
             _ => Ok(args[0].clone()),
         }
     }
 }
 
+/// Sums numbers for `sum()` and `avg()`, applying the overflow policy.
+///
+/// Integers are summed as integers when a policy other than
+/// `Overflow::Float` is in effect, or exactly when the `big-integers`
+/// feature is enabled. Other numbers are summed as floats.
+fn sum_numbers(values: &[Rcvar], ctx: &Context<'_>) -> Result<Variable, JmespathError> {
+    let integers: Option<Vec<Integer>> = values.iter().map(|v| as_integer(v)).collect();
+    if let Some(integers) = integers {
+        let sum = match ctx.options.overflow {
+            Overflow::Float if !cfg!(feature = "big-integers") => None,
+            _ => check_overflow(
+                integers
+                    .iter()
+                    .try_fold(0, |acc: Integer, &i| acc.checked_add(i)),
+                || {
+                    integers
+                        .iter()
+                        .fold(0, |acc: Integer, &i| acc.wrapping_add(i))
+                },
+                || {
+                    integers
+                        .iter()
+                        .fold(0, |acc: Integer, &i| acc.saturating_add(i))
+                },
+                ctx,
+            )?,
+        };
+        if let Some(sum) = sum {
+            return Ok(integer_variable(sum));
+        }
+    }
+    let sum = values
+        .iter()
+        .fold(0.0, |acc, item| acc + item.as_number().unwrap_or(0.0));
+    Number::from_f64(check_float_overflow(sum, ctx)?)
+        .map(Variable::Number)
+        .ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected to be a valid number".to_owned()),
+            )
+        })
+}
+
 defn!(AvgFn, vec![arg!(array_number)], None);
 
 impl Function for AvgFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let values = args[0].as_array().ok_or_else(|| {
@@ -360,17 +690,13 @@ impl Function for AvgFn {
             )
         })?;
 
-        let mut sum = 0.0;
-
-        for value in values {
-            sum += value.as_number().ok_or_else(|| {
-                JmespathError::new(
-                    "",
-                    0,
-                    ErrorReason::Parse("Expected to be a valid f64".to_owned()),
-                )
-            })?;
-        }
+        let sum = sum_numbers(values, ctx)?.as_number().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected to be a valid f64".to_owned()),
+            )
+        })?;
 
         Ok(Rcvar::new(Variable::Number(
             Number::from_f64(sum / (values.len() as f64)).ok_or_else(|| {
@@ -384,9 +710,131 @@ impl Function for AvgFn {
     }
 }
 
+/// Returns every combination that takes one element from each list, in
+/// lexicographic order.
+fn cartesian_product(lists: &[&Vec<Rcvar>]) -> Vec<Rcvar> {
+    let mut tuples: Vec<Vec<Rcvar>> = vec![vec![]];
+    for list in lists {
+        tuples = tuples
+            .iter()
+            .flat_map(|tuple| {
+                list.iter().map(move |value| {
+                    let mut tuple = tuple.clone();
+                    tuple.push(value.clone());
+                    tuple
+                })
+            })
+            .collect();
+    }
+    tuples
+        .into_iter()
+        .map(|tuple| Rcvar::new(Variable::Array(tuple)))
+        .collect()
+}
+
+// Encodes a string (as UTF-8) or bytes using standard, padded base64.
+#[cfg(feature = "bytes")]
+defn!(Base64EncodeFn, vec![arg!(string | bytes)], None);
+
+#[cfg(feature = "bytes")]
+impl Function for Base64EncodeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        use base64::Engine;
+        self.signature.validate(args, ctx)?;
+        let bytes = match *args[0] {
+            Variable::String(ref s) => s.as_bytes(),
+            Variable::Bytes(ref b) => b.as_slice(),
+            _ => return Err(internal_error("Expected args[0] to be a string or bytes")),
+        };
+        Ok(Rcvar::new(Variable::String(
+            base64::engine::general_purpose::STANDARD.encode(bytes),
+        )))
+    }
+}
+
+// Decodes a standard base64 string, with or without padding, into bytes.
+// Returns null if the string is not valid base64.
+#[cfg(feature = "bytes")]
+defn!(Base64DecodeFn, vec![arg!(string)], None);
+
+#[cfg(feature = "bytes")]
+impl Function for Base64DecodeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose};
+        use base64::Engine;
+        self.signature.validate(args, ctx)?;
+        let s = args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        let engine = GeneralPurpose::new(
+            &base64::alphabet::STANDARD,
+            general_purpose::PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+        );
+        Ok(Rcvar::new(
+            engine.decode(s).map_or(Variable::Null, Variable::Bytes),
+        ))
+    }
+}
+
+// Converts a string to lowerCamelCase.
+case_fn!(CamelCaseFn, |s| {
+    words(s)
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            if i == 0 {
+                word.to_lowercase()
+            } else {
+                capitalize(word)
+            }
+        })
+        .collect()
+});
+
+// Uppercases the first character of a string and lowercases the rest.
+case_fn!(CapitalizeFn, capitalize);
+
+// Returns the pairs (or tuples, when more arrays are provided) combining
+// an element of each array.
+defn!(
+    CartesianProductFn,
+    vec![arg!(array), arg!(array)],
+    Some(arg!(array))
+);
+
+impl Function for CartesianProductFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let lists = args
+            .iter()
+            .map(|arg| {
+                arg.as_array()
+                    .ok_or_else(|| internal_error("Expected arguments to be arrays"))
+            })
+            .collect::<Result<Vec<_>, JmespathError>>()?;
+        Ok(Rcvar::new(Variable::Array(cartesian_product(&lists))))
+    }
+}
+
 defn!(CeilFn, vec![arg!(number)], None);
 
 impl Function for CeilFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let n = args[0].as_number().ok_or_else(|| {
@@ -408,9 +856,77 @@ impl Function for CeilFn {
     }
 }
 
+/// Removes the elements of an array or the entries of an object whose values
+/// match `remove`.
+fn retain_values(value: &Rcvar, remove: fn(&Variable) -> bool) -> SearchResult {
+    match **value {
+        Variable::Array(ref array) => Ok(Rcvar::new(Variable::Array(
+            array.iter().filter(|v| !remove(v)).cloned().collect(),
+        ))),
+        Variable::Object(ref object) => Ok(Rcvar::new(Variable::Object(
+            object
+                .iter()
+                .filter(|(_, v)| !remove(v))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        ))),
+        _ => Err(internal_error("Expected args[0] to be an array or object")),
+    }
+}
+
+// Removes null elements of an array or null values of an object.
+defn!(CompactFn, vec![arg!(array | object)], None);
+
+impl Function for CompactFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        retain_values(&args[0], Variable::is_null)
+    }
+}
+
+// Concatenates the string form of each argument. Strings are used as-is,
+// null contributes an empty string, numbers are formatted using the
+// `number_format` search option, and booleans, arrays, and objects are
+// serialized as JSON, matching `to_string()`.
+defn!(ConcatFn, vec![stringable()], Some(stringable()));
+
+impl Function for ConcatFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let mut result = String::new();
+        for arg in args {
+            match **arg {
+                Variable::String(ref s) => result.push_str(s),
+                Variable::Null => {}
+                Variable::Number(ref n) => result.push_str(&ctx.options.number_format.format(n)),
+                #[cfg(feature = "datetime")]
+                Variable::DateTime(ref d) => result.push_str(&d.to_rfc3339()),
+                #[cfg(feature = "bytes")]
+                Variable::Bytes(ref b) => result.push_str(&String::from_utf8_lossy(b)),
+                #[cfg(feature = "decimal")]
+                Variable::Decimal(ref d) => result.push_str(&d.to_string()),
+                _ => result.push_str(&arg.to_string()),
+            }
+        }
+        Ok(Rcvar::new(Variable::String(result)))
+    }
+}
+
 defn!(ContainsFn, vec![arg!(string | array), arg!(any)], None);
 
 impl Function for ContainsFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let haystack = &args[0];
@@ -426,9 +942,75 @@ impl Function for ContainsFn {
     }
 }
 
+/// Merges `source` into `target`, merging nested objects present in both
+/// rather than replacing them.
+fn deep_merge(target: &mut BTreeMap<String, Rcvar>, source: &BTreeMap<String, Rcvar>) {
+    for (key, value) in source {
+        let merged = match (
+            target.get(key).and_then(|v| v.as_object()),
+            value.as_object(),
+        ) {
+            (Some(existing), Some(object)) => {
+                let mut existing = existing.clone();
+                deep_merge(&mut existing, object);
+                Rcvar::new(Variable::Object(existing))
+            }
+            _ => value.clone(),
+        };
+        target.insert(key.clone(), merged);
+    }
+}
+
+// Merges objects like `merge()`, except that objects nested under the same
+// key are merged recursively. Values other than objects, including arrays,
+// are replaced by the value from the rightmost object.
+defn!(DeepMergeFn, vec![arg!(object)], Some(arg!(object)));
+
+impl Function for DeepMergeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let mut result = BTreeMap::new();
+        for arg in args {
+            let object = arg
+                .as_object()
+                .ok_or_else(|| internal_error("Expected to be a valid Object"))?;
+            deep_merge(&mut result, object);
+        }
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+// Returns the first argument unless it is null, in which case the
+// expression is evaluated against the current node and its result is
+// returned. Unlike `not_null`, the fallback is only evaluated when needed.
+defn!(DefaultFn, vec![arg!(any), arg!(expref)], None);
+
+impl Function for DefaultFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        if !args[0].is_null() {
+            return Ok(args[0].clone());
+        }
+        let current = ctx.current().clone();
+        ctx.evaluate(&args[1], &current)
+    }
+}
+
 defn!(EndsWithFn, vec![arg!(string), arg!(string)], None);
 
 impl Function for EndsWithFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let subject = args[0].as_string().ok_or_else(|| {
@@ -449,9 +1031,127 @@ impl Function for EndsWithFn {
     }
 }
 
+// Returns the value of an environment variable of the current process, or
+// null if the variable is not set or is not valid Unicode.
+#[cfg(feature = "env")]
+defn!(EnvFn, vec![arg!(string)], None);
+
+#[cfg(feature = "env")]
+impl Function for EnvFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let name = args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        Ok(Rcvar::new(
+            std::env::var(name).map_or(Variable::Null, Variable::String),
+        ))
+    }
+}
+
+// Parses the expression string and evaluates it against the second argument
+// within the calling search, so its options, depth limit, variables and
+// root value all apply to the evaluated expression.
+#[cfg(feature = "eval")]
+defn!(EvalFn, vec![arg!(string), arg!(any)], None);
+
+/// Number of nesting levels counted against `SearchOptions::max_depth` for
+/// each call to `eval()`, which uses far more stack than other nodes.
+#[cfg(feature = "eval")]
+const EVAL_DEPTH: usize = 8;
+
+#[cfg(feature = "eval")]
+impl Function for EvalFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let expression = args[0].as_string().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected args[0] to be a string".to_owned()),
+            )
+        })?;
+        let ast = crate::parse(expression)?;
+        // Errors raised while evaluating the AST are located in the
+        // evaluated expression rather than the calling one.
+        let caller = ctx.expression;
+        ctx.depth += EVAL_DEPTH;
+        let result = interpret(&args[1], &ast, ctx);
+        ctx.depth -= EVAL_DEPTH;
+        result.map_err(|err| {
+            if err.expression == caller {
+                JmespathError::new(expression, err.offset, err.reason)
+            } else {
+                err
+            }
+        })
+    }
+}
+
+/// Inserts the entries of `object` into `flat`, joining nested keys to
+/// `prefix` with `separator`.
+fn flatten_keys(
+    object: &BTreeMap<String, Rcvar>,
+    prefix: &str,
+    separator: &str,
+    flat: &mut BTreeMap<String, Rcvar>,
+) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}{}{}", prefix, separator, key)
+        };
+        match value.as_object() {
+            Some(nested) if !nested.is_empty() => flatten_keys(nested, &key, separator, flat),
+            _ => {
+                flat.insert(key, value.clone());
+            }
+        }
+    }
+}
+
+// Flattens nested objects into a single object whose keys are the paths of
+// the nested values joined with a separator (`.` by default). Arrays and
+// empty objects are kept as values.
+defn!(FlattenKeysFn, vec![arg!(object)], Some(arg!(string)));
+
+impl Function for FlattenKeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        let object = args[0]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[0] to be an object"))?;
+        let separator = args
+            .get(1)
+            .and_then(|s| s.as_string())
+            .map_or(".", |s| s.as_str());
+        let mut flat = BTreeMap::new();
+        flatten_keys(object, "", separator, &mut flat);
+        Ok(Rcvar::new(Variable::Object(flat)))
+    }
+}
+
 defn!(FloorFn, vec![arg!(number)], None);
 
 impl Function for FloorFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let n = args[0].as_number().ok_or_else(|| {
@@ -473,19 +1173,92 @@ impl Function for FloorFn {
     }
 }
 
-defn!(JoinFn, vec![arg!(string), arg!(array_string)], None);
+// Builds an object from an array of `{"key": ..., "value": ...}` objects,
+// reversing `to_entries()`. As in jq, the key may also be given as `k` or
+// `name` and the value as `v`; numbers and booleans are used as string keys
+// and a missing value is null.
+defn!(FromEntriesFn, vec![arg!(array)], None);
+
+impl Function for FromEntriesFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
 
-impl Function for JoinFn {
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
-        let glue = args[0].as_string().ok_or_else(|| {
-            JmespathError::new(
-                "",
-                0,
-                ErrorReason::Parse("Expected args[0] to be a valid string".to_owned()),
-            )
-        })?;
-        let values = args[1].as_array().ok_or_else(|| {
+        let entries = args[0]
+            .as_array()
+            .ok_or_else(|| internal_error("Expected args[0] to be an array"))?;
+        let mut result = BTreeMap::new();
+        for entry in entries {
+            let field = |names: &[&str]| {
+                entry
+                    .as_object()
+                    .and_then(|o| names.iter().find_map(|name| o.get(*name)))
+            };
+            let key = match field(&["key", "k", "name"]).map(|key| &**key) {
+                Some(Variable::String(s)) => s.clone(),
+                Some(key @ Variable::Number(_)) | Some(key @ Variable::Bool(_)) => key.to_string(),
+                _ => {
+                    return Err(invalid_value(
+                        ctx,
+                        0,
+                        "Expected an array of {key, value} objects",
+                    ))
+                }
+            };
+            let value = field(&["value", "v"])
+                .cloned()
+                .unwrap_or_else(|| Rcvar::new(Variable::Null));
+            result.insert(key, value);
+        }
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+// Evaluates the first expression against the current node if the condition
+// is truthy, and otherwise the second expression, or returns null when it
+// is omitted. Only the chosen expression is evaluated.
+defn!(IfFn, vec![arg!(any), arg!(expref)], Some(arg!(expref)));
+
+impl Function for IfFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 3, ctx)?;
+        let branch = if args[0].is_truthy() {
+            &args[1]
+        } else {
+            match args.get(2) {
+                Some(branch) => branch,
+                None => return Ok(Rcvar::new(Variable::Null)),
+            }
+        };
+        let current = ctx.current().clone();
+        ctx.evaluate(branch, &current)
+    }
+}
+
+defn!(JoinFn, vec![arg!(string), arg!(array_string)], None);
+
+impl Function for JoinFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let glue = args[0].as_string().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected args[0] to be a valid string".to_owned()),
+            )
+        })?;
+        let values = args[1].as_array().ok_or_else(|| {
             JmespathError::new(
                 "",
                 0,
@@ -512,6 +1285,10 @@ impl Function for JoinFn {
 defn!(KeysFn, vec![arg!(object)], None);
 
 impl Function for KeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let object = args[0].as_object().ok_or_else(|| {
@@ -529,9 +1306,13 @@ impl Function for KeysFn {
     }
 }
 
-defn!(LengthFn, vec![arg!(array | object | string)], None);
+defn!(LengthFn, vec![sized()], None);
 
 impl Function for LengthFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         match args[0].as_ref() {
@@ -541,14 +1322,23 @@ impl Function for LengthFn {
             Variable::String(ref s) => Ok(Rcvar::new(Variable::Number(Number::from(
                 s.chars().count(),
             )))),
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) => Ok(Rcvar::new(Variable::Number(Number::from(b.len())))),
             _ => unreachable!(),
         }
     }
 }
 
+// Converts a string to lowercase using Unicode case mapping.
+case_fn!(LowerFn, |s| s.to_lowercase());
+
 defn!(MapFn, vec![arg!(expref), arg!(array)], None);
 
 impl Function for MapFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let ast = args[0].as_expref().ok_or_else(|| {
@@ -573,9 +1363,112 @@ impl Function for MapFn {
     }
 }
 
+// Rewrites every key of an object by evaluating an expression against the
+// key, which must yield a string. When keys are rewritten to the same key,
+// the value of the lexicographically last original key is kept.
+defn!(MapKeysFn, vec![arg!(expref), arg!(object)], None);
+
+impl Function for MapKeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let ast = args[0]
+            .as_expref()
+            .ok_or_else(|| internal_error("Expected args[0] to be an expref"))?;
+        let object = args[1]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[1] to be an object"))?;
+        let mut result = BTreeMap::new();
+        for (invocation, (key, value)) in object.iter().enumerate() {
+            let mapped = interpret(&Rcvar::new(Variable::String(key.clone())), ast, ctx)?;
+            match *mapped {
+                Variable::String(ref new_key) => {
+                    result.insert(new_key.clone(), value.clone());
+                }
+                _ => {
+                    let reason = ErrorReason::Runtime(RuntimeError::InvalidReturnType {
+                        function: None,
+                        expected: ArgumentType::String,
+                        actual: mapped.get_type(),
+                        position: 0,
+                        invocation: invocation + 1,
+                    });
+                    return Err(JmespathError::from_ctx(ctx, reason));
+                }
+            }
+        }
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+/// Returns true if `text` matches a glob pattern, where `*` matches any
+/// run of characters and `?` matches a single character.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), text.chars().collect());
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it is matched up to.
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// Returns the values of an object whose keys match a glob pattern, ordered
+// by key.
+defn!(MatchKeysFn, vec![arg!(object), arg!(string)], None);
+
+impl Function for MatchKeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let object = args[0]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[0] to be an object"))?;
+        let pattern = args[1]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[1] to be a string"))?;
+        Ok(Rcvar::new(Variable::Array(
+            object
+                .iter()
+                .filter(|(key, _)| glob_match(pattern, key))
+                .map(|(_, value)| value.clone())
+                .collect(),
+        )))
+    }
+}
+
 defn!(MaxFn, vec![arg!(array_string | array_number)], None);
 
 impl Function for MaxFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         min_and_max!(max, args)
@@ -585,6 +1478,10 @@ impl Function for MaxFn {
 defn!(MinFn, vec![arg!(array_string | array_number)], None);
 
 impl Function for MinFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         min_and_max!(min, args)
@@ -594,6 +1491,10 @@ impl Function for MinFn {
 defn!(MaxByFn, vec![arg!(array), arg!(expref)], None);
 
 impl Function for MaxByFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         min_and_max_by!(ctx, gt, args)
@@ -603,6 +1504,10 @@ impl Function for MaxByFn {
 defn!(MinByFn, vec![arg!(array), arg!(expref)], None);
 
 impl Function for MinByFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         min_and_max_by!(ctx, lt, args)
@@ -612,6 +1517,10 @@ impl Function for MinByFn {
 defn!(MergeFn, vec![arg!(object)], Some(arg!(object)));
 
 impl Function for MergeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let mut result = BTreeMap::new();
@@ -632,9 +1541,50 @@ impl Function for MergeFn {
     }
 }
 
+/// Applies a JSON Merge Patch (RFC 7386) to `target`.
+fn merge_patch(target: &Rcvar, patch: &Rcvar) -> Rcvar {
+    let patch = match patch.as_object() {
+        Some(patch) => patch,
+        None => return patch.clone(),
+    };
+    let mut result = target.as_object().cloned().unwrap_or_default();
+    for (key, value) in patch {
+        if value.is_null() {
+            result.remove(key);
+        } else {
+            let patched = match result.get(key) {
+                Some(existing) => merge_patch(existing, value),
+                None => merge_patch(&Rcvar::new(Variable::Null), value),
+            };
+            result.insert(key.clone(), patched);
+        }
+    }
+    Rcvar::new(Variable::Object(result))
+}
+
+// Applies a JSON Merge Patch (RFC 7386) to the target: objects in the patch
+// are merged recursively, null values delete keys, and any other value
+// replaces the target.
+defn!(MergePatchFn, vec![arg!(any), arg!(any)], None);
+
+impl Function for MergePatchFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        Ok(merge_patch(&args[0], &args[1]))
+    }
+}
+
 defn!(NotNullFn, vec![arg!(any)], Some(arg!(any)));
 
 impl Function for NotNullFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         for arg in args {
@@ -646,9 +1596,255 @@ impl Function for NotNullFn {
     }
 }
 
+// Removes null, empty string, empty array, and empty object elements of an
+// array or values of an object.
+defn!(OmitEmptyFn, vec![arg!(array | object)], None);
+
+impl Function for OmitEmptyFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        retain_values(&args[0], |v| match *v {
+            Variable::Null => true,
+            Variable::String(ref s) => s.is_empty(),
+            Variable::Array(ref a) => a.is_empty(),
+            Variable::Object(ref o) => o.is_empty(),
+            _ => false,
+        })
+    }
+}
+
+/// Appends the path of every descendant of `value` to `paths`, visiting
+/// parents before their children.
+fn collect_paths(
+    value: &Variable,
+    path: &mut Vec<Rcvar>,
+    max_depth: Option<usize>,
+    paths: &mut Vec<Rcvar>,
+) {
+    if max_depth == Some(path.len()) {
+        return;
+    }
+    let mut visit = |segment: Variable, child: &Variable, path: &mut Vec<Rcvar>| {
+        path.push(Rcvar::new(segment));
+        paths.push(Rcvar::new(Variable::Array(path.clone())));
+        collect_paths(child, path, max_depth, paths);
+        path.pop();
+    };
+    match *value {
+        Variable::Array(ref array) => {
+            for (index, child) in array.iter().enumerate() {
+                visit(Variable::Number(Number::from(index)), child, path);
+            }
+        }
+        Variable::Object(ref object) => {
+            for (key, child) in object {
+                visit(Variable::String(key.clone()), child, path);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Returns the path of every value nested in the argument as an array of
+// keys and indexes, optionally limited to paths of at most `max_depth`
+// segments. The argument itself is not included.
+defn!(PathsFn, vec![arg!(any)], Some(arg!(number)));
+
+impl Function for PathsFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        let mut paths = vec![];
+        collect_paths(&args[0], &mut vec![], count_arg(args, 1, ctx)?, &mut paths);
+        Ok(Rcvar::new(Variable::Array(paths)))
+    }
+}
+
+// Generalizes `cartesian_product` to an array of arrays. The product of
+// an empty array is an array containing a single empty tuple.
+defn!(
+    ProductFn,
+    vec![ArgumentType::TypedArray(Box::new(arg!(array)))],
+    None
+);
+
+impl Function for ProductFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let lists = args[0]
+            .as_array()
+            .ok_or_else(|| internal_error("Expected args[0] to be an array"))?
+            .iter()
+            .map(|list| {
+                list.as_array()
+                    .ok_or_else(|| internal_error("Expected args[0] to contain arrays"))
+            })
+            .collect::<Result<Vec<_>, JmespathError>>()?;
+        Ok(Rcvar::new(Variable::Array(cartesian_product(&lists))))
+    }
+}
+
+/// Maximum number of elements `range()` returns; larger ranges are an
+/// error rather than allocating without bound.
+pub const MAX_RANGE_LENGTH: usize = 1_000_000;
+
+// Returns the integers from start (inclusive) to stop (exclusive), counting
+// by step (1 by default), like Python's `range()`. A negative step counts
+// down. Ranges longer than `MAX_RANGE_LENGTH` are an error.
+defn!(
+    RangeFn,
+    vec![arg!(number), arg!(number)],
+    Some(arg!(number))
+);
+
+impl Function for RangeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 3, ctx)?;
+        let mut bounds = vec![];
+        for (position, arg) in args.iter().enumerate() {
+            match arg.as_number().and_then(as_i64) {
+                Some(n) => bounds.push(n),
+                None => return Err(invalid_value(ctx, position, "Expected an integer")),
+            }
+        }
+        let (start, stop) = (bounds[0], bounds[1]);
+        let step = bounds.get(2).copied().unwrap_or(1);
+        if step == 0 {
+            return Err(invalid_value(ctx, 2, "Step must not be 0"));
+        }
+        // Bounds are within 2^53, so the length cannot overflow an i128.
+        let (distance, step) = (i128::from(stop) - i128::from(start), i128::from(step));
+        let len = if distance.signum() == step.signum() {
+            (distance + step - step.signum()) / step
+        } else {
+            0
+        };
+        if len > MAX_RANGE_LENGTH as i128 {
+            let message = format!("Range has more than {} elements", MAX_RANGE_LENGTH);
+            return Err(invalid_value(ctx, 1, &message));
+        }
+        let values = (0..len)
+            .map(|i| {
+                let n = (i128::from(start) + i * step) as i64;
+                Rcvar::new(Variable::Number(Number::from(n)))
+            })
+            .collect();
+        Ok(Rcvar::new(Variable::Array(values)))
+    }
+}
+
+/// Converts a number into an `i64` if it is an integer.
+fn as_i64(n: f64) -> Option<i64> {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Some(n as i64)
+    } else {
+        None
+    }
+}
+
+/// Appends the value of every `key` field nested at any depth in `value`
+/// to `found`, visiting parents before their children.
+fn recursive_search(value: &Variable, key: &str, found: &mut Vec<Rcvar>) {
+    match *value {
+        Variable::Array(ref array) => {
+            for child in array {
+                recursive_search(child, key, found);
+            }
+        }
+        Variable::Object(ref object) => {
+            for (name, child) in object {
+                if name == key {
+                    found.push(child.clone());
+                }
+                recursive_search(child, key, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Returns the value of every field named `key` nested at any depth in the
+// argument, including fields nested in matched values. Object members are
+// visited in key order.
+defn!(RecursiveSearchFn, vec![arg!(any), arg!(string)], None);
+
+impl Function for RecursiveSearchFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let key = args[1]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[1] to be a string"))?;
+        let mut found = vec![];
+        recursive_search(&args[0], key, &mut found);
+        Ok(Rcvar::new(Variable::Array(found)))
+    }
+}
+
+// Folds the array into a single value. The expression is evaluated once for
+// each element against an object of the form `{"acc": ..., "item": ...}`,
+// and its result becomes the accumulator for the next element.
+defn!(ReduceFn, vec![arg!(array), arg!(expref), arg!(any)], None);
+
+impl Function for ReduceFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let values = args[0].as_array().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected args[0] to be an array".to_owned()),
+            )
+        })?;
+        let ast = args[1].as_expref().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected args[1] to be an expref".to_owned()),
+            )
+        })?;
+        let mut acc = args[2].clone();
+        for item in values {
+            let mut state = BTreeMap::new();
+            state.insert("acc".to_owned(), acc);
+            state.insert("item".to_owned(), item.clone());
+            acc = interpret(&Rcvar::new(Variable::Object(state)), ast, ctx)?;
+        }
+        Ok(acc)
+    }
+}
+
 defn!(ReverseFn, vec![arg!(array | string)], None);
 
 impl Function for ReverseFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         if args[0].is_array() {
@@ -682,9 +1878,78 @@ impl Function for ReverseFn {
     }
 }
 
+// Returns the windows of `size` consecutive elements of an array, starting
+// every `step` elements (1 by default). Windows that would extend past the
+// end of the array are omitted.
+defn!(
+    SlidingWindowFn,
+    vec![arg!(array), arg!(number)],
+    Some(arg!(number))
+);
+
+impl Function for SlidingWindowFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 3, ctx)?;
+        let values = args[0]
+            .as_array()
+            .ok_or_else(|| internal_error("Expected args[0] to be an array"))?;
+        let size = count_arg(args, 1, ctx)?.unwrap_or(0);
+        let step = count_arg(args, 2, ctx)?.unwrap_or(1);
+        if size == 0 {
+            return Err(invalid_value(ctx, 1, "Window size must not be 0"));
+        }
+        if step == 0 {
+            return Err(invalid_value(ctx, 2, "Step must not be 0"));
+        }
+        let windows = values
+            .windows(size)
+            .step_by(step)
+            .map(|window| Rcvar::new(Variable::Array(window.to_vec())))
+            .collect();
+        Ok(Rcvar::new(Variable::Array(windows)))
+    }
+}
+
+// Converts a string to snake_case.
+case_fn!(SnakeCaseFn, |s| {
+    words(s)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+});
+
+// Returns an object with its keys in lexicographic order. Objects always
+// keep their keys sorted, so the object is returned as-is, including when
+// `recursive` is true. The function allows expressions that canonicalize
+// their results to be shared with implementations that preserve insertion
+// order.
+defn!(SortKeysFn, vec![arg!(object)], Some(arg!(bool)));
+
+impl Function for SortKeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        Ok(args[0].clone())
+    }
+}
+
 defn!(SortFn, vec![arg!(array_string | array_number)], None);
 
 impl Function for SortFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let mut values = args[0]
@@ -705,6 +1970,10 @@ impl Function for SortFn {
 defn!(SortByFn, vec![arg!(array), arg!(expref)], None);
 
 impl Function for SortByFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let vals = args[0]
@@ -732,8 +2001,9 @@ impl Function for SortByFn {
         let first_type = first_value.get_type();
         if first_type != JmespathType::String && first_type != JmespathType::Number {
             let reason = ErrorReason::Runtime(RuntimeError::InvalidReturnType {
-                expected: "expression->string|expression->number".to_owned(),
-                actual: first_type.to_string(),
+                function: None,
+                expected: ArgumentType::Union(vec![ArgumentType::String, ArgumentType::Number]),
+                actual: first_type,
                 position: 1,
                 invocation: 1,
             });
@@ -746,8 +2016,9 @@ impl Function for SortByFn {
                 return Err(JmespathError::from_ctx(
                     ctx,
                     ErrorReason::Runtime(RuntimeError::InvalidReturnType {
-                        expected: format!("expression->{}", first_type),
-                        actual: mapped_value.get_type().to_string(),
+                        function: None,
+                        expected: ArgumentType::from(first_type),
+                        actual: mapped_value.get_type(),
                         position: 1,
                         invocation,
                     }),
@@ -764,6 +2035,10 @@ impl Function for SortByFn {
 defn!(StartsWithFn, vec![arg!(string), arg!(string)], None);
 
 impl Function for StartsWithFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let subject = args[0].as_string().ok_or_else(|| {
@@ -787,34 +2062,30 @@ impl Function for StartsWithFn {
 defn!(SumFn, vec![arg!(array_number)], None);
 
 impl Function for SumFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
-        let result = args[0]
-            .as_array()
-            .ok_or_else(|| {
-                JmespathError::new(
-                    "",
-                    0,
-                    ErrorReason::Parse("Expected args[0] to be an array".to_owned()),
-                )
-            })?
-            .iter()
-            .fold(0.0, |acc, item| acc + item.as_number().unwrap_or(0.0));
-        Ok(Rcvar::new(Variable::Number(
-            Number::from_f64(result).ok_or_else(|| {
-                JmespathError::new(
-                    "",
-                    0,
-                    ErrorReason::Parse("Expected to be a valid number".to_owned()),
-                )
-            })?,
-        )))
+        let values = args[0].as_array().ok_or_else(|| {
+            JmespathError::new(
+                "",
+                0,
+                ErrorReason::Parse("Expected args[0] to be an array".to_owned()),
+            )
+        })?;
+        Ok(Rcvar::new(sum_numbers(values, ctx)?))
     }
 }
 
 defn!(ToArrayFn, vec![arg!(any)], None);
 
 impl Function for ToArrayFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         match *args[0] {
@@ -824,13 +2095,48 @@ impl Function for ToArrayFn {
     }
 }
 
+// Converts an object into an array of `{"key": ..., "value": ...}` objects
+// ordered by key, so that object members can be filtered as an array.
+defn!(ToEntriesFn, vec![arg!(object)], None);
+
+impl Function for ToEntriesFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let object = args[0]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[0] to be an object"))?;
+        let entries = object
+            .iter()
+            .map(|(key, value)| {
+                let mut entry = BTreeMap::new();
+                entry.insert("key".to_owned(), Rcvar::new(Variable::String(key.clone())));
+                entry.insert("value".to_owned(), value.clone());
+                Rcvar::new(Variable::Object(entry))
+            })
+            .collect();
+        Ok(Rcvar::new(Variable::Array(entries)))
+    }
+}
+
 defn!(ToNumberFn, vec![arg!(any)], None);
 
 impl Function for ToNumberFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         match *args[0] {
             Variable::Number(_) => Ok(args[0].clone()),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(_) => Ok(args[0].clone()),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(_) => Ok(args[0].clone()),
             Variable::String(ref s) => match Variable::from_json(s) {
                 Ok(f) => Ok(Rcvar::new(f)),
                 Err(_) => Ok(Rcvar::new(Variable::Null)),
@@ -840,17 +2146,57 @@ impl Function for ToNumberFn {
     }
 }
 
-defn!(
-    ToStringFn,
-    vec![arg!(object | array | bool | number | string | null)],
-    None
-);
+// Converts a number or a string containing a decimal number into an exact
+// decimal. Returns null if the string cannot be parsed.
+#[cfg(feature = "decimal")]
+defn!(ToDecimalFn, vec![arg!(number | string)], None);
+
+#[cfg(feature = "decimal")]
+impl Function for ToDecimalFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let decimal = match *args[0] {
+            Variable::String(ref s) => {
+                let s = s.trim();
+                s.parse::<rust_decimal::Decimal>()
+                    .or_else(|_| rust_decimal::Decimal::from_scientific(s))
+                    .ok()
+            }
+            ref n => n.as_decimal(),
+        };
+        Ok(Rcvar::new(
+            decimal.map_or(Variable::Null, Variable::Decimal),
+        ))
+    }
+}
+
+defn!(ToStringFn, vec![stringable()], None);
 
 impl Function for ToStringFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         match *args[0] {
             Variable::String(_) => Ok(args[0].clone()),
+            Variable::Number(ref n) => Ok(Rcvar::new(Variable::String(
+                ctx.options.number_format.format(n),
+            ))),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(ref d) => Ok(Rcvar::new(Variable::String(d.to_rfc3339()))),
+            // Bytes are decoded as UTF-8, replacing invalid sequences.
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) => Ok(Rcvar::new(Variable::String(
+                String::from_utf8_lossy(b).into_owned(),
+            ))),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(ref d) => Ok(Rcvar::new(Variable::String(d.to_string()))),
             _ => Ok(Rcvar::new(Variable::String(args[0].to_string()))),
         }
     }
@@ -859,15 +2205,80 @@ impl Function for ToStringFn {
 defn!(TypeFn, vec![arg!(any)], None);
 
 impl Function for TypeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         Ok(Rcvar::new(Variable::String(args[0].get_type().to_string())))
     }
 }
 
+// Converts a string to uppercase using Unicode case mapping.
+/// Inserts `value` into nested objects at the path of keys, replacing any
+/// non-object value found along the path with an object.
+fn insert_nested(object: &mut BTreeMap<String, Rcvar>, path: &[&str], value: Rcvar) {
+    match path {
+        [] => {}
+        [key] => {
+            object.insert((*key).to_owned(), value);
+        }
+        [key, rest @ ..] => {
+            let child = object
+                .entry((*key).to_owned())
+                .or_insert_with(|| Rcvar::new(Variable::Object(BTreeMap::new())));
+            if !child.is_object() {
+                *child = Rcvar::new(Variable::Object(BTreeMap::new()));
+            }
+            if let Variable::Object(ref mut nested) = *Rcvar::make_mut(child) {
+                insert_nested(nested, rest, value);
+            }
+        }
+    }
+}
+
+// Reverses `flatten_keys` by splitting keys on a separator (`.` by default)
+// and nesting their values in objects. A value whose key is a prefix of
+// another key is replaced by the object nested under it.
+defn!(UnflattenKeysFn, vec![arg!(object)], Some(arg!(string)));
+
+impl Function for UnflattenKeysFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        let object = args[0]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[0] to be an object"))?;
+        let separator = args
+            .get(1)
+            .and_then(|s| s.as_string())
+            .map_or(".", |s| s.as_str());
+        if separator.is_empty() {
+            return Err(invalid_value(ctx, 1, "Separator must not be empty"));
+        }
+        let mut result = BTreeMap::new();
+        for (key, value) in object {
+            let path: Vec<&str> = key.split(separator).collect();
+            insert_nested(&mut result, &path, value.clone());
+        }
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+case_fn!(UpperFn, |s| s.to_uppercase());
+
 defn!(ValuesFn, vec![arg!(object)], None);
 
 impl Function for ValuesFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
     fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
         self.signature.validate(args, ctx)?;
         let map = args[0].as_object().ok_or_else(|| {
@@ -882,3 +2293,821 @@ impl Function for ValuesFn {
         )))
     }
 }
+
+#[cfg(feature = "community-functions")]
+mod community;
+#[cfg(feature = "community-functions")]
+pub use self::community::*;
+
+#[cfg(feature = "datetime-functions")]
+mod datetime;
+#[cfg(feature = "datetime-functions")]
+pub use self::datetime::*;
+
+#[cfg(feature = "hash-functions")]
+mod hash;
+#[cfg(feature = "hash-functions")]
+pub use self::hash::*;
+
+#[cfg(feature = "math-functions")]
+mod math;
+#[cfg(feature = "math-functions")]
+pub use self::math::*;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Runtime;
+
+    fn pairs_signature() -> Signature {
+        Signature::with_variadic(
+            vec![arg!(object)],
+            Variadic::Cycle(vec![arg!(string), arg!(any)]),
+        )
+    }
+
+    fn validate(signature: &Signature, json: &str) -> Result<(), RuntimeError> {
+        let runtime = Runtime::new();
+        let ctx = Context::new("", &runtime);
+        let args = Variable::from_json(json)
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .clone();
+        signature.validate(&args, &ctx).map_err(|e| match e.reason {
+            ErrorReason::Runtime(e) => e,
+            r => panic!("unexpected error: {}", r),
+        })
+    }
+
+    #[test]
+    fn cycles_variadic_types_by_position() {
+        let signature = pairs_signature();
+        assert_eq!(Some(&arg!(object)), signature.type_at(0));
+        assert_eq!(Some(&arg!(string)), signature.type_at(1));
+        assert_eq!(Some(&arg!(any)), signature.type_at(2));
+        assert_eq!(Some(&arg!(string)), signature.type_at(3));
+        assert!(validate(&signature, "[{}]").is_ok());
+        assert!(validate(&signature, "[{}, \"a\", 1, \"b\", [true]]").is_ok());
+    }
+
+    #[test]
+    fn cycled_variadic_errors_point_at_failing_argument() {
+        assert_eq!(
+            Err(RuntimeError::InvalidType {
+                function: None,
+                expected: arg!(string),
+                actual: JmespathType::Number,
+                position: 3,
+            }),
+            validate(&pairs_signature(), "[{}, \"a\", 1, 2, 3]")
+        );
+    }
+
+    #[test]
+    fn cycled_variadic_requires_complete_groups() {
+        assert_eq!(
+            Err(RuntimeError::NotEnoughArguments {
+                expected: 5,
+                actual: 4,
+            }),
+            validate(&pairs_signature(), "[{}, \"a\", 1, \"b\"]")
+        );
+    }
+
+    #[test]
+    fn to_string_uses_number_format_option() {
+        use crate::{compile, NumberFormat, SearchOptions};
+        let expr = compile("[to_string(`1.5`), to_string(`[1.5]`)]").unwrap();
+        let options = SearchOptions {
+            number_format: NumberFormat::Fixed(3),
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            "[\"1.5\",\"[1.5]\"]",
+            expr.search(true).unwrap().to_string()
+        );
+        assert_eq!(
+            "[\"1.500\",\"[1.5]\"]",
+            expr.search_with_options(true, &options)
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn concat_coerces_arguments_to_strings() {
+        use crate::compile;
+        let expr = compile("concat(a, '-', b, c, d, `[1]`)").unwrap();
+        let data = Variable::from_json(r#"{"a": "x", "b": 2, "c": null, "d": true}"#).unwrap();
+        assert_eq!("\"x-2true[1]\"", expr.search(data).unwrap().to_string());
+        assert_eq!(
+            "\"a\"",
+            compile("concat('a')")
+                .unwrap()
+                .search(())
+                .unwrap()
+                .to_string()
+        );
+        assert!(compile("concat(&a)").unwrap().search(()).is_err());
+    }
+
+    #[cfg(feature = "env")]
+    #[test]
+    fn env_reads_environment_variables() {
+        use crate::compile;
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        runtime.register_env_function();
+        let eval = |expr: &str| runtime.compile(expr).unwrap().search(()).unwrap();
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(Some(&path), eval("env('PATH')").as_string());
+        assert!(eval("env('JMESPATH_UNSET_VARIABLE')").is_null());
+        let err = compile("env('HOME')").unwrap().search(()).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::UnknownFunction("env".to_owned())),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn sum_and_avg_apply_overflow_policy() {
+        use crate::{compile, Overflow, SearchOptions};
+        let search = |expr: &str, overflow| {
+            let options = SearchOptions {
+                overflow,
+                ..SearchOptions::default()
+            };
+            compile(expr).unwrap().search_with_options((), &options)
+        };
+        let floats = "sum(`[1e308, 1e308]`)";
+        assert_eq!(
+            Some(f64::MAX),
+            search(floats, Overflow::Saturate).unwrap().as_number()
+        );
+        assert!(search(floats, Overflow::Wrap).is_err());
+        assert!(search(floats, Overflow::Float).is_err());
+        assert_eq!(
+            Some(2.0),
+            search("avg(`[1, 3]`)", Overflow::Error)
+                .unwrap()
+                .as_number()
+        );
+        #[cfg(not(feature = "big-integers"))]
+        {
+            let integers = "sum(`[9223372036854775807, 1]`)";
+            assert_eq!(
+                Some(9.223372036854776e18),
+                search(integers, Overflow::Float).unwrap().as_number()
+            );
+            assert_eq!(
+                "-9223372036854775808",
+                search(integers, Overflow::Wrap).unwrap().to_string()
+            );
+            assert_eq!(
+                "9223372036854775807",
+                search(integers, Overflow::Saturate).unwrap().to_string()
+            );
+            assert_eq!(
+                ErrorReason::Runtime(RuntimeError::Overflow),
+                search(integers, Overflow::Error).unwrap_err().reason
+            );
+        }
+    }
+
+    #[cfg(feature = "eval")]
+    #[test]
+    fn eval_evaluates_expression_strings() {
+        use crate::{compile, DivisionByZero, SearchOptions};
+        let data = Variable::from_json(r#"{"q": "a.b", "v": {"a": {"b": 1}}}"#).unwrap();
+        let expr = compile("eval(q, v)").unwrap();
+        assert_eq!("1", expr.search(data).unwrap().to_string());
+        let err = compile("eval('a[', @)").unwrap().search(()).unwrap_err();
+        assert_eq!("a[", err.expression);
+        let options = SearchOptions {
+            division_by_zero: DivisionByZero::Null,
+            ..SearchOptions::default()
+        };
+        let expr = compile("eval('@ % `0`', `1`)").unwrap();
+        assert!(expr.search(()).is_err());
+        assert!(expr.search_with_options((), &options).unwrap().is_null());
+        let expr = compile("let $x = `2` in eval('[$x, $.v]', `{}`)").unwrap();
+        assert_eq!(
+            "[2,1]",
+            expr.search(Variable::from_json(r#"{"v": 1}"#).unwrap())
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[cfg(feature = "eval")]
+    #[test]
+    fn eval_is_subject_to_the_depth_limit() {
+        use crate::{compile, RuntimeError, DEFAULT_MAX_DEPTH};
+        let data = Variable::from_json(r#"{"q": "eval(q, @)"}"#).unwrap();
+        let err = compile("eval(q, @)").unwrap().search(data).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::MaxDepthExceeded(DEFAULT_MAX_DEPTH)),
+            err.reason
+        );
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn base64_encodes_and_decodes_bytes() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"payload": "eyJhIjogMX0="}"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("\"bytes\"", eval("type(base64_decode(payload))"));
+        assert_eq!("8", eval("length(base64_decode(payload))"));
+        assert_eq!(
+            "\"{\\\"a\\\": 1}\"",
+            eval("to_string(base64_decode(payload))")
+        );
+        assert_eq!(
+            "\"eyJhIjogMX0=\"",
+            eval("base64_encode(base64_decode(payload))")
+        );
+        assert_eq!("\"eyJhIjogMX0=\"", eval("base64_encode('{\"a\": 1}')"));
+        assert_eq!("\"aGk=\"", eval("base64_encode(base64_decode('aGk'))"));
+        assert_eq!("\"eyJhIjogMX0=\"", eval("base64_decode(payload)"));
+        assert_eq!("null", eval("base64_decode('not base64!')"));
+        assert_eq!(
+            "true",
+            eval("base64_decode('aGk=') == base64_decode('aGk')")
+        );
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn to_decimal_compares_and_divides_exactly() {
+        use crate::compile;
+        let data =
+            Variable::from_json(r#"[{"p": "0.1", "q": 0.2}, {"p": "0.30", "q": 0}]"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            "[\"0.30\"]",
+            eval("[?to_decimal(p) == to_decimal('0.3')].p")
+        );
+        assert_eq!("[\"0.1\"]", eval("[?to_decimal(p) < `0.2`].p"));
+        assert_eq!("\"0.3\"", eval("to_decimal(`0.3`)"));
+        assert_eq!("\"1\"", eval("to_decimal('1.5e0') // `1`"));
+        assert_eq!("\"0.1\"", eval("to_decimal('0.7') % to_decimal('0.3')"));
+        assert_eq!("\"number\"", eval("type(to_decimal('1'))"));
+        assert_eq!("\"1.5\"", eval("abs(to_decimal('-1.5'))"));
+        assert_eq!("null", eval("to_decimal('abc')"));
+        assert_eq!("\"x0.30\"", eval("concat('x', to_decimal([1].p))"));
+    }
+
+    #[cfg(feature = "big-integers")]
+    #[test]
+    fn sums_and_compares_big_integers_exactly() {
+        use crate::compile;
+        let data = Variable::from_json("[9007199254740993, 9007199254740992]").unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("18014398509481985", eval("sum(@)"));
+        assert_eq!("9007199254740993", eval("max(@)"));
+        assert_eq!("false", eval("[0] == [1]"));
+        assert_eq!("[9007199254740993]", eval("[?@ > `9007199254740992`]"));
+        assert_eq!(
+            "36893488147419103232",
+            eval("sum([`18446744073709551616`, `18446744073709551616`])")
+        );
+        assert_eq!(
+            "2",
+            eval("`36893488147419103232` // `18446744073709551616`")
+        );
+        assert_eq!("\"number\"", eval("type(`18446744073709551616`)"));
+        assert_eq!("6.5", eval("sum([`1`, `5.5`])"));
+    }
+
+    #[test]
+    fn compact_and_omit_empty_remove_entries() {
+        use crate::compile;
+        let data = Variable::from_json(
+            r#"{"a": [1, null, "", [], {}, false, 0], "o": {"x": null, "y": "", "z": [0]}}"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(r#"[1,"",[],{},false,0]"#, eval("compact(a)"));
+        assert_eq!(r#"{"y":"","z":[0]}"#, eval("compact(o)"));
+        assert_eq!("[1,false,0]", eval("omit_empty(a)"));
+        assert_eq!(r#"{"z":[0]}"#, eval("omit_empty(o)"));
+        assert!(compile("compact('a')").unwrap().search(()).is_err());
+    }
+
+    #[test]
+    fn map_keys_rewrites_keys() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"userId": 1, "user_name": 2, "x": {"a": 3}}"#).unwrap();
+        let eval = |expr: &str| compile(expr).unwrap().search(data.clone());
+        assert_eq!(
+            r#"{"user_id":1,"user_name":2,"x":{"a":3}}"#,
+            eval("map_keys(&snake_case(@), @)").unwrap().to_string()
+        );
+        assert_eq!(
+            r#"{"k":{"a":3}}"#,
+            eval("map_keys(&'k', @)").unwrap().to_string()
+        );
+        let err = eval("map_keys(&length(@), @)").unwrap_err();
+        assert!(err.to_string().contains("map_keys"), "{}", err);
+    }
+
+    #[test]
+    fn match_keys_selects_values_by_glob() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"cpu0": 1, "cpu1": 2, "cpu10": 3, "mem": 4}"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[1,2,3]", eval("match_keys(@, 'cpu*')"));
+        assert_eq!("[1,2]", eval("match_keys(@, 'cpu?')"));
+        assert_eq!("[3]", eval("match_keys(@, '*1*0')"));
+        assert_eq!("[4]", eval("match_keys(@, 'mem')"));
+        assert_eq!("[]", eval("match_keys(@, 'me')"));
+        assert_eq!("[1,2,3,4]", eval("match_keys(@, '*')"));
+    }
+
+    #[test]
+    fn paths_lists_nested_values() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"a": {"b": [1, {"c": 2}]}, "d": 3}"#).unwrap();
+        let eval = |expr: &str| compile(expr).unwrap().search(data.clone());
+        assert_eq!(
+            r#"[["a"],["a","b"],["a","b",0],["a","b",1],["a","b",1,"c"],["d"]]"#,
+            eval("paths(@)").unwrap().to_string()
+        );
+        assert_eq!(
+            r#"[["a"],["a","b"],["d"]]"#,
+            eval("paths(@, `2`)").unwrap().to_string()
+        );
+        assert_eq!("[]", eval("paths(@, `0`)").unwrap().to_string());
+        assert_eq!("[]", eval("paths(d)").unwrap().to_string());
+        assert!(eval("paths(@, `1.5`)").is_err());
+    }
+
+    #[test]
+    fn flatten_keys_joins_nested_keys() {
+        use crate::compile;
+        let data =
+            Variable::from_json(r#"{"a": {"b": {"c": 1}, "d": [{"e": 2}]}, "f": {}, "g": null}"#)
+                .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            r#"{"a.b.c":1,"a.d":[{"e":2}],"f":{},"g":null}"#,
+            eval("flatten_keys(@)")
+        );
+        assert_eq!(r#"{"b/c":1,"d":[{"e":2}]}"#, eval("flatten_keys(a, '/')"));
+    }
+
+    #[test]
+    fn to_entries_and_from_entries_round_trip() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"b": 2, "a": {"c": 1}, "tmp_x": 3}"#).unwrap();
+        let eval = |expr: &str| compile(expr).unwrap().search(data.clone());
+        assert_eq!(
+            r#"[{"key":"a","value":{"c":1}},{"key":"b","value":2},{"key":"tmp_x","value":3}]"#,
+            eval("to_entries(@)").unwrap().to_string()
+        );
+        assert_eq!(
+            r#"{"a":{"c":1},"b":2}"#,
+            eval("from_entries(to_entries(@)[?!starts_with(key, 'tmp_')])")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!("[]", eval("to_entries(`{}`)").unwrap().to_string());
+        assert_eq!(
+            r#"{"1":null,"true":"t","x":"y"}"#,
+            eval(r#"from_entries(`[{"k": "x", "v": "y"}, {"name": 1}, {"key": true, "value": "t"}]`)"#)
+                .unwrap()
+                .to_string()
+        );
+        let malformed = ErrorReason::Runtime(RuntimeError::InvalidValue {
+            function: Some("from_entries".to_owned()),
+            position: 0,
+            message: "Expected an array of {key, value} objects".to_owned(),
+        });
+        assert_eq!(
+            malformed,
+            eval("from_entries(`[{\"value\": 1}]`)").unwrap_err().reason
+        );
+        assert_eq!(malformed, eval("from_entries(`[1]`)").unwrap_err().reason);
+        assert!(eval("to_entries(`[]`)").is_err());
+    }
+
+    #[test]
+    fn range_counts_between_bounds() {
+        use crate::compile;
+        let eval = |expr: &str| compile(expr).unwrap().search(());
+        assert_eq!("[0,1,2,3]", eval("range(`0`, `4`)").unwrap().to_string());
+        assert_eq!(
+            "[1,4,7]",
+            eval("range(`1`, `10`, `3`)").unwrap().to_string()
+        );
+        assert_eq!(
+            "[5,3,1]",
+            eval("range(`5`, `0`, `-2`)").unwrap().to_string()
+        );
+        assert_eq!("[]", eval("range(`4`, `0`)").unwrap().to_string());
+        assert_eq!(
+            "[0,2,4]",
+            eval("map(&(@ * `2`), range(`0`, `3`))")
+                .unwrap()
+                .to_string()
+        );
+        let invalid = |position: usize, message: &str| {
+            ErrorReason::Runtime(RuntimeError::InvalidValue {
+                function: Some("range".to_owned()),
+                position,
+                message: message.to_owned(),
+            })
+        };
+        assert_eq!(
+            invalid(2, "Step must not be 0"),
+            eval("range(`0`, `4`, `0`)").unwrap_err().reason
+        );
+        assert_eq!(
+            invalid(1, "Expected an integer"),
+            eval("range(`0`, `1.5`)").unwrap_err().reason
+        );
+        assert_eq!(
+            invalid(
+                1,
+                &format!("Range has more than {} elements", MAX_RANGE_LENGTH)
+            ),
+            eval("range(`0`, `100000000000`)").unwrap_err().reason
+        );
+        assert_eq!(
+            MAX_RANGE_LENGTH,
+            eval("length(range(`0`, `2000000`, `2`))")
+                .unwrap()
+                .as_number()
+                .unwrap() as usize
+        );
+        assert_eq!("[2,0]", eval("range(`2`, `-1`, `-2`)").unwrap().to_string());
+    }
+
+    #[test]
+    fn recursive_search_finds_nested_fields() {
+        use crate::compile;
+        let data = Variable::from_json(
+            r#"{"id": 1, "b": [{"id": 2, "x": {"id": {"id": 3}}}, 4], "a": {"ids": 5}}"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| compile(expr).unwrap().search(data.clone()).unwrap();
+        assert_eq!(
+            r#"[2,{"id":3},3,1]"#,
+            eval("recursive_search(@, 'id')").to_string()
+        );
+        assert_eq!("[5]", eval("recursive_search(a, 'ids')").to_string());
+        assert_eq!("[]", eval("recursive_search(id, 'id')").to_string());
+        assert_eq!(
+            "[3,2]",
+            eval("recursive_search(b, 'id')[?type(@) == 'number'] | sort(@) | reverse(@)")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn default_evaluates_fallbacks_lazily() {
+        use crate::compile;
+        let data =
+            Variable::from_json(r#"[{"name": "a", "id": 1}, {"id": 2}, {"name": null, "id": 3}]"#)
+                .unwrap();
+        let eval = |expr: &str| {
+            let expr = compile(expr).unwrap();
+            let walked = expr.search(data.clone());
+            let executed = expr.compile().search(data.clone());
+            assert_eq!(walked, executed);
+            walked
+        };
+        assert_eq!(
+            r#"["a","id-2","id-3"]"#,
+            eval("[*].default(name, &join('-', ['id', to_string(id)]))")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            r#"["a",2,3]"#,
+            eval("map(&default(name, &id), @)").unwrap().to_string()
+        );
+        // The fallback is not evaluated when it is not needed.
+        assert_eq!(
+            "1",
+            eval("default(`1`, &nonexistent_function(@))")
+                .unwrap()
+                .to_string()
+        );
+        assert!(eval("default(`null`, &nonexistent_function(@))").is_err());
+        let json = serde_json::json!({"a": null, "b": 2});
+        let expr = compile("default(a, &b)").unwrap();
+        assert_eq!(serde_json::json!(2), *expr.search_json(&json).unwrap());
+    }
+
+    #[test]
+    fn if_evaluates_the_chosen_branch() {
+        use crate::compile;
+        let data = Variable::from_json(
+            r#"[{"kind": "file", "size": 2}, {"kind": "dir", "items": [1, 2, 3]}]"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| {
+            let expr = compile(expr).unwrap();
+            let walked = expr.search(data.clone());
+            let executed = expr.compile().search(data.clone());
+            assert_eq!(walked, executed);
+            walked
+        };
+        assert_eq!(
+            "[2,3]",
+            eval("[*].if(kind == 'file', &size, &length(items))")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            r#"[{"dir":false},{"dir":true,"n":3}]"#,
+            eval("map(&if(items, &{dir: `true`, n: length(items)}, &{dir: `false`}), @)")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "[null,3]",
+            eval("map(&if(items, &length(items)), @)")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "1",
+            eval("if(`true`, &`1`, &nonexistent_function(@))")
+                .unwrap()
+                .to_string()
+        );
+        assert!(eval("if(`true`, &`1`, &`2`, &`3`)").is_err());
+    }
+
+    #[test]
+    fn deep_merge_merges_nested_objects() {
+        use crate::compile;
+        let data = Variable::from_json(
+            r#"{"base": {"a": {"b": 1, "c": [1, 2]}, "d": 1},
+                "overlay": {"a": {"c": [3], "e": {"f": 1}}, "d": {"g": 2}},
+                "extra": {"a": {"e": {"h": 2}}}}"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            r#"{"a":{"b":1,"c":[3],"e":{"f":1,"h":2}},"d":{"g":2}}"#,
+            eval("deep_merge(base, overlay, extra)")
+        );
+        assert_eq!(
+            r#"{"a":{"c":[3],"e":{"f":1}},"d":{"g":2}}"#,
+            eval("merge(base, overlay)")
+        );
+        assert_eq!(r#"{"d":1}"#, eval("deep_merge(base, {a: `null`}).{d: d}"));
+    }
+
+    #[test]
+    fn merge_patch_follows_rfc_7386() {
+        use crate::compile;
+        // Test cases from appendix A of RFC 7386.
+        let cases = [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, "null", "null"),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"a":1,"e":null}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ];
+        for (target, patch, expected) in cases.iter() {
+            let data =
+                Variable::from_json(&format!(r#"{{"target": {}, "patch": {}}}"#, target, patch))
+                    .unwrap();
+            let result = compile("merge_patch(target, patch)")
+                .unwrap()
+                .search(data)
+                .unwrap();
+            assert_eq!(*expected, result.to_string(), "{} + {}", target, patch);
+        }
+    }
+
+    #[test]
+    fn unflatten_keys_nests_values() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"a": 0, "a.b.c": 1, "a.d": [2], "e/f": 3}"#).unwrap();
+        let eval = |expr: &str| compile(expr).unwrap().search(data.clone());
+        assert_eq!(
+            r#"{"a":{"b":{"c":1},"d":[2]},"e/f":3}"#,
+            eval("unflatten_keys(@)").unwrap().to_string()
+        );
+        assert_eq!(
+            r#"{"a":0,"a.b.c":1,"a.d":[2],"e":{"f":3}}"#,
+            eval("unflatten_keys(@, '/')").unwrap().to_string()
+        );
+        assert_eq!(
+            r#"{"x.y":1,"z":2}"#,
+            eval("flatten_keys(unflatten_keys(`{\"x.y\": 1, \"z\": 2}`))")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidValue {
+                function: Some("unflatten_keys".to_owned()),
+                position: 1,
+                message: "Separator must not be empty".to_owned(),
+            }),
+            eval("unflatten_keys(@, '')").unwrap_err().reason
+        );
+    }
+
+    #[test]
+    fn transforms_case() {
+        use crate::compile;
+        let eval = |expr: &str| compile(expr).unwrap().search(()).unwrap().to_string();
+        assert_eq!("\"STRASSE\"", eval("upper('straße')"));
+        assert_eq!("\"ὀδυσσεύς\"", eval("lower('ὈΔΥΣΣΕΎΣ')"));
+        assert_eq!("\"Élan vital\"", eval("capitalize('éLAN VITAL')"));
+        assert_eq!("\"\"", eval("capitalize('')"));
+        assert_eq!(
+            "\"http_server_error_2_field\"",
+            eval("snake_case('HTTPServer-error 2Field')")
+        );
+        assert_eq!("\"user_id\"", eval("snake_case('userId')"));
+        assert_eq!("\"userId\"", eval("camel_case('user_id')"));
+        assert_eq!("\"httpServerÉtat\"", eval("camel_case('HTTP server_état')"));
+        assert!(compile("upper(`1`)").unwrap().search(()).is_err());
+    }
+
+    #[test]
+    fn cartesian_product_combines_arrays() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"r": ["us", "eu"], "t": ["a", "b"], "e": []}"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            r#"[["us","a"],["us","b"],["eu","a"],["eu","b"]]"#,
+            eval("cartesian_product(r, t)")
+        );
+        assert_eq!("[]", eval("cartesian_product(r, e)"));
+        assert_eq!(
+            r#"[["us","a","us"],["us","a","eu"]]"#,
+            eval("cartesian_product(r, t, r)[:2]")
+        );
+        assert_eq!(eval("cartesian_product(r, t)"), eval("product([r, t])"));
+        assert_eq!("[[]]", eval("product(`[]`)"));
+        assert!(compile("product([r, 'x'])")
+            .unwrap()
+            .search(data.clone())
+            .is_err());
+    }
+
+    #[test]
+    fn sliding_window_yields_overlapping_windows() {
+        use crate::compile;
+        let data = Variable::from_json("[1, 2, 3, 4, 5]").unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[[1,2],[2,3],[3,4],[4,5]]", eval("sliding_window(@, `2`)"));
+        assert_eq!("[[1,2,3],[3,4,5]]", eval("sliding_window(@, `3`, `2`)"));
+        assert_eq!("[]", eval("sliding_window(@, `6`)"));
+        assert_eq!(
+            "[1.5,2.5,3.5,4.5]",
+            eval("sliding_window(@, `2`)[*].avg(@)")
+        );
+        let error = |expr: &str| compile(expr).unwrap().search(data.clone()).unwrap_err();
+        let invalid = |position: usize, message: &str| {
+            ErrorReason::Runtime(RuntimeError::InvalidValue {
+                function: Some("sliding_window".to_owned()),
+                position,
+                message: message.to_owned(),
+            })
+        };
+        assert_eq!(
+            invalid(1, "Window size must not be 0"),
+            error("sliding_window(@, `0`)").reason
+        );
+        assert_eq!(
+            invalid(2, "Step must not be 0"),
+            error("sliding_window(@, `2`, `0`)").reason
+        );
+        assert_eq!(
+            invalid(1, "Expected a non-negative integer"),
+            error("sliding_window(@, `1.5`)").reason
+        );
+    }
+
+    #[test]
+    fn sort_keys_returns_objects_with_ordered_keys() {
+        use crate::compile;
+        let data = Variable::from_json(r#"{"b": {"d": 1, "c": 2}, "a": 3}"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(r#"{"a":3,"b":{"c":2,"d":1}}"#, eval("sort_keys(@)"));
+        assert_eq!(r#"{"a":3,"b":{"c":2,"d":1}}"#, eval("sort_keys(@, `true`)"));
+        assert_eq!(r#"["c","d"]"#, eval("keys(sort_keys(b))"));
+        assert!(compile("sort_keys(@, `true`, `true`)")
+            .unwrap()
+            .search(data.clone())
+            .is_err());
+        assert!(compile("sort_keys(a)")
+            .unwrap()
+            .search(data.clone())
+            .is_err());
+    }
+
+    #[test]
+    fn reduce_folds_with_accumulator() {
+        use crate::compile;
+        let data = Variable::from_json(r#"[{"n": 1}, {"n": 2}, {"n": 3}]"#).unwrap();
+        let expr = compile("reduce(@, &sum([acc, item.n]), `0`)").unwrap();
+        assert_eq!(Some(6.0), expr.search(data.clone()).unwrap().as_number());
+        let expr = compile("reduce(@, &[acc, item.n][], `[]`)").unwrap();
+        assert_eq!("[1,2,3]", expr.search(data).unwrap().to_string());
+        let expr = compile("reduce(`[]`, &item, 'initial')").unwrap();
+        assert_eq!("\"initial\"", expr.search(()).unwrap().to_string());
+    }
+
+    #[test]
+    fn empty_cycle_accepts_no_variadic_arguments() {
+        let signature = Signature::with_variadic(vec![arg!(any)], Variadic::Cycle(vec![]));
+        assert!(validate(&signature, "[1]").is_ok());
+        assert_eq!(
+            Err(RuntimeError::TooManyArguments {
+                expected: 1,
+                actual: 2,
+            }),
+            validate(&signature, "[1, 2]")
+        );
+    }
+}
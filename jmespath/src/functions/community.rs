@@ -0,0 +1,596 @@
+//! Functions from the JMESPath community specification.
+//!
+//! These functions are not part of the original JMESPath specification but
+//! are commonly provided by other implementations. They are registered by
+//! `Runtime::register_builtin_functions` when the `community-functions`
+//! feature is enabled, or explicitly using
+//! `Runtime::register_community_functions`.
+
+use std::collections::BTreeMap;
+
+use crate::interpreter::{interpret, SearchResult};
+use crate::variable::Variable;
+use crate::{Context, ErrorReason, JmespathError, Rcvar, RuntimeError};
+
+use super::{
+    check_max_arguments, count_arg, internal_error, invalid_value, ArgumentType, Function,
+    Signature,
+};
+
+defn!(GroupByFn, vec![arg!(array), arg!(expref)], None);
+
+impl Function for GroupByFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let values = args[0]
+            .as_array()
+            .ok_or_else(|| internal_error("Expected args[0] to be an array"))?;
+        let ast = args[1]
+            .as_expref()
+            .ok_or_else(|| internal_error("Expected args[1] to be an expref"))?;
+        let mut groups: BTreeMap<String, Vec<Rcvar>> = BTreeMap::new();
+        for (invocation, value) in values.iter().enumerate() {
+            let key = interpret(value, ast, ctx)?;
+            match *key {
+                Variable::String(ref s) => {
+                    groups.entry(s.clone()).or_default().push(value.clone());
+                }
+                // Elements without a key are not placed in any group.
+                Variable::Null => {}
+                _ => {
+                    return Err(JmespathError::from_ctx(
+                        ctx,
+                        ErrorReason::Runtime(RuntimeError::InvalidReturnType {
+                            function: None,
+                            expected: ArgumentType::Union(vec![
+                                ArgumentType::String,
+                                ArgumentType::Null,
+                            ]),
+                            actual: key.get_type(),
+                            position: 1,
+                            invocation,
+                        }),
+                    ));
+                }
+            }
+        }
+        let result = groups
+            .into_iter()
+            .map(|(k, v)| (k, Rcvar::new(Variable::Array(v))))
+            .collect();
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+defn!(ItemsFn, vec![arg!(object)], None);
+
+impl Function for ItemsFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let object = args[0]
+            .as_object()
+            .ok_or_else(|| internal_error("Expected args[0] to be an object"))?;
+        let result = object
+            .iter()
+            .map(|(k, v)| {
+                Rcvar::new(Variable::Array(vec![
+                    Rcvar::new(Variable::String(k.clone())),
+                    v.clone(),
+                ]))
+            })
+            .collect();
+        Ok(Rcvar::new(Variable::Array(result)))
+    }
+}
+
+defn!(FromItemsFn, vec![arg!(array)], None);
+
+impl Function for FromItemsFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let items = args[0]
+            .as_array()
+            .ok_or_else(|| internal_error("Expected args[0] to be an array"))?;
+        let mut result = BTreeMap::new();
+        for item in items {
+            match item.as_array().map(|pair| pair.as_slice()) {
+                Some([key, value]) if key.is_string() => {
+                    result.insert(key.as_string().unwrap().clone(), value.clone());
+                }
+                _ => {
+                    return Err(invalid_value(
+                        ctx,
+                        0,
+                        "Expected an array of [string, any] pairs",
+                    ));
+                }
+            }
+        }
+        Ok(Rcvar::new(Variable::Object(result)))
+    }
+}
+
+defn!(ZipFn, vec![arg!(array)], Some(arg!(array)));
+
+impl Function for ZipFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let arrays = args
+            .iter()
+            .map(|arg| {
+                arg.as_array()
+                    .ok_or_else(|| internal_error("Expected arguments to be arrays"))
+            })
+            .collect::<Result<Vec<_>, JmespathError>>()?;
+        // The result is as long as the shortest array.
+        let len = arrays.iter().map(|a| a.len()).min().unwrap_or(0);
+        let result = (0..len)
+            .map(|i| {
+                Rcvar::new(Variable::Array(
+                    arrays.iter().map(|a| a[i].clone()).collect(),
+                ))
+            })
+            .collect();
+        Ok(Rcvar::new(Variable::Array(result)))
+    }
+}
+
+/// Macro used to implement pad_left and pad_right functions.
+macro_rules! pad {
+    ($args:expr, $ctx:expr, $left:expr) => {{
+        check_max_arguments($args, 3, $ctx)?;
+        let subject = $args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        let width = count_arg($args, 1, $ctx)?.unwrap_or(0);
+        let pad = match $args.get(2) {
+            Some(value) => value
+                .as_string()
+                .ok_or_else(|| internal_error("Expected args[2] to be a string"))?
+                .as_str(),
+            None => " ",
+        };
+        let pad_char = match (pad.chars().next(), pad.chars().count()) {
+            (Some(c), 1) => c,
+            _ => return Err(invalid_value($ctx, 2, "Padding must be a single character")),
+        };
+        let len = subject.chars().count();
+        if len >= width {
+            return Ok($args[0].clone());
+        }
+        let padding: String = std::iter::repeat(pad_char).take(width - len).collect();
+        let result = if $left {
+            padding + subject
+        } else {
+            subject.clone() + &padding
+        };
+        Ok(Rcvar::new(Variable::String(result)))
+    }};
+}
+
+defn!(
+    PadLeftFn,
+    vec![arg!(string), arg!(number)],
+    Some(arg!(string))
+);
+
+impl Function for PadLeftFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        pad!(args, ctx, true)
+    }
+}
+
+defn!(
+    PadRightFn,
+    vec![arg!(string), arg!(number)],
+    Some(arg!(string))
+);
+
+impl Function for PadRightFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        pad!(args, ctx, false)
+    }
+}
+
+defn!(
+    SplitFn,
+    vec![arg!(string), arg!(string)],
+    Some(arg!(number))
+);
+
+impl Function for SplitFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 3, ctx)?;
+        let subject = args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        let separator = args[1]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[1] to be a string"))?;
+        let count = count_arg(args, 2, ctx)?;
+        let to_var = |s: &str| Rcvar::new(Variable::String(s.to_owned()));
+        let parts: Vec<Rcvar> = if separator.is_empty() {
+            // An empty separator splits the string into characters.
+            let chars: Vec<char> = subject.chars().collect();
+            let splits = count.unwrap_or(chars.len()).min(chars.len());
+            let mut parts: Vec<Rcvar> = chars[..splits]
+                .iter()
+                .map(|c| to_var(&c.to_string()))
+                .collect();
+            if splits < chars.len() {
+                parts.push(to_var(&chars[splits..].iter().collect::<String>()));
+            }
+            parts
+        } else {
+            match count {
+                Some(n) => subject
+                    .splitn(n + 1, separator.as_str())
+                    .map(to_var)
+                    .collect(),
+                None => subject.split(separator.as_str()).map(to_var).collect(),
+            }
+        };
+        Ok(Rcvar::new(Variable::Array(parts)))
+    }
+}
+
+defn!(
+    ReplaceFn,
+    vec![arg!(string), arg!(string), arg!(string)],
+    Some(arg!(number))
+);
+
+impl Function for ReplaceFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 4, ctx)?;
+        let strings = args[..3]
+            .iter()
+            .map(|arg| {
+                arg.as_string()
+                    .ok_or_else(|| internal_error("Expected arguments to be strings"))
+            })
+            .collect::<Result<Vec<_>, JmespathError>>()?;
+        let (subject, old, new) = (strings[0], strings[1], strings[2]);
+        let result = match count_arg(args, 3, ctx)? {
+            Some(n) => subject.replacen(old.as_str(), new, n),
+            None => subject.replace(old.as_str(), new),
+        };
+        Ok(Rcvar::new(Variable::String(result)))
+    }
+}
+
+/// Converts an optional string index argument into a character offset
+/// within `len`, counting from the end if negative.
+fn string_index(
+    args: &[Rcvar],
+    position: usize,
+    len: usize,
+    ctx: &Context<'_>,
+) -> Result<Option<usize>, JmespathError> {
+    match args.get(position).map(|value| value.as_number()) {
+        None => Ok(None),
+        Some(Some(n)) if n.fract() == 0.0 => {
+            let index = if n < 0.0 { n + len as f64 } else { n };
+            Ok(Some(index.max(0.0).min(len as f64) as usize))
+        }
+        Some(_) => Err(invalid_value(
+            ctx,
+            position,
+            "String indices must be integers",
+        )),
+    }
+}
+
+/// Macro used to implement find_first and find_last functions.
+macro_rules! find {
+    ($args:expr, $ctx:expr, $last:expr) => {{
+        check_max_arguments($args, 4, $ctx)?;
+        let subject: Vec<char> = $args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?
+            .chars()
+            .collect();
+        let sub: Vec<char> = $args[1]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[1] to be a string"))?
+            .chars()
+            .collect();
+        let start = string_index($args, 2, subject.len(), $ctx)?.unwrap_or(0);
+        let end = string_index($args, 3, subject.len(), $ctx)?.unwrap_or(subject.len());
+        // Indices count characters, consistent with length().
+        let found = if sub.is_empty() || start >= end {
+            None
+        } else {
+            let mut windows = subject[start..end].windows(sub.len());
+            if $last {
+                windows.rposition(|w| w == sub.as_slice())
+            } else {
+                windows.position(|w| w == sub.as_slice())
+            }
+        };
+        Ok(Rcvar::new(match found {
+            Some(i) => Variable::Number((start + i).into()),
+            None => Variable::Null,
+        }))
+    }};
+}
+
+defn!(
+    FindFirstFn,
+    vec![arg!(string), arg!(string)],
+    Some(arg!(number))
+);
+
+impl Function for FindFirstFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        find!(args, ctx, false)
+    }
+}
+
+defn!(
+    FindLastFn,
+    vec![arg!(string), arg!(string)],
+    Some(arg!(number))
+);
+
+impl Function for FindLastFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        find!(args, ctx, true)
+    }
+}
+
+/// Macro used to implement trim, trim_left and trim_right functions.
+macro_rules! trim {
+    ($args:expr, $ctx:expr, $left:expr, $right:expr) => {{
+        check_max_arguments($args, 2, $ctx)?;
+        let subject = $args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        let chars: Vec<char> = match $args.get(1) {
+            Some(value) => value
+                .as_string()
+                .ok_or_else(|| internal_error("Expected args[1] to be a string"))?
+                .chars()
+                .collect(),
+            None => vec![],
+        };
+        // An empty set of characters strips whitespace.
+        let strip = |c: char| {
+            if chars.is_empty() {
+                c.is_whitespace()
+            } else {
+                chars.contains(&c)
+            }
+        };
+        let mut result = subject.as_str();
+        if $left {
+            result = result.trim_start_matches(strip);
+        }
+        if $right {
+            result = result.trim_end_matches(strip);
+        }
+        Ok(Rcvar::new(Variable::String(result.to_owned())))
+    }};
+}
+
+defn!(TrimFn, vec![arg!(string)], Some(arg!(string)));
+
+impl Function for TrimFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        trim!(args, ctx, true, true)
+    }
+}
+
+defn!(TrimLeftFn, vec![arg!(string)], Some(arg!(string)));
+
+impl Function for TrimLeftFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        trim!(args, ctx, true, false)
+    }
+}
+
+defn!(TrimRightFn, vec![arg!(string)], Some(arg!(string)));
+
+impl Function for TrimRightFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        trim!(args, ctx, false, true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{compile, ErrorReason, RuntimeError, Variable};
+
+    fn search(expr: &str, json: &str) -> String {
+        let data = Variable::from_json(json).unwrap();
+        compile(expr).unwrap().search(data).unwrap().to_string()
+    }
+
+    /// Returns the `InvalidValue` error reason for an argument of a function.
+    fn invalid_value(function: &str, position: usize, message: &str) -> ErrorReason {
+        ErrorReason::Runtime(RuntimeError::InvalidValue {
+            function: Some(function.to_owned()),
+            position,
+            message: message.to_owned(),
+        })
+    }
+
+    fn error(expr: &str) -> ErrorReason {
+        compile(expr).unwrap().search(()).unwrap_err().reason
+    }
+
+    #[test]
+    fn groups_by_expression() {
+        assert_eq!(
+            r#"{"a":[{"k":"a","v":1},{"k":"a","v":3}],"b":[{"k":"b","v":2}]}"#,
+            search(
+                "group_by(@, &k)",
+                r#"[{"k": "a", "v": 1}, {"k": "b", "v": 2}, {"k": "a", "v": 3}, {"v": 4}]"#
+            )
+        );
+        assert!(compile("group_by(@, &v)")
+            .unwrap()
+            .search(Variable::from_json(r#"[{"v": 1}]"#).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn converts_objects_to_and_from_items() {
+        assert_eq!(
+            r#"[["a",1],["b",2]]"#,
+            search("items(@)", r#"{"b": 2, "a": 1}"#)
+        );
+        assert_eq!(
+            r#"{"a":1,"b":2}"#,
+            search("from_items(items(@))", r#"{"b": 2, "a": 1}"#)
+        );
+        assert_eq!(
+            invalid_value("from_items", 0, "Expected an array of [string, any] pairs"),
+            error("from_items(`[[1, 2]]`)")
+        );
+    }
+
+    #[test]
+    fn zips_arrays_to_shortest_length() {
+        assert_eq!(
+            r#"[[1,"a",true],[2,"b",false]]"#,
+            search(
+                "zip(a, b, c)",
+                r#"{"a": [1, 2, 3], "b": ["a", "b"], "c": [true, false]}"#
+            )
+        );
+    }
+
+    #[test]
+    fn pads_strings() {
+        assert_eq!(r#""  ab""#, search("pad_left('ab', `4`)", "null"));
+        assert_eq!(r#""ab--""#, search("pad_right('ab', `4`, '-')", "null"));
+        assert_eq!(r#""abc""#, search("pad_left('abc', `2`)", "null"));
+        assert_eq!(
+            invalid_value("pad_left", 2, "Padding must be a single character"),
+            error("pad_left('a', `4`, 'xy')")
+        );
+        assert_eq!(
+            invalid_value("pad_right", 1, "Expected a non-negative integer"),
+            error("pad_right('a', `-1`)")
+        );
+    }
+
+    #[test]
+    fn finds_substrings() {
+        assert_eq!(
+            "8",
+            search("find_first('subject string', 'string')", "null")
+        );
+        assert_eq!("6", search("find_first('héllo wörld', 'w')", "null"));
+        assert_eq!("6", search("find_first('ab ab ab', 'ab', `4`)", "null"));
+        assert_eq!("3", search("find_first('ab ab ab', 'ab', `-6`)", "null"));
+        assert_eq!(
+            "null",
+            search("find_first('ab ab ab', 'ab', `4`, `5`)", "null")
+        );
+        assert_eq!("null", search("find_first('abc', '')", "null"));
+        assert_eq!("null", search("find_first('abc', 'd')", "null"));
+        assert_eq!("6", search("find_last('ab ab ab', 'ab')", "null"));
+        assert_eq!(
+            "3",
+            search("find_last('ab ab ab', 'ab', `0`, `-1`)", "null")
+        );
+        assert_eq!("5", search("find_last('aéaéaé', 'é')", "null"));
+        assert_eq!(
+            invalid_value("find_first", 2, "String indices must be integers"),
+            error("find_first('abc', 'b', `1.5`)")
+        );
+    }
+
+    #[test]
+    fn trims_strings() {
+        assert_eq!("\"abc\"", search("trim(' \t abc \n')", "null"));
+        assert_eq!("\"abc \"", search("trim_left('  abc ')", "null"));
+        assert_eq!("\"  abc\"", search("trim_right('  abc ')", "null"));
+        assert_eq!("\"abc\"", search("trim('xyabcyx', 'xy')", "null"));
+        assert_eq!("\"abcyx\"", search("trim_left('xyabcyx', 'yx')", "null"));
+        assert_eq!("\"xyabc\"", search("trim_right('xyabcyx', 'xy')", "null"));
+        assert_eq!("\"abc\"", search("trim(' abc ', '')", "null"));
+        assert_eq!("\"é\"", search("trim('ñéñ', 'ñ')", "null"));
+        assert_eq!("\"\"", search("trim('aaa', 'a')", "null"));
+    }
+
+    #[test]
+    fn splits_strings() {
+        assert_eq!(r#"["a","b","c"]"#, search("split('a,b,c', ',')", "null"));
+        assert_eq!(r#"["a","b,c"]"#, search("split('a,b,c', ',', `1`)", "null"));
+        assert_eq!(r#"["a","b","c"]"#, search("split('abc', '')", "null"));
+        assert_eq!(r#"["a","bc"]"#, search("split('abc', '', `1`)", "null"));
+    }
+
+    #[test]
+    fn replaces_substrings() {
+        assert_eq!(r#""x-x-x""#, search("replace('a-a-a', 'a', 'x')", "null"));
+        assert_eq!(
+            r#""x-a-a""#,
+            search("replace('a-a-a', 'a', 'x', `1`)", "null")
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Functions for parsing, formatting and converting datetimes.
+//!
+//! These functions produce and consume `Variable::DateTime` values, which
+//! compare by the instant they represent. They are registered by
+//! `Runtime::register_builtin_functions` when the `datetime-functions`
+//! feature is enabled, or explicitly using
+//! `Runtime::register_datetime_functions`.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Number;
+
+use crate::interpreter::SearchResult;
+use crate::variable::Variable;
+use crate::{Context, Rcvar};
+
+use super::{
+    check_max_arguments, internal_error, invalid_value, ArgumentType, Function, Signature,
+};
+
+/// Parses `s` using a strftime-style `format`. Formats without an offset
+/// are interpreted as UTC, and formats without a time as midnight.
+fn parse_with_format(s: &str, format: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_str(s, format)
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(s, format)
+                .ok()
+                .map(|d| d.and_utc().fixed_offset())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, format)
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|d| d.and_utc().fixed_offset())
+        })
+}
+
+defn!(ParseDatetimeFn, vec![arg!(string)], Some(arg!(string)));
+
+impl Function for ParseDatetimeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        let subject = args[0]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[0] to be a string"))?;
+        // Strings that cannot be parsed produce null, like to_number().
+        let parsed = match args.get(1) {
+            Some(format) => {
+                let format = format
+                    .as_string()
+                    .ok_or_else(|| internal_error("Expected args[1] to be a string"))?;
+                parse_with_format(subject, format)
+            }
+            None => DateTime::parse_from_rfc3339(subject).ok(),
+        };
+        Ok(Rcvar::new(match parsed {
+            Some(d) => Variable::DateTime(d),
+            None => Variable::Null,
+        }))
+    }
+}
+
+defn!(FormatDatetimeFn, vec![arg!(datetime), arg!(string)], None);
+
+impl Function for FormatDatetimeFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let datetime = args[0]
+            .as_datetime()
+            .ok_or_else(|| internal_error("Expected args[0] to be a datetime"))?;
+        let format = args[1]
+            .as_string()
+            .ok_or_else(|| internal_error("Expected args[1] to be a string"))?;
+        let items = StrftimeItems::new(format).collect::<Vec<Item<'_>>>();
+        // Formatting panics on invalid specifiers, so reject them up front.
+        if items.contains(&Item::Error) {
+            let message = format!("Invalid datetime format: {}", format);
+            return Err(invalid_value(ctx, 1, &message));
+        }
+        Ok(Rcvar::new(Variable::String(
+            datetime.format_with_items(items.into_iter()).to_string(),
+        )))
+    }
+}
+
+defn!(NowFn, vec![], None);
+
+impl Function for NowFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let now = DateTime::<Utc>::from(std::time::SystemTime::now());
+        Ok(Rcvar::new(Variable::DateTime(now.fixed_offset())))
+    }
+}
+
+defn!(ToEpochFn, vec![arg!(datetime)], None);
+
+impl Function for ToEpochFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let datetime = args[0]
+            .as_datetime()
+            .ok_or_else(|| internal_error("Expected args[0] to be a datetime"))?;
+        let seconds = datetime.timestamp();
+        let nanos = datetime.timestamp_subsec_nanos();
+        let number = if nanos == 0 {
+            Number::from(seconds)
+        } else {
+            Number::from_f64(seconds as f64 + f64::from(nanos) / 1e9)
+                .ok_or_else(|| internal_error("Expected a finite timestamp"))?
+        };
+        Ok(Rcvar::new(Variable::Number(number)))
+    }
+}
+
+defn!(FromEpochFn, vec![arg!(number)], None);
+
+impl Function for FromEpochFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let n = args[0]
+            .as_number()
+            .ok_or_else(|| internal_error("Expected args[0] to be a number"))?;
+        let seconds = n.floor();
+        let nanos = ((n - seconds) * 1e9).round().min(999_999_999.0) as u32;
+        let datetime = if seconds.abs() < i64::MAX as f64 {
+            DateTime::from_timestamp(seconds as i64, nanos)
+        } else {
+            None
+        };
+        match datetime {
+            Some(d) => Ok(Rcvar::new(Variable::DateTime(d.fixed_offset()))),
+            None => Err(invalid_value(
+                ctx,
+                0,
+                &format!("Timestamp out of range: {}", args[0]),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{compile, ErrorReason, RuntimeError, Variable};
+
+    fn invalid_value(function: &str, position: usize, message: &str) -> ErrorReason {
+        ErrorReason::Runtime(RuntimeError::InvalidValue {
+            function: Some(function.to_owned()),
+            position,
+            message: message.to_owned(),
+        })
+    }
+
+    fn search(expr: &str, json: &str) -> String {
+        let data = Variable::from_json(json).unwrap();
+        compile(expr).unwrap().search(data).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_datetimes() {
+        assert_eq!(
+            "\"2020-01-02T03:04:05+01:00\"",
+            search("parse_datetime(@)", "\"2020-01-02T03:04:05+01:00\"")
+        );
+        assert_eq!(
+            "\"2020-01-02T03:04:00+00:00\"",
+            search(
+                "parse_datetime(@, '%d/%m/%Y %H:%M')",
+                "\"02/01/2020 03:04\""
+            )
+        );
+        assert_eq!(
+            "\"2020-01-02T00:00:00+00:00\"",
+            search("parse_datetime(@, '%Y-%m-%d')", "\"2020-01-02\"")
+        );
+        assert_eq!("null", search("parse_datetime(@)", "\"yesterday\""));
+        assert_eq!(
+            "true",
+            search(
+                "parse_datetime(a) == parse_datetime(b)",
+                r#"{"a": "2020-01-01T00:00:00Z", "b": "2020-01-01T01:00:00+01:00"}"#
+            )
+        );
+        assert_eq!(
+            "[\"b\"]",
+            search(
+                "[?parse_datetime(t) > parse_datetime('2020-06-01T00:00:00Z')].n",
+                r#"[{"n": "a", "t": "2020-01-01T00:00:00Z"},
+                    {"n": "b", "t": "2021-01-01T00:00:00Z"}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn formats_datetimes() {
+        assert_eq!(
+            "\"2020-01-02\"",
+            search(
+                "format_datetime(parse_datetime(@), '%Y-%m-%d')",
+                "\"2020-01-02T03:04:05Z\""
+            )
+        );
+        let err = compile("format_datetime(parse_datetime(@), '%Q')")
+            .unwrap()
+            .search(Variable::String("2020-01-02T03:04:05Z".to_owned()))
+            .unwrap_err();
+        assert_eq!(
+            invalid_value("format_datetime", 1, "Invalid datetime format: %Q"),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn converts_epoch_timestamps() {
+        assert_eq!(
+            "1577934245",
+            search("to_epoch(parse_datetime(@))", "\"2020-01-02T03:04:05Z\"")
+        );
+        assert_eq!(
+            "1577934245.5",
+            search("to_epoch(parse_datetime(@))", "\"2020-01-02T03:04:05.5Z\"")
+        );
+        assert_eq!(
+            "\"2020-01-02T03:04:05+00:00\"",
+            search("from_epoch(@)", "1577934245")
+        );
+        assert_eq!("1.5", search("to_epoch(from_epoch(`1.5`))", "null"));
+        let err = compile("from_epoch(`1e300`)")
+            .unwrap()
+            .search(())
+            .unwrap_err();
+        assert_eq!(
+            invalid_value("from_epoch", 0, "Timestamp out of range: 1e+300"),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn returns_the_current_time() {
+        assert_eq!("\"datetime\"", search("type(now())", "null"));
+        assert_eq!("true", search("to_epoch(now()) > `1577934245`", "null"));
+    }
+}
@@ -0,0 +1,277 @@
+//! Functions for hashing strings and generating identifiers.
+//!
+//! The digests are returned as lowercase hexadecimal strings so that they
+//! can be used as stable keys derived from the searched data. They are
+//! registered by `Runtime::register_builtin_functions` when the
+//! `hash-functions` feature is enabled, or explicitly using
+//! `Runtime::register_hash_functions`.
+
+use crate::interpreter::SearchResult;
+use crate::variable::Variable;
+use crate::{Context, Rcvar};
+
+use super::{internal_error, ArgumentType, Function, Signature};
+
+/// Round constants of SHA-256.
+#[rustfmt::skip]
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+    0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+    0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+    0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+    0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+    0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Initial hash value of SHA-256.
+#[rustfmt::skip]
+const SHA256_H: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Per-round constants of MD5.
+#[rustfmt::skip]
+const MD5_K: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee,
+    0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be,
+    0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa,
+    0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed,
+    0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c,
+    0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05,
+    0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039,
+    0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1,
+    0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// Per-round left rotation amounts of MD5.
+#[rustfmt::skip]
+const MD5_S: [u32; 16] = [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+
+/// Pads a message to a multiple of 64 bytes, ending with its length in
+/// bits encoded by `encode_length`.
+fn pad(message: &[u8], encode_length: fn(u64) -> [u8; 8]) -> Vec<u8> {
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&encode_length((message.len() as u64).wrapping_mul(8)));
+    padded
+}
+
+/// Computes the SHA-256 digest of a message.
+fn sha256(message: &[u8]) -> Vec<u8> {
+    let mut h = SHA256_H;
+    for chunk in pad(message, u64::to_be_bytes).chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(SHA256_K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    h.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+/// Computes the MD5 digest of a message.
+fn md5(message: &[u8]) -> Vec<u8> {
+    let mut h: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+    for chunk in pad(message, u64::to_le_bytes).chunks(64) {
+        let mut m = [0u32; 16];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = h;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let f = f.wrapping_add(a).wrapping_add(MD5_K[i]).wrapping_add(m[g]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(f.rotate_left(MD5_S[(i / 16) * 4 + i % 4]));
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+    h.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Types that can be hashed: strings are hashed as UTF-8.
+fn hashable() -> ArgumentType {
+    #[cfg(feature = "bytes")]
+    return arg!(string | bytes);
+    #[cfg(not(feature = "bytes"))]
+    return arg!(string);
+}
+
+/// Returns the bytes of a hashable argument.
+fn hash_input(value: &Variable) -> Result<&[u8], crate::JmespathError> {
+    match *value {
+        Variable::String(ref s) => Ok(s.as_bytes()),
+        #[cfg(feature = "bytes")]
+        Variable::Bytes(ref b) => Ok(b),
+        _ => Err(internal_error("Expected args[0] to be a string")),
+    }
+}
+
+/// Formats a digest as a lowercase hexadecimal string.
+fn to_hex(digest: &[u8]) -> Rcvar {
+    let hex = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    Rcvar::new(Variable::String(hex))
+}
+
+defn!(Md5Fn, vec![hashable()], None);
+
+impl Function for Md5Fn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        Ok(to_hex(&md5(hash_input(&args[0])?)))
+    }
+}
+
+defn!(Sha256Fn, vec![hashable()], None);
+
+impl Function for Sha256Fn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        Ok(to_hex(&sha256(hash_input(&args[0])?)))
+    }
+}
+
+// Generates a random (version 4) UUID in its hyphenated form.
+defn!(UuidFn, vec![], None);
+
+impl Function for UuidFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        Ok(Rcvar::new(Variable::String(
+            uuid::Uuid::new_v4().hyphenated().to_string(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile;
+
+    fn search(expr: &str, json: &str) -> String {
+        let data = Variable::from_json(json).unwrap();
+        compile(expr).unwrap().search(data).unwrap().to_string()
+    }
+
+    fn hex(digest: Vec<u8>) -> String {
+        to_hex(&digest).as_string().unwrap().to_owned()
+    }
+
+    #[test]
+    fn computes_sha256_digests() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            hex(sha256(b""))
+        );
+        assert_eq!(
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            hex(sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            ))
+        );
+        assert_eq!(
+            "\"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\"",
+            search("sha256(@)", "\"abc\"")
+        );
+    }
+
+    #[test]
+    fn computes_md5_digests() {
+        assert_eq!("d41d8cd98f00b204e9800998ecf8427e", hex(md5(b"")));
+        assert_eq!(
+            "9e107d9d372bb6826bd81d3542a419d6",
+            hex(md5(b"The quick brown fox jumps over the lazy dog"))
+        );
+        assert_eq!(
+            "57edf4a22be3c955ac49da2e2107b67a",
+            hex(md5(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ))
+        );
+        assert_eq!(
+            "\"900150983cd24fb0d6963f7d28e17f72\"",
+            search("md5(@)", "\"abc\"")
+        );
+    }
+
+    #[test]
+    fn generates_random_uuids() {
+        let uuid = search("uuid()", "null");
+        assert_eq!(38, uuid.len());
+        assert_eq!(Some('4'), uuid.chars().nth(15));
+        assert_eq!("false", search("uuid() == uuid()", "null"));
+    }
+}
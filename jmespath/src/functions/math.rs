@@ -0,0 +1,279 @@
+//! Extended math functions.
+//!
+//! These functions complement `abs`, `ceil` and `floor`. Results that are
+//! not finite, such as the square root of a negative number, are null.
+//! They are registered by `Runtime::register_builtin_functions` when the
+//! `math-functions` feature is enabled, or explicitly using
+//! `Runtime::register_math_functions`.
+
+use std::convert::TryFrom;
+
+use serde_json::Number;
+
+use crate::ast::ArithmeticOperator;
+use crate::interpreter::{arithmetic, as_integer, check_overflow, integer_variable, SearchResult};
+use crate::variable::Variable;
+use crate::{Context, JmespathError, Rcvar};
+
+use super::{
+    check_max_arguments, internal_error, invalid_value, ArgumentType, Function, Signature,
+};
+
+/// Largest magnitude below which every integer is exactly representable as
+/// an `f64`.
+const MAX_SAFE_FLOAT: f64 = 9_007_199_254_740_992.0;
+
+/// Creates a number from a floating point result, or null if it is not
+/// finite.
+fn float_result(n: f64) -> Rcvar {
+    Rcvar::new(Number::from_f64(n).map_or(Variable::Null, Variable::Number))
+}
+
+/// Returns the argument at `position` as a number.
+fn number_arg(args: &[Rcvar], position: usize) -> Result<f64, JmespathError> {
+    args[position]
+        .as_number()
+        .ok_or_else(|| internal_error(&format!("Expected args[{}] to be a number", position)))
+}
+
+/// Converts an optional precision argument into a number of decimal
+/// places, which is negative to round to the left of the decimal point.
+fn precision_arg(args: &[Rcvar], ctx: &Context<'_>) -> Result<i32, JmespathError> {
+    match args.get(1).map(|value| value.as_number()) {
+        None => Ok(0),
+        Some(Some(n)) if n.fract() == 0.0 && n.abs() <= 308.0 => Ok(n as i32),
+        Some(_) => Err(invalid_value(
+            ctx,
+            1,
+            "Precision must be an integer between -308 and 308",
+        )),
+    }
+}
+
+/// Macro used to implement round and trunc functions.
+macro_rules! round {
+    ($args:expr, $ctx:expr, $method:ident) => {{
+        check_max_arguments($args, 2, $ctx)?;
+        let precision = precision_arg($args, $ctx)?;
+        // Integers already have every requested decimal place.
+        if precision >= 0 && as_integer(&$args[0]).is_some() {
+            return Ok($args[0].clone());
+        }
+        let n = number_arg($args, 0)?;
+        let scale = 10f64.powi(precision);
+        let result = (n * scale).$method() / scale;
+        if precision <= 0 && result.abs() < MAX_SAFE_FLOAT {
+            return Ok(Rcvar::new(Variable::Number(Number::from(result as i64))));
+        }
+        Ok(float_result(result))
+    }};
+}
+
+// Divides integers, rounding toward negative infinity like `//`.
+defn!(DivFn, vec![arg!(number), arg!(number)], None);
+
+impl Function for DivFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        arithmetic(&ArithmeticOperator::IntegerDivide, &args[0], &args[1], ctx)
+    }
+}
+
+// Returns the natural logarithm, or the logarithm to the given base.
+defn!(LogFn, vec![arg!(number)], Some(arg!(number)));
+
+impl Function for LogFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        check_max_arguments(args, 2, ctx)?;
+        let n = number_arg(args, 0)?;
+        let base = match args.get(1) {
+            Some(_) => Some(number_arg(args, 1)?),
+            None => None,
+        };
+        // Common bases use the dedicated, more accurate functions.
+        let result = match base {
+            None => n.ln(),
+            Some(10.0) => n.log10(),
+            Some(2.0) => n.log2(),
+            Some(b) => n.log(b),
+        };
+        Ok(float_result(result))
+    }
+}
+
+// Returns the remainder with the sign of the divisor, like `%`.
+defn!(ModFn, vec![arg!(number), arg!(number)], None);
+
+impl Function for ModFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        arithmetic(&ArithmeticOperator::Modulo, &args[0], &args[1], ctx)
+    }
+}
+
+defn!(PowFn, vec![arg!(number), arg!(number)], None);
+
+impl Function for PowFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        // Integers raised to non-negative integer powers are exact.
+        if let (Some(base), Some(exp)) = (as_integer(&args[0]), as_integer(&args[1])) {
+            if let Ok(exp) = u32::try_from(exp) {
+                let result = check_overflow(
+                    base.checked_pow(exp),
+                    || base.wrapping_pow(exp),
+                    || base.saturating_pow(exp),
+                    ctx,
+                )?;
+                if let Some(result) = result {
+                    return Ok(Rcvar::new(integer_variable(result)));
+                }
+            }
+        }
+        Ok(float_result(
+            number_arg(args, 0)?.powf(number_arg(args, 1)?),
+        ))
+    }
+}
+
+// Rounds half away from zero to the given number of decimal places.
+defn!(RoundFn, vec![arg!(number)], Some(arg!(number)));
+
+impl Function for RoundFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        round!(args, ctx, round)
+    }
+}
+
+defn!(SignFn, vec![arg!(number)], None);
+
+impl Function for SignFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        let n = number_arg(args, 0)?;
+        let sign = if n > 0.0 {
+            1
+        } else if n < 0.0 {
+            -1
+        } else {
+            0
+        };
+        Ok(Rcvar::new(Variable::Number(Number::from(sign))))
+    }
+}
+
+defn!(SqrtFn, vec![arg!(number)], None);
+
+impl Function for SqrtFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        Ok(float_result(number_arg(args, 0)?.sqrt()))
+    }
+}
+
+// Rounds toward zero to the given number of decimal places.
+defn!(TruncFn, vec![arg!(number)], Some(arg!(number)));
+
+impl Function for TruncFn {
+    fn signature(&self) -> Option<&Signature> {
+        Some(&self.signature)
+    }
+
+    fn evaluate(&self, args: &[Rcvar], ctx: &mut Context<'_>) -> SearchResult {
+        self.signature.validate(args, ctx)?;
+        round!(args, ctx, trunc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{compile, ErrorReason, RuntimeError, Variable};
+
+    fn search(expr: &str, json: &str) -> String {
+        let data = Variable::from_json(json).unwrap();
+        compile(expr).unwrap().search(data).unwrap().to_string()
+    }
+
+    #[test]
+    fn computes_roots_powers_and_logarithms() {
+        assert_eq!("3.0", search("sqrt(`9`)", "null"));
+        assert_eq!("null", search("sqrt(`-1`)", "null"));
+        assert_eq!("1024", search("pow(`2`, `10`)", "null"));
+        assert_eq!("0.5", search("pow(`2`, `-1`)", "null"));
+        assert_eq!("1.5", search("pow(`2.25`, `0.5`)", "null"));
+        assert_eq!("1e+300", search("pow(`10`, `300`)", "null"));
+        assert_eq!("0.0", search("log(`1`)", "null"));
+        assert_eq!("3.0", search("log(`1000`, `10`)", "null"));
+        assert_eq!("10.0", search("log(`1024`, `2`)", "null"));
+        assert_eq!("4.0", search("log(`81`, `3`)", "null"));
+        assert_eq!("null", search("log(`0`)", "null"));
+    }
+
+    #[test]
+    fn rounds_numbers() {
+        assert_eq!("3", search("round(`2.5`)", "null"));
+        assert_eq!("-3", search("round(`-2.5`)", "null"));
+        assert_eq!("3.14", search("round(`3.14159`, `2`)", "null"));
+        assert_eq!("1200", search("round(`1234`, `-2`)", "null"));
+        assert_eq!("7", search("round(`7`, `2`)", "null"));
+        assert_eq!("3.141", search("trunc(`3.14159`, `3`)", "null"));
+        assert_eq!("-2", search("trunc(`-2.9`)", "null"));
+        let invalid = ErrorReason::Runtime(RuntimeError::InvalidValue {
+            function: Some("round".to_owned()),
+            position: 1,
+            message: "Precision must be an integer between -308 and 308".to_owned(),
+        });
+        for expr in ["round(`1.5`, `0.5`)", "round(`1.5`, `400`)"].iter() {
+            let err = compile(expr).unwrap().search(()).unwrap_err();
+            assert_eq!(invalid, err.reason);
+        }
+    }
+
+    #[test]
+    fn computes_signs() {
+        assert_eq!(
+            "[-1,0,1]",
+            search("[sign(`-4.5`), sign(`0`), sign(`3`)]", "true")
+        );
+    }
+
+    #[test]
+    fn divides_integers() {
+        assert_eq!("3", search("div(`7`, `2`)", "null"));
+        assert_eq!("-4", search("div(`-7`, `2`)", "null"));
+        assert_eq!("1", search("mod(`7`, `2`)", "null"));
+        assert_eq!("1", search("mod(`-7`, `2`)", "null"));
+        assert!(compile("div(`1`, `0`)").unwrap().search(()).is_err());
+        assert!(compile("mod(`1`, `0`)").unwrap().search(()).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! Random generation of syntactically valid JMESPath expressions.
+//!
+//! Generated expressions are intended for differential fuzzing against
+//! other JMESPath implementations. Every generated expression parses, but
+//! it may fail at runtime (e.g., when calling a function with arguments of
+//! the wrong type). The mix of generated features is controlled with
+//! `GeneratorOptions`, and generation is deterministic for a given seed.
+//!
+//! ```
+//! use jmespath::generator::{Generator, GeneratorOptions};
+//!
+//! let options = GeneratorOptions {
+//!     functions: 0,
+//!     ..GeneratorOptions::default()
+//! };
+//! let mut generator = Generator::with_options(42, options);
+//! for _ in 0..10 {
+//!     let expression = generator.generate();
+//!     assert!(jmespath::compile(&expression).is_ok());
+//! }
+//! ```
+//!
+//! A cargo-fuzz target can derive the seed from the fuzzer input:
+//!
+//! ```
+//! use jmespath::generator::Generator;
+//!
+//! let data: &[u8] = b"fuzzer input";
+//! let expression = Generator::from_bytes(data).generate();
+//! assert!(jmespath::compile(&expression).is_ok());
+//! ```
+
+/// Controls the shape of generated expressions.
+///
+/// Each feature has a relative weight; a weight of `0` disables the
+/// feature. Plain field references, identity, and literals are always
+/// generated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratorOptions {
+    /// Maximum nesting depth of generated expressions.
+    pub max_depth: usize,
+    /// Weight of subexpressions, index expressions, and slices.
+    pub paths: u32,
+    /// Weight of wildcard, flatten, and object projections.
+    pub projections: u32,
+    /// Weight of filter projections.
+    pub filters: u32,
+    /// Weight of calls to the builtin functions.
+    pub functions: u32,
+    /// Weight of multi-select lists and hashes.
+    pub multiselects: u32,
+    /// Weight of pipe, `||`, `&&`, `!`, and comparison expressions.
+    pub operators: u32,
+    /// Weight of syntax not in the original JMESPath specification, such
+    /// as arithmetic operators and let expressions. Defaults to `0`.
+    pub extensions: u32,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            max_depth: 4,
+            paths: 4,
+            projections: 2,
+            filters: 1,
+            functions: 1,
+            multiselects: 1,
+            operators: 2,
+            extensions: 0,
+        }
+    }
+}
+
+const IDENTIFIERS: &[&str] = &["foo", "bar", "baz", "a", "b", "\"with space\"", "\"1\""];
+
+const LITERALS: &[&str] = &[
+    "`1`",
+    "`-2.5`",
+    "`true`",
+    "`false`",
+    "`null`",
+    "`\"str\"`",
+    "`[1, 2]`",
+    "`{\"a\": 1}`",
+    "'raw'",
+];
+
+const ARITHMETIC_OPERATORS: &[&str] = &["+", "-", "*", "/", "//", "%"];
+
+const COMPARATORS: &[&str] = &["==", "!=", "<", "<=", ">", ">="];
+
+/// Functions from the original specification and the number of arguments
+/// they accept. Variadic functions list their minimum arity.
+const FUNCTIONS: &[(&str, usize, bool)] = &[
+    ("abs", 1, false),
+    ("avg", 1, false),
+    ("ceil", 1, false),
+    ("contains", 2, false),
+    ("ends_with", 2, false),
+    ("floor", 1, false),
+    ("join", 2, false),
+    ("keys", 1, false),
+    ("length", 1, false),
+    ("max", 1, false),
+    ("merge", 1, true),
+    ("min", 1, false),
+    ("not_null", 1, true),
+    ("reverse", 1, false),
+    ("sort", 1, false),
+    ("starts_with", 2, false),
+    ("sum", 1, false),
+    ("to_array", 1, false),
+    ("to_number", 1, false),
+    ("to_string", 1, false),
+    ("type", 1, false),
+    ("values", 1, false),
+];
+
+/// Functions that take an array and an expression reference.
+const EXPREF_FUNCTIONS: &[&str] = &["max_by", "min_by", "sort_by"];
+
+#[derive(Clone, Copy)]
+enum Form {
+    Path,
+    Projection,
+    Filter,
+    Function,
+    Multiselect,
+    Operator,
+    Extension,
+}
+
+/// Generates random JMESPath expressions.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    state: u64,
+    options: GeneratorOptions,
+}
+
+impl Generator {
+    /// Creates a generator using the default options.
+    pub fn new(seed: u64) -> Generator {
+        Generator::with_options(seed, GeneratorOptions::default())
+    }
+
+    /// Creates a generator using the given options.
+    pub fn with_options(seed: u64, options: GeneratorOptions) -> Generator {
+        Generator {
+            state: seed,
+            options,
+        }
+    }
+
+    /// Creates a generator seeded from arbitrary bytes, such as fuzzer input.
+    pub fn from_bytes(data: &[u8]) -> Generator {
+        // FNV-1a, so that every byte of the input affects the seed.
+        let seed = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        Generator::new(seed)
+    }
+
+    /// Returns the next randomly generated expression.
+    pub fn generate(&mut self) -> String {
+        let depth = self.options.max_depth;
+        self.expr(depth)
+    }
+
+    /// splitmix64, which is small and has no dependencies.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'s>(&mut self, items: &[&'s str]) -> &'s str {
+        items[self.below(items.len())]
+    }
+
+    fn choose_form(&mut self) -> Option<Form> {
+        let options = &self.options;
+        let weights = [
+            (Form::Path, options.paths),
+            (Form::Projection, options.projections),
+            (Form::Filter, options.filters),
+            (Form::Function, options.functions),
+            (Form::Multiselect, options.multiselects),
+            (Form::Operator, options.operators),
+            (Form::Extension, options.extensions),
+        ];
+        let total: u64 = weights.iter().map(|&(_, w)| u64::from(w)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = self.next_u64() % total;
+        for &(form, weight) in &weights {
+            if roll < u64::from(weight) {
+                return Some(form);
+            }
+            roll -= u64::from(weight);
+        }
+        None
+    }
+
+    fn leaf(&mut self) -> String {
+        match self.below(4) {
+            0 => "@".to_owned(),
+            1 => self.pick(LITERALS).to_owned(),
+            _ => self.pick(IDENTIFIERS).to_owned(),
+        }
+    }
+
+    /// Generates an expression that can be followed by `.`, `[`, or an operator.
+    fn operand(&mut self, depth: usize) -> String {
+        let expr = self.expr(depth);
+        format!("({})", expr)
+    }
+
+    fn expr(&mut self, depth: usize) -> String {
+        if depth == 0 {
+            return self.leaf();
+        }
+        let next = depth - 1;
+        match self.choose_form() {
+            None => self.leaf(),
+            Some(Form::Path) => match self.below(4) {
+                0 => {
+                    let lhs = self.operand(next);
+                    format!("{}.{}", lhs, self.pick(IDENTIFIERS))
+                }
+                1 => {
+                    let lhs = self.operand(next);
+                    format!("{}[{}]", lhs, self.below(5) as i64 - 2)
+                }
+                2 => {
+                    let lhs = self.operand(next);
+                    let (start, stop, step) = (self.below(4), self.below(4), self.below(3) + 1);
+                    format!("{}[{}:{}:{}]", lhs, start, stop, step)
+                }
+                _ => format!("{}.{}", self.pick(IDENTIFIERS), self.pick(IDENTIFIERS)),
+            },
+            Some(Form::Projection) => {
+                let lhs = self.operand(next);
+                let rhs = self.pick(IDENTIFIERS);
+                match self.below(3) {
+                    0 => format!("{}[*].{}", lhs, rhs),
+                    1 => format!("{}[].{}", lhs, rhs),
+                    _ => format!("{}.*.{}", lhs, rhs),
+                }
+            }
+            Some(Form::Filter) => {
+                let lhs = self.operand(next);
+                let predicate = self.expr(next);
+                format!("{}[?{}]", lhs, predicate)
+            }
+            Some(Form::Function) => {
+                if self.below(4) == 0 {
+                    let name = self.pick(EXPREF_FUNCTIONS);
+                    let (array, key) = (self.expr(next), self.expr(next));
+                    return format!("{}({}, &{})", name, array, key);
+                }
+                let (name, arity, variadic) = FUNCTIONS[self.below(FUNCTIONS.len())];
+                let count = if variadic {
+                    arity + self.below(3)
+                } else {
+                    arity
+                };
+                let args = (0..count).map(|_| self.expr(next)).collect::<Vec<_>>();
+                format!("{}({})", name, args.join(", "))
+            }
+            Some(Form::Multiselect) => {
+                let count = self.below(3) + 1;
+                if self.below(2) == 0 {
+                    let elements = (0..count).map(|_| self.expr(next)).collect::<Vec<_>>();
+                    format!("[{}]", elements.join(", "))
+                } else {
+                    let elements = (0..count)
+                        .map(|i| format!("k{}: {}", i, self.expr(next)))
+                        .collect::<Vec<_>>();
+                    format!("{{{}}}", elements.join(", "))
+                }
+            }
+            Some(Form::Operator) => {
+                if self.below(5) == 0 {
+                    return format!("!{}", self.operand(next));
+                }
+                let operator = match self.below(4) {
+                    0 => "|",
+                    1 => "||",
+                    2 => "&&",
+                    _ => self.pick(COMPARATORS),
+                };
+                let (lhs, rhs) = (self.operand(next), self.operand(next));
+                format!("{} {} {}", lhs, operator, rhs)
+            }
+            Some(Form::Extension) => {
+                if self.below(3) == 0 {
+                    let (value, body) = (self.operand(next), self.operand(next));
+                    return format!("let $v = {} in [$v, {}]", value, body);
+                }
+                let operator = self.pick(ARITHMETIC_OPERATORS);
+                let (lhs, rhs) = (self.operand(next), self.operand(next));
+                format!("{} {} {}", lhs, operator, rhs)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn generates_parseable_expressions() {
+        let options = GeneratorOptions {
+            extensions: 1,
+            ..GeneratorOptions::default()
+        };
+        let mut generator = Generator::with_options(7, options);
+        for _ in 0..2000 {
+            let expression = generator.generate();
+            if let Err(e) = parse(&expression) {
+                panic!("{}\n{}", expression, e);
+            }
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let first: Vec<String> = {
+            let mut generator = Generator::new(1);
+            (0..10).map(|_| generator.generate()).collect()
+        };
+        let mut generator = Generator::new(1);
+        for expected in first {
+            assert_eq!(expected, generator.generate());
+        }
+    }
+
+    #[test]
+    fn disabled_features_are_not_generated() {
+        let options = GeneratorOptions {
+            paths: 0,
+            projections: 0,
+            filters: 1,
+            functions: 0,
+            multiselects: 0,
+            operators: 0,
+            ..GeneratorOptions::default()
+        };
+        let mut generator = Generator::with_options(3, options);
+        for _ in 0..100 {
+            let expression = generator.generate();
+            assert!(!expression.contains('|'), "{}", expression);
+            assert!(!expression.contains("[*]"), "{}", expression);
+        }
+    }
+}
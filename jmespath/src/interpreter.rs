@@ -2,33 +2,65 @@
 
 use std::collections::BTreeMap;
 
-use super::ast::Ast;
-use super::variable::Variable;
+use serde_json::Number;
+
+use super::ast::{ArithmeticOperator, Ast, Comparator, UnaryOperator};
+use super::functions::ArgumentType;
+use super::ir::{Instruction, Program};
+use super::profile;
+use super::variable::{find_ignore_case, slice_str, JmespathType, Variable};
 use super::Context;
-use super::{ErrorReason, JmespathError, Rcvar, RuntimeError};
+use super::{DivisionByZero, NullPropagation, Overflow};
+use super::{ErrorReason, JmespathError, Operand, Rcvar, RuntimeError};
 
 /// Result of searching data using a JMESPath Expression.
 pub type SearchResult = Result<Rcvar, JmespathError>;
 
 /// Interprets the given data using an AST node.
+///
+/// Returns `RuntimeError::MaxDepthExceeded` instead of recursing past
+/// `SearchOptions::max_depth` nested nodes.
 pub fn interpret(data: &Rcvar, node: &Ast, ctx: &mut Context<'_>) -> SearchResult {
+    enter(node, ctx)?;
+    let result = profile::record(node, ctx, |ctx| interpret_node(data, node, ctx));
+    ctx.depth -= 1;
+    result
+}
+
+/// Counts the evaluation of a node and increments the depth, returning
+/// an error if it would exceed `SearchOptions::max_depth`.
+pub(crate) fn enter(node: &Ast, ctx: &mut Context<'_>) -> Result<(), JmespathError> {
+    ctx.evaluated_nodes += 1;
+    if let Some(max_depth) = ctx.options.max_depth {
+        if ctx.depth >= max_depth {
+            ctx.offset = node.offset();
+            return Err(JmespathError::from_ctx(
+                ctx,
+                ErrorReason::Runtime(RuntimeError::MaxDepthExceeded(max_depth)),
+            ));
+        }
+    }
+    ctx.depth += 1;
+    Ok(())
+}
+
+/// Interprets a single AST node, recursing into its children.
+fn interpret_node(data: &Rcvar, node: &Ast, ctx: &mut Context<'_>) -> SearchResult {
     match *node {
-        Ast::Field { ref name, .. } => Ok(data.get_field(name)),
+        Ast::Field { ref name, offset } => field(data, name, offset, ctx),
         Ast::Subexpr {
-            ref lhs, ref rhs, ..
+            ref lhs,
+            ref rhs,
+            offset,
         } => {
             let left_result = interpret(data, lhs, ctx)?;
+            check_not_null(&left_result, offset, ctx)?;
             interpret(&left_result, rhs, ctx)
         }
         Ast::Identity { .. } => Ok(data.clone()),
+        Ast::Root { .. } => Ok(ctx.root.clone()),
         Ast::Literal { ref value, .. } => Ok(value.clone()),
-        Ast::Index { idx, .. } => {
-            if idx >= 0 {
-                Ok(data.get_index(idx as usize))
-            } else {
-                Ok(data.get_negative_index((-idx) as usize))
-            }
-        }
+        Ast::Index { idx, offset } => index(data, idx, offset, ctx),
         Ast::Or {
             ref lhs, ref rhs, ..
         } => {
@@ -74,52 +106,75 @@ pub fn interpret(data: &Rcvar, node: &Ast, ctx: &mut Context<'_>) -> SearchResul
         } => {
             let left = interpret(data, lhs, ctx)?;
             let right = interpret(data, rhs, ctx)?;
-            Ok(left
-                .compare(comparator, &*right)
-                .map_or(Rcvar::new(Variable::Null), |result| {
-                    Rcvar::new(Variable::Bool(result))
-                }))
+            Ok(compare(comparator, &left, &right, ctx))
         }
-        // Converts an object into a JSON array of its values.
-        Ast::ObjectValues { ref node, .. } => {
-            let subject = interpret(data, node, ctx)?;
-            match *subject {
-                Variable::Object(ref v) => Ok(Rcvar::new(Variable::Array(
-                    v.values().cloned().collect::<Vec<Rcvar>>(),
-                ))),
-                _ => Ok(Rcvar::new(Variable::Null)),
+        Ast::Arithmetic {
+            ref operator,
+            ref lhs,
+            ref rhs,
+            offset,
+        } => {
+            let left = interpret(data, lhs, ctx)?;
+            let right = interpret(data, rhs, ctx)?;
+            ctx.offset = offset;
+            arithmetic(operator, &left, &right, ctx)
+        }
+        // Evaluates every binding against the current node before any of
+        // them is in scope, then evaluates the body.
+        Ast::Let {
+            ref bindings,
+            ref body,
+            ..
+        } => {
+            let mut values = Vec::with_capacity(bindings.len());
+            for binding in bindings {
+                values.push((binding.name.clone(), interpret(data, &binding.value, ctx)?));
             }
+            let len = ctx.scope.len();
+            ctx.scope.extend(values);
+            let result = interpret(data, body, ctx);
+            ctx.scope.truncate(len);
+            result
+        }
+        Ast::VariableRef { ref name, offset } => variable(name, offset, ctx),
+        Ast::Unary {
+            ref operator,
+            ref node,
+            offset,
+        } => {
+            let value = interpret(data, node, ctx)?;
+            ctx.offset = offset;
+            unary(operator, &value, ctx)
         }
+        // Converts an object into a JSON array of its values.
+        Ast::ObjectValues { ref node, .. } => Ok(object_values(&*interpret(data, node, ctx)?)),
         // Passes the results of lhs into rhs if lhs yields an array and
         // each node of lhs that passes through rhs yields a non-null value.
         Ast::Projection {
             ref lhs, ref rhs, ..
-        } => match interpret(data, lhs, ctx)?.as_array() {
-            None => Ok(Rcvar::new(Variable::Null)),
-            Some(left) => {
-                let mut collected = vec![];
-                for element in left {
-                    let current = interpret(element, rhs, ctx)?;
-                    if !current.is_null() {
-                        collected.push(current);
-                    }
-                }
-                Ok(Rcvar::new(Variable::Array(collected)))
+        } => {
+            let left = interpret(data, lhs, ctx)?;
+            // A sliced string is not projected; the rhs applies to the substring.
+            if left.is_string() && matches!(**lhs, Ast::Slice { .. }) {
+                return interpret(&left, rhs, ctx);
             }
-        },
-        Ast::Flatten { ref node, .. } => match interpret(data, node, ctx)?.as_array() {
-            None => Ok(Rcvar::new(Variable::Null)),
-            Some(a) => {
-                let mut collected: Vec<Rcvar> = vec![];
-                for element in a {
-                    match element.as_array() {
-                        Some(array) => collected.extend(array.iter().cloned()),
-                        _ => collected.push(element.clone()),
+            match project_object(left, ctx).as_array() {
+                None => Ok(Rcvar::new(Variable::Null)),
+                Some(left) => {
+                    let mut collected = vec![];
+                    for element in left {
+                        let current = interpret(element, rhs, ctx)?;
+                        let duplicate =
+                            ctx.options.dedup_projections && collected.contains(&current);
+                        if !current.is_null() && !duplicate {
+                            collected.push(current);
+                        }
                     }
+                    Ok(Rcvar::new(Variable::Array(collected)))
                 }
-                Ok(Rcvar::new(Variable::Array(collected)))
             }
-        },
+        }
+        Ast::Flatten { ref node, .. } => Ok(flatten(interpret(data, node, ctx)?, ctx)),
         Ast::MultiList { ref elements, .. } => {
             if data.is_null() {
                 Ok(Rcvar::new(Variable::Null))
@@ -152,34 +207,965 @@ pub fn interpret(data: &Rcvar, node: &Ast, ctx: &mut Context<'_>) -> SearchResul
             for arg in args {
                 fn_args.push(interpret(data, arg, ctx)?);
             }
-            // Reset the offset so that it points to the function being evaluated.
-            ctx.offset = offset;
-            match ctx.runtime.get_function(name) {
-                Some(f) => f.evaluate(&fn_args, ctx),
-                None => {
-                    let reason =
-                        ErrorReason::Runtime(RuntimeError::UnknownFunction(name.to_owned()));
-                    Err(JmespathError::from_ctx(ctx, reason))
-                }
+            call_function(name, &fn_args, data, offset, ctx)
+        }
+        Ast::Expref { ref ast, .. } => {
+            let expref = Rcvar::new(Variable::Expref(*ast.clone()));
+            if let (Some(profiler), Some(clone)) = (ctx.profiler.as_mut(), expref.as_expref()) {
+                profiler.alias(clone, ast);
             }
+            Ok(expref)
         }
-        Ast::Expref { ref ast, .. } => Ok(Rcvar::new(Variable::Expref(*ast.clone()))),
         Ast::Slice {
             start,
             stop,
             step,
             offset,
-        } => {
-            if step == 0 {
+        } => slice(data, start, stop, step, offset, ctx),
+    }
+}
+
+/// A projection frame of the VM.
+struct Frame {
+    /// The array being projected.
+    elements: Rcvar,
+    /// Index of the element being evaluated.
+    position: usize,
+    /// Non-null results of the elements evaluated so far.
+    collected: Vec<Rcvar>,
+}
+
+/// Executes a lowered program against data.
+///
+/// The VM produces the same results as `interpret` and honors the same
+/// `SearchOptions`. `Context::evaluated_nodes` counts the executed
+/// instructions rather than AST nodes.
+pub(crate) fn execute(program: &Program, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult {
+    let scope = ctx.scope.len();
+    let result = run(program, data, ctx);
+    // Errors skip the `Unbind` instructions of enclosing let expressions.
+    ctx.scope.truncate(scope);
+    result
+}
+
+fn run(program: &Program, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult {
+    let instructions = program.instructions();
+    let mut stack = vec![data.clone()];
+    let mut frames: Vec<Frame> = vec![];
+    let mut pc = 0;
+    // Lowered programs never underflow the stack, but programs built from
+    // arbitrary instructions can.
+    macro_rules! pop {
+        () => {
+            match stack.pop() {
+                Some(value) => value,
+                None => return Err(invalid_program(ctx, pc - 1, "stack underflow")),
+            }
+        };
+    }
+    macro_rules! top {
+        () => {
+            match stack.last_mut() {
+                Some(value) => value,
+                None => return Err(invalid_program(ctx, pc - 1, "stack underflow")),
+            }
+        };
+    }
+    // Returns the index of the first of the top `count` values.
+    macro_rules! start {
+        ($count:expr) => {
+            match stack.len().checked_sub($count) {
+                Some(start) => start,
+                None => return Err(invalid_program(ctx, pc - 1, "stack underflow")),
+            }
+        };
+    }
+    while let Some(instruction) = instructions.get(pc) {
+        ctx.evaluated_nodes += 1;
+        pc += 1;
+        match *instruction {
+            Instruction::Dup => {
+                let value = top!().clone();
+                stack.push(value);
+            }
+            Instruction::Swap => {
+                let start = start!(2);
+                stack.swap(start, start + 1);
+            }
+            Instruction::Pop => {
+                pop!();
+            }
+            Instruction::Field { ref name, offset } => {
+                let value = field(top!(), name, offset, ctx)?;
+                *top!() = value;
+            }
+            Instruction::Index { idx, offset } => {
+                let value = index(top!(), idx, offset, ctx)?;
+                *top!() = value;
+            }
+            Instruction::Slice {
+                start,
+                stop,
+                step,
+                offset,
+            } => {
+                let value = slice(top!(), start, stop, step, offset, ctx)?;
+                *top!() = value;
+            }
+            Instruction::Literal(ref value) => *top!() = value.clone(),
+            Instruction::Root => *top!() = ctx.root.clone(),
+            Instruction::Expref(ref ast) => *top!() = Rcvar::new(Variable::Expref(ast.clone())),
+            Instruction::CheckNotNull { offset } => check_not_null(top!(), offset, ctx)?,
+            Instruction::Not => {
+                let value = !top!().is_truthy();
+                *top!() = Rcvar::new(Variable::Bool(value));
+            }
+            Instruction::Compare(ref comparator) => {
+                let right = pop!();
+                let left = pop!();
+                stack.push(compare(comparator, &left, &right, ctx));
+            }
+            Instruction::Arithmetic {
+                ref operator,
+                offset,
+            } => {
+                let right = pop!();
+                let left = pop!();
+                ctx.offset = offset;
+                stack.push(arithmetic(operator, &left, &right, ctx)?);
+            }
+            Instruction::Unary {
+                ref operator,
+                offset,
+            } => {
                 ctx.offset = offset;
-                let reason = ErrorReason::Runtime(RuntimeError::InvalidSlice);
-                Err(JmespathError::from_ctx(ctx, reason))
+                let value = unary(operator, top!(), ctx)?;
+                *top!() = value;
+            }
+            Instruction::OrElse(target) => {
+                let value = pop!();
+                if value.is_truthy() {
+                    *top!() = value;
+                    pc = target;
+                }
+            }
+            Instruction::AndThen(target) => {
+                let value = pop!();
+                if !value.is_truthy() {
+                    *top!() = value;
+                    pc = target;
+                }
+            }
+            Instruction::Filter(target) => {
+                if !pop!().is_truthy() {
+                    *top!() = Rcvar::new(Variable::Null);
+                    pc = target;
+                }
+            }
+            Instruction::JumpIfNull(target) => {
+                if top!().is_null() {
+                    pc = target;
+                }
+            }
+            Instruction::MakeArray(len) => {
+                let elements = stack.split_off(start!(len));
+                stack.push(Rcvar::new(Variable::Array(elements)));
+            }
+            Instruction::MakeObject(ref keys) => {
+                let start = start!(keys.len());
+                let object = keys.iter().cloned().zip(stack.drain(start..)).collect();
+                stack.push(Rcvar::new(Variable::Object(object)));
+            }
+            Instruction::Call {
+                ref name,
+                args,
+                offset,
+            } => {
+                let current = pop!();
+                let start = start!(args);
+                let result = call_function(name, &stack[start..], &current, offset, ctx)?;
+                stack.truncate(start);
+                stack.push(result);
+            }
+            Instruction::Bind(ref names) => {
+                let current = pop!();
+                let start = start!(names.len());
+                let values = stack.drain(start..);
+                ctx.scope.extend(names.iter().cloned().zip(values));
+                stack.push(current);
+            }
+            Instruction::Unbind(count) => match ctx.scope.len().checked_sub(count) {
+                Some(len) => ctx.scope.truncate(len),
+                None => return Err(invalid_program(ctx, pc - 1, "no variables to unbind")),
+            },
+            Instruction::LoadVariable { ref name, offset } => {
+                *top!() = variable(name, offset, ctx)?;
+            }
+            Instruction::ObjectValues => {
+                let value = object_values(top!());
+                *top!() = value;
+            }
+            Instruction::Flatten => {
+                let value = flatten(pop!(), ctx);
+                stack.push(value);
+            }
+            Instruction::SliceProjectStart(_) if top!().is_string() => {
+                let value = top!().clone();
+                frames.push(Frame {
+                    elements: value,
+                    position: 0,
+                    collected: vec![],
+                });
+            }
+            Instruction::ProjectStart(target) | Instruction::SliceProjectStart(target) => {
+                let elements = project_object(pop!(), ctx);
+                match elements.as_array().and_then(|a| a.first()) {
+                    Some(first) => {
+                        stack.push(first.clone());
+                        frames.push(Frame {
+                            elements: elements.clone(),
+                            position: 0,
+                            collected: vec![],
+                        });
+                    }
+                    None => {
+                        if elements.is_array() {
+                            stack.push(elements);
+                        } else {
+                            stack.push(Rcvar::new(Variable::Null));
+                        }
+                        pc = target;
+                    }
+                }
+            }
+            Instruction::ProjectEnd(target) => {
+                let current = pop!();
+                let frame = match frames.last_mut() {
+                    Some(frame) => frame,
+                    None => return Err(invalid_program(ctx, pc - 1, "no projection to end")),
+                };
+                // The frame of a sliced string yields the result of its only
+                // iteration as-is.
+                if !frame.elements.is_array() {
+                    frames.pop();
+                    stack.push(current);
+                    continue;
+                }
+                let duplicate = ctx.options.dedup_projections && frame.collected.contains(&current);
+                if !current.is_null() && !duplicate {
+                    frame.collected.push(current);
+                }
+                frame.position += 1;
+                match frame
+                    .elements
+                    .as_array()
+                    .and_then(|a| a.get(frame.position))
+                {
+                    Some(next) => {
+                        stack.push(next.clone());
+                        pc = target;
+                    }
+                    None => {
+                        let collected = std::mem::take(&mut frame.collected);
+                        frames.pop();
+                        stack.push(Rcvar::new(Variable::Array(collected)));
+                    }
+                }
+            }
+        }
+    }
+    match stack.pop() {
+        Some(result) => Ok(result),
+        None => Err(invalid_program(ctx, pc, "no result on the stack")),
+    }
+}
+
+/// Creates the error returned when the VM executes a malformed program.
+fn invalid_program(ctx: &Context<'_>, instruction: usize, message: &str) -> JmespathError {
+    let reason = ErrorReason::Runtime(RuntimeError::InvalidProgram {
+        instruction,
+        message: message.to_owned(),
+    });
+    JmespathError::from_ctx(ctx, reason)
+}
+
+/// Lazily yields the results of a projection.
+///
+/// The left hand side of a projection is evaluated when the iterator is
+/// created, and the right hand side is evaluated for one element each time
+/// the iterator is advanced. Other expressions yield a single result.
+pub(crate) struct SearchIter<'a> {
+    ctx: Context<'a>,
+    state: IterState<'a>,
+}
+
+enum IterState<'a> {
+    /// A result that has not been yielded yet.
+    Single(Option<SearchResult>),
+    /// A projection whose remaining elements have not been evaluated.
+    Projection {
+        rhs: &'a Ast,
+        elements: Rcvar,
+        position: usize,
+        /// Results yielded so far, kept when `dedup_projections` is set.
+        seen: Vec<Rcvar>,
+    },
+}
+
+impl<'a> SearchIter<'a> {
+    /// Starts searching `data` using the given AST.
+    pub(crate) fn new(data: &Rcvar, node: &'a Ast, mut ctx: Context<'a>) -> SearchIter<'a> {
+        let state = match *node {
+            Ast::Projection {
+                ref lhs, ref rhs, ..
+            } => match interpret(data, lhs, &mut ctx) {
+                Ok(left) if left.is_string() && matches!(**lhs, Ast::Slice { .. }) => {
+                    IterState::Single(Some(interpret(&left, rhs, &mut ctx)))
+                }
+                Ok(left) => {
+                    let elements = project_object(left, &ctx);
+                    if elements.is_array() {
+                        IterState::Projection {
+                            rhs,
+                            elements,
+                            position: 0,
+                            seen: vec![],
+                        }
+                    } else {
+                        IterState::Single(None)
+                    }
+                }
+                Err(err) => IterState::Single(Some(Err(err))),
+            },
+            _ => IterState::Single(Some(interpret(data, node, &mut ctx))),
+        };
+        SearchIter { ctx, state }
+    }
+
+    /// Creates an iterator that only yields the given error.
+    pub(crate) fn error(err: JmespathError, ctx: Context<'a>) -> SearchIter<'a> {
+        SearchIter {
+            ctx,
+            state: IterState::Single(Some(Err(err))),
+        }
+    }
+}
+
+impl<'a> Iterator for SearchIter<'a> {
+    type Item = SearchResult;
+
+    fn next(&mut self) -> Option<SearchResult> {
+        let (rhs, elements, position, seen) = match self.state {
+            IterState::Single(ref mut result) => return result.take(),
+            IterState::Projection {
+                rhs,
+                ref elements,
+                ref mut position,
+                ref mut seen,
+            } => (rhs, elements, position, seen),
+        };
+        // Projections are only created over arrays.
+        let elements = elements.as_array()?;
+        while let Some(element) = elements.get(*position) {
+            *position += 1;
+            let current = match interpret(element, rhs, &mut self.ctx) {
+                Ok(current) => current,
+                Err(err) => {
+                    self.state = IterState::Single(None);
+                    return Some(Err(err));
+                }
+            };
+            if current.is_null() {
+                continue;
+            }
+            if self.ctx.options.dedup_projections {
+                if seen.contains(&current) {
+                    continue;
+                }
+                seen.push(current.clone());
+            }
+            return Some(Ok(current));
+        }
+        None
+    }
+}
+
+/// Returns an error if a subexpression's left side is null and
+/// `NullPropagation::Error` is in effect.
+pub(crate) fn check_not_null(
+    value: &Variable,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> Result<(), JmespathError> {
+    if value.is_null() && ctx.options.null_propagation == NullPropagation::Error {
+        ctx.offset = offset;
+        let reason = ErrorReason::Runtime(RuntimeError::NullValue);
+        return Err(JmespathError::from_ctx(ctx, reason));
+    }
+    Ok(())
+}
+
+/// Returns the value bound to a variable by the innermost let expression
+/// that binds it.
+pub(crate) fn variable(name: &str, offset: usize, ctx: &mut Context<'_>) -> SearchResult {
+    match ctx.scope.iter().rev().find(|(bound, _)| bound == name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => {
+            ctx.offset = offset;
+            let reason = ErrorReason::Runtime(RuntimeError::UndefinedVariable(name.to_owned()));
+            Err(JmespathError::from_ctx(ctx, reason))
+        }
+    }
+}
+
+/// Returns an error if an object does not have a field, or the value is
+/// not an object. Only used when `SearchOptions::strict` is enabled.
+pub(crate) fn check_field(
+    actual: JmespathType,
+    found: bool,
+    name: &str,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> Result<(), JmespathError> {
+    let reason = match actual {
+        _ if found => return Ok(()),
+        JmespathType::Object => RuntimeError::MissingField(name.to_owned()),
+        actual => RuntimeError::InvalidAccess {
+            expected: JmespathType::Object,
+            actual,
+        },
+    };
+    ctx.offset = offset;
+    Err(JmespathError::from_ctx(ctx, ErrorReason::Runtime(reason)))
+}
+
+/// Returns an error if the value is not an array. Only used when
+/// `SearchOptions::strict` is enabled.
+pub(crate) fn check_array(
+    actual: JmespathType,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> Result<(), JmespathError> {
+    if actual == JmespathType::Array {
+        return Ok(());
+    }
+    ctx.offset = offset;
+    let reason = RuntimeError::InvalidAccess {
+        expected: JmespathType::Array,
+        actual,
+    };
+    Err(JmespathError::from_ctx(ctx, ErrorReason::Runtime(reason)))
+}
+
+/// Returns an error if an index is out of the bounds of an array of
+/// `length` elements, or the value is not an array. Only used when
+/// `SearchOptions::strict` is enabled.
+pub(crate) fn check_index(
+    actual: JmespathType,
+    length: usize,
+    idx: i32,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> Result<(), JmespathError> {
+    check_array(actual, offset, ctx)?;
+    let in_bounds = if idx >= 0 {
+        (idx as usize) < length
+    } else {
+        (-(idx as i64)) as usize <= length
+    };
+    if in_bounds {
+        return Ok(());
+    }
+    ctx.offset = offset;
+    let reason = RuntimeError::IndexOutOfBounds { index: idx, length };
+    Err(JmespathError::from_ctx(ctx, ErrorReason::Runtime(reason)))
+}
+
+/// Gets a field of an object, or returns null.
+fn field(data: &Rcvar, name: &str, offset: usize, ctx: &mut Context<'_>) -> SearchResult {
+    let ignore_case = ctx.options.case_insensitive_fields;
+    let value = data.as_object().and_then(|map| match map.get(name) {
+        None if ignore_case => find_ignore_case(map, name),
+        value => value,
+    });
+    if ctx.options.strict {
+        check_field(data.get_type(), value.is_some(), name, offset, ctx)?;
+    }
+    Ok(value.cloned().unwrap_or_else(|| Rcvar::new(Variable::Null)))
+}
+
+/// Gets an element of an array by index, counting from the end if negative.
+fn index(data: &Rcvar, idx: i32, offset: usize, ctx: &mut Context<'_>) -> SearchResult {
+    let data = project_object(data.clone(), ctx);
+    if ctx.options.strict {
+        let length = data.as_array().map_or(0, Vec::len);
+        check_index(data.get_type(), length, idx, offset, ctx)?;
+    }
+    Ok(if idx >= 0 {
+        data.get_index(idx as usize)
+    } else {
+        data.get_negative_index((-idx) as usize)
+    })
+}
+
+/// Slices an array, or returns null. Fails if `step` is zero.
+fn slice(
+    data: &Rcvar,
+    start: Option<i32>,
+    stop: Option<i32>,
+    step: i32,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> SearchResult {
+    if step == 0 {
+        ctx.offset = offset;
+        let reason = ErrorReason::Runtime(RuntimeError::InvalidSlice);
+        Err(JmespathError::from_ctx(ctx, reason))
+    } else if let Some(s) = data.as_string().filter(|_| ctx.slices_strings()) {
+        Ok(Rcvar::new(Variable::String(slice_str(
+            s, start, stop, step,
+        ))))
+    } else {
+        let data = project_object(data.clone(), ctx);
+        if ctx.options.strict {
+            check_array(data.get_type(), offset, ctx)?;
+        }
+        match data.slice(start, stop, step) {
+            Some(array) => Ok(Rcvar::new(Variable::Array(array))),
+            None => Ok(Rcvar::new(Variable::Null)),
+        }
+    }
+}
+
+/// Converts an object into a JSON array of its values, or returns null.
+fn object_values(value: &Variable) -> Rcvar {
+    match *value {
+        Variable::Object(ref v) => {
+            Rcvar::new(Variable::Array(v.values().cloned().collect::<Vec<Rcvar>>()))
+        }
+        _ => Rcvar::new(Variable::Null),
+    }
+}
+
+/// Flattens one level of nested arrays, or returns null.
+fn flatten(value: Rcvar, ctx: &Context<'_>) -> Rcvar {
+    match project_object(value, ctx).as_array() {
+        None => Rcvar::new(Variable::Null),
+        Some(a) => {
+            let mut collected: Vec<Rcvar> = vec![];
+            for element in a {
+                match element.as_array() {
+                    Some(array) => collected.extend(array.iter().cloned()),
+                    _ => collected.push(element.clone()),
+                }
+            }
+            Rcvar::new(Variable::Array(collected))
+        }
+    }
+}
+
+/// Converts an object into an array of its values when
+/// `SearchOptions::project_objects` is enabled.
+fn project_object(value: Rcvar, ctx: &Context<'_>) -> Rcvar {
+    match *value {
+        Variable::Object(ref map) if ctx.options.project_objects => {
+            Rcvar::new(Variable::Array(map.values().cloned().collect()))
+        }
+        _ => value,
+    }
+}
+
+/// Records the called function's name on argument errors that do not have
+/// one.
+///
+/// Errors raised by nested function calls already carry the name of the
+/// innermost function, so they are left untouched.
+fn with_function_name(mut err: JmespathError, name: &str) -> JmespathError {
+    if let ErrorReason::Runtime(RuntimeError::InvalidType {
+        ref mut function, ..
+    })
+    | ErrorReason::Runtime(RuntimeError::InvalidReturnType {
+        ref mut function, ..
+    })
+    | ErrorReason::Runtime(RuntimeError::InvalidValue {
+        ref mut function, ..
+    }) = err.reason
+    {
+        if function.is_none() {
+            *function = Some(name.to_owned());
+        }
+    }
+    err
+}
+
+/// Applies an arithmetic operator to the evaluated operands.
+///
+/// Integer operands use integer arithmetic, falling back to floating point
+/// when either operand is a float or the integer operation overflows.
+pub(crate) fn arithmetic(
+    operator: &ArithmeticOperator,
+    left: &Rcvar,
+    right: &Rcvar,
+    ctx: &Context<'_>,
+) -> SearchResult {
+    if !left.is_number() {
+        return Err(invalid_operand(
+            ctx,
+            operator.to_string(),
+            Operand::Left,
+            left,
+        ));
+    }
+    if !right.is_number() {
+        return Err(invalid_operand(
+            ctx,
+            operator.to_string(),
+            Operand::Right,
+            right,
+        ));
+    }
+    #[cfg(feature = "decimal")]
+    if left.is_decimal() || right.is_decimal() {
+        return decimal_arithmetic(operator, left, right, ctx);
+    }
+    if is_division(operator) && right.as_number() == Some(0.0) {
+        return division_by_zero(ctx);
+    }
+    if let Some(result) = integer_arithmetic(operator, left, right, ctx)? {
+        return Ok(Rcvar::new(result));
+    }
+    let (a, b) = (
+        left.as_number().unwrap_or(0.0),
+        right.as_number().unwrap_or(0.0),
+    );
+    let result = match *operator {
+        ArithmeticOperator::Add => a + b,
+        ArithmeticOperator::Subtract => a - b,
+        ArithmeticOperator::Multiply => a * b,
+        ArithmeticOperator::Divide => a / b,
+        ArithmeticOperator::IntegerDivide => {
+            let q = (a / b).floor();
+            if q.abs() < 9_007_199_254_740_992.0 {
+                return Ok(Rcvar::new(Variable::Number(Number::from(q as i64))));
+            }
+            q
+        }
+        ArithmeticOperator::Modulo => {
+            let r = a % b;
+            if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                r + b
             } else {
-                match data.slice(start, stop, step) {
-                    Some(array) => Ok(Rcvar::new(Variable::Array(array))),
-                    None => Ok(Rcvar::new(Variable::Null)),
+                r
+            }
+        }
+    };
+    let result = check_float_overflow(result, ctx)?;
+    Ok(Rcvar::new(
+        Number::from_f64(result).map_or(Variable::Null, Variable::Number),
+    ))
+}
+
+/// Returns true if the operator fails or yields null when dividing by zero.
+fn is_division(operator: &ArithmeticOperator) -> bool {
+    matches!(
+        *operator,
+        ArithmeticOperator::Divide | ArithmeticOperator::IntegerDivide | ArithmeticOperator::Modulo
+    )
+}
+
+/// Applies a unary operator to the evaluated operand.
+pub(crate) fn unary(operator: &UnaryOperator, value: &Rcvar, ctx: &Context<'_>) -> SearchResult {
+    if !value.is_number() {
+        return Err(invalid_operand(
+            ctx,
+            operator.to_string(),
+            Operand::Unary,
+            value,
+        ));
+    }
+    match *operator {
+        UnaryOperator::Plus => Ok(value.clone()),
+        UnaryOperator::Minus => {
+            #[cfg(feature = "decimal")]
+            if value.is_decimal() {
+                let negated = value.as_decimal().map(|d| -d);
+                return Ok(Rcvar::new(
+                    negated.map_or(Variable::Null, Variable::Decimal),
+                ));
+            }
+            if let Some(i) = as_integer(value) {
+                let negated = check_overflow(
+                    i.checked_neg(),
+                    || i.wrapping_neg(),
+                    || i.saturating_neg(),
+                    ctx,
+                )?;
+                if let Some(n) = negated {
+                    return Ok(Rcvar::new(integer_variable(n)));
                 }
             }
+            let n = -value.as_number().unwrap_or(0.0);
+            Ok(Rcvar::new(
+                Number::from_f64(n).map_or(Variable::Null, Variable::Number),
+            ))
         }
     }
 }
+
+/// Compares two values, returning null if they cannot be compared.
+pub(crate) fn compare(
+    comparator: &Comparator,
+    left: &Variable,
+    right: &Variable,
+    ctx: &Context<'_>,
+) -> Rcvar {
+    if let (Some(epsilon), Some(a), Some(b)) =
+        (ctx.options.epsilon, left.as_number(), right.as_number())
+    {
+        return Rcvar::new(Variable::Bool(compare_within(comparator, a, b, epsilon)));
+    }
+    left.compare(comparator, right)
+        .map_or(Rcvar::new(Variable::Null), |result| {
+            Rcvar::new(Variable::Bool(result))
+        })
+}
+
+/// Calls the function registered under `name` with evaluated arguments.
+///
+/// `current` is the value the arguments were evaluated against, which the
+/// function can access using `Context::current`.
+pub(crate) fn call_function(
+    name: &str,
+    args: &[Rcvar],
+    current: &Rcvar,
+    offset: usize,
+    ctx: &mut Context<'_>,
+) -> SearchResult {
+    // Reset the offset so that it points to the function being evaluated.
+    ctx.offset = offset;
+    match ctx.runtime.get_function(name) {
+        Some(f) => {
+            let caller = std::mem::replace(&mut ctx.current, current.clone());
+            let result = f.evaluate(args, ctx);
+            ctx.current = caller;
+            result.map_err(|err| with_function_name(err, name))
+        }
+        None => {
+            let reason = ErrorReason::Runtime(RuntimeError::UnknownFunction(name.to_owned()));
+            Err(JmespathError::from_ctx(ctx, reason))
+        }
+    }
+}
+
+/// Compares two numbers, treating numbers within `epsilon` as equal.
+fn compare_within(comparator: &Comparator, a: f64, b: f64, epsilon: f64) -> bool {
+    let equal = (a - b).abs() <= epsilon;
+    match *comparator {
+        Comparator::Equal => equal,
+        Comparator::NotEqual => !equal,
+        Comparator::LessThan => !equal && a < b,
+        Comparator::LessThanEqual => equal || a < b,
+        Comparator::GreaterThan => !equal && a > b,
+        Comparator::GreaterThanEqual => equal || a > b,
+    }
+}
+
+/// Integer type used for exact integer arithmetic.
+#[cfg(feature = "big-integers")]
+pub(crate) type Integer = i128;
+/// Integer type used for exact integer arithmetic.
+#[cfg(not(feature = "big-integers"))]
+pub(crate) type Integer = i64;
+
+/// Returns the value as an `Integer` if it is an integer number.
+pub(crate) fn as_integer(value: &Variable) -> Option<Integer> {
+    #[cfg(feature = "big-integers")]
+    return value.as_i128();
+    #[cfg(not(feature = "big-integers"))]
+    match value {
+        Variable::Number(n) => n.as_i64(),
+        _ => None,
+    }
+}
+
+/// Creates a number from an `Integer`.
+pub(crate) fn integer_variable(value: Integer) -> Variable {
+    #[cfg(feature = "big-integers")]
+    return Variable::from_i128(value);
+    #[cfg(not(feature = "big-integers"))]
+    Variable::Number(Number::from(value))
+}
+
+/// Applies the overflow policy to the result of an integer operation.
+///
+/// `checked` is None if the operation overflowed, in which case the
+/// wrapping or saturating result is used depending on the policy. Returns
+/// None if the result must be computed using floating point numbers.
+pub(crate) fn check_overflow(
+    checked: Option<Integer>,
+    wrapping: impl FnOnce() -> Integer,
+    saturating: impl FnOnce() -> Integer,
+    ctx: &Context<'_>,
+) -> Result<Option<Integer>, JmespathError> {
+    match (checked, ctx.options.overflow) {
+        (Some(i), _) => Ok(Some(i)),
+        (None, Overflow::Float) => Ok(None),
+        (None, Overflow::Wrap) => Ok(Some(wrapping())),
+        (None, Overflow::Saturate) => Ok(Some(saturating())),
+        (None, Overflow::Error) => Err(overflow_error(ctx)),
+    }
+}
+
+/// Applies the overflow policy to a floating point result.
+///
+/// Results that overflow to infinity saturate at `f64::MAX` or `f64::MIN`
+/// with `Overflow::Saturate`, and are an error with `Overflow::Wrap` and
+/// `Overflow::Error`. Other results are returned unchanged.
+pub(crate) fn check_float_overflow(result: f64, ctx: &Context<'_>) -> Result<f64, JmespathError> {
+    if !result.is_infinite() {
+        return Ok(result);
+    }
+    match ctx.options.overflow {
+        Overflow::Float => Ok(result),
+        Overflow::Saturate => Ok(f64::MAX.copysign(result)),
+        Overflow::Wrap | Overflow::Error => Err(overflow_error(ctx)),
+    }
+}
+
+/// Creates the error returned when a result overflows and
+/// `Overflow::Error` is in effect.
+pub(crate) fn overflow_error(ctx: &Context<'_>) -> JmespathError {
+    JmespathError::from_ctx(ctx, ErrorReason::Runtime(RuntimeError::Overflow))
+}
+
+/// Applies an arithmetic operator to integer operands.
+///
+/// Returns None if either operand is not an integer, or if the operation
+/// overflows and the result must be computed using floating point numbers.
+fn integer_arithmetic(
+    operator: &ArithmeticOperator,
+    left: &Variable,
+    right: &Variable,
+    ctx: &Context<'_>,
+) -> Result<Option<Variable>, JmespathError> {
+    let (a, b) = match (as_integer(left), as_integer(right)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Ok(None),
+    };
+    let result = match *operator {
+        ArithmeticOperator::Add => check_overflow(
+            a.checked_add(b),
+            || a.wrapping_add(b),
+            || a.saturating_add(b),
+            ctx,
+        )?,
+        ArithmeticOperator::Subtract => check_overflow(
+            a.checked_sub(b),
+            || a.wrapping_sub(b),
+            || a.saturating_sub(b),
+            ctx,
+        )?,
+        ArithmeticOperator::Multiply => check_overflow(
+            a.checked_mul(b),
+            || a.wrapping_mul(b),
+            || a.saturating_mul(b),
+            ctx,
+        )?,
+        // Inexact quotients are computed using floating point numbers.
+        ArithmeticOperator::Divide if a.wrapping_rem(b) != 0 => None,
+        ArithmeticOperator::Divide => check_overflow(
+            a.checked_div(b),
+            || a.wrapping_div(b),
+            || a.saturating_div(b),
+            ctx,
+        )?,
+        ArithmeticOperator::IntegerDivide => check_overflow(
+            a.checked_div(b),
+            || a.wrapping_div(b),
+            || a.saturating_div(b),
+            ctx,
+        )?
+        .map(|q| {
+            // Round toward negative infinity rather than toward zero.
+            if a.wrapping_rem(b) != 0 && (a < 0) != (b < 0) {
+                q - 1
+            } else {
+                q
+            }
+        }),
+        // The remainder always fits, including for `MIN % -1`.
+        ArithmeticOperator::Modulo => {
+            let r = a.wrapping_rem(b);
+            // Give the remainder the sign of the divisor.
+            if r != 0 && (r < 0) != (b < 0) {
+                Some(r + b)
+            } else {
+                Some(r)
+            }
+        }
+    };
+    Ok(result.map(integer_variable))
+}
+
+/// Applies an arithmetic operator to numbers when either is a decimal.
+///
+/// Returns null if an operand or the result is out of the range of a
+/// decimal.
+#[cfg(feature = "decimal")]
+fn decimal_arithmetic(
+    operator: &ArithmeticOperator,
+    left: &Variable,
+    right: &Variable,
+    ctx: &Context<'_>,
+) -> SearchResult {
+    let (a, b) = match (left.as_decimal(), right.as_decimal()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Ok(Rcvar::new(Variable::Null)),
+    };
+    if is_division(operator) && b.is_zero() {
+        return division_by_zero(ctx);
+    }
+    let result = match *operator {
+        ArithmeticOperator::Add => a.checked_add(b),
+        ArithmeticOperator::Subtract => a.checked_sub(b),
+        ArithmeticOperator::Multiply => a.checked_mul(b),
+        ArithmeticOperator::Divide => a.checked_div(b),
+        ArithmeticOperator::IntegerDivide => a.checked_div(b).map(|q| q.floor()),
+        ArithmeticOperator::Modulo => a.checked_rem(b).and_then(|r| {
+            // Give the remainder the sign of the divisor.
+            if !r.is_zero() && r.is_sign_negative() != b.is_sign_negative() {
+                r.checked_add(b)
+            } else {
+                Some(r)
+            }
+        }),
+    };
+    Ok(Rcvar::new(result.map_or(Variable::Null, Variable::Decimal)))
+}
+
+/// Returns the result of dividing by zero according to the search options.
+fn division_by_zero(ctx: &Context<'_>) -> SearchResult {
+    match ctx.options.division_by_zero {
+        DivisionByZero::Error => Err(JmespathError::from_ctx(
+            ctx,
+            ErrorReason::Runtime(RuntimeError::DivisionByZero),
+        )),
+        DivisionByZero::Null => Ok(Rcvar::new(Variable::Null)),
+    }
+}
+
+/// Creates the error returned when an arithmetic operand is not a number.
+fn invalid_operand(
+    ctx: &Context<'_>,
+    operator: String,
+    operand: Operand,
+    value: &Variable,
+) -> JmespathError {
+    let reason = ErrorReason::Runtime(RuntimeError::InvalidOperand {
+        operator,
+        operand,
+        expected: ArgumentType::Number,
+        actual: value.get_type(),
+    });
+    JmespathError::from_ctx(ctx, reason)
+}
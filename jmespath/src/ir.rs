@@ -0,0 +1,758 @@
+//! JMESPath intermediate representation (IR).
+//!
+//! The IR is a flat list of instructions produced by lowering an `Ast`. It
+//! describes a stack machine: the value on top of the stack is the
+//! *current* value that the next instruction operates on. A program starts
+//! with the searched data as the only value on the stack and ends with the
+//! result as the only value on the stack.
+//!
+//! Control flow uses absolute instruction indices. Projections are lowered
+//! into a `ProjectStart` / `ProjectEnd` pair that delimit a *projection
+//! frame*: the instructions between them are run once for each projected
+//! element.
+//!
+//! ```
+//! use jmespath::ir::{Instruction, Program};
+//!
+//! let ast = jmespath::parse("foo[*].bar").unwrap();
+//! let program = Program::lower(&ast);
+//! assert_eq!(
+//!     &[
+//!         Instruction::Field {
+//!             name: "foo".to_owned(),
+//!             offset: 0,
+//!         },
+//!         Instruction::ProjectStart(4),
+//!         Instruction::Field {
+//!             name: "bar".to_owned(),
+//!             offset: 7,
+//!         },
+//!         Instruction::ProjectEnd(2),
+//!     ],
+//!     program.instructions()
+//! );
+//! ```
+//!
+//! Programs can be executed by a bytecode VM using `Program::execute`,
+//! which is how `Expression::compile` evaluates expressions.
+//!
+//! Expression references are not lowered. `Instruction::Expref` keeps the
+//! referenced `Ast` so that it can be passed to functions as a
+//! `Variable::Expref`.
+
+use std::fmt;
+
+use crate::ast::{ArithmeticOperator, Ast, Comparator, UnaryOperator};
+use crate::interpreter::{self, SearchResult};
+use crate::{Context, ErrorReason, JmespathError, Rcvar, RuntimeError};
+
+/// A single IR instruction.
+///
+/// Unless noted otherwise, an instruction replaces the current value (the
+/// top of the stack) with its result.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Instruction {
+    /// Pushes a copy of the current value.
+    Dup,
+    /// Swaps the two values on top of the stack.
+    Swap,
+    /// Removes the current value.
+    Pop,
+    /// Gets a field of an object, or null.
+    Field { name: String, offset: usize },
+    /// Gets an element of an array by index (negative indices count from
+    /// the end), or null.
+    Index { idx: i32, offset: usize },
+    /// Slices an array, or returns null. Fails if `step` is zero.
+    Slice {
+        start: Option<i32>,
+        stop: Option<i32>,
+        step: i32,
+        offset: usize,
+    },
+    /// Replaces the current value with a literal value.
+    Literal(Rcvar),
+    /// Replaces the current value with the document originally provided to
+    /// the search (`Context::root`).
+    Root,
+    /// Replaces the current value with an expression reference.
+    Expref(Ast),
+    /// Fails if the current value is null and null propagation is set to
+    /// `NullPropagation::Error`. Does not modify the stack.
+    CheckNotNull { offset: usize },
+    /// Replaces the current value with the negation of its truthiness.
+    Not,
+    /// Pops the right and then the left operand and pushes the result of
+    /// comparing them (a boolean, or null for invalid comparisons).
+    Compare(Comparator),
+    /// Pops the right and then the left operand and pushes the result of
+    /// the arithmetic operation.
+    Arithmetic {
+        operator: ArithmeticOperator,
+        offset: usize,
+    },
+    /// Applies a unary arithmetic operator to the current value.
+    Unary {
+        operator: UnaryOperator,
+        offset: usize,
+    },
+    /// Pops the current value. If it is truthy, it replaces the value below
+    /// it and execution jumps to the target; otherwise it is discarded.
+    OrElse(usize),
+    /// Pops the current value. If it is falsey, it replaces the value below
+    /// it and execution jumps to the target; otherwise it is discarded.
+    AndThen(usize),
+    /// Pops the current value. If it is falsey, the value below it is
+    /// replaced with null and execution jumps to the target.
+    Filter(usize),
+    /// Jumps to the target if the current value is null, leaving the null
+    /// on the stack.
+    JumpIfNull(usize),
+    /// Pops the given number of values and pushes an array of them, in the
+    /// order they were pushed.
+    MakeArray(usize),
+    /// Pops one value per key and pushes an object mapping each key to its
+    /// value, in the order they were pushed.
+    MakeObject(Vec<String>),
+    /// Pops the current value and then `args` values, and pushes the result
+    /// of calling the function with them as arguments, in the order they
+    /// were pushed. The current value is passed as `Context::current`.
+    Call {
+        name: String,
+        args: usize,
+        offset: usize,
+    },
+    /// Pops the current value, then pops one value per name and binds each
+    /// name to its value, in the order they were pushed. Pushes the current
+    /// value back.
+    Bind(Vec<String>),
+    /// Removes the given number of the most recently bound variables. Does
+    /// not modify the stack.
+    Unbind(usize),
+    /// Replaces the current value with the value bound to a variable. Fails
+    /// if the variable is not bound.
+    LoadVariable { name: String, offset: usize },
+    /// Replaces an object with an array of its values, or null.
+    ObjectValues,
+    /// Flattens one level of nested arrays, or returns null.
+    Flatten,
+    /// Starts a projection frame over the current value. If it is not an
+    /// array, it is replaced with null and execution jumps to the target
+    /// (the instruction after the matching `ProjectEnd`). If it is an empty
+    /// array, it is left as-is and execution jumps to the target.
+    /// Otherwise, the array is replaced with its first element.
+    ProjectStart(usize),
+    /// Starts the projection frame of a slice. Behaves like `ProjectStart`,
+    /// except that a string (the result of slicing a string) is the only
+    /// element of the frame, and the frame yields the result of evaluating
+    /// it rather than an array.
+    SliceProjectStart(usize),
+    /// Ends an iteration of a projection frame. Pops the result of the
+    /// iteration and collects it unless it is null. If elements remain,
+    /// pushes the next element and jumps to the target (the start of the
+    /// frame's body). Otherwise, closes the frame and pushes an array of
+    /// the collected results.
+    ProjectEnd(usize),
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        use self::Instruction::*;
+        match *self {
+            Dup => write!(fmt, "dup"),
+            Swap => write!(fmt, "swap"),
+            Pop => write!(fmt, "pop"),
+            Field { ref name, .. } => write!(fmt, "field {:?}", name),
+            Index { idx, .. } => write!(fmt, "index {}", idx),
+            Slice {
+                start, stop, step, ..
+            } => {
+                let bound = |b: Option<i32>| b.map(|i| i.to_string()).unwrap_or_default();
+                write!(fmt, "slice {}:{}:{}", bound(start), bound(stop), step)
+            }
+            Literal(ref value) => write!(fmt, "literal {}", value),
+            Root => write!(fmt, "root"),
+            Expref(ref ast) => write!(fmt, "expref {:?}", ast),
+            CheckNotNull { .. } => write!(fmt, "check_not_null"),
+            Not => write!(fmt, "not"),
+            Compare(ref comparator) => write!(fmt, "compare {:?}", comparator),
+            Arithmetic { ref operator, .. } => write!(fmt, "arithmetic {}", operator),
+            Unary { ref operator, .. } => write!(fmt, "unary {}", operator),
+            OrElse(target) => write!(fmt, "or_else {}", target),
+            AndThen(target) => write!(fmt, "and_then {}", target),
+            Filter(target) => write!(fmt, "filter {}", target),
+            JumpIfNull(target) => write!(fmt, "jump_if_null {}", target),
+            MakeArray(len) => write!(fmt, "make_array {}", len),
+            MakeObject(ref keys) => write!(fmt, "make_object {:?}", keys),
+            Call { ref name, args, .. } => write!(fmt, "call {} {}", name, args),
+            Bind(ref names) => write!(fmt, "bind {:?}", names),
+            Unbind(count) => write!(fmt, "unbind {}", count),
+            LoadVariable { ref name, .. } => write!(fmt, "load_variable {:?}", name),
+            ObjectValues => write!(fmt, "object_values"),
+            Flatten => write!(fmt, "flatten"),
+            ProjectStart(target) => write!(fmt, "project_start {}", target),
+            SliceProjectStart(target) => write!(fmt, "slice_project_start {}", target),
+            ProjectEnd(target) => write!(fmt, "project_end {}", target),
+        }
+    }
+}
+
+/// A lowered JMESPath expression.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+}
+
+impl Program {
+    /// Lowers an AST into a program.
+    pub fn lower(ast: &Ast) -> Program {
+        let mut program = Program::default();
+        program.emit(ast);
+        program
+    }
+
+    /// Creates a program from a list of instructions, such as the
+    /// instructions of a transformed program.
+    ///
+    /// Returns a `RuntimeError::InvalidProgram` error unless every jump
+    /// targets an instruction of the program (or its end), each instruction
+    /// finds the values, variables and projection frames it needs no matter
+    /// how it is reached, and the program ends with only its result on the
+    /// stack.
+    ///
+    /// ```
+    /// use jmespath::ir::{Instruction, Program};
+    ///
+    /// let program = Program::lower(&jmespath::parse("a || b").unwrap());
+    /// assert!(Program::from_instructions(program.into_instructions()).is_ok());
+    /// assert!(Program::from_instructions(vec![Instruction::Pop]).is_err());
+    /// ```
+    pub fn from_instructions(instructions: Vec<Instruction>) -> Result<Program, JmespathError> {
+        verify(&instructions).map_err(|(instruction, message)| {
+            let reason = ErrorReason::Runtime(RuntimeError::InvalidProgram {
+                instruction,
+                message: message.to_owned(),
+            });
+            JmespathError::new("", 0, reason)
+        })?;
+        Ok(Program { instructions })
+    }
+
+    /// Returns the instructions of the program.
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Executes the program against data using the bytecode VM.
+    ///
+    /// This is what `Expression::compile` uses to search data. The context
+    /// holds the runtime used to call functions and the search options.
+    ///
+    /// ```
+    /// use jmespath::ir::Program;
+    /// use jmespath::{Context, Rcvar, Runtime, Variable};
+    ///
+    /// let program = Program::lower(&jmespath::parse("a[*].b").unwrap());
+    /// let data = Rcvar::new(Variable::from_json(r#"{"a": [{"b": 1}]}"#).unwrap());
+    /// let runtime = Runtime::new();
+    /// let mut ctx = Context::new("a[*].b", &runtime);
+    /// assert_eq!("[1]", program.execute(&data, &mut ctx).unwrap().to_string());
+    /// ```
+    pub fn execute(&self, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult {
+        interpreter::execute(self, data, ctx)
+    }
+
+    /// Consumes the program and returns its instructions.
+    pub fn into_instructions(self) -> Vec<Instruction> {
+        self.instructions
+    }
+
+    fn push(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    /// Sets the jump target of a previously emitted instruction.
+    fn patch(&mut self, at: usize, target: usize) {
+        use self::Instruction::*;
+        match self.instructions[at] {
+            OrElse(ref mut t)
+            | AndThen(ref mut t)
+            | Filter(ref mut t)
+            | JumpIfNull(ref mut t)
+            | ProjectStart(ref mut t)
+            | SliceProjectStart(ref mut t)
+            | ProjectEnd(ref mut t) => *t = target,
+            _ => unreachable!("instruction {} is not a jump", at),
+        }
+    }
+
+    /// Emits `node` evaluated against a copy of the current value, leaving
+    /// the result below the current value.
+    fn emit_operand(&mut self, node: &Ast) {
+        self.push(Instruction::Dup);
+        self.emit(node);
+        self.push(Instruction::Swap);
+    }
+
+    fn emit(&mut self, node: &Ast) {
+        match *node {
+            Ast::Identity { .. } => {}
+            Ast::Field { ref name, offset } => {
+                self.push(Instruction::Field {
+                    name: name.clone(),
+                    offset,
+                });
+            }
+            Ast::Index { idx, offset } => {
+                self.push(Instruction::Index { idx, offset });
+            }
+            Ast::Slice {
+                start,
+                stop,
+                step,
+                offset,
+            } => {
+                self.push(Instruction::Slice {
+                    start,
+                    stop,
+                    step,
+                    offset,
+                });
+            }
+            Ast::Literal { ref value, .. } => {
+                self.push(Instruction::Literal(value.clone()));
+            }
+            Ast::Root { .. } => {
+                self.push(Instruction::Root);
+            }
+            Ast::Expref { ref ast, .. } => {
+                self.push(Instruction::Expref((**ast).clone()));
+            }
+            Ast::Subexpr {
+                ref lhs,
+                ref rhs,
+                offset,
+            } => {
+                self.emit(lhs);
+                self.push(Instruction::CheckNotNull { offset });
+                self.emit(rhs);
+            }
+            Ast::Not { ref node, .. } => {
+                self.emit(node);
+                self.push(Instruction::Not);
+            }
+            Ast::ObjectValues { ref node, .. } => {
+                self.emit(node);
+                self.push(Instruction::ObjectValues);
+            }
+            Ast::Flatten { ref node, .. } => {
+                self.emit(node);
+                self.push(Instruction::Flatten);
+            }
+            Ast::Comparison {
+                ref comparator,
+                ref lhs,
+                ref rhs,
+                ..
+            } => {
+                self.emit_operand(lhs);
+                self.emit(rhs);
+                self.push(Instruction::Compare(comparator.clone()));
+            }
+            Ast::Arithmetic {
+                ref operator,
+                ref lhs,
+                ref rhs,
+                offset,
+            } => {
+                self.emit_operand(lhs);
+                self.emit(rhs);
+                self.push(Instruction::Arithmetic {
+                    operator: operator.clone(),
+                    offset,
+                });
+            }
+            Ast::Unary {
+                ref operator,
+                ref node,
+                offset,
+            } => {
+                self.emit(node);
+                self.push(Instruction::Unary {
+                    operator: operator.clone(),
+                    offset,
+                });
+            }
+            Ast::Or {
+                ref lhs, ref rhs, ..
+            } => {
+                self.push(Instruction::Dup);
+                self.emit(lhs);
+                let jump = self.push(Instruction::OrElse(0));
+                self.emit(rhs);
+                let end = self.instructions.len();
+                self.patch(jump, end);
+            }
+            Ast::And {
+                ref lhs, ref rhs, ..
+            } => {
+                self.push(Instruction::Dup);
+                self.emit(lhs);
+                let jump = self.push(Instruction::AndThen(0));
+                self.emit(rhs);
+                let end = self.instructions.len();
+                self.patch(jump, end);
+            }
+            Ast::Condition {
+                ref predicate,
+                ref then,
+                ..
+            } => {
+                self.push(Instruction::Dup);
+                self.emit(predicate);
+                let jump = self.push(Instruction::Filter(0));
+                self.emit(then);
+                let end = self.instructions.len();
+                self.patch(jump, end);
+            }
+            Ast::Projection {
+                ref lhs, ref rhs, ..
+            } => {
+                self.emit(lhs);
+                let start = match **lhs {
+                    Ast::Slice { .. } => self.push(Instruction::SliceProjectStart(0)),
+                    _ => self.push(Instruction::ProjectStart(0)),
+                };
+                self.emit(rhs);
+                self.push(Instruction::ProjectEnd(start + 1));
+                let end = self.instructions.len();
+                self.patch(start, end);
+            }
+            Ast::MultiList { ref elements, .. } => {
+                let jump = self.push(Instruction::JumpIfNull(0));
+                for element in elements {
+                    self.emit_operand(element);
+                }
+                self.push(Instruction::Pop);
+                self.push(Instruction::MakeArray(elements.len()));
+                let end = self.instructions.len();
+                self.patch(jump, end);
+            }
+            Ast::MultiHash { ref elements, .. } => {
+                let jump = self.push(Instruction::JumpIfNull(0));
+                for kvp in elements {
+                    self.emit_operand(&kvp.value);
+                }
+                self.push(Instruction::Pop);
+                let keys = elements.iter().map(|kvp| kvp.key.clone()).collect();
+                self.push(Instruction::MakeObject(keys));
+                let end = self.instructions.len();
+                self.patch(jump, end);
+            }
+            Ast::Let {
+                ref bindings,
+                ref body,
+                ..
+            } => {
+                for binding in bindings {
+                    self.emit_operand(&binding.value);
+                }
+                let names = bindings.iter().map(|b| b.name.clone()).collect();
+                self.push(Instruction::Bind(names));
+                self.emit(body);
+                self.push(Instruction::Unbind(bindings.len()));
+            }
+            Ast::VariableRef { ref name, offset } => {
+                self.push(Instruction::LoadVariable {
+                    name: name.clone(),
+                    offset,
+                });
+            }
+            Ast::Function {
+                ref name,
+                ref args,
+                offset,
+            } => {
+                for arg in args {
+                    self.emit_operand(arg);
+                }
+                self.push(Instruction::Call {
+                    name: name.clone(),
+                    args: args.len(),
+                    offset,
+                });
+            }
+        }
+    }
+}
+
+/// Writes one numbered instruction per line.
+/// Depths of the value stack, the bound variables and the projection frames
+/// before an instruction is executed.
+#[derive(Clone, Copy, PartialEq)]
+struct Depths {
+    stack: usize,
+    scope: usize,
+    frames: usize,
+}
+
+/// Checks that a program can be executed without underflowing the stack,
+/// the bound variables or the projection frames, returning the index of the
+/// first offending instruction otherwise.
+///
+/// The depths before an instruction do not depend on the values being
+/// searched, so each instruction is checked once with the depths of the
+/// first path that reaches it, and every other path must agree with them.
+fn verify(instructions: &[Instruction]) -> Result<(), (usize, &'static str)> {
+    use self::Instruction::*;
+    let mut seen: Vec<Option<Depths>> = vec![None; instructions.len() + 1];
+    let start = Depths {
+        stack: 1,
+        scope: 0,
+        frames: 0,
+    };
+    let mut pending = vec![(0, start)];
+    while let Some((pc, depths)) = pending.pop() {
+        match seen[pc] {
+            Some(previous) if previous == depths => continue,
+            Some(_) => return Err((pc, "reached with different stack depths")),
+            None => seen[pc] = Some(depths),
+        }
+        let instruction = match instructions.get(pc) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        let (pops, pushes) = match *instruction {
+            Dup => (1, 2),
+            Swap => (2, 2),
+            Pop => (1, 0),
+            Compare(_) | Arithmetic { .. } | OrElse(_) | AndThen(_) | Filter(_) => (2, 1),
+            MakeArray(len) => (len, 1),
+            MakeObject(ref keys) => (keys.len(), 1),
+            Call { args, .. } => (args + 1, 1),
+            Bind(ref names) => (names.len() + 1, 1),
+            Unbind(_) => (0, 0),
+            _ => (1, 1),
+        };
+        if depths.stack < pops {
+            return Err((pc, "stack underflow"));
+        }
+        let mut next = Depths {
+            stack: depths.stack - pops + pushes,
+            ..depths
+        };
+        let mut target = None;
+        match *instruction {
+            OrElse(to) | AndThen(to) | Filter(to) | JumpIfNull(to) => target = Some((to, next)),
+            Bind(ref names) => next.scope += names.len(),
+            Unbind(count) => match depths.scope.checked_sub(count) {
+                Some(scope) => next.scope = scope,
+                None => return Err((pc, "no variables to unbind")),
+            },
+            ProjectStart(to) | SliceProjectStart(to) => {
+                target = Some((to, next));
+                next.frames += 1;
+            }
+            ProjectEnd(to) => {
+                if depths.frames == 0 {
+                    return Err((pc, "no projection to end"));
+                }
+                target = Some((to, next));
+                next.frames -= 1;
+            }
+            _ => {}
+        }
+        if let Some((to, depths)) = target {
+            if to > instructions.len() {
+                return Err((pc, "jump target out of range"));
+            }
+            pending.push((to, depths));
+        }
+        pending.push((pc + 1, next));
+    }
+    match seen[instructions.len()] {
+        None
+        | Some(Depths {
+            stack: 1,
+            scope: 0,
+            frames: 0,
+        }) => Ok(()),
+        Some(_) => Err((instructions.len(), "does not end with only the result")),
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            if i > 0 {
+                writeln!(fmt)?;
+            }
+            write!(fmt, "{:04} {}", i, instruction)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    fn lower(expr: &str) -> String {
+        Program::lower(&parse(expr).unwrap()).to_string()
+    }
+
+    #[test]
+    fn lowers_let_expressions() {
+        assert_eq!(
+            "0000 dup\n0001 field \"a\"\n0002 swap\n0003 bind [\"x\"]\n\
+             0004 field \"b\"\n0005 check_not_null\n0006 load_variable \"x\"\n0007 unbind 1",
+            lower("let $x = a in b | $x")
+        );
+    }
+
+    #[test]
+    fn lowers_subexpressions() {
+        assert_eq!(
+            "0000 field \"a\"\n0001 check_not_null\n0002 index -1",
+            lower("a[-1]")
+        );
+        assert_eq!("", lower("@"));
+    }
+
+    #[test]
+    fn lowers_projections_into_frames() {
+        assert_eq!(
+            "0000 field \"a\"\n\
+             0001 project_start 7\n\
+             0002 dup\n\
+             0003 field \"b\"\n\
+             0004 filter 6\n\
+             0005 field \"c\"\n\
+             0006 project_end 2\n\
+             0007 flatten\n\
+             0008 project_start 10\n\
+             0009 project_end 9",
+            lower("a[?b].c[]")
+        );
+    }
+
+    #[test]
+    fn lowers_short_circuit_operators() {
+        assert_eq!(
+            "0000 dup\n0001 field \"a\"\n0002 or_else 7\n0003 dup\n0004 field \"b\"\n0005 and_then 7\n0006 literal 1",
+            lower("a || b && `1`")
+        );
+    }
+
+    #[test]
+    fn lowers_operands_in_order() {
+        assert_eq!(
+            "0000 dup\n0001 field \"a\"\n0002 swap\n0003 dup\n0004 literal \"b\"\n0005 swap\n0006 call f 2",
+            lower("f(a, 'b')")
+        );
+        assert_eq!(
+            "0000 jump_if_null 8\n0001 dup\n0002 field \"a\"\n0003 swap\n0004 dup\n0005 swap\n0006 pop\n0007 make_object [\"k\", \"l\"]",
+            lower("{k: a, l: @}")
+        );
+    }
+
+    #[test]
+    fn verifies_programs() {
+        use crate::{ErrorReason, RuntimeError};
+        let expressions = [
+            "@",
+            "let $x = a in b | $x",
+            "a[?b].c[]",
+            "a[1:2].b",
+            "a.*.b[0]",
+            "a || b && !c",
+            "f(a, 'b')",
+            "{k: a, l: [b, c]}",
+            "a[*].[b, c[*].d]",
+            "-a + b * `2`",
+        ];
+        for expression in expressions.iter() {
+            let instructions = Program::lower(&parse(expression).unwrap()).into_instructions();
+            assert!(
+                Program::from_instructions(instructions).is_ok(),
+                "{}",
+                expression
+            );
+        }
+        let error = |instructions| match Program::from_instructions(instructions) {
+            Err(err) => match err.reason {
+                ErrorReason::Runtime(RuntimeError::InvalidProgram {
+                    instruction,
+                    message,
+                }) => (instruction, message),
+                reason => panic!("unexpected error {}", reason),
+            },
+            Ok(program) => panic!("expected an error, got\n{}", program),
+        };
+        use self::Instruction::*;
+        let expect = |instruction, message: &str| (instruction, message.to_owned());
+        assert_eq!(expect(1, "stack underflow"), error(vec![Pop, Pop]));
+        assert_eq!(
+            expect(0, "jump target out of range"),
+            error(vec![JumpIfNull(2)])
+        );
+        assert_eq!(expect(0, "no variables to unbind"), error(vec![Unbind(1)]));
+        assert_eq!(
+            expect(0, "no projection to end"),
+            error(vec![ProjectEnd(0)])
+        );
+        assert_eq!(
+            expect(2, "reached with different stack depths"),
+            error(vec![JumpIfNull(2), Dup, MakeArray(1)])
+        );
+        assert_eq!(
+            expect(1, "does not end with only the result"),
+            error(vec![Dup])
+        );
+        assert_eq!(
+            expect(2, "does not end with only the result"),
+            error(vec![Dup, Bind(vec!["x".to_owned()])])
+        );
+    }
+
+    #[test]
+    fn vm_rejects_malformed_programs() {
+        use crate::{ErrorReason, Runtime, RuntimeError, Variable};
+        let runtime = Runtime::new();
+        let run = |instructions| {
+            let mut ctx = Context::new("", &runtime);
+            let program = Program { instructions };
+            match program.execute(&Rcvar::new(Variable::Null), &mut ctx) {
+                Err(err) => err.reason,
+                Ok(result) => panic!("expected an error, got {}", result),
+            }
+        };
+        let invalid = |instruction, message: &str| {
+            ErrorReason::Runtime(RuntimeError::InvalidProgram {
+                instruction,
+                message: message.to_owned(),
+            })
+        };
+        use self::Instruction::*;
+        let underflow = "stack underflow";
+        assert_eq!(invalid(1, underflow), run(vec![Pop, Pop]));
+        assert_eq!(invalid(0, underflow), run(vec![Swap]));
+        assert_eq!(invalid(0, underflow), run(vec![MakeArray(2)]));
+        assert_eq!(
+            invalid(0, underflow),
+            run(vec![MakeObject(vec!["a".to_owned(), "b".to_owned()])])
+        );
+        let call = Call {
+            name: "abs".to_owned(),
+            args: 1,
+            offset: 0,
+        };
+        assert_eq!(invalid(0, underflow), run(vec![call]));
+        assert_eq!(invalid(0, underflow), run(vec![Bind(vec!["x".to_owned()])]));
+        assert_eq!(invalid(0, "no variables to unbind"), run(vec![Unbind(1)]));
+        assert_eq!(invalid(0, "no projection to end"), run(vec![ProjectEnd(0)]));
+        assert_eq!(invalid(1, "no result on the stack"), run(vec![Pop]));
+    }
+}
@@ -18,6 +18,8 @@ use crate::{ErrorReason, JmespathError, Rcvar};
 #[derive(Clone, PartialEq, Debug)]
 pub enum Token {
     Identifier(String),
+    Variable(String),
+    Root,
     QuotedIdentifier(String),
     Number(i32),
     Literal(Rcvar),
@@ -45,6 +47,13 @@ pub enum Token {
     Rparen,
     Lbrace,
     Rbrace,
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    IntegerDivide,
+    Modulo,
+    Assign,
     Eof,
 }
 
@@ -66,8 +75,14 @@ impl Token {
             Gte => 5,
             Lte => 5,
             Ne => 5,
+            Plus => 6,
+            Minus => 6,
+            Star => 7,
+            Multiply => 7,
+            Divide => 7,
+            IntegerDivide => 7,
+            Modulo => 7,
             Flatten => 9,
-            Star => 20,
             Filter => 21,
             Dot => 40,
             Not => 45,
@@ -87,6 +102,27 @@ pub fn tokenize(expr: &str) -> Result<VecDeque<TokenTuple>, JmespathError> {
     Lexer::new(expr).tokenize()
 }
 
+/// Returns true if the token can end the left operand of a binary operator.
+fn ends_operand(token: Option<&TokenTuple>) -> bool {
+    matches!(
+        token,
+        Some((
+            _,
+            Identifier(_)
+                | QuotedIdentifier(_)
+                | Variable(_)
+                | Root
+                | Literal(_)
+                | At
+                | Star
+                | Flatten
+                | Rbracket
+                | Rparen
+                | Rbrace
+        ))
+    )
+}
+
 struct Lexer<'a> {
     iter: Peekable<CharIndices<'a>>,
     expr: &'a str,
@@ -126,8 +162,16 @@ impl<'a> Lexer<'a> {
                         '"' => tokens.push_back((pos, self.consume_quoted_identifier(pos)?)),
                         '\'' => tokens.push_back((pos, self.consume_raw_string(pos)?)),
                         '`' => tokens.push_back((pos, self.consume_literal(pos)?)),
-                        '=' => match self.iter.next() {
-                            Some((_, c)) if c == '=' => tokens.push_back((pos, Eq)),
+                        '$' => tokens.push_back((pos, self.consume_variable())),
+                        '=' => match self.iter.peek() {
+                            Some(&(_, '=')) => {
+                                self.iter.next();
+                                tokens.push_back((pos, Eq))
+                            }
+                            // A single '=' only binds a variable in a let expression.
+                            _ if matches!(tokens.back(), Some((_, Variable(_)))) => {
+                                tokens.push_back((pos, Assign))
+                            }
                             _ => {
                                 let message = "'=' is not valid. Did you mean '=='?";
                                 let reason = ErrorReason::Parse(message.to_owned());
@@ -137,8 +181,21 @@ impl<'a> Lexer<'a> {
                         '>' => tokens.push_back((pos, self.alt('=', Gte, Gt))),
                         '<' => tokens.push_back((pos, self.alt('=', Lte, Lt))),
                         '!' => tokens.push_back((pos, self.alt('=', Ne, Not))),
-                        '0'..='9' => tokens.push_back((pos, self.consume_number(pos,ch, false)?)),
-                        '-' => tokens.push_back((pos, self.consume_negative_number(pos)?)),
+                        '%' => tokens.push_back((pos, Modulo)),
+                        '/' => tokens.push_back((pos, self.alt('/', IntegerDivide, Divide))),
+                        '+' => tokens.push_back((pos, Plus)),
+                        '×' => tokens.push_back((pos, Multiply)),
+                        '÷' => tokens.push_back((pos, Divide)),
+                        '−' => tokens.push_back((pos, Minus)),
+                        '0'..='9' => tokens.push_back((pos, self.consume_number(pos, ch, false)?)),
+                        // A '-' that follows an operand is a subtraction; otherwise
+                        // it starts a negative number or a unary minus.
+                        '-' => match self.iter.peek() {
+                            Some(&(_, c)) if c.is_ascii_digit() && !ends_operand(tokens.back()) => {
+                                tokens.push_back((pos, self.consume_negative_number(pos)?))
+                            }
+                            _ => tokens.push_back((pos, Minus)),
+                        },
                         // Skip whitespace tokens
                         ' ' | '\n' | '\t' | '\r' => {}
                         c => {
@@ -199,10 +256,31 @@ impl<'a> Lexer<'a> {
         ))
     }
 
+    // Consumes variable references: "$" unquoted-string, or the root node
+    // reference when "$" is not followed by a name.
+    #[inline]
+    fn consume_variable(&mut self) -> Token {
+        match self.iter.peek() {
+            Some(&(_, ch)) if matches!(ch, 'a'..='z' | 'A'..='Z' | '_') => {
+                self.iter.next();
+                Variable(self.consume_while(
+                    ch.to_string(),
+                    |c| matches!(c, 'a'..='z' | '_' | 'A'..='Z' | '0'..='9'),
+                ))
+            }
+            _ => Root,
+        }
+    }
+
     // Consumes numbers: *"-" "0" / ( %x31-39 *DIGIT )
     #[inline]
-    fn consume_number(&mut self, pos: usize, first_char: char, is_negative: bool) -> Result<Token, JmespathError> {
-        let lexeme = self.consume_while(first_char.to_string(), |c| c.is_digit(10));
+    fn consume_number(
+        &mut self,
+        pos: usize,
+        first_char: char,
+        is_negative: bool,
+    ) -> Result<Token, JmespathError> {
+        let lexeme = self.consume_while(first_char.to_string(), |c| c.is_ascii_digit());
         let numeric_value: i32 = lexeme.parse().map_err(|_| {
             let reason = ErrorReason::Parse("Expected valid number".to_owned());
             JmespathError::new(self.expr, pos, reason)
@@ -292,6 +370,15 @@ impl<'a> Lexer<'a> {
         self.consume_inside(pos, '`', |s| {
             let unescaped = s.replace("\\`", "`");
             match Variable::from_json(unescaped.as_ref()) {
+                // serde_json parses integers outside of the range of a u64
+                // as floats, so parse them again as big integers.
+                #[cfg(feature = "big-integers")]
+                Ok(ref j) if j.is_number() && j.as_i128().is_none() => {
+                    match unescaped.trim().parse::<i128>() {
+                        Ok(i) => Ok(Literal(Rcvar::new(Variable::from_i128(i)))),
+                        Err(_) => Ok(Literal(Rcvar::new(j.clone()))),
+                    }
+                }
                 Ok(j) => Ok(Literal(Rcvar::new(j))),
                 Err(err) => Err(format!("Unable to parse literal JSON {}: {}", s, err)),
             }
@@ -372,11 +459,53 @@ mod tests {
         assert_eq!(tokenize_queue("!="), vec![(0, Ne), (2, Eof)]);
     }
 
+    #[test]
+    fn tokenize_arithmetic_test() {
+        assert_eq!(tokenize_queue("//"), vec![(0, IntegerDivide), (2, Eof)]);
+        assert_eq!(tokenize_queue("%"), vec![(0, Modulo), (1, Eof)]);
+        assert_eq!(tokenize_queue("/"), vec![(0, Divide), (1, Eof)]);
+        assert_eq!(
+            tokenize_queue("+ - * × ÷ −"),
+            vec![
+                (0, Plus),
+                (2, Minus),
+                (4, Star),
+                (6, Multiply),
+                (9, Divide),
+                (12, Minus),
+                (15, Eof)
+            ]
+        );
+    }
+
     #[test]
     fn ensures_eq_valid() {
         assert!(tokenize("=").is_err());
     }
 
+    #[test]
+    fn tokenizes_variables_and_assignments() {
+        assert_eq!(
+            tokenize_queue("$foo_1 = $bar"),
+            vec![
+                (0, Variable("foo_1".to_string())),
+                (7, Assign),
+                (9, Variable("bar".to_string())),
+                (13, Eof)
+            ]
+        );
+        assert_eq!(
+            tokenize_queue("$.a"),
+            vec![
+                (0, Root),
+                (1, Dot),
+                (2, Identifier("a".to_string())),
+                (3, Eof)
+            ]
+        );
+        assert!(tokenize("foo = bar").is_err());
+    }
+
     #[test]
     fn skips_whitespace() {
         let tokens = tokenize_queue(" \t\n\r\t. (");
@@ -506,6 +635,24 @@ mod tests {
         assert_eq!(tokenize_queue("-10"), vec![(0, Number(-10)), (3, Eof)]);
     }
 
+    #[test]
+    fn tokenizes_subtraction_after_operands() {
+        assert_eq!(
+            tokenize_queue("a-1"),
+            vec![
+                (0, Identifier("a".to_string())),
+                (1, Minus),
+                (2, Number(1)),
+                (3, Eof)
+            ]
+        );
+        assert_eq!(
+            tokenize_queue("[-1]"),
+            vec![(0, Lbracket), (1, Number(-1)), (3, Rbracket), (4, Eof)]
+        );
+        assert_eq!(tokenize_queue("-a")[0], (0, Minus));
+    }
+
     #[test]
     fn tokenize_negative_number_test_failure() {
         assert!(tokenize("-01").unwrap_err().to_string().contains("'-'"));
@@ -43,7 +43,9 @@
 //!
 //! By default, `Rcvar` is an `std::rc::Rc<Variable>`. However, by specifying
 //! the `sync` feature, you can utilize an `std::sync::Arc<Variable>` to
-//! share `Expression` structs across threads.
+//! share `Expression` structs across threads. The `rayon` feature, which
+//! implies `sync`, makes `Expression::search_batch` search documents in
+//! parallel.
 //!
 //! Any type that implements `jmespath::ToJmespath` can be used in a JMESPath
 //! Expression. Various types have default `ToJmespath` implementations,
@@ -53,19 +55,57 @@
 //! using serde's macros or code generation capabilities. This includes a
 //! number of common types, including serde's `serde_json::Value` enum.
 //!
+//! When the `datetime` feature is enabled, `Variable::DateTime` can be used
+//! to pass points in time to and from custom functions. Datetimes can be
+//! compared with ordering comparators and are serialized as RFC 3339
+//! strings.
+//!
+//! When the `decimal` feature is enabled, `Variable::Decimal` holds exact
+//! decimal numbers. The `to_decimal` function converts numbers and strings
+//! such as `"10.01"` into decimals, and comparisons and arithmetic involving
+//! a decimal do not suffer from floating point rounding.
+//!
+//! When the `big-integers` feature is enabled, integers are compared,
+//! summed, and divided exactly rather than as `f64`s, and integers outside
+//! the range of `i64` and `u64` are held in `Variable::BigInt`. Because
+//! `serde_json` parses such integers in JSON documents as floats, big
+//! integers come from literals, from serializing `i128`s, or from custom
+//! functions.
+//!
+//! Applications can also pass their own domain values through expressions
+//! by implementing `jmespath::JmesExternal` and wrapping the value in
+//! `Variable::External`.
+//!
 //! The return value of searching data with JMESPath is also an `Rcvar`.
 //! `Variable` has a number of helper methods that make it a data type that
 //! can be used directly, or you can convert `Variable` to any serde value
 //! implementing `serde::de::Deserialize`.
 //!
+//! # Tracing
+//!
+//! When the `tracing` feature is enabled, compiling expressions and
+//! searching data emit spans and events using the `tracing` crate. Spans
+//! contain the expression and a stable fingerprint of it, and events record
+//! the number of AST nodes and the elapsed duration.
+//!
+//! # Logging slow searches
+//!
+//! When the `log` feature is enabled, searches that exceed the duration or
+//! node count set in `SearchOptions::slow_search` are reported using
+//! `log::warn!`, along with the expression and the measured statistics.
+//!
 //! # Custom Functions
 //!
 //! You can register custom functions with a JMESPath expression by using
 //! a custom `Runtime`. When you call `jmespath::compile`, you are using a
 //! shared `Runtime` instance that is created lazily using `once_cell::sync::Lazy`.
 //! This shared `Runtime` utilizes all of the builtin JMESPath functions
-//! by default. However, custom functions may be utilized by creating a custom
-//! `Runtime` and compiling expressions directly from the `Runtime`.
+//! by default, including the JMESPath community functions when the
+//! `community-functions` feature is enabled, and likewise the datetime, hash
+//! and math functions with the `datetime-functions`, `hash-functions` and
+//! `math-functions` features. However, custom functions may be utilized by
+//! creating a custom `Runtime` and compiling expressions directly from the
+//! `Runtime`.
 //!
 //! ```
 //! use jmespath::{Runtime, Context, Rcvar};
@@ -93,35 +133,75 @@
 //! ```
 
 #![cfg_attr(feature = "specialized", feature(specialization))]
+// Errors carry the expected and actual types of invalid values, which
+// includes the type names of external values. Boxing them would change
+// every public signature that returns a `JmespathError`.
+#![allow(clippy::result_large_err)]
 
-pub use crate::errors::{ErrorReason, JmespathError, RuntimeError};
-pub use crate::parser::{parse, ParseResult};
+pub use crate::cache::COMPILE_CACHE_CAPACITY;
+pub use crate::errors::{ErrorReason, JmespathError, Operand, ParseLimit, RuntimeError};
+#[cfg(feature = "log")]
+pub use crate::options::SlowSearchThreshold;
+pub use crate::options::{
+    DivisionByZero, NullPropagation, NumberFormat, Overflow, SearchOptions, DEFAULT_MAX_DEPTH,
+};
+pub use crate::parser::{
+    parse, parse_with_options, ParseResult, ParserOptions, DEFAULT_MAX_PARSE_DEPTH,
+};
 pub use crate::runtime::Runtime;
-pub use crate::variable::Variable;
+pub use crate::set::ExpressionSet;
+pub use crate::spec::SpecVersion;
+pub use crate::variable::{JmesExternal, JmespathType, PathSegment, RcExternal, Variable};
 
 pub mod ast;
+pub mod backend;
 pub mod functions;
+pub mod generator;
+pub mod ir;
+pub mod lint;
+pub mod mutate;
+pub mod patch;
+pub mod profile;
+pub mod provenance;
+pub mod stream;
+#[cfg(feature = "tabular")]
+pub mod tabular;
+pub mod testing;
+pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use serde::ser;
-#[cfg(feature = "specialized")]
+use serde::{de, ser};
 use serde_json::Value;
+use std::borrow::Cow;
 #[cfg(feature = "specialized")]
 use std::convert::TryInto;
 use std::fmt;
+use std::sync::Arc;
 
 use once_cell::sync::Lazy;
 
 use crate::ast::Ast;
-use crate::interpreter::{interpret, SearchResult};
+use crate::backend::EvalBackend;
+use crate::interpreter::{interpret, SearchIter, SearchResult};
+use crate::ir::Program;
+use crate::profile::{Profile, Profiler};
+use crate::provenance::PathMatch;
+use crate::runtime::RuntimeRef;
+use crate::value::ValueLike;
 
+mod cache;
 mod errors;
 mod interpreter;
 mod lexer;
+mod options;
 mod parser;
 mod runtime;
+mod set;
+mod spec;
+mod trace;
 mod variable;
 
-
 static DEFAULT_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
     let mut runtime = Runtime::new();
     runtime.register_builtin_functions();
@@ -140,11 +220,16 @@ pub type Rcvar = std::sync::Arc<Variable>;
 /// The default Runtime is created lazily the first time it is dereferenced
 /// by using the `once_cell::sync::Lazy` .
 ///
+/// The most recently used `COMPILE_CACHE_CAPACITY` expressions are cached,
+/// so compiling the same string again returns a copy of the cached
+/// expression without parsing it. Use `Runtime::compile` or
+/// `ExpressionBuilder` to always parse the expression.
+///
 /// The provided expression is expected to adhere to the JMESPath
 /// grammar: <https://jmespath.org/specification.html>
 #[inline]
 pub fn compile(expression: &str) -> Result<Expression<'static>, JmespathError> {
-    DEFAULT_RUNTIME.compile(expression)
+    cache::compile(&DEFAULT_RUNTIME, expression)
 }
 
 /// Converts a value into a reference-counted JMESPath Variable.
@@ -174,7 +259,7 @@ pub trait ToJmespath {
 }
 
 /// Create searchable values from Serde serializable values.
-impl<'a, T: ser::Serialize> ToJmespath for T {
+impl<T: ser::Serialize> ToJmespath for T {
     #[cfg(not(feature = "specialized"))]
     fn to_jmespath(self) -> Result<Rcvar, JmespathError> {
         Variable::from_serializable(self).map(Rcvar::new)
@@ -366,7 +451,11 @@ impl ToJmespath for bool {
 pub struct Expression<'a> {
     ast: Ast,
     expression: String,
-    runtime: &'a Runtime,
+    runtime: RuntimeRef<'a>,
+    backend: &'static dyn EvalBackend,
+    program: Option<Program>,
+    params: Vec<(String, Rcvar)>,
+    spec_version: SpecVersion,
 }
 
 impl<'a> Expression<'a> {
@@ -382,10 +471,39 @@ impl<'a> Expression<'a> {
         Expression {
             expression: expression.into(),
             ast,
-            runtime,
+            runtime: RuntimeRef::Borrowed(runtime),
+            backend: &backend::DEFAULT_BACKEND,
+            program: None,
+            params: vec![],
+            spec_version: SpecVersion::default(),
         }
     }
 
+    /// Creates an expression from its string and a previously parsed `Ast`,
+    /// using the default `Runtime`.
+    ///
+    /// The AST is trusted to be the result of parsing `expression`; it is
+    /// not parsed or checked again. Combined with the serde implementations
+    /// of `Ast`, this lets build scripts parse expressions ahead of time.
+    ///
+    /// ```
+    /// use jmespath::{ast::Ast, Expression};
+    ///
+    /// // At build time:
+    /// let json = serde_json::to_string(&jmespath::parse("foo.bar").unwrap()).unwrap();
+    ///
+    /// // At startup:
+    /// let ast: Ast = serde_json::from_str(&json).unwrap();
+    /// let expr = Expression::from_parts("foo.bar", ast);
+    /// assert_eq!(Some(true), expr.search(serde_json::json!({"foo": {"bar": true}})).unwrap().as_boolean());
+    /// ```
+    pub fn from_parts<S>(expression: S, ast: Ast) -> Expression<'static>
+    where
+        S: Into<String>,
+    {
+        Expression::new(expression, ast, &DEFAULT_RUNTIME)
+    }
+
     /// Returns the result of searching data with the compiled expression.
     ///
     /// The SearchResult contains a JMESPath Rcvar, or a reference counted
@@ -393,8 +511,472 @@ impl<'a> Expression<'a> {
     /// Alternatively, Variable does implement Serde serialzation and
     /// deserialization, so it can easily be marshalled to another type.
     pub fn search<T: ToJmespath>(&self, data: T) -> SearchResult {
-        let mut ctx = Context::new(&self.expression, self.runtime);
-        interpret(&data.to_jmespath()?, &self.ast, &mut ctx)
+        self.search_with_options(data, &SearchOptions::default())
+    }
+
+    /// Returns the result of searching data using the given `SearchOptions`.
+    pub fn search_with_options<T: ToJmespath>(
+        &self,
+        data: T,
+        options: &SearchOptions,
+    ) -> SearchResult {
+        self.search_variable(data.to_jmespath()?, options)
+    }
+
+    /// Searches data with additional variables bound for this search only.
+    ///
+    /// Each `(name, value)` pair binds `$name` to `value`, in addition to the
+    /// parameters bound using `ExpressionBuilder::with_param`, which it
+    /// overrides. Binding untrusted values as parameters, rather than
+    /// formatting them into the expression string, ensures they are only
+    /// ever treated as values, never as part of the expression.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let expr = jmespath::compile("items[?owner == $owner].id").unwrap();
+    /// let data = Variable::from_json(
+    ///     r#"{"items": [{"id": 1, "owner": "a"}, {"id": 2, "owner": "b' || 'x"}]}"#,
+    /// )
+    /// .unwrap();
+    /// let owner = Variable::String("b' || 'x".to_owned());
+    /// let result = expr.search_with_params(data, &[("owner", owner)]).unwrap();
+    /// assert_eq!("[2]", result.to_string());
+    /// ```
+    pub fn search_with_params<T: ToJmespath>(
+        &self,
+        data: T,
+        params: &[(&str, Variable)],
+    ) -> SearchResult {
+        self.search_with_params_and_options(data, params, &SearchOptions::default())
+    }
+
+    /// Searches data with additional variables bound for this search only,
+    /// using the given options.
+    ///
+    /// See `search_with_params` and `search_with_options`.
+    pub fn search_with_params_and_options<T: ToJmespath>(
+        &self,
+        data: T,
+        params: &[(&str, Variable)],
+        options: &SearchOptions,
+    ) -> SearchResult {
+        let data = data.to_jmespath()?;
+        let mut ctx = self.context(options);
+        for (name, value) in params {
+            ctx.scope
+                .push(((*name).to_owned(), Rcvar::new(value.clone())));
+        }
+        self.run(data, ctx)
+    }
+
+    /// Creates the context of a search, with the expression's parameters
+    /// in scope.
+    fn context(&self, options: &SearchOptions) -> Context<'_> {
+        let mut ctx = Context::new(&self.expression, &self.runtime);
+        ctx.options = options.clone();
+        ctx.spec_version = self.spec_version;
+        ctx.scope = self.params.clone();
+        ctx
+    }
+
+    /// Searches data that has already been converted to an `Rcvar`.
+    pub(crate) fn search_variable(&self, data: Rcvar, options: &SearchOptions) -> SearchResult {
+        self.run(data, self.context(options))
+    }
+
+    /// Searches data using a context created by `context`.
+    fn run(&self, data: Rcvar, mut ctx: Context<'_>) -> SearchResult {
+        ctx.root = data.clone();
+        #[cfg(feature = "log")]
+        let start = std::time::Instant::now();
+        let result = trace::search(&self.expression, &self.ast, || match self.program {
+            Some(ref program) => program.execute(&data, &mut ctx),
+            None => self.backend.evaluate(&self.ast, &data, &mut ctx),
+        });
+        #[cfg(feature = "log")]
+        trace::log_slow_search(&ctx, start.elapsed());
+        result
+    }
+
+    /// Searches each of the given documents, returning the results in the
+    /// same order.
+    ///
+    /// When the `rayon` feature is enabled, the documents are searched in
+    /// parallel using rayon's global thread pool. Otherwise they are searched
+    /// one after another on the current thread.
+    ///
+    /// ```
+    /// use jmespath::{Rcvar, Variable};
+    ///
+    /// let expr = jmespath::compile("a").unwrap();
+    /// let inputs: Vec<Rcvar> = vec![r#"{"a": 1}"#, r#"{"a": 2}"#]
+    ///     .into_iter()
+    ///     .map(|json| Rcvar::new(Variable::from_json(json).unwrap()))
+    ///     .collect();
+    /// let results = expr.search_batch(&inputs);
+    /// assert_eq!("2", results[1].as_ref().unwrap().to_string());
+    /// ```
+    pub fn search_batch(&self, inputs: &[Rcvar]) -> Vec<SearchResult> {
+        self.search_batch_with_options(inputs, &SearchOptions::default())
+    }
+
+    /// Searches each of the given documents using the given options,
+    /// returning the results in the same order.
+    ///
+    /// See `search_batch` and `search_with_options`.
+    #[cfg(not(feature = "rayon"))]
+    pub fn search_batch_with_options(
+        &self,
+        inputs: &[Rcvar],
+        options: &SearchOptions,
+    ) -> Vec<SearchResult> {
+        inputs
+            .iter()
+            .map(|input| self.search_variable(input.clone(), options))
+            .collect()
+    }
+
+    /// Searches each of the given documents in parallel using the given
+    /// options, returning the results in the same order.
+    #[cfg(feature = "rayon")]
+    pub fn search_batch_with_options(
+        &self,
+        inputs: &[Rcvar],
+        options: &SearchOptions,
+    ) -> Vec<SearchResult> {
+        use rayon::prelude::*;
+        inputs
+            .par_iter()
+            .map(|input| self.search_variable(input.clone(), options))
+            .collect()
+    }
+
+    /// Searches a `ValueLike` document without converting it to a `Variable`.
+    ///
+    /// The expression walks the document directly, and results that are
+    /// part of it (e.g., the result of `a.b[0]`) are borrowed instead of
+    /// being copied. Arrays and objects created by the expression, such as
+    /// the results of projections, are returned as owned values. Function
+    /// arguments and comparison operands are converted to `Variable`s, so
+    /// only the parts of the document that are passed to functions are
+    /// copied. See the `value` module for details.
+    ///
+    /// Expressions that use the root node reference `$` convert the whole
+    /// document to a `Variable` first, which custom functions then see as
+    /// `Context::root`; otherwise they see a null root. Searches always walk
+    /// the AST, even when the expression is compiled.
+    pub fn search_value<'v, V: ValueLike>(&self, data: &'v V) -> Result<Cow<'v, V>, JmespathError> {
+        self.search_value_with_options(data, &SearchOptions::default())
+    }
+
+    /// Searches a `ValueLike` document using the given `SearchOptions`.
+    pub fn search_value_with_options<'v, V: ValueLike>(
+        &self,
+        data: &'v V,
+        options: &SearchOptions,
+    ) -> Result<Cow<'v, V>, JmespathError> {
+        let mut ctx = self.context(options);
+        if references_root(&self.ast) {
+            ctx.root = Rcvar::new(data.to_variable()?);
+        }
+        value::search(&self.ast, &Cow::Borrowed(data), &mut ctx)
+    }
+
+    /// Searches a `serde_json::Value` without converting it to a `Variable`.
+    ///
+    /// This is `search_value` for `serde_json::Value` documents.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use serde_json::json;
+    ///
+    /// let data = json!({"a": {"b": [1, 2, 3]}});
+    /// let expr = jmespath::compile("a.b").unwrap();
+    /// let result = expr.search_json(&data).unwrap();
+    /// assert!(matches!(result, Cow::Borrowed(_)));
+    /// assert_eq!(json!([1, 2, 3]), *result);
+    /// ```
+    pub fn search_json<'v>(&self, data: &'v Value) -> Result<Cow<'v, Value>, JmespathError> {
+        self.search_value(data)
+    }
+
+    /// Searches a `serde_json::Value` using the given `SearchOptions`.
+    ///
+    /// See `search_json`.
+    pub fn search_json_with_options<'v>(
+        &self,
+        data: &'v Value,
+        options: &SearchOptions,
+    ) -> Result<Cow<'v, Value>, JmespathError> {
+        self.search_value_with_options(data, options)
+    }
+
+    /// Searches data and deserializes the result into a `T`.
+    ///
+    /// The result is deserialized directly from the `Variable`, so it does
+    /// not need to be serialized to JSON and parsed again. Results that
+    /// contain expression references cannot be deserialized.
+    ///
+    /// ```
+    /// let expr = jmespath::compile("people[*].age").unwrap();
+    /// let data = jmespath::Variable::from_json(r#"{"people": [{"age": 3}]}"#).unwrap();
+    /// let ages: Vec<u32> = expr.search_into(data).unwrap();
+    /// assert_eq!(vec![3], ages);
+    /// ```
+    pub fn search_into<T, D>(&self, data: D) -> Result<T, JmespathError>
+    where
+        T: de::DeserializeOwned,
+        D: ToJmespath,
+    {
+        let result = self.search(data)?;
+        Ok(T::deserialize(&*result)?)
+    }
+
+    /// Searches data while recording per-node statistics.
+    ///
+    /// Returns the result of the search together with a `Profile` of the
+    /// time spent evaluating each node of the expression. See the
+    /// `profile` module for details.
+    pub fn search_with_profile<T: ToJmespath>(
+        &self,
+        data: T,
+        options: &SearchOptions,
+    ) -> (SearchResult, Profile) {
+        let mut profiler = Profiler::default();
+        let result = data.to_jmespath().and_then(|data| {
+            let mut ctx = self.context(options);
+            ctx.root = data.clone();
+            ctx.profiler = Some(Box::default());
+            let result = self.backend.evaluate(&self.ast, &data, &mut ctx);
+            profiler = *ctx.profiler.take().unwrap_or_default();
+            result
+        });
+        (result, profiler.report(&self.ast))
+    }
+
+    /// Searches data, returning the result along with the location in the
+    /// document of each value in it.
+    ///
+    /// Each `PathMatch` holds a value of the result that was taken from
+    /// the document, and its path from the root of the document. See the
+    /// `provenance` module for details.
+    pub fn search_with_paths<T: ToJmespath>(
+        &self,
+        data: T,
+    ) -> Result<(Rcvar, Vec<PathMatch>), JmespathError> {
+        self.search_with_paths_and_options(data, &SearchOptions::default())
+    }
+
+    /// Searches data using the given options, returning the result along
+    /// with the location in the document of each value in it.
+    ///
+    /// See `search_with_paths` and `search_with_options`.
+    pub fn search_with_paths_and_options<T: ToJmespath>(
+        &self,
+        data: T,
+        options: &SearchOptions,
+    ) -> Result<(Rcvar, Vec<PathMatch>), JmespathError> {
+        let data = data.to_jmespath()?;
+        let mut ctx = self.context(options);
+        ctx.root = data.clone();
+        provenance::search(&self.ast, data, &mut ctx)
+    }
+
+    /// Searches data, lazily yielding the results of a projection.
+    ///
+    /// When the expression is a projection (e.g., `items[*].name` or
+    /// `items[?active].name`), its left hand side is evaluated up front and
+    /// its right hand side is evaluated for one element each time the
+    /// iterator is advanced, so callers that only need the first few results
+    /// can stop early. The items are the elements of the array `search`
+    /// would return: null results are skipped, and nothing is yielded when
+    /// the left hand side is not an array. Other expressions are evaluated
+    /// eagerly and yield their result as a single item.
+    ///
+    /// The iterator stops after yielding an error. It always uses the tree
+    /// walking interpreter and the default `SearchOptions`.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let expr = jmespath::compile("items[*].name").unwrap();
+    /// let data = Variable::from_json(
+    ///     r#"{"items": [{"name": "a"}, {"id": 2}, {"name": "b"}, {"name": "c"}]}"#,
+    /// )
+    /// .unwrap();
+    /// let first: Vec<_> = expr
+    ///     .search_iter(data)
+    ///     .take(2)
+    ///     .map(|result| result.unwrap().to_string())
+    ///     .collect();
+    /// assert_eq!(vec![r#""a""#, r#""b""#], first);
+    /// ```
+    pub fn search_iter<'e, T: ToJmespath>(
+        &'e self,
+        data: T,
+    ) -> impl Iterator<Item = SearchResult> + 'e {
+        self.search_iter_with_options(data, &SearchOptions::default())
+    }
+
+    /// Lazily searches data using the given `SearchOptions`.
+    ///
+    /// See `search_iter`.
+    pub fn search_iter_with_options<'e, T: ToJmespath>(
+        &'e self,
+        data: T,
+        options: &SearchOptions,
+    ) -> impl Iterator<Item = SearchResult> + 'e {
+        let mut ctx = self.context(options);
+        match data.to_jmespath() {
+            Ok(data) => {
+                ctx.root = data.clone();
+                SearchIter::new(&data, &self.ast, ctx)
+            }
+            Err(err) => SearchIter::error(err, ctx),
+        }
+    }
+
+    /// Returns a lazy iterator over the results of searching each document.
+    ///
+    /// Documents are searched only as the iterator is advanced. A document
+    /// that cannot be converted or searched yields an `Err` item without
+    /// stopping the iteration.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let expr = jmespath::compile("a").unwrap();
+    /// let documents = vec![r#"{"a": 1}"#, r#"{"a": 2}"#]
+    ///     .into_iter()
+    ///     .map(|json| Variable::from_json(json).unwrap());
+    /// let results: Vec<_> = expr
+    ///     .over(documents)
+    ///     .map(|result| result.unwrap().to_string())
+    ///     .collect();
+    /// assert_eq!(vec!["1", "2"], results);
+    /// ```
+    pub fn over<'e, I>(&'e self, documents: I) -> impl Iterator<Item = SearchResult> + 'e
+    where
+        I: IntoIterator,
+        I::IntoIter: 'e,
+        I::Item: ToJmespath,
+    {
+        documents
+            .into_iter()
+            .map(move |document| self.search(document))
+    }
+
+    /// Lowers the expression to an `ir::Program` that is executed by a
+    /// bytecode VM instead of walking the AST.
+    ///
+    /// The VM runs a flat list of instructions instead of recursing over
+    /// the AST; `benches/vm.rs` compares the two on projection-heavy
+    /// expressions. Compiled expressions produce the same results and honor
+    /// the same `SearchOptions`, but they are not evaluated by the
+    /// configured `EvalBackend`, and `search_with_profile` still walks
+    /// the AST.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let expr = jmespath::compile("a[*].b").unwrap().compile();
+    /// assert!(expr.is_compiled());
+    /// let data = Variable::from_json(r#"{"a": [{"b": 1}, {"b": 2}]}"#).unwrap();
+    /// assert_eq!("[1,2]", expr.search(data).unwrap().to_string());
+    /// ```
+    pub fn compile(mut self) -> Expression<'a> {
+        self.program = Some(Program::lower(&self.ast));
+        self
+    }
+
+    /// Combines the expression with another, piping the result of this
+    /// expression into `other` as in `(self) | (other)`.
+    ///
+    /// The combined expression uses the runtime and backend of this
+    /// expression, and the parameters bound to both expressions. A name
+    /// bound by both expressions keeps its value on each side, as if each
+    /// side were wrapped in a `let` expression binding it. The combined
+    /// expression is compiled if this expression was compiled, and only
+    /// follows `SpecVersion::Original` if both expressions do.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let active = jmespath::compile("users[?active]").unwrap();
+    /// let names = jmespath::compile("[*].name").unwrap();
+    /// let expr = active.pipe(names);
+    /// assert_eq!("(users[?active]) | ([*].name)", expr.as_str());
+    /// let data = Variable::from_json(
+    ///     r#"{"users": [{"name": "a", "active": true}, {"name": "b", "active": false}]}"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(r#"["a"]"#, expr.search(data).unwrap().to_string());
+    /// ```
+    pub fn pipe(self, other: Expression<'a>) -> Expression<'a> {
+        self.combine(other, "|", |offset, lhs, rhs| Ast::Subexpr {
+            offset,
+            lhs,
+            rhs,
+        })
+    }
+
+    /// Combines the expression with another as in `(self) && (other)`.
+    ///
+    /// See `pipe` for how the expressions are combined.
+    pub fn and(self, other: Expression<'a>) -> Expression<'a> {
+        self.combine(other, "&&", |offset, lhs, rhs| Ast::And {
+            offset,
+            lhs,
+            rhs,
+        })
+    }
+
+    /// Combines the expression with another as in `(self) || (other)`.
+    ///
+    /// See `pipe` for how the expressions are combined.
+    pub fn or(self, other: Expression<'a>) -> Expression<'a> {
+        self.combine(other, "||", |offset, lhs, rhs| Ast::Or { offset, lhs, rhs })
+    }
+
+    /// Joins two expressions with a binary operator, shifting the offsets of
+    /// both ASTs to match the combined expression string.
+    fn combine(
+        mut self,
+        mut other: Expression<'a>,
+        operator: &str,
+        node: fn(usize, Box<Ast>, Box<Ast>) -> Ast,
+    ) -> Expression<'a> {
+        let expression = format!("({}) {} ({})", self.expression, operator, other.expression);
+        let operator_offset = self.expression.len() + 3;
+        self.ast.shift_offsets(1);
+        other
+            .ast
+            .shift_offsets(operator_offset + operator.len() + 2);
+        let shared: Vec<String> = self
+            .params
+            .iter()
+            .filter(|(name, _)| other.params.iter().any(|(param, _)| param == name))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let lhs = bind_params(self.ast, &mut self.params, &shared);
+        let rhs = bind_params(other.ast, &mut other.params, &shared);
+        let ast = node(operator_offset, Box::new(lhs), Box::new(rhs));
+        self.params.append(&mut other.params);
+        Expression {
+            program: self.program.map(|_| Program::lower(&ast)),
+            ast,
+            expression,
+            runtime: self.runtime,
+            backend: self.backend,
+            params: self.params,
+            spec_version: self.spec_version.max(other.spec_version),
+        }
+    }
+
+    /// Returns true if the expression was compiled using `compile`.
+    pub fn is_compiled(&self) -> bool {
+        self.program.is_some()
     }
 
     /// Returns the JMESPath expression from which the Expression was compiled.
@@ -432,6 +1014,253 @@ impl<'a> PartialEq for Expression<'a> {
     }
 }
 
+/// Serializes the expression as its expression string.
+impl<'a> ser::Serialize for Expression<'a> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Deserializes an expression string and compiles it using
+/// `jmespath::compile`, so expressions can be part of configuration.
+///
+/// ```
+/// let expr: jmespath::Expression = serde_json::from_str(r#""length(@)""#).unwrap();
+/// assert_eq!(Some(3.0), expr.search("abc").unwrap().as_number());
+/// ```
+impl<'de> de::Deserialize<'de> for Expression<'static> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let expression = String::deserialize(deserializer)?;
+        compile(&expression).map_err(de::Error::custom)
+    }
+}
+
+/// Builds `Expression`s from an expression string.
+///
+/// By default, expressions are compiled against the shared `Runtime` used
+/// by `jmespath::compile`. A custom `Runtime` can be provided with
+/// `with_runtime`, or shared with `with_shared_runtime` to build an
+/// `Expression<'static>` that owns a reference to its runtime.
+///
+/// ```
+/// use jmespath::{ExpressionBuilder, Runtime};
+///
+/// let mut runtime = Runtime::new();
+/// runtime.register_builtin_functions();
+///
+/// let expr = ExpressionBuilder::new("length(foo)")
+///     .with_runtime(&runtime)
+///     .build()
+///     .unwrap();
+/// assert_eq!("length(foo)", expr.as_str());
+/// ```
+pub struct ExpressionBuilder<'a> {
+    expression: String,
+    runtime: RuntimeRef<'a>,
+    spec_version: SpecVersion,
+    backend: &'static dyn EvalBackend,
+    parser_options: ParserOptions,
+    params: Vec<(String, Rcvar)>,
+}
+
+impl ExpressionBuilder<'static> {
+    /// Creates a new builder for the given expression string.
+    pub fn new<S>(expression: S) -> ExpressionBuilder<'static>
+    where
+        S: Into<String>,
+    {
+        ExpressionBuilder {
+            expression: expression.into(),
+            runtime: RuntimeRef::Borrowed(&DEFAULT_RUNTIME),
+            spec_version: SpecVersion::default(),
+            backend: &backend::DEFAULT_BACKEND,
+            parser_options: ParserOptions::default(),
+            params: vec![],
+        }
+    }
+}
+
+impl<'a> ExpressionBuilder<'a> {
+    /// Uses the given `Runtime` to compile and evaluate the expression.
+    pub fn with_runtime<'b>(self, runtime: &'b Runtime) -> ExpressionBuilder<'b> {
+        self.with_runtime_ref(RuntimeRef::Borrowed(runtime))
+    }
+
+    /// Uses a shared `Runtime` to compile and evaluate the expression.
+    ///
+    /// The built expression holds a clone of the `Arc`, so it is not tied
+    /// to the lifetime of a borrowed runtime and can be stored in structs
+    /// or returned from functions.
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use jmespath::{Expression, ExpressionBuilder, Runtime};
+    ///
+    /// fn build(expression: &str) -> Expression<'static> {
+    ///     let mut runtime = Runtime::new();
+    ///     runtime.register_builtin_functions();
+    ///     ExpressionBuilder::new(expression)
+    ///         .with_shared_runtime(Arc::new(runtime))
+    ///         .build()
+    ///         .unwrap()
+    /// }
+    ///
+    /// assert_eq!(Some(3.0), build("length(@)").search("abc").unwrap().as_number());
+    /// ```
+    pub fn with_shared_runtime(self, runtime: Arc<Runtime>) -> ExpressionBuilder<'static> {
+        self.with_runtime_ref(RuntimeRef::Shared(runtime))
+    }
+
+    fn with_runtime_ref<'b>(self, runtime: RuntimeRef<'b>) -> ExpressionBuilder<'b> {
+        ExpressionBuilder {
+            expression: self.expression,
+            runtime,
+            spec_version: self.spec_version,
+            backend: self.backend,
+            parser_options: self.parser_options,
+            params: self.params,
+        }
+    }
+
+    /// Sets the version of the JMESPath specification the expression must
+    /// conform to.
+    ///
+    /// Defaults to `SpecVersion::Community`, which accepts every extension
+    /// supported by this crate.
+    ///
+    /// ```
+    /// use jmespath::{ExpressionBuilder, SpecVersion};
+    ///
+    /// let builder = ExpressionBuilder::new("a % `2`");
+    /// assert!(builder.spec_version(SpecVersion::Original).build().is_err());
+    /// ```
+    pub fn spec_version(mut self, version: SpecVersion) -> ExpressionBuilder<'a> {
+        self.spec_version = version;
+        self
+    }
+
+    /// Sets the `EvalBackend` used to search data with the expression.
+    ///
+    /// Defaults to `backend::TreeWalker`.
+    pub fn backend(mut self, backend: &'static dyn EvalBackend) -> ExpressionBuilder<'a> {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the limits applied while parsing the expression.
+    ///
+    /// Defaults to `ParserOptions::default()`, which limits the nesting
+    /// depth to `DEFAULT_MAX_PARSE_DEPTH`.
+    ///
+    /// ```
+    /// use jmespath::{ExpressionBuilder, ParserOptions};
+    ///
+    /// let options = ParserOptions {
+    ///     max_depth: Some(4),
+    ///     ..ParserOptions::default()
+    /// };
+    /// let builder = ExpressionBuilder::new("[[[[a]]]]").parser_options(options);
+    /// assert!(builder.build().is_err());
+    /// ```
+    pub fn parser_options(mut self, options: ParserOptions) -> ExpressionBuilder<'a> {
+        self.parser_options = options;
+        self
+    }
+
+    /// Binds the variable `$name` to `value` for every search with the
+    /// expression.
+    ///
+    /// Parameters are bound like variables of an enclosing `let`
+    /// expression, so `let` bindings of the same name shadow them.
+    /// `Expression::search_with_params` binds parameters for a single
+    /// search.
+    ///
+    /// ```
+    /// use jmespath::{ExpressionBuilder, Variable};
+    ///
+    /// let expr = ExpressionBuilder::new("[?age >= $min].name")
+    ///     .with_param("min", Variable::Number(18.into()))
+    ///     .build()
+    ///     .unwrap();
+    /// let data = Variable::from_json(r#"[{"name": "a", "age": 20}, {"name": "b", "age": 8}]"#)
+    ///     .unwrap();
+    /// assert_eq!(r#"["a"]"#, expr.search(data).unwrap().to_string());
+    /// ```
+    pub fn with_param<S: Into<String>>(
+        mut self,
+        name: S,
+        value: Variable,
+    ) -> ExpressionBuilder<'a> {
+        self.params.push((name.into(), Rcvar::new(value)));
+        self
+    }
+
+    /// Parses the expression and returns the compiled `Expression`.
+    pub fn build(self) -> Result<Expression<'a>, JmespathError> {
+        let ast = trace::compile(&self.expression, || {
+            parse_with_options(&self.expression, &self.parser_options)
+        })?;
+        self.spec_version.check(&self.expression, &ast)?;
+        Ok(Expression {
+            expression: self.expression,
+            ast,
+            runtime: self.runtime,
+            backend: self.backend,
+            program: None,
+            params: self.params,
+            spec_version: self.spec_version,
+        })
+    }
+
+    /// Parses the expression and lints it against the chosen `Runtime`.
+    ///
+    /// Returns the compiled `Expression` along with any warnings that were
+    /// found. Warnings do not prevent the expression from being compiled.
+    pub fn build_checked(self) -> Result<(Expression<'a>, Vec<lint::Warning>), JmespathError> {
+        let expr = self.build()?;
+        let warnings = lint::lint(&expr.ast, &expr.runtime)
+            .into_iter()
+            .filter(|warning| match warning.kind {
+                lint::WarningKind::UndefinedVariable(ref name) => {
+                    !expr.params.iter().any(|(param, _)| param == name)
+                }
+                _ => true,
+            })
+            .collect();
+        Ok((expr, warnings))
+    }
+}
+
+/// Removes the parameters named in `names` from `params`, binding them
+/// around `ast` with a `let` node instead.
+fn bind_params(ast: Ast, params: &mut Vec<(String, Rcvar)>, names: &[String]) -> Ast {
+    let offset = ast.offset();
+    let (bound, kept) = params
+        .drain(..)
+        .partition::<Vec<_>, _>(|(name, _)| names.contains(name));
+    *params = kept;
+    if bound.is_empty() {
+        return ast;
+    }
+    let bindings = bound
+        .into_iter()
+        .map(|(name, value)| ast::Binding {
+            name,
+            value: Ast::Literal { offset, value },
+        })
+        .collect();
+    Ast::Let {
+        offset,
+        bindings,
+        body: Box::new(ast),
+    }
+}
+
+/// Returns true if the AST contains a root node reference (`$`).
+fn references_root(ast: &Ast) -> bool {
+    matches!(*ast, Ast::Root { .. }) || ast.children().into_iter().any(references_root)
+}
+
 /// Context object used for error reporting.
 ///
 /// The Context struct is mostly used when interacting between the
@@ -444,6 +1273,23 @@ pub struct Context<'a> {
     pub runtime: &'a Runtime,
     /// Ast offset that is currently being evaluated.
     pub offset: usize,
+    /// Options that control how the expression is evaluated.
+    pub options: SearchOptions,
+    /// The document originally provided to the search.
+    root: Rcvar,
+    /// Number of AST nodes evaluated so far.
+    evaluated_nodes: usize,
+    /// Number of AST nodes that are currently being interpreted.
+    depth: usize,
+    /// Statistics recorded by `Expression::search_with_profile`.
+    profiler: Option<Box<Profiler>>,
+    /// Variables bound by the enclosing `let` expressions, innermost last.
+    scope: Vec<(String, Rcvar)>,
+    /// The value the arguments of the function being called were
+    /// evaluated against.
+    current: Rcvar,
+    /// Version of the specification the expression was compiled for.
+    spec_version: SpecVersion,
 }
 
 impl<'a> Context<'a> {
@@ -454,8 +1300,98 @@ impl<'a> Context<'a> {
             expression,
             runtime,
             offset: 0,
+            options: SearchOptions::default(),
+            root: Rcvar::new(Variable::Null),
+            evaluated_nodes: 0,
+            depth: 0,
+            profiler: None,
+            scope: vec![],
+            current: Rcvar::new(Variable::Null),
+            spec_version: SpecVersion::default(),
         }
     }
+
+    /// Returns true if slices apply to strings, which is an extension to
+    /// the original specification.
+    #[inline]
+    pub(crate) fn slices_strings(&self) -> bool {
+        self.spec_version == SpecVersion::Community
+    }
+
+    /// Returns the document that was originally provided to the search.
+    ///
+    /// Functions only receive their evaluated arguments, so this can be
+    /// used to consult other parts of the input (e.g., lookup tables).
+    /// Returns null when the context was not created by a search.
+    #[inline]
+    pub fn root(&self) -> &Rcvar {
+        &self.root
+    }
+
+    /// Returns the value that the arguments of the function being called
+    /// were evaluated against, i.e. `@` where the function was called.
+    ///
+    /// Functions taking expression references can evaluate them against
+    /// this value to compute results from the surrounding data. Returns
+    /// null outside of function calls. When searching a `ValueLike`
+    /// document using `Expression::search_value`, it is only provided to
+    /// calls with an expression reference argument, and is null otherwise.
+    #[inline]
+    pub fn current(&self) -> &Rcvar {
+        &self.current
+    }
+
+    /// Returns the number of AST nodes evaluated so far using the context.
+    ///
+    /// Nodes are counted each time they are evaluated, so a node inside a
+    /// projection is counted once per projected element.
+    #[inline]
+    pub fn evaluated_nodes(&self) -> usize {
+        self.evaluated_nodes
+    }
+
+    /// Evaluates an expression reference against a value.
+    ///
+    /// This allows custom functions that accept `ArgumentType::Expref`
+    /// arguments to apply the referenced expression to values of their
+    /// choosing. Returns a `RuntimeError::InvalidType` error if `expref` is
+    /// not an expression reference. The context does not know which
+    /// argument `expref` was, so the error reports position 0.
+    ///
+    /// ```
+    /// use jmespath::{Context, Rcvar, Runtime, Variable};
+    /// use jmespath::functions::{ArgumentType, CustomFunction, Signature};
+    ///
+    /// let mut runtime = Runtime::new();
+    /// runtime.register_builtin_functions();
+    /// runtime.register_function("apply", Box::new(CustomFunction::new(
+    ///     Signature::new(vec![ArgumentType::Expref, ArgumentType::Any], None),
+    ///     Box::new(|args: &[Rcvar], ctx: &mut Context| ctx.evaluate(&args[0], &args[1]))
+    /// )));
+    ///
+    /// let expr = runtime.compile("apply(&length(@), 'abc')").unwrap();
+    /// assert_eq!(Some(3.0), expr.search(()).unwrap().as_number());
+    /// ```
+    pub fn evaluate(&mut self, expref: &Variable, value: &Rcvar) -> SearchResult {
+        let ast = match expref.as_expref() {
+            Some(ast) => ast,
+            None => {
+                let reason = ErrorReason::Runtime(RuntimeError::InvalidType {
+                    function: None,
+                    expected: functions::ArgumentType::Expref,
+                    actual: expref.get_type(),
+                    position: 0,
+                });
+                return Err(JmespathError::from_ctx(self, reason));
+            }
+        };
+        // Nested function calls move the offset, so restore it for any
+        // errors raised by the caller after evaluation.
+        let offset = self.offset;
+        let result = interpret(value, ast, self);
+        self.offset = offset;
+        result
+    }
 }
 
 #[cfg(test)]
@@ -508,6 +1444,1092 @@ mod test {
         let _ = expr.clone();
     }
 
+    #[test]
+    fn expressions_round_trip_through_serde() {
+        #[derive(serde_derive::Serialize, serde_derive::Deserialize)]
+        struct Config {
+            query: Expression<'static>,
+        }
+        let config: Config = serde_json::from_str(r#"{"query": "a[0]"}"#).unwrap();
+        let data = Variable::from_json(r#"{"a": [1]}"#).unwrap();
+        assert_eq!("1", config.query.search(data).unwrap().to_string());
+        assert_eq!(
+            r#"{"query":"a[0]"}"#,
+            serde_json::to_string(&config).unwrap()
+        );
+        let err = serde_json::from_str::<Config>(r#"{"query": "a["}"#)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("a["), "{}", err);
+    }
+
+    #[test]
+    fn builder_compiles_against_custom_runtime() {
+        let runtime = Runtime::new();
+        let expr = ExpressionBuilder::new("length(@)")
+            .with_runtime(&runtime)
+            .build()
+            .unwrap();
+        assert!(expr.search(()).is_err());
+        assert!(ExpressionBuilder::new("foo{").build().is_err());
+    }
+
+    #[test]
+    fn builder_shares_owned_runtime() {
+        struct Holder {
+            expr: Expression<'static>,
+        }
+        let mut runtime = Runtime::new();
+        runtime.register_function(
+            "one",
+            Box::new(|_: &[Rcvar], _: &mut Context<'_>| Ok(Rcvar::new(Variable::Bool(true)))),
+        );
+        let runtime = Arc::new(runtime);
+        let holder = Holder {
+            expr: ExpressionBuilder::new("one()")
+                .with_shared_runtime(runtime.clone())
+                .build()
+                .unwrap(),
+        };
+        drop(runtime);
+        let copy = holder.expr.clone();
+        assert_eq!("true", copy.search(()).unwrap().to_string());
+    }
+
+    #[test]
+    fn combines_expressions() {
+        let lhs = "a[*].b.{c: length(c), d: let $x = d in [$x, `1`]}";
+        let rhs = "[?c > `1`] || e[0]";
+        let combinations = [
+            (compile(lhs).unwrap().pipe(compile(rhs).unwrap()), "|"),
+            (compile(lhs).unwrap().and(compile(rhs).unwrap()), "&&"),
+            (compile(lhs).unwrap().or(compile(rhs).unwrap()), "||"),
+        ];
+        for (expr, operator) in combinations.iter() {
+            let expression = format!("({}) {} ({})", lhs, operator, rhs);
+            assert_eq!(expression, expr.as_str());
+            assert_eq!(&parse(&expression).unwrap(), expr.as_ast());
+        }
+        let data =
+            Variable::from_json(r#"{"a": [{"b": {"c": "xy"}}, {"b": {"c": "z"}}]}"#).unwrap();
+        let expr = compile("a[*].b").unwrap().compile();
+        let expr = expr.pipe(compile("[0]").unwrap());
+        assert!(expr.is_compiled());
+        assert_eq!(r#"{"c":"xy"}"#, expr.search(data).unwrap().to_string());
+        let with_x = |expression, x: i32| {
+            ExpressionBuilder::new(expression)
+                .with_param("x", Variable::Number(x.into()))
+                .with_param(format!("y{}", x), Variable::Bool(true))
+                .build()
+                .unwrap()
+        };
+        let expr = with_x("$x", 1).pipe(with_x("[@, $x, $y1, $y2]", 2));
+        assert_eq!("[1,2,true,true]", expr.search(()).unwrap().to_string());
+        let expr = expr.compile();
+        assert_eq!("[1,2,true,true]", expr.search(()).unwrap().to_string());
+    }
+
+    #[test]
+    fn builder_returns_lint_warnings() {
+        let (expr, warnings) = ExpressionBuilder::new("abs(a, b)").build_checked().unwrap();
+        assert_eq!("abs(a, b)", expr.as_str());
+        assert_eq!(1, warnings.len());
+        assert_eq!(3, warnings[0].offset);
+    }
+
+    #[test]
+    fn binds_parameters() {
+        let data =
+            Variable::from_json(r#"[{"id": 1, "owner": "a"}, {"id": 2, "owner": "b"}]"#).unwrap();
+        let json = serde_json::json!([{"id": 1, "owner": "a"}, {"id": 2, "owner": "b"}]);
+        let expr = ExpressionBuilder::new("[?owner == $owner].id")
+            .with_param("owner", Variable::String("a".to_owned()))
+            .build()
+            .unwrap();
+        assert_eq!("[1]", expr.search(data.clone()).unwrap().to_string());
+        assert_eq!(
+            "[1]",
+            expr.clone()
+                .compile()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!("[1]", expr.search_json(&json).unwrap().to_string());
+        let b = Variable::String("b".to_owned());
+        assert_eq!(
+            "[2]",
+            expr.search_with_params(data.clone(), &[("owner", b)])
+                .unwrap()
+                .to_string()
+        );
+        let injected = Variable::String("a' || 'b".to_owned());
+        assert_eq!(
+            "[]",
+            expr.search_with_params(data.clone(), &[("owner", injected)])
+                .unwrap()
+                .to_string()
+        );
+        let strict = SearchOptions {
+            strict: true,
+            ..SearchOptions::default()
+        };
+        let missing = expr
+            .search_with_params_and_options(
+                Variable::from_json(r#"[{"id": 1}]"#).unwrap(),
+                &[("owner", Variable::String("b".to_owned()))],
+                &strict,
+            )
+            .unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::MissingField("owner".to_owned())),
+            missing.reason
+        );
+        let shadowed = ExpressionBuilder::new("let $owner = 'b' in [?owner == $owner].id")
+            .with_param("owner", Variable::String("a".to_owned()))
+            .build()
+            .unwrap();
+        assert_eq!("[2]", shadowed.search(data).unwrap().to_string());
+        let (_, warnings) = ExpressionBuilder::new("[$a, $b]")
+            .with_param("a", Variable::Null)
+            .build_checked()
+            .unwrap();
+        assert_eq!(1, warnings.len());
+        assert_eq!(5, warnings[0].offset);
+    }
+
+    #[test]
+    fn round_trips_serialized_asts() {
+        let expressions = [
+            "a.b[0][1:-1:2].*[] | {x: c, y: [d, e]}",
+            "a[?b >= `{\"c\": [1, 2.5, null]}` && !c || d != 'e']",
+            "sort_by(a, &b) // `2` % `3`",
+        ];
+        for expression in expressions.iter() {
+            let ast = parse(expression).unwrap();
+            let json = serde_json::to_string(&ast).unwrap();
+            let parsed: Ast = serde_json::from_str(&json).unwrap();
+            assert_eq!(ast, parsed, "{}", expression);
+            let expr = Expression::from_parts(*expression, parsed);
+            assert_eq!(*expression, expr.as_str());
+        }
+    }
+
+    #[test]
+    fn limits_parser_nesting_depth() {
+        let depth_error = |expression: &str| compile(expression).unwrap_err().reason;
+        let limit = ErrorReason::ParseLimit(ParseLimit::Depth(DEFAULT_MAX_PARSE_DEPTH));
+        let parens = format!("{}a{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(limit, depth_error(&parens));
+        assert_eq!(limit, depth_error(&"!".repeat(100_000)));
+        assert_eq!(limit, depth_error(&"[".repeat(100_000)));
+        assert!(compile(&vec!["a"; 1000].join(".")).is_ok());
+        assert!(compile(&vec!["a"; 1000].join(" || ")).is_ok());
+        let options = ParserOptions {
+            max_depth: Some(3),
+            ..ParserOptions::default()
+        };
+        let build = |expression| {
+            ExpressionBuilder::new(expression)
+                .parser_options(options.clone())
+                .build()
+        };
+        assert!(build("[a.b]").is_ok());
+        let err = build("[[a.b]]").unwrap_err();
+        assert_eq!(ErrorReason::ParseLimit(ParseLimit::Depth(3)), err.reason);
+        assert_eq!(4, err.offset);
+        let unlimited = ParserOptions {
+            max_depth: None,
+            ..ParserOptions::default()
+        };
+        assert!(parse_with_options(&vec!["a"; 200].join("."), &unlimited).is_ok());
+    }
+
+    #[test]
+    fn type_errors_carry_function_name_and_types() {
+        let err = compile("map(&abs(@), `[\"a\"]`)")
+            .unwrap()
+            .search(())
+            .unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidType {
+                function: Some("abs".to_owned()),
+                expected: functions::ArgumentType::Number,
+                actual: JmespathType::String,
+                position: 0,
+            }),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn null_propagation_can_raise_errors() {
+        let expr = compile("foo.bar.baz").unwrap();
+        let data = Variable::from_json("{\"foo\":{\"baz\":true}}").unwrap();
+        let options = SearchOptions {
+            null_propagation: NullPropagation::Error,
+            ..SearchOptions::default()
+        };
+        assert!(expr.search(data.clone()).unwrap().is_null());
+        let err = expr.search_with_options(data, &options).unwrap_err();
+        assert_eq!(ErrorReason::Runtime(RuntimeError::NullValue), err.reason);
+        assert_eq!(7, err.offset);
+        // A null at the final step is still returned as a result.
+        let data = Variable::from_json("{\"foo\":{\"bar\":{}}}").unwrap();
+        assert!(expr.search_with_options(data, &options).unwrap().is_null());
+    }
+
+    #[test]
+    fn strict_mode_reports_missing_values() {
+        let data = Variable::from_json(r#"{"a": {"b": null, "c": [1, 2]}, "d": "xyz"}"#).unwrap();
+        let json = serde_json::json!({"a": {"b": null, "c": [1, 2]}, "d": "xyz"});
+        let options = SearchOptions {
+            strict: true,
+            ..SearchOptions::default()
+        };
+        let check = |expression: &str, expected: Option<RuntimeError>, offset: usize| {
+            let expr = compile(expression).unwrap();
+            let results = [
+                expr.search_with_options(data.clone(), &options).map(|_| ()),
+                expr.search_json_with_options(&json, &options).map(|_| ()),
+            ];
+            for result in results {
+                match (result, &expected) {
+                    (Ok(()), None) => {}
+                    (Err(err), Some(reason)) => {
+                        assert_eq!(ErrorReason::Runtime(reason.clone()), err.reason);
+                        assert_eq!(offset, err.offset, "{}", expression);
+                    }
+                    (result, _) => panic!("{}: {:?}", expression, result),
+                }
+            }
+        };
+        check("a.b", None, 0);
+        check("a.c[-2]", None, 0);
+        check("d[1:]", None, 0);
+        check("a.x", Some(RuntimeError::MissingField("x".to_owned())), 2);
+        check(
+            "a.c[2]",
+            Some(RuntimeError::IndexOutOfBounds {
+                index: 2,
+                length: 2,
+            }),
+            5,
+        );
+        check(
+            "a.c[-3]",
+            Some(RuntimeError::IndexOutOfBounds {
+                index: -3,
+                length: 2,
+            }),
+            6,
+        );
+        check(
+            "d.e",
+            Some(RuntimeError::InvalidAccess {
+                expected: JmespathType::Object,
+                actual: JmespathType::String,
+            }),
+            2,
+        );
+        check(
+            "a[0]",
+            Some(RuntimeError::InvalidAccess {
+                expected: JmespathType::Array,
+                actual: JmespathType::Object,
+            }),
+            3,
+        );
+        assert_eq!(
+            "Runtime error: Object has no field \"x\"",
+            compile("x")
+                .unwrap()
+                .search_with_options(data.clone(), &options)
+                .unwrap_err()
+                .reason
+                .to_string()
+        );
+        assert!(compile("a.x").unwrap().search(data).unwrap().is_null());
+    }
+
+    #[test]
+    fn fields_can_be_looked_up_ignoring_case() {
+        let json = r#"{"EventID": 4624, "eventid": 1, "Keywords": {"LEVEL": null}}"#;
+        let data = Variable::from_json(json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        let options = SearchOptions {
+            case_insensitive_fields: true,
+            ..SearchOptions::default()
+        };
+        let strict = SearchOptions {
+            strict: true,
+            ..options.clone()
+        };
+        let search = |expression: &str, options: &SearchOptions| {
+            let expr = compile(expression).unwrap();
+            let walked = expr.search_with_options(data.clone(), options).unwrap();
+            let borrowed = expr.search_json_with_options(&value, options).unwrap();
+            assert_eq!(walked.to_string(), borrowed.to_string());
+            let executed = expr.compile().search_with_options(data.clone(), options);
+            assert_eq!(walked, executed.unwrap());
+            walked.to_string()
+        };
+        assert_eq!("4624", search("EventID", &options));
+        assert_eq!("1", search("eventid", &options));
+        assert_eq!("4624", search("EVENTID", &options));
+        assert_eq!("null", search("EVENTID", &SearchOptions::default()));
+        assert_eq!("null", search("keywords.level", &strict));
+        assert!(compile("keywords.levels")
+            .unwrap()
+            .search_with_options(data.clone(), &strict)
+            .is_err());
+    }
+
+    #[test]
+    fn searches_documents_lazily() {
+        use std::cell::Cell;
+        let expr = compile("length(@)").unwrap();
+        let pulled = Cell::new(0);
+        let documents = ["\"ab\"", "1", "[1, 2, 3]"].iter().map(|json| {
+            pulled.set(pulled.get() + 1);
+            Variable::from_json(json).unwrap()
+        });
+        let mut results = expr.over(documents);
+        assert_eq!(0, pulled.get());
+        assert_eq!(Some(2.0), results.next().unwrap().unwrap().as_number());
+        assert_eq!(1, pulled.get());
+        assert!(results.next().unwrap().is_err());
+        assert_eq!(Some(3.0), results.next().unwrap().unwrap().as_number());
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn projections_can_be_deduplicated() {
+        let data = Variable::from_json(
+            r#"{"a": [{"b": 2}, {"b": 1}, {"b": 2.0}, {"c": 1}, {"b": [1]}, {"b": [1]}]}"#,
+        )
+        .unwrap();
+        let options = SearchOptions {
+            dedup_projections: true,
+            ..SearchOptions::default()
+        };
+        let search = |expr: &str, options: &SearchOptions| {
+            compile(expr)
+                .unwrap()
+                .search_with_options(data.clone(), options)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            "[2,1,2.0,[1],[1]]",
+            search("a[*].b", &SearchOptions::default())
+        );
+        assert_eq!("[2,1,[1]]", search("a[*].b", &options));
+        assert_eq!("[2,1]", search("a[*].b[]", &options));
+    }
+
+    #[test]
+    fn projects_objects_as_values() {
+        let data =
+            Variable::from_json(r#"{"users": {"b": {"age": 40}, "a": {"age": 30}}}"#).unwrap();
+        let options = SearchOptions {
+            project_objects: true,
+            ..SearchOptions::default()
+        };
+        let search = |expr: &str, options: &SearchOptions| {
+            compile(expr)
+                .unwrap()
+                .search_with_options(data.clone(), options)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("null", search("users[*].age", &SearchOptions::default()));
+        assert_eq!("[30,40]", search("users[*].age", &options));
+        assert_eq!("[40]", search("users[?age > `35`].age", &options));
+        assert_eq!("[{\"age\":40}]", search("users[1:]", &options));
+        assert_eq!("30", search("users[0].age", &options));
+        assert_eq!("[{\"age\":30},{\"age\":40}]", search("users[]", &options));
+        assert_eq!("2", search("length(users)", &options));
+    }
+
+    #[test]
+    fn compares_numbers_within_epsilon() {
+        let data = Variable::from_json(r#"[{"n": 0.3000001}, {"n": 0.31}]"#).unwrap();
+        let options = SearchOptions {
+            epsilon: Some(1e-6),
+            ..SearchOptions::default()
+        };
+        let search = |expr: &str, options: &SearchOptions| {
+            compile(expr)
+                .unwrap()
+                .search_with_options(data.clone(), options)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[]", search("[?n == `0.3`].n", &SearchOptions::default()));
+        assert_eq!("[0.3000001]", search("[?n == `0.3`].n", &options));
+        assert_eq!("[0.31]", search("[?n > `0.3`].n", &options));
+        assert_eq!("[]", search("[?n < `0.3`].n", &options));
+        assert_eq!("[0.3000001]", search("[?n <= `0.3`].n", &options));
+        assert_eq!("[0.31]", search("[?n != `0.3`].n", &options));
+        assert_eq!("[]", search("[?n == 'a'].n", &options));
+    }
+
+    #[test]
+    fn custom_functions_can_evaluate_exprefs() {
+        use crate::functions::{ArgumentType, CustomFunction, Signature};
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        runtime.register_function(
+            "my_sort_by",
+            Box::new(CustomFunction::new(
+                Signature::new(vec![ArgumentType::Array, ArgumentType::Expref], None),
+                Box::new(|args: &[Rcvar], ctx: &mut Context<'_>| {
+                    let mut keyed = vec![];
+                    for value in args[0].as_array().unwrap() {
+                        keyed.push((ctx.evaluate(&args[1], value)?, value.clone()));
+                    }
+                    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                    let sorted = keyed.into_iter().map(|(_, v)| v).collect();
+                    Ok(Rcvar::new(Variable::Array(sorted)))
+                }),
+            )),
+        );
+        let expr = runtime.compile("my_sort_by(@, &a)[*].a").unwrap();
+        let data = Variable::from_json("[{\"a\":3},{\"a\":1},{\"a\":2}]").unwrap();
+        assert_eq!("[1,2,3]", expr.search(data).unwrap().to_string());
+    }
+
+    #[test]
+    fn context_evaluate_rejects_non_exprefs() {
+        let runtime = Runtime::new();
+        let mut ctx = Context::new("foo", &runtime);
+        let value = Rcvar::new(Variable::Null);
+        let err = ctx.evaluate(&Variable::Bool(true), &value).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidType {
+                function: None,
+                expected: functions::ArgumentType::Expref,
+                actual: JmespathType::Boolean,
+                position: 0,
+            }),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn custom_functions_can_access_root() {
+        let mut runtime = Runtime::new();
+        runtime.register_function(
+            "resolve_ref",
+            Box::new(|args: &[Rcvar], ctx: &mut Context<'_>| {
+                let id = args[0].as_string().cloned().unwrap_or_default();
+                Ok(ctx.root().get_field("definitions").get_field(&id))
+            }),
+        );
+        let expr = runtime.compile("items[*].resolve_ref(ref)").unwrap();
+        let data = Variable::from_json(
+            "{\"definitions\":{\"a\":1,\"b\":2},\"items\":[{\"ref\":\"b\"},{\"ref\":\"a\"}]}",
+        )
+        .unwrap();
+        assert_eq!("[2,1]", expr.search(data).unwrap().to_string());
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn expressions_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Expression<'static>>();
+        assert_send_sync::<Rcvar>();
+        static EXPR: Lazy<Expression<'static>> = Lazy::new(|| compile("length(@)").unwrap());
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|n| scope.spawn(move || EXPR.search(vec![true; n]).unwrap()))
+                .collect();
+            for (n, handle) in handles.into_iter().enumerate() {
+                assert_eq!(Some(n as f64), handle.join().unwrap().as_number());
+            }
+        });
+    }
+
+    #[test]
+    fn searches_into_deserializable_types() {
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        struct Person {
+            name: String,
+            age: Option<u32>,
+        }
+        let data =
+            Variable::from_json(r#"{"people": [{"name": "a", "age": 3}, {"name": "b"}]}"#).unwrap();
+        let people: Vec<Person> = compile("people")
+            .unwrap()
+            .search_into(data.clone())
+            .unwrap();
+        assert_eq!(
+            vec![
+                Person {
+                    name: "a".to_owned(),
+                    age: Some(3)
+                },
+                Person {
+                    name: "b".to_owned(),
+                    age: None
+                },
+            ],
+            people
+        );
+        let err = compile("people[0].name")
+            .unwrap()
+            .search_into::<u32, _>(data)
+            .unwrap_err();
+        assert!(err.to_string().contains("Serde"), "{}", err);
+    }
+
+    #[test]
+    fn compiled_expressions_match_the_interpreter() {
+        let data = Variable::from_json(
+            r#"{"a": [{"b": 1}, {"b": 1}, {"c": 2}], "o": {"x": {"v": 1.0000001}, "y": {"v": 2}}}"#,
+        )
+        .unwrap();
+        let options = [
+            SearchOptions::default(),
+            SearchOptions {
+                dedup_projections: true,
+                project_objects: true,
+                epsilon: Some(1e-6),
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                null_propagation: NullPropagation::Error,
+                division_by_zero: DivisionByZero::Null,
+                ..SearchOptions::default()
+            },
+            SearchOptions {
+                strict: true,
+                ..SearchOptions::default()
+            },
+        ];
+        let expressions = [
+            "a[*].b",
+            "o[*].v",
+            "o[?v == `1`].v",
+            "a[].c || a[0:2].b && `true`",
+            "{k: a[-1].c, l: [length(a), !a]}",
+            "a[*].b.c",
+            "sort_by(a, &b)[*].b",
+            "`1` // `0`",
+            "a[::0]",
+            "o.*.v | [@[0] % `2`]",
+            "let $v = o.y.v in a[?b < $v].[b, $v]",
+            "let $a = a, $b = $a in $b",
+            "o.y.v * `3` - -o.x.v / `2` + a[0].b",
+            "-a",
+            "a[?b == $.o.x.v].c || $.o.y",
+            "['abcdef'[1:4], 'abcdef'[::-2], a[0:2].b]",
+        ];
+        for options in &options {
+            for expression in &expressions {
+                let expr = compile(expression).unwrap();
+                let walked = expr.search_with_options(data.clone(), options);
+                let executed = expr
+                    .clone()
+                    .compile()
+                    .search_with_options(data.clone(), options);
+                let show = |result: SearchResult| match result {
+                    Ok(value) => value.to_string(),
+                    Err(err) => format!("{:?}@{}", err.reason, err.offset),
+                };
+                assert_eq!(show(walked), show(executed), "{}", expression);
+            }
+        }
+    }
+
+    #[test]
+    fn counts_evaluated_nodes() {
+        let runtime = Runtime::new();
+        let mut ctx = Context::new("a[*].b", &runtime);
+        let ast = parse("a[*].b").unwrap();
+        let data = Variable::from_json(r#"{"a": [{"b": 1}, {"b": 2}]}"#).unwrap();
+        interpret(&Rcvar::new(data), &ast, &mut ctx).unwrap();
+        // The projection and its lhs, then the rhs once per element.
+        assert_eq!(4, ctx.evaluated_nodes());
+    }
+
+    #[test]
+    fn search_iter_evaluates_projections_lazily() {
+        let expr = compile("[*].length(@)").unwrap();
+        let data = Variable::from_json(r#"["a", "bb", "ccc", 1, "d"]"#).unwrap();
+        assert!(expr.search(data.clone()).is_err());
+        let first: Vec<_> = expr
+            .search_iter(data.clone())
+            .take(2)
+            .map(|result| result.unwrap().to_string())
+            .collect();
+        assert_eq!(vec!["1", "2"], first);
+        // The iterator stops after the error.
+        let results: Vec<_> = expr.search_iter(data).collect();
+        assert_eq!(4, results.len());
+        assert!(results[3].is_err());
+
+        let expr = compile("[*].a").unwrap();
+        let data = Variable::from_json(r#"[{"a": 1}, {"a": 1}, {"a": 2}, {}]"#).unwrap();
+        let collect = |options: &SearchOptions| {
+            let results: Vec<_> = expr
+                .search_iter_with_options(data.clone(), options)
+                .map(|result| result.unwrap().to_string())
+                .collect();
+            results.join(",")
+        };
+        assert_eq!("1,1,2", collect(&SearchOptions::default()));
+        let options = SearchOptions {
+            dedup_projections: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!("1,2", collect(&options));
+        assert_eq!(0, expr.search_iter(true).count());
+
+        let single: Vec<_> = compile("[a, b]").unwrap().search_iter(true).collect();
+        assert_eq!(1, single.len());
+        assert_eq!("[null,null]", single[0].as_ref().unwrap().to_string());
+    }
+
+    #[test]
+    fn search_batch_preserves_input_order() {
+        let expr = compile("length(a)").unwrap();
+        let inputs: Vec<Rcvar> = (0..100)
+            .map(|i| match i {
+                50 => Rcvar::new(Variable::from_json(r#"{"a": 1}"#).unwrap()),
+                _ => Rcvar::new(Variable::Object(
+                    vec![("a".to_owned(), Rcvar::new(Variable::String("x".repeat(i))))]
+                        .into_iter()
+                        .collect(),
+                )),
+            })
+            .collect();
+        let results = expr.search_batch(&inputs);
+        assert_eq!(100, results.len());
+        for (i, result) in results.iter().enumerate() {
+            if i == 50 {
+                assert!(result.is_err());
+            } else {
+                assert_eq!(Some(i as f64), result.as_ref().unwrap().as_number());
+            }
+        }
+        let options = SearchOptions {
+            strict: true,
+            ..SearchOptions::default()
+        };
+        let inputs = [
+            Rcvar::new(Variable::from_json(r#"{"a": "x"}"#).unwrap()),
+            Rcvar::new(Variable::from_json("{}").unwrap()),
+        ];
+        let results = expr.search_batch_with_options(&inputs, &options);
+        assert_eq!(Some(1.0), results[0].as_ref().unwrap().as_number());
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::MissingField("a".to_owned())),
+            results[1].as_ref().unwrap_err().reason
+        );
+    }
+
+    #[test]
+    fn searches_with_configured_backend() {
+        use crate::backend::{EvalBackend, TreeWalker};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counting(AtomicUsize);
+
+        impl EvalBackend for Counting {
+            fn evaluate(&self, ast: &Ast, data: &Rcvar, ctx: &mut Context<'_>) -> SearchResult {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                TreeWalker.evaluate(ast, data, ctx)
+            }
+        }
+
+        static BACKEND: Counting = Counting(AtomicUsize::new(0));
+        let expr = ExpressionBuilder::new("a")
+            .backend(&BACKEND)
+            .build()
+            .unwrap();
+        let data = Variable::from_json(r#"{"a": true}"#).unwrap();
+        assert_eq!(Rcvar::new(Variable::Bool(true)), expr.search(data).unwrap());
+        assert_eq!(1, BACKEND.0.load(Ordering::SeqCst));
+        // Clones share the backend.
+        expr.clone().search(()).unwrap();
+        assert_eq!(2, BACKEND.0.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn evaluates_integer_division_and_modulo() {
+        let eval = |expr: &str| compile(expr).unwrap().search(()).unwrap().to_string();
+        assert_eq!("3", eval("`7` // `2`"));
+        assert_eq!("-4", eval("`-7` // `2`"));
+        assert_eq!("-4", eval("`7` // `-2`"));
+        assert_eq!("3", eval("`-7` // `-2`"));
+        assert_eq!("3", eval("`7.5` // `2`"));
+        assert_eq!("1", eval("`7` % `2`"));
+        assert_eq!("1", eval("`-7` % `2`"));
+        assert_eq!("-1", eval("`7` % `-2`"));
+        assert_eq!("-1", eval("`-7` % `-2`"));
+        assert_eq!("1.5", eval("`7.5` % `2`"));
+        assert_eq!("1", eval("`10` // `3` % `2`"));
+        assert_eq!("true", eval("`5` % `2` == `1`"));
+    }
+
+    #[test]
+    fn evaluates_arithmetic_operators() {
+        let data = Variable::from_json(r#"{"a": 7, "b": 2, "c": 0.5, "i": [{"n": 1}, {"n": 2}]}"#)
+            .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("9", eval("a + b"));
+        assert_eq!("5", eval("a - b"));
+        assert_eq!("14", eval("a * b"));
+        assert_eq!("3.5", eval("a / b"));
+        assert_eq!("4", eval("`8` / b"));
+        assert_eq!("3.5", eval("a × c"));
+        assert_eq!("14.0", eval("a ÷ c"));
+        assert_eq!("5", eval("a − b"));
+        assert_eq!("7.5", eval("a+c"));
+        assert_eq!("5", eval("a-b"));
+        // Multiplicative operators bind tighter than additive ones, and
+        // operators of the same precedence are left associative.
+        assert_eq!("11", eval("a + b * b"));
+        assert_eq!("18", eval("(a + b) * b"));
+        assert_eq!("3", eval("a - b - b"));
+        assert_eq!("4", eval("a - b // b * b - a % b"));
+        assert_eq!("true", eval("a - b == `5`"));
+        assert_eq!("[-7,7,-2]", eval("[-a, +a, -b]"));
+        assert_eq!("-6", eval("-a + -i[0].n * `-1`"));
+        assert_eq!("[[2],[4]]", eval("let $b = b in i[*].[n * $b]"));
+        assert_eq!("[2,3]", eval("map(&(n + `1`), i)"));
+    }
+
+    #[test]
+    fn unary_operators_require_numbers() {
+        let err = compile("-a").unwrap().search(()).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidOperand {
+                operator: "-".to_owned(),
+                operand: Operand::Unary,
+                expected: functions::ArgumentType::Number,
+                actual: JmespathType::Null,
+            }),
+            err.reason
+        );
+        assert_eq!(0, err.offset);
+    }
+
+    #[test]
+    fn arithmetic_division_by_zero_is_configurable() {
+        assert_eq!(
+            "1",
+            compile("`1` + `0`")
+                .unwrap()
+                .search(())
+                .unwrap()
+                .to_string()
+        );
+        let expr = compile("[`1` // `0`, `1` % `0.0`, `1` / `0`]").unwrap();
+        let err = expr.search(true).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::DivisionByZero),
+            err.reason
+        );
+        assert_eq!(5, err.offset);
+        let options = SearchOptions {
+            division_by_zero: DivisionByZero::Null,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            "[null,null,null]",
+            expr.search_with_options(true, &options)
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[cfg(not(feature = "big-integers"))]
+    #[test]
+    fn arithmetic_overflow_is_configurable() {
+        let expr = compile("`-9223372036854775808` // `-1`").unwrap();
+        let search = |overflow| {
+            let options = SearchOptions {
+                overflow,
+                ..SearchOptions::default()
+            };
+            expr.search_with_options((), &options)
+        };
+        assert_eq!(
+            Some(9.223372036854776e18),
+            search(Overflow::Float).unwrap().as_number()
+        );
+        assert_eq!(
+            "-9223372036854775808",
+            search(Overflow::Wrap).unwrap().to_string()
+        );
+        assert_eq!(
+            "9223372036854775807",
+            search(Overflow::Saturate).unwrap().to_string()
+        );
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::Overflow),
+            search(Overflow::Error).unwrap_err().reason
+        );
+        let options = SearchOptions {
+            overflow: Overflow::Saturate,
+            ..SearchOptions::default()
+        };
+        let saturate = |expression: &str| {
+            compile(expression)
+                .unwrap()
+                .search_with_options((), &options)
+                .unwrap()
+                .to_string()
+        };
+        let max = "`9223372036854775807`";
+        assert_eq!("9223372036854775807", saturate(&format!("{} + `1`", max)));
+        assert_eq!("-9223372036854775808", saturate(&format!("-{} - `2`", max)));
+        assert_eq!("9223372036854775807", saturate(&format!("{} * `2`", max)));
+        assert_eq!("9223372036854775807", saturate("-`-9223372036854775808`"));
+    }
+
+    #[test]
+    fn float_overflow_follows_the_overflow_policy() {
+        let search = |expression: &str, overflow| {
+            let options = SearchOptions {
+                overflow,
+                ..SearchOptions::default()
+            };
+            let expr = compile(expression).unwrap();
+            let result = expr.search_with_options((), &options);
+            let compiled = expr.compile().search_with_options((), &options);
+            assert_eq!(result, compiled, "{}", expression);
+            result
+        };
+        let overflow = Err(ErrorReason::Runtime(RuntimeError::Overflow));
+        for expression in ["`1e308` * `10`", "`1e308` / `0.1`", "`1e308` // `0.1`"] {
+            assert!(search(expression, Overflow::Float).unwrap().is_null());
+            assert_eq!(
+                Some(f64::MAX),
+                search(expression, Overflow::Saturate).unwrap().as_number()
+            );
+            assert_eq!(
+                overflow,
+                search(expression, Overflow::Wrap).map_err(|err| err.reason)
+            );
+            assert_eq!(
+                overflow,
+                search(expression, Overflow::Error).map_err(|err| err.reason)
+            );
+        }
+        assert_eq!(
+            Some(f64::MIN),
+            search("-`1e308` - `1e308`", Overflow::Saturate)
+                .unwrap()
+                .as_number()
+        );
+        assert_eq!(
+            Some(2e307),
+            search("`1e307` + `1e307`", Overflow::Error)
+                .unwrap()
+                .as_number()
+        );
+    }
+
+    #[test]
+    fn limits_evaluation_depth() {
+        let search = |expression: &str, max_depth| {
+            let options = SearchOptions {
+                max_depth,
+                ..SearchOptions::default()
+            };
+            compile(expression)
+                .unwrap()
+                .search_with_options(Variable::from_json(r#"{"a": {"b": 1}}"#).unwrap(), &options)
+        };
+        assert_eq!("1", search("a.b", Some(2)).unwrap().to_string());
+        let err = search("a.b", Some(1)).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::MaxDepthExceeded(1)),
+            err.reason
+        );
+        assert_eq!(0, err.offset);
+        // Nodes evaluated by expression references are nested in the call.
+        let expression = r#"map(&a.b, `[{"a": {"b": 2}}]`)"#;
+        assert!(search(expression, Some(2)).is_err());
+        assert_eq!("[2]", search(expression, Some(3)).unwrap().to_string());
+        assert_eq!("1", search("a.b", None).unwrap().to_string());
+        let nested = format!("{}@{}", "not_null(".repeat(60), ")".repeat(60));
+        let err = search(&nested, Some(50)).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::MaxDepthExceeded(50)),
+            err.reason
+        );
+        assert_eq!(Some(DEFAULT_MAX_DEPTH), SearchOptions::default().max_depth);
+    }
+
+    #[test]
+    fn evaluates_let_expressions() {
+        let data = Variable::from_json(
+            r#"{"min": 2, "items": [{"n": 1}, {"n": 2}, {"n": 3}], "name": "x"}"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[2,3]", eval("let $min = min in items[?n >= $min].n"));
+        assert_eq!("[\"x\",2]", eval("let $a = name, $b = min in [$a, $b]"));
+        // Inner bindings shadow outer ones, which are restored afterwards.
+        assert_eq!(
+            "[2,\"x\"]",
+            eval("let $a = name in [let $a = min in $a, $a]")
+        );
+        // Bindings are evaluated against the current node.
+        assert_eq!(
+            "[[1,2],[2,2],[3,2]]",
+            eval("let $m = min in items[*].[n, $m]")
+        );
+        // Expression references see the bindings of the enclosing scope.
+        assert_eq!(
+            "[false,true,true]",
+            eval("let $m = min in map(&(n >= $m), items)")
+        );
+        // `let` and `in` are only keywords in a let expression.
+        assert_eq!("null", eval("let"));
+        assert!(compile("let $a = min").is_err());
+        assert!(compile("let $a min in $a").is_err());
+    }
+
+    #[test]
+    fn slices_strings() {
+        let data = Variable::from_json(r#"{"s": "héllo wörld", "a": ["ab", "cd"]}"#).unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("\"hél\"", eval("s[:3]"));
+        assert_eq!("\"wörld\"", eval("s[-5:]"));
+        assert_eq!("\"dlröw olléh\"", eval("s[::-1]"));
+        assert_eq!("\"hlowrd\"", eval("s[::2]"));
+        assert_eq!("\"\"", eval("s[20:]"));
+        // The rest of the expression applies to the substring itself.
+        assert_eq!("5", eval("length(s[6:])"));
+        assert_eq!("\"llo\"", eval("s[2:5] | @"));
+        assert_eq!("[\"b\",\"d\"]", eval("a[*][1:]"));
+        assert_eq!("null", eval("s[1:].foo"));
+        let first: Vec<_> = compile("s[:2]")
+            .unwrap()
+            .search_iter(data.clone())
+            .map(|r| r.unwrap().to_string())
+            .collect();
+        assert_eq!(vec!["\"hé\""], first);
+    }
+
+    #[test]
+    fn original_spec_does_not_slice_strings() {
+        let data = Variable::from_json(r#"{"s": "héllo", "a": ["ab", "cd"]}"#).unwrap();
+        let json = serde_json::json!({"s": "héllo", "a": ["ab", "cd"]});
+        let build = |expr: &str| {
+            ExpressionBuilder::new(expr)
+                .spec_version(SpecVersion::Original)
+                .build()
+                .unwrap()
+        };
+        for expr in ["s[1:3]", "s[::-1]", "s[1:].foo"] {
+            let expr = build(expr);
+            assert!(expr.search(data.clone()).unwrap().is_null());
+            assert!(expr
+                .clone()
+                .compile()
+                .search(data.clone())
+                .unwrap()
+                .is_null());
+            assert!(expr.search_json(&json).unwrap().is_null());
+            assert!(expr.search_iter(data.clone()).all(|r| r.unwrap().is_null()));
+        }
+        assert_eq!(
+            "[]",
+            build("a[*][1:]").search(data.clone()).unwrap().to_string()
+        );
+        // Strings can still be sliced when either side of a combination
+        // follows the community specification.
+        let combined = build("s").pipe(compile("[1:3]").unwrap());
+        assert_eq!("\"él\"", combined.search(data).unwrap().to_string());
+    }
+
+    #[test]
+    fn root_references_the_searched_document() {
+        let data = Variable::from_json(
+            r#"{"threshold": 2, "items": [{"price": 1, "name": "a"}, {"price": 3, "name": "b"}]}"#,
+        )
+        .unwrap();
+        let eval = |expr: &str| {
+            compile(expr)
+                .unwrap()
+                .search(data.clone())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[\"b\"]", eval("items[?price > $.threshold].name"));
+        assert_eq!(
+            "[[\"a\",2],[\"b\",2]]",
+            eval("items[*].[name, $.threshold]")
+        );
+        assert_eq!("[true,false]", eval("map(&(price < $.threshold), items)"));
+        assert_eq!("2", eval("items[0] | $.threshold"));
+        assert_eq!("true", eval("$ == @"));
+    }
+
+    #[test]
+    fn undefined_variables_are_runtime_errors() {
+        let expr = compile("let $a = foo, $b = $a in $b").unwrap();
+        let err = expr.search(()).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::UndefinedVariable("a".to_owned())),
+            err.reason
+        );
+        assert_eq!(19, err.offset);
+        let err = compile("[let $a = `1` in $a, $a]")
+            .unwrap()
+            .search(true)
+            .unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::UndefinedVariable("a".to_owned())),
+            err.reason
+        );
+    }
+
+    #[test]
+    fn arithmetic_requires_numbers() {
+        let err = compile("`1` % 'a'").unwrap().search(()).unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidOperand {
+                operator: "%".to_owned(),
+                operand: Operand::Right,
+                expected: functions::ArgumentType::Number,
+                actual: JmespathType::String,
+            }),
+            err.reason
+        );
+        let err = compile("abs(a + `1`)").unwrap().search(()).unwrap_err();
+        assert_eq!(
+            "The left operand of `+` expects type number, given null",
+            err.reason.to_string().trim_start_matches("Runtime error: ")
+        );
+    }
+
     #[test]
     fn test_invalid_number() {
         let _ = compile("6455555524");
@@ -0,0 +1,373 @@
+//! Static analysis of JMESPath expressions.
+//!
+//! Linting walks a parsed AST and reports problems that can be detected
+//! without evaluating the expression, such as calls to functions that are
+//! not registered with a `Runtime` or filters that can never match.
+//!
+//! ```
+//! use jmespath::ExpressionBuilder;
+//! use jmespath::lint::WarningKind;
+//!
+//! let (expr, warnings) = ExpressionBuilder::new("foo[?`false`] | bar(@)")
+//!     .build_checked()
+//!     .unwrap();
+//! assert_eq!("foo[?`false`] | bar(@)", expr.as_str());
+//! assert_eq!(WarningKind::ConstantFalseFilter, warnings[0].kind);
+//! assert_eq!(WarningKind::UnknownFunction("bar".to_owned()), warnings[1].kind);
+//! ```
+
+use std::fmt;
+
+use crate::ast::Ast;
+use crate::variable::Variable;
+use crate::{Rcvar, Runtime, RuntimeError};
+
+/// A problem found while linting an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    /// Approximate absolute position in the linted expression.
+    pub offset: usize,
+    /// The kind of problem that was found.
+    pub kind: WarningKind,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(fmt, "{} (offset {})", self.kind, self.offset)
+    }
+}
+
+/// Kinds of problems reported by the linter.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WarningKind {
+    /// A function is called that is not registered with the runtime.
+    UnknownFunction(String),
+    /// A function is called with a number of arguments its signature rejects.
+    InvalidArity {
+        /// Name of the function being called.
+        name: String,
+        /// Minimum number of arguments accepted by the function.
+        expected: usize,
+        /// Number of arguments provided.
+        actual: usize,
+        /// Whether the function accepts additional variadic arguments.
+        variadic: bool,
+    },
+    /// A filter predicate that always evaluates to a falsey value.
+    ConstantFalseFilter,
+    /// A variable is referenced outside of any let expression that binds it.
+    UndefinedVariable(String),
+    /// A slice has a step of 0, which is an error whenever it is evaluated.
+    ZeroSliceStep,
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            WarningKind::UnknownFunction(ref name) => {
+                write!(fmt, "Call to undefined function {}", name)
+            }
+            WarningKind::InvalidArity {
+                ref name,
+                expected,
+                actual,
+                variadic,
+            } => write!(
+                fmt,
+                "Function {} expects {}{} arguments, found {}",
+                name,
+                if variadic { "at least " } else { "" },
+                expected,
+                actual
+            ),
+            WarningKind::ConstantFalseFilter => {
+                write!(fmt, "Filter predicate never matches any element")
+            }
+            WarningKind::UndefinedVariable(ref name) => {
+                write!(fmt, "Reference to undefined variable ${}", name)
+            }
+            WarningKind::ZeroSliceStep => write!(fmt, "Slice step cannot be 0"),
+        }
+    }
+}
+
+/// Lints an AST against the functions registered with a runtime.
+///
+/// Warnings are returned in the order in which they appear in the
+/// expression.
+pub fn lint(ast: &Ast, runtime: &Runtime) -> Vec<Warning> {
+    let mut warnings = vec![];
+    walk(ast, runtime, &mut vec![], &mut warnings);
+    warnings.sort_by_key(|w| w.offset);
+    warnings
+}
+
+fn walk<'a>(
+    node: &'a Ast,
+    runtime: &Runtime,
+    scope: &mut Vec<&'a str>,
+    warnings: &mut Vec<Warning>,
+) {
+    match *node {
+        Ast::Field { .. }
+        | Ast::Identity { .. }
+        | Ast::Root { .. }
+        | Ast::Index { .. }
+        | Ast::Literal { .. } => {}
+        Ast::Slice { step, offset, .. } => {
+            if step == 0 {
+                warnings.push(Warning {
+                    offset,
+                    kind: WarningKind::ZeroSliceStep,
+                });
+            }
+        }
+        Ast::VariableRef { ref name, offset } => {
+            if !scope.contains(&name.as_str()) {
+                warnings.push(Warning {
+                    offset,
+                    kind: WarningKind::UndefinedVariable(name.to_owned()),
+                });
+            }
+        }
+        Ast::Let {
+            ref bindings,
+            ref body,
+            ..
+        } => {
+            for binding in bindings {
+                walk(&binding.value, runtime, scope, warnings);
+            }
+            let len = scope.len();
+            scope.extend(bindings.iter().map(|b| b.name.as_str()));
+            walk(body, runtime, scope, warnings);
+            scope.truncate(len);
+        }
+        Ast::Condition {
+            ref predicate,
+            ref then,
+            offset,
+        } => {
+            if let Some(value) = constant_value(predicate) {
+                if !value.is_truthy() {
+                    warnings.push(Warning {
+                        offset,
+                        kind: WarningKind::ConstantFalseFilter,
+                    });
+                }
+            }
+            walk(predicate, runtime, scope, warnings);
+            walk(then, runtime, scope, warnings);
+        }
+        Ast::Function {
+            ref name,
+            ref args,
+            offset,
+        } => {
+            match runtime.get_function(name) {
+                None => warnings.push(Warning {
+                    offset,
+                    kind: WarningKind::UnknownFunction(name.to_owned()),
+                }),
+                Some(f) => {
+                    if let Some(signature) = f.signature() {
+                        if let Err(
+                            RuntimeError::NotEnoughArguments { expected, actual }
+                            | RuntimeError::TooManyArguments { expected, actual },
+                        ) = signature.check_arity(args.len())
+                        {
+                            warnings.push(Warning {
+                                offset,
+                                kind: WarningKind::InvalidArity {
+                                    name: name.to_owned(),
+                                    expected,
+                                    actual,
+                                    variadic: signature.variadic.is_some(),
+                                },
+                            });
+                        }
+                    }
+                }
+            }
+            for arg in args {
+                walk(arg, runtime, scope, warnings);
+            }
+        }
+        Ast::Expref { ref ast, .. } => walk(ast, runtime, scope, warnings),
+        Ast::Flatten { ref node, .. }
+        | Ast::Not { ref node, .. }
+        | Ast::Unary { ref node, .. }
+        | Ast::ObjectValues { ref node, .. } => walk(node, runtime, scope, warnings),
+        Ast::MultiList { ref elements, .. } => {
+            for element in elements {
+                walk(element, runtime, scope, warnings);
+            }
+        }
+        Ast::MultiHash { ref elements, .. } => {
+            for kvp in elements {
+                walk(&kvp.value, runtime, scope, warnings);
+            }
+        }
+        Ast::Arithmetic {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Comparison {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Projection {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::And {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Or {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Subexpr {
+            ref lhs, ref rhs, ..
+        } => {
+            walk(lhs, runtime, scope, warnings);
+            walk(rhs, runtime, scope, warnings);
+        }
+    }
+}
+
+/// Folds a node into a value if it does not depend on the current node.
+fn constant_value(node: &Ast) -> Option<Rcvar> {
+    match *node {
+        Ast::Literal { ref value, .. } => Some(value.clone()),
+        Ast::Not { ref node, .. } => {
+            constant_value(node).map(|v| Rcvar::new(Variable::Bool(!v.is_truthy())))
+        }
+        Ast::And {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = constant_value(lhs)?;
+            if !left.is_truthy() {
+                Some(left)
+            } else {
+                constant_value(rhs)
+            }
+        }
+        Ast::Or {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = constant_value(lhs)?;
+            if left.is_truthy() {
+                Some(left)
+            } else {
+                constant_value(rhs)
+            }
+        }
+        Ast::Comparison {
+            ref comparator,
+            ref lhs,
+            ref rhs,
+            ..
+        } => {
+            let left = constant_value(lhs)?;
+            let right = constant_value(rhs)?;
+            Some(Rcvar::new(
+                left.compare(comparator, &right)
+                    .map_or(Variable::Null, Variable::Bool),
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    fn lint_expr(expr: &str) -> Vec<WarningKind> {
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        lint(&parse(expr).unwrap(), &runtime)
+            .into_iter()
+            .map(|w| w.kind)
+            .collect()
+    }
+
+    #[test]
+    fn valid_expressions_have_no_warnings() {
+        assert!(lint_expr("foo[?bar == `1`].baz | length(@)").is_empty());
+        assert!(lint_expr("sort_by(@, &foo)").is_empty());
+        assert!(lint_expr("merge(a, b, c)").is_empty());
+    }
+
+    #[test]
+    fn warns_on_unknown_functions() {
+        assert_eq!(
+            vec![WarningKind::UnknownFunction("nope".to_owned())],
+            lint_expr("foo | map(&nope(@), bar)")
+        );
+    }
+
+    #[test]
+    fn warns_on_invalid_arity() {
+        assert_eq!(
+            vec![WarningKind::InvalidArity {
+                name: "length".to_owned(),
+                expected: 1,
+                actual: 2,
+                variadic: false,
+            }],
+            lint_expr("length(a, b)")
+        );
+        assert_eq!(
+            vec![WarningKind::InvalidArity {
+                name: "merge".to_owned(),
+                expected: 1,
+                actual: 0,
+                variadic: true,
+            }],
+            lint_expr("merge()")
+        );
+    }
+
+    #[test]
+    fn warns_on_constant_false_filters() {
+        assert_eq!(
+            vec![WarningKind::ConstantFalseFilter],
+            lint_expr("foo[?`1` == `2`]")
+        );
+        assert_eq!(
+            vec![WarningKind::ConstantFalseFilter],
+            lint_expr("foo[?!`true`]")
+        );
+        assert!(lint_expr("foo[?`1` == `1`]").is_empty());
+    }
+
+    #[test]
+    fn warns_on_zero_slice_steps() {
+        assert_eq!(vec![WarningKind::ZeroSliceStep], lint_expr("foo[::0]"));
+        assert!(lint_expr("foo[::-1]").is_empty());
+        assert!(lint_expr("foo[?a && `false`]").is_empty());
+    }
+
+    #[test]
+    fn warns_on_undefined_variables() {
+        assert!(lint_expr("let $a = foo, $b = bar in sort_by($a, &$b)").is_empty());
+        assert_eq!(
+            vec![
+                WarningKind::UndefinedVariable("a".to_owned()),
+                WarningKind::UndefinedVariable("c".to_owned())
+            ],
+            lint_expr("let $a = $a in let $b = $a in $c")
+        );
+    }
+
+    #[test]
+    fn displays_warnings() {
+        let warning = Warning {
+            offset: 6,
+            kind: WarningKind::UnknownFunction("foo".to_owned()),
+        };
+        assert_eq!(
+            "Call to undefined function foo (offset 6)",
+            warning.to_string()
+        );
+    }
+}
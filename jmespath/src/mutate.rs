@@ -0,0 +1,347 @@
+//! Setting and deleting values at the locations an expression denotes.
+//!
+//! `set` and `delete` accept the subset of expressions that refer to
+//! concrete locations in a document: fields (`a.b`), indexes (`a[0]`,
+//! `a[-1]`), slices (`a[1:3]`) and list wildcards (`a[*].b`), combined using
+//! subexpressions. Slices and wildcards denote every element they select.
+//!
+//! ```
+//! use jmespath::mutate::{delete, set};
+//! use jmespath::Variable;
+//!
+//! let mut config =
+//!     Variable::from_json(r#"{"servers": [{"port": 80}, {"port": 81}]}"#).unwrap();
+//! let port = Variable::Number(8080.into());
+//! assert_eq!(2, set(&mut config, "servers[*].port", port).unwrap());
+//! let level = Variable::String("debug".to_owned());
+//! assert_eq!(1, set(&mut config, "logging.level", level).unwrap());
+//! assert_eq!(1, delete(&mut config, "servers[-1]").unwrap());
+//! assert_eq!(
+//!     r#"{"logging":{"level":"debug"},"servers":[{"port":8080}]}"#,
+//!     config.to_string()
+//! );
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use crate::ast::Ast;
+use crate::patch::to_pointer;
+use crate::variable::{slice, PathSegment, Variable};
+use crate::{parse, JmespathError, Rcvar};
+
+/// An error that prevented `set` or `delete` from changing a document.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MutateError {
+    /// The expression could not be parsed.
+    Parse(JmespathError),
+    /// The expression contains a node that does not denote a location.
+    NotALocation {
+        /// Approximate absolute position of the node in the expression.
+        offset: usize,
+    },
+    /// A value cannot be set because the parent of the location is not an
+    /// object or an array, or an index is out of bounds.
+    InvalidPath {
+        /// JSON Pointer of the location that could not be set.
+        path: String,
+    },
+}
+
+impl fmt::Display for MutateError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            MutateError::Parse(err) => write!(fmt, "{}", err),
+            MutateError::NotALocation { offset } => write!(
+                fmt,
+                "Expression at offset {} does not denote a location",
+                offset
+            ),
+            MutateError::InvalidPath { path } => write!(fmt, "Invalid path {:?}", path),
+        }
+    }
+}
+
+impl Error for MutateError {}
+
+impl From<JmespathError> for MutateError {
+    fn from(err: JmespathError) -> Self {
+        MutateError::Parse(err)
+    }
+}
+
+/// Sets every location denoted by `expression` to `value`, returning the
+/// number of locations that were set.
+///
+/// Missing fields are added, and missing or null parents of a field are
+/// replaced with objects. Indexes must refer to existing elements. If any
+/// location cannot be set, an error is returned and `target` is unchanged.
+pub fn set(target: &mut Variable, expression: &str, value: Variable) -> Result<usize, MutateError> {
+    let ast = parse(expression)?;
+    let paths = locations(&ast, target, vec![vec![]], true)?;
+    let mut doc = target.clone();
+    let value = Rcvar::new(value);
+    for path in &paths {
+        if path.is_empty() {
+            doc = (*value).clone();
+            continue;
+        }
+        *set_path(&mut doc, path).ok_or_else(|| MutateError::InvalidPath {
+            path: to_pointer(path),
+        })? = value.clone();
+    }
+    *target = doc;
+    Ok(paths.len())
+}
+
+/// Removes every location denoted by `expression`, returning the number of
+/// values that were removed.
+///
+/// Locations that do not exist are ignored. Elements selected by a slice or
+/// wildcard are all removed, and the remaining elements shift down to fill
+/// the gaps.
+pub fn delete(target: &mut Variable, expression: &str) -> Result<usize, MutateError> {
+    let ast = parse(expression)?;
+    let mut paths = locations(&ast, target, vec![vec![]], false)?;
+    if paths.iter().any(Vec::is_empty) {
+        return Err(MutateError::InvalidPath {
+            path: String::new(),
+        });
+    }
+    // Removing later elements first keeps the indexes of earlier ones valid.
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    let mut removed = 0;
+    for path in &paths {
+        let (last, parent) = path.split_last().expect("paths are not empty");
+        let removed_value = match (target.get_mut_path(parent), last) {
+            (Some(Variable::Object(map)), PathSegment::Key(key)) => map.remove(key),
+            (Some(Variable::Array(array)), PathSegment::Index(index)) if *index < array.len() => {
+                Some(array.remove(*index))
+            }
+            _ => None,
+        };
+        removed += removed_value.is_some() as usize;
+    }
+    Ok(removed)
+}
+
+/// Returns the value at `path`, if it exists.
+fn lookup<'a>(doc: &'a Variable, path: &[PathSegment]) -> Option<&'a Variable> {
+    let mut current = doc;
+    for segment in path {
+        current = match (current, segment) {
+            (Variable::Object(map), PathSegment::Key(key)) => map.get(key)?,
+            (Variable::Array(array), PathSegment::Index(index)) => array.get(*index)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Returns a mutable reference to the value at `path`, adding missing
+/// fields and replacing null parents of fields with objects.
+fn set_path<'a>(doc: &'a mut Variable, path: &[PathSegment]) -> Option<&'a mut Rcvar> {
+    let (last, parent) = path.split_last()?;
+    let mut current = doc;
+    for segment in parent {
+        current = Rcvar::make_mut(child(current, segment)?);
+    }
+    child(current, last)
+}
+
+/// Returns the child of a value to be set, adding it if it is a field.
+fn child<'a>(value: &'a mut Variable, segment: &PathSegment) -> Option<&'a mut Rcvar> {
+    if value.is_null() {
+        *value = Variable::Object(Default::default());
+    }
+    match (value, segment) {
+        (Variable::Object(map), PathSegment::Key(key)) => Some(
+            map.entry(key.clone())
+                .or_insert_with(|| Rcvar::new(Variable::Null)),
+        ),
+        (Variable::Array(array), PathSegment::Index(index)) => array.get_mut(*index),
+        _ => None,
+    }
+}
+
+/// Resolves the locations denoted by `node` relative to each of `bases`.
+///
+/// When `strict` is set, indexes, slices and wildcards applied to values
+/// that are not arrays, and indexes that are out of bounds, are errors;
+/// otherwise they denote no location.
+fn locations(
+    node: &Ast,
+    doc: &Variable,
+    bases: Vec<Vec<PathSegment>>,
+    strict: bool,
+) -> Result<Vec<Vec<PathSegment>>, MutateError> {
+    let mut resolved = vec![];
+    match *node {
+        Ast::Identity { .. } => return Ok(bases),
+        Ast::Field { ref name, .. } => {
+            for mut path in bases {
+                path.push(PathSegment::Key(name.clone()));
+                resolved.push(path);
+            }
+        }
+        Ast::Subexpr {
+            ref lhs, ref rhs, ..
+        } => {
+            let bases = locations(lhs, doc, bases, strict)?;
+            return locations(rhs, doc, bases, strict);
+        }
+        Ast::Index { idx, .. } => {
+            for path in bases {
+                let len = array_len(doc, &path, strict)?;
+                let index = if idx < 0 {
+                    len + i64::from(idx)
+                } else {
+                    i64::from(idx)
+                };
+                if index >= 0 && index < len {
+                    resolved.push(extend(&path, index as usize));
+                } else if strict {
+                    return Err(MutateError::InvalidPath {
+                        path: to_pointer(&extend(&path, index.max(0) as usize)),
+                    });
+                }
+            }
+        }
+        Ast::Projection {
+            ref lhs, ref rhs, ..
+        } => {
+            let lhs_paths = locations(lhs, doc, bases, strict)?;
+            // A slice already denotes the elements; `[*]` denotes every
+            // element of the arrays denoted by its left hand side.
+            let elements = if let Ast::Slice { .. } = **lhs {
+                lhs_paths
+            } else {
+                let mut elements = vec![];
+                for path in lhs_paths {
+                    let len = array_len(doc, &path, strict)?;
+                    elements.extend((0..len as usize).map(|i| extend(&path, i)));
+                }
+                elements
+            };
+            return locations(rhs, doc, elements, strict);
+        }
+        Ast::Slice {
+            start,
+            stop,
+            step,
+            offset,
+        } => {
+            if step == 0 {
+                return Err(MutateError::NotALocation { offset });
+            }
+            for path in bases {
+                let indices: Vec<usize> = (0..array_len(doc, &path, strict)? as usize).collect();
+                for index in slice(&indices, start, stop, step) {
+                    resolved.push(extend(&path, index));
+                }
+            }
+        }
+        _ => {
+            return Err(MutateError::NotALocation {
+                offset: node.offset(),
+            })
+        }
+    }
+    Ok(resolved)
+}
+
+/// Returns a copy of `path` extended with an index.
+fn extend(path: &[PathSegment], index: usize) -> Vec<PathSegment> {
+    let mut path = path.to_vec();
+    path.push(PathSegment::Index(index));
+    path
+}
+
+/// Returns the length of the array at `path`, or 0 if there is no array
+/// and `strict` is not set.
+fn array_len(doc: &Variable, path: &[PathSegment], strict: bool) -> Result<i64, MutateError> {
+    match lookup(doc, path) {
+        Some(Variable::Array(array)) => Ok(array.len() as i64),
+        _ if strict => Err(MutateError::InvalidPath {
+            path: to_pointer(path),
+        }),
+        _ => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn doc() -> Variable {
+        Variable::from_json(r#"{"a": {"b": [1, 2, 3, 4]}, "c": [{"d": 1}, {"d": 2}], "e": 5}"#)
+            .unwrap()
+    }
+
+    #[test]
+    fn sets_locations() {
+        let mut value = doc();
+        assert_eq!(
+            1,
+            set(&mut value, "a.b[-1]", Variable::Number(40.into())).unwrap()
+        );
+        assert_eq!(2, set(&mut value, "a.b[0:2]", Variable::Null).unwrap());
+        assert_eq!(2, set(&mut value, "c[*].d", Variable::Bool(true)).unwrap());
+        assert_eq!(
+            1,
+            set(&mut value, "x.y.z", Variable::Number(1.into())).unwrap()
+        );
+        assert_eq!(
+            r#"{"a":{"b":[null,null,3,40]},"c":[{"d":true},{"d":true}],"e":5,"x":{"y":{"z":1}}}"#,
+            value.to_string()
+        );
+        assert_eq!(1, set(&mut value, "@", Variable::Number(1.into())).unwrap());
+        assert_eq!("1", value.to_string());
+    }
+
+    #[test]
+    fn rejects_invalid_sets() {
+        let mut value = doc();
+        assert_eq!(
+            Err(MutateError::InvalidPath {
+                path: "/e/f".to_owned()
+            }),
+            set(&mut value, "e.f", Variable::Null)
+        );
+        assert_eq!(
+            Err(MutateError::InvalidPath {
+                path: "/a/b/4".to_owned()
+            }),
+            set(&mut value, "a.b[4]", Variable::Null)
+        );
+        assert_eq!(
+            Err(MutateError::InvalidPath {
+                path: "/x".to_owned()
+            }),
+            set(&mut value, "x[0]", Variable::Null)
+        );
+        assert_eq!(
+            Err(MutateError::NotALocation { offset: 8 }),
+            set(&mut value, "a.length(@)", Variable::Null)
+        );
+        assert!(matches!(
+            set(&mut value, "a.", Variable::Null),
+            Err(MutateError::Parse(_))
+        ));
+        assert_eq!(doc(), value);
+    }
+
+    #[test]
+    fn deletes_locations() {
+        let mut value = doc();
+        assert_eq!(2, delete(&mut value, "a.b[::2]").unwrap());
+        assert_eq!(1, delete(&mut value, "c[0].d").unwrap());
+        assert_eq!(0, delete(&mut value, "missing.field").unwrap());
+        assert_eq!(0, delete(&mut value, "e[0]").unwrap());
+        assert_eq!(1, delete(&mut value, "e").unwrap());
+        assert_eq!(r#"{"a":{"b":[2,4]},"c":[{},{"d":2}]}"#, value.to_string());
+        assert_eq!(2, delete(&mut value, "c[*]").unwrap());
+        assert_eq!(r#"{"a":{"b":[2,4]},"c":[]}"#, value.to_string());
+        assert!(delete(&mut value, "@").is_err());
+    }
+}
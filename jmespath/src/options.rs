@@ -0,0 +1,266 @@
+//! Options that control how expressions are evaluated.
+
+use serde_json::Number;
+
+/// Options used when searching data with an `Expression`.
+///
+/// Options are applied per search, so the same compiled expression can be
+/// evaluated with different semantics.
+///
+/// ```
+/// use jmespath::{compile, NullPropagation, SearchOptions};
+///
+/// let expr = compile("foo.bar").unwrap();
+/// let options = SearchOptions {
+///     null_propagation: NullPropagation::Error,
+///     ..SearchOptions::default()
+/// };
+/// assert!(expr.search(()).unwrap().is_null());
+/// assert!(expr.search_with_options((), &options).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchOptions {
+    /// How a null produced by an intermediate step of a subexpression is handled.
+    pub null_propagation: NullPropagation,
+    /// How `to_string()` formats number arguments.
+    pub number_format: NumberFormat,
+    /// How the `//` and `%` operators handle a divisor of zero.
+    pub division_by_zero: DivisionByZero,
+    /// Whether projections omit results equal to an earlier result.
+    ///
+    /// When true, the first occurrence of each result is kept, so the order
+    /// of the projection is preserved. Results are compared using JMESPath
+    /// equality (the `==` comparator). Defaults to false (spec behavior).
+    pub dedup_projections: bool,
+    /// How integer results of `sum()`, `avg()`, and arithmetic operators
+    /// that overflow are handled.
+    pub overflow: Overflow,
+    /// Tolerance used when comparing two numbers with a comparator.
+    ///
+    /// When set, numbers within `epsilon` of each other are equal, and a
+    /// number is only less than another if it is smaller by more than
+    /// `epsilon`. Defaults to None, which compares numbers exactly.
+    pub epsilon: Option<f64>,
+    /// Whether list operations treat objects as arrays of their values.
+    ///
+    /// When true, projections (`[*]` and filters), flattens, slices, and
+    /// indexes applied to an object operate over its values, ordered by
+    /// key, instead of returning null. Defaults to false (spec behavior).
+    pub project_objects: bool,
+    /// Maximum number of nested AST nodes that may be evaluated at once.
+    ///
+    /// The interpreter recurses once per nested node, including nodes
+    /// evaluated by expression references, so this bounds the stack used by
+    /// a search. Exceeding it returns `RuntimeError::MaxDepthExceeded`.
+    /// Defaults to `Some(DEFAULT_MAX_DEPTH)`; `None` removes the limit.
+    pub max_depth: Option<usize>,
+    /// Whether fields and elements that do not exist are errors.
+    ///
+    /// When true, getting a field that an object does not have returns
+    /// `RuntimeError::MissingField`, an index past the end of an array
+    /// returns `RuntimeError::IndexOutOfBounds`, and getting a field of a
+    /// value that is not an object, or an index or slice of a value that is
+    /// not an array, returns `RuntimeError::InvalidAccess`. Fields whose
+    /// value is null are still found. Defaults to false (spec behavior).
+    pub strict: bool,
+    /// Whether field names are compared ignoring case.
+    ///
+    /// When true, a field that an object does not have is looked up using
+    /// the first key, in key order, that is equal to the field name when
+    /// both are lowercased. Keys that match exactly are always preferred.
+    /// Defaults to false (spec behavior).
+    pub case_insensitive_fields: bool,
+    /// Thresholds above which a search is logged as slow. Searches are not
+    /// logged when `None` (the default).
+    #[cfg(feature = "log")]
+    pub slow_search: Option<SlowSearchThreshold>,
+}
+
+/// Default value of `SearchOptions::max_depth`.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            null_propagation: NullPropagation::default(),
+            number_format: NumberFormat::default(),
+            division_by_zero: DivisionByZero::default(),
+            dedup_projections: false,
+            overflow: Overflow::default(),
+            epsilon: None,
+            project_objects: false,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            strict: false,
+            case_insensitive_fields: false,
+            #[cfg(feature = "log")]
+            slow_search: None,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// Creates the default search options, which follow the JMESPath specification.
+    pub fn new() -> SearchOptions {
+        Default::default()
+    }
+}
+
+/// Limits used to decide whether a search is logged as slow.
+///
+/// A search is logged using `log::warn!` when it exceeds any of the limits
+/// that are set.
+///
+/// ```
+/// use std::time::Duration;
+/// use jmespath::{SearchOptions, SlowSearchThreshold};
+///
+/// let options = SearchOptions {
+///     slow_search: Some(SlowSearchThreshold {
+///         duration: Some(Duration::from_millis(10)),
+///         nodes: Some(100_000),
+///     }),
+///     ..SearchOptions::default()
+/// };
+/// ```
+#[cfg(feature = "log")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SlowSearchThreshold {
+    /// Maximum elapsed time of a search.
+    pub duration: Option<std::time::Duration>,
+    /// Maximum number of AST nodes evaluated by a search, as reported by
+    /// `Context::evaluated_nodes`.
+    pub nodes: Option<usize>,
+}
+
+#[cfg(feature = "log")]
+impl SlowSearchThreshold {
+    /// Returns true if a search with the given statistics exceeds a limit.
+    pub fn is_exceeded(&self, duration: std::time::Duration, nodes: usize) -> bool {
+        self.duration.is_some_and(|max| duration > max) || self.nodes.is_some_and(|max| nodes > max)
+    }
+}
+
+/// Controls what happens when the left hand side of a subexpression is null.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NullPropagation {
+    /// The null is passed to the right hand side, which yields null (spec behavior).
+    #[default]
+    Propagate,
+    /// A `RuntimeError::NullValue` error is returned.
+    Error,
+}
+
+/// Controls what happens when an arithmetic operator divides by zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DivisionByZero {
+    /// A `RuntimeError::DivisionByZero` error is returned.
+    #[default]
+    Error,
+    /// The operation evaluates to null.
+    Null,
+}
+
+/// Controls what happens when an integer result overflows.
+///
+/// Integers are `i64`s, or `i128`s when the `big-integers` feature is
+/// enabled. Policies other than `Overflow::Float` also apply to floating
+/// point results of arithmetic operators and `sum()` that overflow to
+/// infinity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// The result is computed using floating point numbers, which may lose
+    /// precision. Floating point results that overflow are handled as they
+    /// are without a policy.
+    #[default]
+    Float,
+    /// Integer results wrap around at the bounds of an integer.
+    /// Floating point results that overflow return an error.
+    Wrap,
+    /// Each integer operation saturates at the bounds of an integer, and
+    /// floating point results saturate at `f64::MAX` or `f64::MIN`.
+    Saturate,
+    /// A `RuntimeError::Overflow` error is returned.
+    Error,
+}
+
+/// Controls how `to_string()` converts numbers into strings.
+///
+/// The policy applies to number arguments. Numbers nested inside arrays
+/// and objects are always serialized using `NumberFormat::Shortest`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// Integers are written without a fractional part and floats use the
+    /// shortest representation that round-trips to the same value
+    /// (e.g., `1`, `1.5`, `0.1`, `1e+300`).
+    #[default]
+    Shortest,
+    /// Numbers are written with exactly the given number of decimal places,
+    /// rounding half away from zero (e.g., `Fixed(2)` formats `1` as `1.00`).
+    Fixed(usize),
+}
+
+impl NumberFormat {
+    /// Formats a number according to the policy.
+    pub fn format(&self, number: &Number) -> String {
+        match *self {
+            NumberFormat::Shortest => number.to_string(),
+            NumberFormat::Fixed(decimals) => match number.as_f64() {
+                Some(f) => {
+                    let factor = 10f64.powi(decimals as i32);
+                    let rounded = (f * factor).round() / factor;
+                    if rounded.is_finite() {
+                        format!("{:.*}", decimals, rounded)
+                    } else {
+                        format!("{:.*}", decimals, f)
+                    }
+                }
+                None => number.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn detects_exceeded_slow_search_thresholds() {
+        use std::time::Duration;
+        let threshold = SlowSearchThreshold {
+            duration: Some(Duration::from_millis(5)),
+            nodes: None,
+        };
+        assert!(!threshold.is_exceeded(Duration::from_millis(5), 1_000_000));
+        assert!(threshold.is_exceeded(Duration::from_millis(6), 0));
+        let threshold = SlowSearchThreshold {
+            duration: None,
+            nodes: Some(10),
+        };
+        assert!(!threshold.is_exceeded(Duration::from_secs(60), 10));
+        assert!(threshold.is_exceeded(Duration::from_secs(0), 11));
+        assert!(!SlowSearchThreshold::default().is_exceeded(Duration::from_secs(60), 1000));
+    }
+
+    #[test]
+    fn formats_shortest_numbers() {
+        let format = NumberFormat::Shortest;
+        assert_eq!("1", format.format(&Number::from(1)));
+        assert_eq!("1.5", format.format(&Number::from_f64(1.5).unwrap()));
+        assert_eq!("0.1", format.format(&Number::from_f64(0.1).unwrap()));
+    }
+
+    #[test]
+    fn formats_fixed_numbers() {
+        assert_eq!("1.00", NumberFormat::Fixed(2).format(&Number::from(1)));
+        assert_eq!(
+            "2.68",
+            NumberFormat::Fixed(2).format(&Number::from_f64(2.675).unwrap())
+        );
+        assert_eq!(
+            "-2",
+            NumberFormat::Fixed(0).format(&Number::from_f64(-1.5).unwrap())
+        );
+    }
+}
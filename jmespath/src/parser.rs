@@ -6,22 +6,86 @@
 
 use std::collections::VecDeque;
 
-use crate::ast::{Ast, Comparator, KeyValuePair};
+use crate::ast::{ArithmeticOperator, Ast, Binding, Comparator, KeyValuePair, UnaryOperator};
 use crate::lexer::{tokenize, Token, TokenTuple};
-use crate::{ErrorReason, JmespathError};
+use crate::{ErrorReason, JmespathError, ParseLimit};
 
 /// Result of parsing an expression.
 pub type ParseResult = Result<Ast, JmespathError>;
 
-/// Parses a JMESPath expression into an AST.
+/// Default value of `ParserOptions::max_depth`.
+pub const DEFAULT_MAX_PARSE_DEPTH: usize = 128;
+
+/// Limits applied while parsing an expression.
+///
+/// The parser recurses once per nested expression, so untrusted
+/// expressions should be parsed with a depth limit to avoid overflowing
+/// the stack. Exceeding a limit returns an `ErrorReason::ParseLimit` error.
+///
+/// ```
+/// use jmespath::{parse_with_options, ErrorReason, ParseLimit, ParserOptions};
+///
+/// let options = ParserOptions {
+///     max_tokens: Some(3),
+///     ..ParserOptions::default()
+/// };
+/// assert!(parse_with_options("a.b", &options).is_ok());
+/// let err = parse_with_options("a.b.c", &options).unwrap_err();
+/// assert_eq!(ErrorReason::ParseLimit(ParseLimit::Tokens(3)), err.reason);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Maximum number of nested expressions, such as parenthesized
+    /// expressions, brackets, and function arguments. Left associative
+    /// chains like `a.b.c` do not nest, so only `max_tokens` limits their
+    /// length. Defaults to `Some(DEFAULT_MAX_PARSE_DEPTH)`; `None` removes the limit.
+    pub max_depth: Option<usize>,
+    /// Maximum number of tokens in the expression. Defaults to `None`, as
+    /// the number of tokens is bounded by the length of the expression.
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            max_depth: Some(DEFAULT_MAX_PARSE_DEPTH),
+            max_tokens: None,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Creates the default parser options.
+    pub fn new() -> ParserOptions {
+        Default::default()
+    }
+}
+
+/// Parses a JMESPath expression into an AST using the default `ParserOptions`.
 pub fn parse(expr: &str) -> ParseResult {
+    parse_with_options(expr, &ParserOptions::default())
+}
+
+/// Parses a JMESPath expression into an AST, enforcing the given limits.
+pub fn parse_with_options(expr: &str, options: &ParserOptions) -> ParseResult {
     let tokens = tokenize(expr)?;
-    Parser::new(tokens, expr).parse()
+    if let Some(max_tokens) = options.max_tokens {
+        // The queue always ends with an EOF token.
+        if tokens.len() - 1 > max_tokens {
+            let offset = tokens[max_tokens].0;
+            let reason = ErrorReason::ParseLimit(ParseLimit::Tokens(max_tokens));
+            return Err(JmespathError::new(expr, offset, reason));
+        }
+    }
+    Parser::new(tokens, expr, options.max_depth).parse()
 }
 
 /// The maximum binding power for a token that can stop a projection.
 const PROJECTION_STOP: usize = 10;
 
+/// The binding power used to parse the right hand side of a wildcard.
+const WILDCARD_RBP: usize = 20;
+
 struct Parser<'a> {
     /// Parsed tokens
     token_queue: VecDeque<TokenTuple>,
@@ -31,15 +95,21 @@ struct Parser<'a> {
     expr: &'a str,
     /// The current character offset in the expression
     offset: usize,
+    /// Number of nested expressions currently being parsed
+    depth: usize,
+    /// Maximum allowed nesting depth
+    max_depth: Option<usize>,
 }
 
 impl<'a> Parser<'a> {
-    fn new(tokens: VecDeque<TokenTuple>, expr: &'a str) -> Parser<'a> {
+    fn new(tokens: VecDeque<TokenTuple>, expr: &'a str, max_depth: Option<usize>) -> Parser<'a> {
         Parser {
             token_queue: tokens,
             eof_token: Token::Eof,
             offset: 0,
             expr,
+            depth: 0,
+            max_depth,
         }
     }
 
@@ -73,7 +143,7 @@ impl<'a> Parser<'a> {
     #[inline]
     fn peek(&self, lookahead: usize) -> &Token {
         match self.token_queue.get(lookahead) {
-            Some(&(_, ref t)) => t,
+            Some((_, t)) => t,
             None => &self.eof_token,
         }
     }
@@ -84,7 +154,7 @@ impl<'a> Parser<'a> {
         let mut buff = error_msg.to_string();
         buff.push_str(&format!(" -- found {:?}", current_token));
         if is_peek {
-            if let Some(&(p, _)) = self.token_queue.get(0) {
+            if let Some(&(p, _)) = self.token_queue.front() {
                 actual_pos = p;
             }
         }
@@ -93,17 +163,49 @@ impl<'a> Parser<'a> {
 
     /// Main parse function of the Pratt parser that parses while RBP < LBP
     fn expr(&mut self, rbp: usize) -> ParseResult {
-        let mut left = self.nud();
+        let depth = self.depth;
+        let result = self.nested_expr(rbp);
+        self.depth = depth;
+        result
+    }
+
+    /// Parses an expression, counting it towards the depth limit.
+    fn nested_expr(&mut self, rbp: usize) -> ParseResult {
+        self.enter()?;
+        let mut left = self.nud()?;
         while rbp < self.peek(0).lbp() {
-            left = self.led(Box::new(left?));
+            left = self.led(Box::new(left))?;
+        }
+        Ok(left)
+    }
+
+    /// Increments the depth, returning an error if it exceeds the limit.
+    fn enter(&mut self) -> Result<(), JmespathError> {
+        self.depth += 1;
+        match self.max_depth {
+            Some(max_depth) if self.depth > max_depth => {
+                let offset = match self.token_queue.front() {
+                    Some(&(pos, _)) => pos,
+                    None => self.offset,
+                };
+                let reason = ErrorReason::ParseLimit(ParseLimit::Depth(max_depth));
+                Err(JmespathError::new(self.expr, offset, reason))
+            }
+            _ => Ok(()),
         }
-        left
     }
 
     fn nud(&mut self) -> ParseResult {
         let (offset, token) = self.advance_with_pos();
         match token {
             Token::At => Ok(Ast::Identity { offset }),
+            Token::Root => Ok(Ast::Root { offset }),
+            Token::Identifier(ref value)
+                if value == "let" && matches!(self.peek(0), Token::Variable(_)) =>
+            {
+                self.parse_let(offset)
+            }
+            Token::Variable(name) => Ok(Ast::VariableRef { name, offset }),
             Token::Identifier(value) => Ok(Ast::Field {
                 name: value,
                 offset,
@@ -154,6 +256,8 @@ impl<'a> Parser<'a> {
                     offset,
                 })
             }
+            Token::Plus => self.parse_unary(UnaryOperator::Plus, offset),
+            Token::Minus => self.parse_unary(UnaryOperator::Minus, offset),
             t @ Token::Not => Ok(Ast::Not {
                 node: Box::new(self.expr(t.lbp())?),
                 offset,
@@ -179,7 +283,6 @@ impl<'a> Parser<'a> {
                     self.advance();
                     self.parse_wildcard_values(left)
                 } else {
-                    let offset = offset;
                     let rhs = self.parse_dot(t.lbp())?;
                     Ok(Ast::Subexpr {
                         offset,
@@ -205,7 +308,6 @@ impl<'a> Parser<'a> {
                 }
             }
             t @ Token::Or => {
-                let offset = offset;
                 let rhs = self.expr(t.lbp())?;
                 Ok(Ast::Or {
                     offset,
@@ -214,7 +316,6 @@ impl<'a> Parser<'a> {
                 })
             }
             t @ Token::And => {
-                let offset = offset;
                 let rhs = self.expr(t.lbp())?;
                 Ok(Ast::And {
                     offset,
@@ -223,7 +324,6 @@ impl<'a> Parser<'a> {
                 })
             }
             t @ Token::Pipe => {
-                let offset = offset;
                 let rhs = self.expr(t.lbp())?;
                 Ok(Ast::Subexpr {
                     offset,
@@ -247,6 +347,17 @@ impl<'a> Parser<'a> {
             Token::Gte => self.parse_comparator(Comparator::GreaterThanEqual, left),
             Token::Lt => self.parse_comparator(Comparator::LessThan, left),
             Token::Lte => self.parse_comparator(Comparator::LessThanEqual, left),
+            t @ Token::Plus => self.parse_arithmetic(ArithmeticOperator::Add, left, t.lbp()),
+            t @ Token::Minus => self.parse_arithmetic(ArithmeticOperator::Subtract, left, t.lbp()),
+            // A star that follows an expression is a multiplication.
+            t @ (Token::Star | Token::Multiply) => {
+                self.parse_arithmetic(ArithmeticOperator::Multiply, left, t.lbp())
+            }
+            t @ Token::Divide => self.parse_arithmetic(ArithmeticOperator::Divide, left, t.lbp()),
+            t @ Token::IntegerDivide => {
+                self.parse_arithmetic(ArithmeticOperator::IntegerDivide, left, t.lbp())
+            }
+            t @ Token::Modulo => self.parse_arithmetic(ArithmeticOperator::Modulo, left, t.lbp()),
             ref t => Err(self.err(t, "Unexpected led token", false)),
         }
     }
@@ -268,6 +379,36 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the bindings and body of a let expression:
+    /// `let $a = expr, $b = expr in body`.
+    fn parse_let(&mut self, offset: usize) -> ParseResult {
+        let mut bindings = vec![];
+        loop {
+            let name = match self.advance() {
+                Token::Variable(name) => name,
+                ref t => return Err(self.err(t, "Expected a variable to bind", false)),
+            };
+            match self.advance() {
+                Token::Assign => {}
+                ref t => return Err(self.err(t, "Expected '=' after the variable", false)),
+            }
+            bindings.push(Binding {
+                name,
+                value: self.expr(0)?,
+            });
+            match self.advance() {
+                Token::Comma => continue,
+                Token::Identifier(ref value) if value == "in" => break,
+                ref t => return Err(self.err(t, "Expected ',' or 'in'", false)),
+            }
+        }
+        Ok(Ast::Let {
+            offset,
+            bindings,
+            body: Box::new(self.expr(0)?),
+        })
+    }
+
     /// Parses a filter token into a Projection that filters the right
     /// side of the projection using a Condition node. If the Condition node
     /// returns a truthy value, then the value is yielded by the projection.
@@ -315,6 +456,34 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses an arithmetic operator token into an Arithmetic node (e.g., foo % `2`)
+    fn parse_arithmetic(
+        &mut self,
+        operator: ArithmeticOperator,
+        lhs: Box<Ast>,
+        lbp: usize,
+    ) -> ParseResult {
+        let offset = self.offset;
+        let rhs = Box::new(self.expr(lbp)?);
+        Ok(Ast::Arithmetic {
+            offset,
+            operator,
+            lhs,
+            rhs,
+        })
+    }
+
+    /// Parses the operand of a unary operator (e.g., -foo.bar), which stops
+    /// at the next binary arithmetic operator.
+    fn parse_unary(&mut self, operator: UnaryOperator, offset: usize) -> ParseResult {
+        let node = Box::new(self.expr(Token::Multiply.lbp())?);
+        Ok(Ast::Unary {
+            offset,
+            operator,
+            node,
+        })
+    }
+
     /// Parses the right hand side of a dot expression.
     fn parse_dot(&mut self, lbp: usize) -> ParseResult {
         if match self.peek(0) {
@@ -359,7 +528,7 @@ impl<'a> Parser<'a> {
     fn parse_wildcard_index(&mut self, lhs: Box<Ast>) -> ParseResult {
         match self.advance() {
             Token::Rbracket => {
-                let rhs = Box::new(self.projection_rhs(Token::Star.lbp())?);
+                let rhs = Box::new(self.projection_rhs(WILDCARD_RBP)?);
                 Ok(Ast::Projection {
                     offset: self.offset,
                     lhs,
@@ -372,7 +541,7 @@ impl<'a> Parser<'a> {
 
     /// Creates a projection for "*"
     fn parse_wildcard_values(&mut self, lhs: Box<Ast>) -> ParseResult {
-        let rhs = Box::new(self.projection_rhs(Token::Star.lbp())?);
+        let rhs = Box::new(self.projection_rhs(WILDCARD_RBP)?);
         Ok(Ast::Projection {
             offset: self.offset,
             lhs: Box::new(Ast::ObjectValues {
@@ -435,7 +604,7 @@ impl<'a> Parser<'a> {
                     stop: parts[1],
                     step: parts[2].unwrap_or(1),
                 }),
-                rhs: Box::new(self.projection_rhs(Token::Star.lbp())?),
+                rhs: Box::new(self.projection_rhs(WILDCARD_RBP)?),
             })
         }
     }
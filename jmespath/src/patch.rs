@@ -0,0 +1,438 @@
+//! Structural diffs between variables as JSON Patch (RFC 6902) operations.
+//!
+//! `diff` computes the operations that turn one variable into another, and
+//! `apply_patch` applies operations to a variable. Paths are JSON Pointers
+//! (RFC 6901), and operations serialize to their RFC 6902 form.
+//!
+//! ```
+//! use jmespath::patch::{apply_patch, diff, PatchOp};
+//! use jmespath::Variable;
+//!
+//! let desired = Variable::from_json(r#"{"replicas": 3, "tags": ["a"]}"#).unwrap();
+//! let actual = Variable::from_json(r#"{"replicas": 2, "tags": ["a", "b"]}"#).unwrap();
+//! let ops = diff(&actual, &desired);
+//! assert_eq!(
+//!     r#"[{"op":"replace","path":"/replicas","value":3},{"op":"remove","path":"/tags/1"}]"#,
+//!     serde_json::to_string(&ops).unwrap()
+//! );
+//! assert_eq!(desired, apply_patch(&actual, &ops).unwrap());
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::variable::{PathSegment, Variable};
+use crate::Rcvar;
+
+/// An operation of a JSON Patch.
+///
+/// `path` and `from` are JSON Pointers, where `""` refers to the whole
+/// document.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Adds a member to an object, inserts an element into an array (`-`
+    /// appends), or replaces the whole document.
+    Add { path: String, value: Variable },
+    /// Removes a member of an object or an element of an array.
+    Remove { path: String },
+    /// Replaces an existing value.
+    Replace { path: String, value: Variable },
+    /// Removes the value at `from` and adds it at `path`.
+    Move { from: String, path: String },
+    /// Adds a copy of the value at `from` at `path`.
+    Copy { from: String, path: String },
+    /// Checks that the value at `path` is equal to `value`.
+    Test { path: String, value: Variable },
+}
+
+/// Error encountered while applying a JSON Patch.
+///
+/// `op` is the position of the operation that failed in the patch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchError {
+    /// A path is not a valid JSON Pointer, or does not refer to a location
+    /// that the operation can use.
+    InvalidPath {
+        /// Position of the failed operation.
+        op: usize,
+        /// Path that could not be used.
+        path: String,
+    },
+    /// A `test` operation found a different value.
+    TestFailed {
+        /// Position of the failed operation.
+        op: usize,
+        /// Path of the tested value.
+        path: String,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            PatchError::InvalidPath { op, path } => {
+                write!(fmt, "Operation {}: invalid path {:?}", op, path)
+            }
+            PatchError::TestFailed { op, path } => {
+                write!(fmt, "Operation {}: test failed at {:?}", op, path)
+            }
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// Returns the operations that turn `from` into `to`.
+///
+/// Members of objects are compared recursively, as are the elements of
+/// arrays at the same index; extra elements are added or removed at the end
+/// of arrays. Values of different types are replaced. Applying the result
+/// to `from` using `apply_patch` yields a value equal to `to`.
+pub fn diff(from: &Variable, to: &Variable) -> Vec<PatchOp> {
+    let mut ops = vec![];
+    diff_at(&mut String::new(), from, to, &mut ops);
+    ops
+}
+
+fn diff_at(path: &mut String, from: &Variable, to: &Variable, ops: &mut Vec<PatchOp>) {
+    if from == to && from.get_type() == to.get_type() {
+        return;
+    }
+    let len = path.len();
+    match (from, to) {
+        (Variable::Object(a), Variable::Object(b)) => {
+            for key in a.keys().filter(|key| !b.contains_key(*key)) {
+                push_token(path, key);
+                ops.push(PatchOp::Remove { path: path.clone() });
+                path.truncate(len);
+            }
+            for (key, value) in b {
+                push_token(path, key);
+                match a.get(key) {
+                    Some(existing) => diff_at(path, existing, value, ops),
+                    None => ops.push(PatchOp::Add {
+                        path: path.clone(),
+                        value: (**value).clone(),
+                    }),
+                }
+                path.truncate(len);
+            }
+        }
+        (Variable::Array(a), Variable::Array(b)) => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                push_token(path, &i.to_string());
+                diff_at(path, x, y, ops);
+                path.truncate(len);
+            }
+            // Remove from the end so that earlier indexes stay valid.
+            for i in (b.len()..a.len()).rev() {
+                push_token(path, &i.to_string());
+                ops.push(PatchOp::Remove { path: path.clone() });
+                path.truncate(len);
+            }
+            for (i, value) in b.iter().enumerate().skip(a.len()) {
+                push_token(path, &i.to_string());
+                ops.push(PatchOp::Add {
+                    path: path.clone(),
+                    value: (**value).clone(),
+                });
+                path.truncate(len);
+            }
+        }
+        _ => ops.push(PatchOp::Replace {
+            path: path.clone(),
+            value: to.clone(),
+        }),
+    }
+}
+
+/// Appends a reference token to a JSON Pointer, escaping `~` and `/`.
+fn push_token(path: &mut String, token: &str) {
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+/// Converts a path of segments into a JSON Pointer.
+pub(crate) fn to_pointer(path: &[PathSegment]) -> String {
+    let mut pointer = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => push_token(&mut pointer, key),
+            PathSegment::Index(index) => push_token(&mut pointer, &index.to_string()),
+        }
+    }
+    pointer
+}
+
+/// Splits a JSON Pointer into its unescaped reference tokens.
+fn parse_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(vec![]);
+    }
+    pointer.strip_prefix('/').map(|rest| {
+        rest.split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect()
+    })
+}
+
+/// Parses an array index token, which has no sign or leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    let digits = token.bytes().all(|b| b.is_ascii_digit());
+    if digits && (token == "0" || !token.starts_with('0')) {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Returns a mutable reference to the value at `tokens`, cloning shared
+/// values along the way.
+fn resolve_mut<'a>(doc: &'a mut Variable, tokens: &[String]) -> Option<&'a mut Variable> {
+    let mut current = doc;
+    for token in tokens {
+        let child = match current {
+            Variable::Object(map) => map.get_mut(token)?,
+            Variable::Array(array) => array.get_mut(parse_index(token)?)?,
+            _ => return None,
+        };
+        current = Rcvar::make_mut(child);
+    }
+    Some(current)
+}
+
+fn get(doc: &mut Variable, tokens: &[String]) -> Option<Variable> {
+    resolve_mut(doc, tokens).map(|value| value.clone())
+}
+
+fn add(doc: &mut Variable, tokens: &[String], value: Variable) -> Option<()> {
+    let (last, parent) = match tokens.split_last() {
+        Some(split) => split,
+        None => {
+            *doc = value;
+            return Some(());
+        }
+    };
+    match resolve_mut(doc, parent)? {
+        Variable::Object(map) => {
+            map.insert(last.clone(), Rcvar::new(value));
+        }
+        Variable::Array(array) if last == "-" => array.push(Rcvar::new(value)),
+        Variable::Array(array) => {
+            let index = parse_index(last).filter(|i| *i <= array.len())?;
+            array.insert(index, Rcvar::new(value));
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+fn remove(doc: &mut Variable, tokens: &[String]) -> Option<Variable> {
+    let (last, parent) = tokens.split_last()?;
+    let removed = match resolve_mut(doc, parent)? {
+        Variable::Object(map) => map.remove(last)?,
+        Variable::Array(array) => {
+            let index = parse_index(last).filter(|i| *i < array.len())?;
+            array.remove(index)
+        }
+        _ => return None,
+    };
+    Some((*removed).clone())
+}
+
+/// Applies the operations of a JSON Patch to `target`, returning the patched
+/// value.
+///
+/// Operations are applied in order, and the patch is applied as a whole:
+/// if any operation fails, the error identifies it and `target` is left
+/// unchanged.
+pub fn apply_patch(target: &Variable, ops: &[PatchOp]) -> Result<Variable, PatchError> {
+    let mut doc = target.clone();
+    for (op, operation) in ops.iter().enumerate() {
+        let invalid = |path: &str| PatchError::InvalidPath {
+            op,
+            path: path.to_owned(),
+        };
+        let tokens = |path: &str| parse_pointer(path).ok_or_else(|| invalid(path));
+        match operation {
+            PatchOp::Add { path, value } => {
+                add(&mut doc, &tokens(path)?, value.clone()).ok_or_else(|| invalid(path))?
+            }
+            PatchOp::Remove { path } => {
+                remove(&mut doc, &tokens(path)?).ok_or_else(|| invalid(path))?;
+            }
+            PatchOp::Replace { path, value } => {
+                let target = resolve_mut(&mut doc, &tokens(path)?).ok_or_else(|| invalid(path))?;
+                *target = value.clone();
+            }
+            PatchOp::Move { from, path } => {
+                let (source, destination) = (tokens(from)?, tokens(path)?);
+                // A value cannot be moved into one of its own descendants.
+                if destination.len() > source.len() && destination.starts_with(&source) {
+                    return Err(invalid(path));
+                }
+                let value = remove(&mut doc, &source).ok_or_else(|| invalid(from))?;
+                add(&mut doc, &destination, value).ok_or_else(|| invalid(path))?;
+            }
+            PatchOp::Copy { from, path } => {
+                let value = get(&mut doc, &tokens(from)?).ok_or_else(|| invalid(from))?;
+                add(&mut doc, &tokens(path)?, value).ok_or_else(|| invalid(path))?;
+            }
+            PatchOp::Test { path, value } => {
+                let actual = get(&mut doc, &tokens(path)?).ok_or_else(|| invalid(path))?;
+                if actual != *value || actual.get_type() != value.get_type() {
+                    return Err(PatchError::TestFailed {
+                        op,
+                        path: path.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(doc)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn json(s: &str) -> Variable {
+        Variable::from_json(s).unwrap()
+    }
+
+    fn ops(s: &str) -> Vec<PatchOp> {
+        serde_json::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn applies_rfc_6902_examples() {
+        let cases = [
+            (
+                r#"{"foo": "bar"}"#,
+                r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#,
+                r#"{"baz":"qux","foo":"bar"}"#,
+            ),
+            (
+                r#"{"foo": ["bar", "baz"]}"#,
+                r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#,
+                r#"{"foo":["bar","qux","baz"]}"#,
+            ),
+            (
+                r#"{"baz": "qux", "foo": "bar"}"#,
+                r#"[{"op": "remove", "path": "/baz"}]"#,
+                r#"{"foo":"bar"}"#,
+            ),
+            (
+                r#"{"foo": ["bar", "qux", "baz"]}"#,
+                r#"[{"op": "remove", "path": "/foo/1"}]"#,
+                r#"{"foo":["bar","baz"]}"#,
+            ),
+            (
+                r#"{"baz": "qux", "foo": "bar"}"#,
+                r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#,
+                r#"{"baz":"boo","foo":"bar"}"#,
+            ),
+            (
+                r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+                r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+                r#"{"foo":{"bar":"baz"},"qux":{"corge":"grault","thud":"fred"}}"#,
+            ),
+            (
+                r#"{"foo": ["all", "grass", "cows", "eat"]}"#,
+                r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#,
+                r#"{"foo":["all","cows","eat","grass"]}"#,
+            ),
+            (
+                r#"{"foo": ["bar"]}"#,
+                r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#,
+                r#"{"foo":["bar",["abc","def"]]}"#,
+            ),
+            (
+                r#"{"a/b": {"m~n": 1}}"#,
+                r#"[{"op": "copy", "from": "/a~1b/m~0n", "path": "/c"},
+                    {"op": "test", "path": "/c", "value": 1}]"#,
+                r#"{"a/b":{"m~n":1},"c":1}"#,
+            ),
+            (
+                r#"{"foo": 1}"#,
+                r#"[{"op": "replace", "path": "", "value": [1]}]"#,
+                "[1]",
+            ),
+        ];
+        for (target, patch, expected) in cases.iter() {
+            let result = apply_patch(&json(target), &ops(patch)).unwrap();
+            assert_eq!(*expected, result.to_string(), "{}", patch);
+        }
+    }
+
+    #[test]
+    fn reports_the_failed_operation() {
+        let target = json(r#"{"foo": ["bar"], "baz": "qux"}"#);
+        let fails = |patch: &str| apply_patch(&target, &ops(patch)).unwrap_err();
+        assert_eq!(
+            PatchError::TestFailed {
+                op: 1,
+                path: "/baz".to_owned()
+            },
+            fails(
+                r#"[{"op": "remove", "path": "/foo/0"},
+                    {"op": "test", "path": "/baz", "value": "bar"}]"#
+            )
+        );
+        let invalid = |op: usize, path: &str| PatchError::InvalidPath {
+            op,
+            path: path.to_owned(),
+        };
+        assert_eq!(
+            invalid(0, "/missing"),
+            fails(r#"[{"op": "remove", "path": "/missing"}]"#)
+        );
+        assert_eq!(
+            invalid(0, "/foo/2"),
+            fails(r#"[{"op": "add", "path": "/foo/2", "value": 1}]"#)
+        );
+        assert_eq!(
+            invalid(0, "/foo/01"),
+            fails(r#"[{"op": "replace", "path": "/foo/01", "value": 1}]"#)
+        );
+        assert_eq!(
+            invalid(0, "foo"),
+            fails(r#"[{"op": "remove", "path": "foo"}]"#)
+        );
+        assert_eq!(
+            invalid(0, "/foo/0/x"),
+            fails(r#"[{"op": "move", "from": "/foo", "path": "/foo/0/x"}]"#)
+        );
+        assert_eq!(json(r#"{"foo": ["bar"], "baz": "qux"}"#), target);
+    }
+
+    #[test]
+    fn diffs_round_trip() {
+        let cases = [
+            ("1", "1"),
+            ("1", "\"1\""),
+            (r#"{"a": 1, "b": 2}"#, r#"{"b": 3, "c": 4}"#),
+            (r#"{"a": {"b": [1, 2, 3]}}"#, r#"{"a": {"b": [1, 4]}}"#),
+            (r#"[1, 2]"#, r#"[1, 2, {"x/y~": null}, [3]]"#),
+            (r#"{"a/b": {"c~d": 1}}"#, r#"{"a/b": {"c~d": 2}}"#),
+            (r#"{"a": []}"#, r#"{"a": {}}"#),
+        ];
+        for (from, to) in cases.iter() {
+            let (from, to) = (json(from), json(to));
+            let ops = diff(&from, &to);
+            assert_eq!(to, apply_patch(&from, &ops).unwrap(), "{:?}", ops);
+        }
+        assert!(diff(&json(r#"{"a": [1]}"#), &json(r#"{"a": [1]}"#)).is_empty());
+        assert_eq!(
+            r#"[{"op":"remove","path":"/a"},{"op":"replace","path":"/b/c~1d","value":2}]"#,
+            serde_json::to_string(&diff(
+                &json(r#"{"a": 1, "b": {"c/d": 1}}"#),
+                &json(r#"{"b": {"c/d": 2}}"#)
+            ))
+            .unwrap()
+        );
+    }
+}
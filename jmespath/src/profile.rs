@@ -0,0 +1,256 @@
+//! Per-node profiling of searches.
+//!
+//! `Expression::search_with_profile` records the number of times each AST
+//! node is evaluated and the wall time spent evaluating it, including the
+//! time spent evaluating its children. The resulting `Profile` is a tree
+//! that mirrors the AST, and its `Display` implementation shows each node
+//! with the share of the total time it accounts for.
+//!
+//! ```
+//! use jmespath::Variable;
+//!
+//! let expr = jmespath::compile("sort_by(people, &age)[*].name").unwrap();
+//! let data = Variable::from_json(
+//!     r#"{"people": [{"name": "a", "age": 2}, {"name": "b", "age": 1}]}"#,
+//! )
+//! .unwrap();
+//! let (result, profile) = expr.search_with_profile(data, &Default::default());
+//! assert_eq!(r#"["b","a"]"#, result.unwrap().to_string());
+//! assert_eq!(1, profile.root().calls());
+//! println!("{}", profile);
+//! ```
+//!
+//! Profiling is only performed by the default `TreeWalker` backend. Time
+//! is measured using `std::time::Instant`, so the timing overhead is
+//! included in the reported durations of nodes with many children.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::ast::Ast;
+use crate::interpreter::SearchResult;
+use crate::Context;
+
+/// Statistics recorded for an AST node during a profiled search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileNode {
+    label: String,
+    offset: usize,
+    calls: usize,
+    elapsed: Duration,
+    children: Vec<ProfileNode>,
+}
+
+impl ProfileNode {
+    /// Returns a short description of the node (e.g., `Field foo`).
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the offset of the node in the expression string.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the number of times the node was evaluated.
+    ///
+    /// Nodes inside projections are evaluated once per element, and nodes
+    /// that are short-circuited are not evaluated at all.
+    pub fn calls(&self) -> usize {
+        self.calls
+    }
+
+    /// Returns the total time spent evaluating the node and its children.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the profiles of the child nodes, in AST order.
+    pub fn children(&self) -> &[ProfileNode] {
+        &self.children
+    }
+}
+
+/// Report of a profiled search, annotating each AST node with statistics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    root: ProfileNode,
+}
+
+impl Profile {
+    /// Returns the profile of the root node of the expression.
+    pub fn root(&self) -> &ProfileNode {
+        &self.root
+    }
+
+    /// Returns the total time spent evaluating the expression.
+    pub fn elapsed(&self) -> Duration {
+        self.root.elapsed
+    }
+}
+
+impl fmt::Display for Profile {
+    /// Shows one line per node, indented by depth, with the number of
+    /// evaluations, the elapsed time, and its percentage of the total.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_node(
+            f: &mut fmt::Formatter<'_>,
+            node: &ProfileNode,
+            total: Duration,
+            depth: usize,
+        ) -> fmt::Result {
+            let percent = if total.as_nanos() == 0 {
+                0.0
+            } else {
+                node.elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(
+                f,
+                "{:indent$}{} @{}: {} calls, {:?} ({:.1}%)",
+                "",
+                node.label,
+                node.offset,
+                node.calls,
+                node.elapsed,
+                percent,
+                indent = depth * 2
+            )?;
+            for child in &node.children {
+                write_node(f, child, total, depth + 1)?;
+            }
+            Ok(())
+        }
+        write_node(f, &self.root, self.root.elapsed, 0)
+    }
+}
+
+/// Records statistics while a profiled search is evaluated.
+///
+/// Nodes are identified by their address. Expression references are
+/// cloned when evaluated, so the nodes of each clone are registered as
+/// aliases of the nodes they were cloned from.
+#[derive(Default)]
+pub(crate) struct Profiler {
+    stats: HashMap<usize, (usize, Duration)>,
+    aliases: HashMap<usize, usize>,
+}
+
+fn address(node: &Ast) -> usize {
+    node as *const Ast as usize
+}
+
+impl Profiler {
+    /// Records that `clone` and its descendants were cloned from `original`.
+    pub(crate) fn alias(&mut self, clone: &Ast, original: &Ast) {
+        let original_address = self.resolve(original);
+        self.aliases.insert(address(clone), original_address);
+        for (clone, original) in clone.children().into_iter().zip(original.children()) {
+            self.alias(clone, original);
+        }
+    }
+
+    fn resolve(&self, node: &Ast) -> usize {
+        let address = address(node);
+        self.aliases.get(&address).cloned().unwrap_or(address)
+    }
+
+    /// Builds the report for the AST that was searched.
+    pub(crate) fn report(&self, ast: &Ast) -> Profile {
+        Profile {
+            root: self.report_node(ast),
+        }
+    }
+
+    fn report_node(&self, node: &Ast) -> ProfileNode {
+        let (calls, elapsed) = self.stats.get(&address(node)).cloned().unwrap_or_default();
+        ProfileNode {
+            label: label(node),
+            offset: node.offset(),
+            calls,
+            elapsed,
+            children: node
+                .children()
+                .into_iter()
+                .map(|child| self.report_node(child))
+                .collect(),
+        }
+    }
+}
+
+/// Evaluates `node` using `evaluate`, recording its statistics if the
+/// context is profiling.
+pub(crate) fn record<F>(node: &Ast, ctx: &mut Context<'_>, evaluate: F) -> SearchResult
+where
+    F: FnOnce(&mut Context<'_>) -> SearchResult,
+{
+    if ctx.profiler.is_none() {
+        return evaluate(ctx);
+    }
+    let start = Instant::now();
+    let result = evaluate(ctx);
+    let elapsed = start.elapsed();
+    if let Some(profiler) = ctx.profiler.as_mut() {
+        let key = profiler.resolve(node);
+        let entry = profiler.stats.entry(key).or_default();
+        entry.0 += 1;
+        entry.1 += elapsed;
+    }
+    result
+}
+
+fn label(node: &Ast) -> String {
+    match *node {
+        Ast::Arithmetic { ref operator, .. } => format!("Arithmetic {:?}", operator),
+        Ast::Comparison { ref comparator, .. } => format!("Comparison {:?}", comparator),
+        Ast::Condition { .. } => "Condition".to_owned(),
+        Ast::Identity { .. } => "Identity".to_owned(),
+        Ast::Root { .. } => "Root".to_owned(),
+        Ast::Expref { .. } => "Expref".to_owned(),
+        Ast::Flatten { .. } => "Flatten".to_owned(),
+        Ast::Function { ref name, .. } => format!("Function {}", name),
+        Ast::Let { .. } => "Let".to_owned(),
+        Ast::VariableRef { ref name, .. } => format!("VariableRef {}", name),
+        Ast::Field { ref name, .. } => format!("Field {}", name),
+        Ast::Index { idx, .. } => format!("Index {}", idx),
+        Ast::Literal { ref value, .. } => format!("Literal {}", value),
+        Ast::MultiList { .. } => "MultiList".to_owned(),
+        Ast::MultiHash { .. } => "MultiHash".to_owned(),
+        Ast::Not { .. } => "Not".to_owned(),
+        Ast::Unary { ref operator, .. } => format!("Unary {:?}", operator),
+        Ast::Projection { .. } => "Projection".to_owned(),
+        Ast::ObjectValues { .. } => "ObjectValues".to_owned(),
+        Ast::And { .. } => "And".to_owned(),
+        Ast::Or { .. } => "Or".to_owned(),
+        Ast::Slice { .. } => "Slice".to_owned(),
+        Ast::Subexpr { .. } => "Subexpr".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compile;
+    use crate::Variable;
+
+    #[test]
+    fn counts_node_evaluations() {
+        let expr = compile("a[*].b || map(&c, a)").unwrap();
+        let data = Variable::from_json(r#"{"a": [{"c": 1}, {"c": 2}, {"c": 3}]}"#).unwrap();
+        let (result, profile) = expr.search_with_profile(data, &Default::default());
+        assert_eq!("[1,2,3]", result.unwrap().to_string());
+        let root = profile.root();
+        assert_eq!("Or", root.label());
+        assert_eq!(1, root.calls());
+        let (projection, map) = (&root.children()[0], &root.children()[1]);
+        assert_eq!(3, projection.children()[1].calls());
+        assert_eq!("Function map", map.label());
+        // The nodes of expression references are reported where they
+        // appear in the expression.
+        let expref = &map.children()[0];
+        assert_eq!(1, expref.calls());
+        assert_eq!("Field c", expref.children()[0].label());
+        assert_eq!(3, expref.children()[0].calls());
+        assert!(root.elapsed() >= map.elapsed());
+        assert_eq!(8, profile.to_string().lines().count());
+    }
+}
@@ -0,0 +1,500 @@
+//! Locations in the searched document that produced a result.
+//!
+//! `Expression::search_with_paths` evaluates an expression while tracking
+//! where each value came from, and returns the result together with a
+//! `PathMatch` for every value in it that was taken from the document.
+//! Values are reported in the order they appear in the result: a value
+//! selected directly (such as `a.b`) is a single match, while the elements
+//! of projections, slices, flattens and multi-selects are reported
+//! individually.
+//!
+//! ```
+//! use jmespath::Variable;
+//!
+//! let expr = jmespath::compile("people[?age > `20`].name").unwrap();
+//! let data = Variable::from_json(
+//!     r#"{"people": [{"name": "a", "age": 30}, {"name": "b", "age": 10},
+//!                    {"name": "c", "age": 25}]}"#,
+//! )
+//! .unwrap();
+//! let (result, matches) = expr.search_with_paths(data).unwrap();
+//! assert_eq!(r#"["a","c"]"#, result.to_string());
+//! let pointers: Vec<String> = matches.iter().map(|m| m.pointer()).collect();
+//! assert_eq!(vec!["/people/0/name", "/people/2/name"], pointers);
+//! ```
+//!
+//! Values computed by the expression, such as the results of functions,
+//! comparisons and arithmetic, and literals, have no location and are not
+//! reported. Only the tree walking interpreter tracks locations.
+//! `Expression::search_with_paths_and_options` honors the given
+//! `SearchOptions`: fields found ignoring case are reported under the key
+//! the document uses, and the values of objects projected when
+//! `project_objects` is enabled are reported under their keys.
+
+use std::collections::BTreeMap;
+
+use crate::ast::Ast;
+use crate::interpreter::{check_not_null, enter, interpret};
+use crate::patch::to_pointer;
+use crate::variable::{find_ignore_case, slice, PathSegment, Variable};
+use crate::{Context, JmespathError, Rcvar};
+
+/// A value of a search result along with its location in the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathMatch {
+    /// Path from the root of the document to the value.
+    pub path: Vec<PathSegment>,
+    /// The value found at `path`.
+    pub value: Rcvar,
+}
+
+impl PathMatch {
+    /// Returns the path as a JSON Pointer (RFC 6901).
+    ///
+    /// ```
+    /// use jmespath::provenance::PathMatch;
+    /// use jmespath::{Rcvar, Variable};
+    ///
+    /// let m = PathMatch {
+    ///     path: vec!["a/b".into(), 2.into()],
+    ///     value: Rcvar::new(Variable::Null),
+    /// };
+    /// assert_eq!("/a~1b/2", m.pointer());
+    /// ```
+    pub fn pointer(&self) -> String {
+        to_pointer(&self.path)
+    }
+}
+
+/// Where an evaluated value came from.
+#[derive(Clone, Debug)]
+enum Origin {
+    /// The value is found in the document at the path.
+    Path(Vec<PathSegment>),
+    /// The value is an array built by the expression, with the origin of
+    /// each of its elements.
+    Elements(Vec<Origin>),
+    /// The value is an object built by the expression, with the origin of
+    /// each of its members.
+    Members(BTreeMap<String, Origin>),
+    /// The value was computed by the expression.
+    None,
+}
+
+impl Origin {
+    /// Returns the origin of a child of a value with this origin.
+    fn child(&self, segment: PathSegment) -> Origin {
+        match (self, &segment) {
+            (Origin::Path(path), _) => {
+                let mut path = path.clone();
+                path.push(segment);
+                Origin::Path(path)
+            }
+            (Origin::Elements(elements), PathSegment::Index(index)) => {
+                elements.get(*index).cloned().unwrap_or(Origin::None)
+            }
+            (Origin::Members(members), PathSegment::Key(key)) => {
+                members.get(key).cloned().unwrap_or(Origin::None)
+            }
+            _ => Origin::None,
+        }
+    }
+}
+
+/// A value along with its origin.
+#[derive(Clone)]
+struct Traced {
+    value: Rcvar,
+    origin: Origin,
+}
+
+impl Traced {
+    fn computed(value: Rcvar) -> Traced {
+        Traced {
+            value,
+            origin: Origin::None,
+        }
+    }
+
+    fn null() -> Traced {
+        Traced::computed(Rcvar::new(Variable::Null))
+    }
+
+    /// Returns the element of an array at `index`, with its origin.
+    fn element(&self, index: usize) -> Traced {
+        match self.value.as_array().and_then(|array| array.get(index)) {
+            Some(value) => Traced {
+                value: value.clone(),
+                origin: self.origin.child(PathSegment::Index(index)),
+            },
+            None => Traced::null(),
+        }
+    }
+
+    /// Returns the elements of an array with their origins, or None if the
+    /// value is not an array.
+    fn elements(&self) -> Option<Vec<Traced>> {
+        let len = self.value.as_array()?.len();
+        Some((0..len).map(|index| self.element(index)).collect())
+    }
+
+    /// Returns the values of an object with their origins, or None if the
+    /// value is not an object.
+    fn members(&self) -> Option<Vec<Traced>> {
+        let map = self.value.as_object()?;
+        Some(
+            map.iter()
+                .map(|(key, value)| Traced {
+                    value: value.clone(),
+                    origin: self.origin.child(PathSegment::Key(key.clone())),
+                })
+                .collect(),
+        )
+    }
+
+    /// Converts an object into an array of its values when
+    /// `SearchOptions::project_objects` is enabled, keeping their origins.
+    fn projected(&self, ctx: &Context<'_>) -> Traced {
+        match self.members() {
+            Some(members) if ctx.options.project_objects => collect(members),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Builds an array from traced values, remembering their origins.
+fn collect(values: Vec<Traced>) -> Traced {
+    let (values, origins) = values.into_iter().map(|v| (v.value, v.origin)).unzip();
+    Traced {
+        value: Rcvar::new(Variable::Array(values)),
+        origin: Origin::Elements(origins),
+    }
+}
+
+/// Searches `data`, returning the result and the locations of its values.
+pub(crate) fn search(
+    node: &Ast,
+    data: Rcvar,
+    ctx: &mut Context<'_>,
+) -> Result<(Rcvar, Vec<PathMatch>), JmespathError> {
+    let root = Traced {
+        value: data,
+        origin: Origin::Path(vec![]),
+    };
+    let result = trace(&root, node, ctx)?;
+    let mut matches = vec![];
+    push_matches(&result.value, &result.origin, &mut matches);
+    Ok((result.value, matches))
+}
+
+/// Appends the values of a result that have a location to `matches`.
+fn push_matches(value: &Rcvar, origin: &Origin, matches: &mut Vec<PathMatch>) {
+    match origin {
+        Origin::Path(path) => matches.push(PathMatch {
+            path: path.clone(),
+            value: value.clone(),
+        }),
+        Origin::Elements(origins) => {
+            if let Some(array) = value.as_array() {
+                for (value, origin) in array.iter().zip(origins) {
+                    push_matches(value, origin, matches);
+                }
+            }
+        }
+        Origin::Members(origins) => {
+            if let Some(map) = value.as_object() {
+                for (key, value) in map {
+                    if let Some(origin) = origins.get(key) {
+                        push_matches(value, origin, matches);
+                    }
+                }
+            }
+        }
+        Origin::None => {}
+    }
+}
+
+/// Interprets a node, tracking the origin of the result.
+fn trace(data: &Traced, node: &Ast, ctx: &mut Context<'_>) -> Result<Traced, JmespathError> {
+    enter(node, ctx)?;
+    let result = trace_node(data, node, ctx);
+    ctx.depth -= 1;
+    result
+}
+
+fn trace_node(data: &Traced, node: &Ast, ctx: &mut Context<'_>) -> Result<Traced, JmespathError> {
+    match *node {
+        Ast::Identity { .. } => Ok(data.clone()),
+        Ast::Root { .. } => Ok(Traced {
+            value: ctx.root.clone(),
+            origin: Origin::Path(vec![]),
+        }),
+        Ast::Field { ref name, .. } => {
+            let ignore_case = ctx.options.case_insensitive_fields;
+            let found = data
+                .value
+                .as_object()
+                .and_then(|map| match map.get_key_value(name) {
+                    None if ignore_case => find_ignore_case(map.keys().map(|key| (key, key)), name)
+                        .map(|key| (key, &map[key])),
+                    entry => entry,
+                });
+            match found {
+                Some((key, value)) => Ok(Traced {
+                    value: value.clone(),
+                    origin: data.origin.child(PathSegment::Key(key.clone())),
+                }),
+                None => interpret(&data.value, node, ctx).map(Traced::computed),
+            }
+        }
+        Ast::Index { idx, .. } => {
+            let data = &data.projected(ctx);
+            let len = data.value.as_array().map_or(0, Vec::len) as i64;
+            let idx = if idx < 0 {
+                len + i64::from(idx)
+            } else {
+                i64::from(idx)
+            };
+            if idx >= 0 && idx < len {
+                Ok(data.element(idx as usize))
+            } else {
+                interpret(&data.value, node, ctx).map(Traced::computed)
+            }
+        }
+        Ast::Slice {
+            start, stop, step, ..
+        } => match data.projected(ctx).elements() {
+            Some(elements) if step != 0 => {
+                let indices: Vec<usize> = (0..elements.len()).collect();
+                let selected = slice(&indices, start, stop, step);
+                Ok(collect(
+                    selected.into_iter().map(|i| elements[i].clone()).collect(),
+                ))
+            }
+            _ => interpret(&data.value, node, ctx).map(Traced::computed),
+        },
+        Ast::Subexpr {
+            ref lhs,
+            ref rhs,
+            offset,
+        } => {
+            let left = trace(data, lhs, ctx)?;
+            check_not_null(&left.value, offset, ctx)?;
+            trace(&left, rhs, ctx)
+        }
+        Ast::Projection {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = trace(data, lhs, ctx)?;
+            if left.value.is_string() && matches!(**lhs, Ast::Slice { .. }) {
+                return trace(&left, rhs, ctx);
+            }
+            match left.projected(ctx).elements() {
+                None => Ok(Traced::null()),
+                Some(elements) => {
+                    let mut collected: Vec<Traced> = vec![];
+                    for element in elements {
+                        let current = trace(&element, rhs, ctx)?;
+                        let duplicate = ctx.options.dedup_projections
+                            && collected.iter().any(|c| c.value == current.value);
+                        if !current.value.is_null() && !duplicate {
+                            collected.push(current);
+                        }
+                    }
+                    Ok(collect(collected))
+                }
+            }
+        }
+        Ast::ObjectValues { ref node, .. } => {
+            let object = trace(data, node, ctx)?;
+            match object.members() {
+                Some(members) => Ok(collect(members)),
+                None => Ok(Traced::null()),
+            }
+        }
+        Ast::Flatten { ref node, .. } => {
+            let array = trace(data, node, ctx)?;
+            match array.projected(ctx).elements() {
+                None => Ok(Traced::null()),
+                Some(elements) => {
+                    let mut collected = vec![];
+                    for element in elements {
+                        match element.elements() {
+                            Some(nested) => collected.extend(nested),
+                            None => collected.push(element),
+                        }
+                    }
+                    Ok(collect(collected))
+                }
+            }
+        }
+        Ast::Condition {
+            ref predicate,
+            ref then,
+            ..
+        } => {
+            if interpret(&data.value, predicate, ctx)?.is_truthy() {
+                trace(data, then, ctx)
+            } else {
+                Ok(Traced::null())
+            }
+        }
+        Ast::Or {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = trace(data, lhs, ctx)?;
+            if left.value.is_truthy() {
+                Ok(left)
+            } else {
+                trace(data, rhs, ctx)
+            }
+        }
+        Ast::And {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = trace(data, lhs, ctx)?;
+            if !left.value.is_truthy() {
+                Ok(left)
+            } else {
+                trace(data, rhs, ctx)
+            }
+        }
+        Ast::MultiList { ref elements, .. } if !data.value.is_null() => {
+            let mut collected = vec![];
+            for node in elements {
+                collected.push(trace(data, node, ctx)?);
+            }
+            Ok(collect(collected))
+        }
+        Ast::MultiHash { ref elements, .. } if !data.value.is_null() => {
+            let mut values = BTreeMap::new();
+            let mut origins = BTreeMap::new();
+            for kvp in elements {
+                let value = trace(data, &kvp.value, ctx)?;
+                values.insert(kvp.key.clone(), value.value);
+                origins.insert(kvp.key.clone(), value.origin);
+            }
+            Ok(Traced {
+                value: Rcvar::new(Variable::Object(values)),
+                origin: Origin::Members(origins),
+            })
+        }
+        Ast::Let {
+            ref bindings,
+            ref body,
+            ..
+        } => {
+            let mut values = Vec::with_capacity(bindings.len());
+            for binding in bindings {
+                let value = interpret(&data.value, &binding.value, ctx)?;
+                values.push((binding.name.clone(), value));
+            }
+            let len = ctx.scope.len();
+            ctx.scope.extend(values);
+            let result = trace(data, body, ctx);
+            ctx.scope.truncate(len);
+            result
+        }
+        // Every other node computes a new value.
+        _ => interpret(&data.value, node, ctx).map(Traced::computed),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{compile, ErrorReason, RuntimeError, SearchOptions, Variable};
+
+    fn pointers(expr: &str, json: &str) -> Vec<String> {
+        pointers_with_options(expr, json, &SearchOptions::default())
+    }
+
+    fn pointers_with_options(expr: &str, json: &str, options: &SearchOptions) -> Vec<String> {
+        let data = Variable::from_json(json).unwrap();
+        let expr = compile(expr).unwrap();
+        let (result, matches) = expr
+            .search_with_paths_and_options(data.clone(), options)
+            .unwrap();
+        assert_eq!(expr.search_with_options(data, options).unwrap(), result);
+        matches.iter().map(|m| m.pointer()).collect()
+    }
+
+    #[test]
+    fn reports_selected_locations() {
+        let json = r#"{"a": {"b": [10, 20, 30], "c/d": {"e~f": 1}}, "g": null}"#;
+        assert_eq!(vec![""], pointers("@", json));
+        assert_eq!(vec!["/a/b/2"], pointers("a.b[-1]", json));
+        assert_eq!(vec!["/a/c~1d/e~0f"], pointers(r#"a."c/d"."e~f""#, json));
+        assert_eq!(vec!["/g"], pointers("g", json));
+        assert!(pointers("a.missing", json).is_empty());
+        assert!(pointers("length(a.b)", json).is_empty());
+        assert_eq!(vec!["/a/b"], pointers("a.b | $.a.b", json));
+    }
+
+    #[test]
+    fn reports_elements_of_built_values() {
+        let json = r#"{"a": [[1, 2], [3], 4], "b": {"x": 1, "y": 2}}"#;
+        assert_eq!(
+            vec!["/a/0/0", "/a/0/1", "/a/1/0", "/a/2"],
+            pointers("a[]", json)
+        );
+        assert_eq!(vec!["/a/2", "/a/0"], pointers("a[::-2]", json));
+        assert_eq!(vec!["/b/x", "/b/y"], pointers("b.*", json));
+        assert_eq!(vec!["/a/0/0", "/a/1/0"], pointers("a[*][0]", json));
+        assert_eq!(vec!["/b/y", "/a/2"], pointers("[b.y, `5`, a[2]]", json));
+        assert_eq!(vec!["/b/x"], pointers("{k: b.x, l: `1`}", json));
+        assert_eq!(vec!["/b/y"], pointers("{k: b.x, l: b.y}.l", json));
+        assert_eq!(vec!["/a/1"], pointers("[b.x, a[1]][1]", json));
+        assert_eq!(vec!["/b/x"], pointers("missing || b.x", json));
+        assert_eq!(
+            vec!["/a/2"],
+            pointers("let $n = `4` in a[?@ == $n] | [0]", json)
+        );
+    }
+
+    #[test]
+    fn honors_search_options() {
+        let json = r#"{"a": {"Name": 1, "name": 2, "NAME": 3}, "b": {"x": [1], "y": [1, 2]}}"#;
+        let options = SearchOptions {
+            case_insensitive_fields: true,
+            project_objects: true,
+            dedup_projections: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(
+            vec!["/a/name"],
+            pointers_with_options("a.name", json, &options)
+        );
+        assert_eq!(
+            vec!["/a/NAME"],
+            pointers_with_options("a.nAmE", json, &options)
+        );
+        assert_eq!(
+            vec!["/b/x/0", "/b/y/1"],
+            pointers_with_options("b[*][-1]", json, &options)
+        );
+        assert_eq!(vec!["/b/y"], pointers_with_options("b[1]", json, &options));
+        // The flattened values are projected, so the duplicate 1 is dropped.
+        assert_eq!(
+            vec!["/b/x/0", "/b/y/1"],
+            pointers_with_options("b[]", json, &options)
+        );
+        assert!(pointers_with_options("a.nAmE", json, &SearchOptions::default()).is_empty());
+
+        let strict = SearchOptions {
+            strict: true,
+            ..SearchOptions::default()
+        };
+        let data = Variable::from_json(json).unwrap();
+        let err = compile("b.x[3]")
+            .unwrap()
+            .search_with_paths_and_options(data, &strict)
+            .unwrap_err();
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::IndexOutOfBounds {
+                index: 3,
+                length: 1,
+            }),
+            err.reason
+        );
+    }
+}
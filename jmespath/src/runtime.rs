@@ -1,4 +1,6 @@
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
 
 use crate::functions::*;
 use crate::parse;
@@ -34,7 +36,8 @@ impl Runtime {
     /// grammar: <https://jmespath.org/specification.html>
     #[inline]
     pub fn compile<'a>(&'a self, expression: &str) -> Result<Expression<'a>, JmespathError> {
-        parse(expression).map(|ast| Expression::new(expression, ast, self))
+        crate::trace::compile(expression, || parse(expression))
+            .map(|ast| Expression::new(expression, ast, self))
     }
 
     /// Adds a new function to the runtime.
@@ -60,29 +63,169 @@ impl Runtime {
     pub fn register_builtin_functions(&mut self) {
         self.register_function("abs", Box::new(AbsFn::new()));
         self.register_function("avg", Box::new(AvgFn::new()));
+        #[cfg(feature = "bytes")]
+        self.register_function("base64_decode", Box::new(Base64DecodeFn::new()));
+        #[cfg(feature = "bytes")]
+        self.register_function("base64_encode", Box::new(Base64EncodeFn::new()));
+        self.register_function("camel_case", Box::new(CamelCaseFn::new()));
+        self.register_function("capitalize", Box::new(CapitalizeFn::new()));
+        self.register_function("cartesian_product", Box::new(CartesianProductFn::new()));
         self.register_function("ceil", Box::new(CeilFn::new()));
+        self.register_function("compact", Box::new(CompactFn::new()));
+        self.register_function("concat", Box::new(ConcatFn::new()));
         self.register_function("contains", Box::new(ContainsFn::new()));
+        self.register_function("deep_merge", Box::new(DeepMergeFn::new()));
+        self.register_function("default", Box::new(DefaultFn::new()));
         self.register_function("ends_with", Box::new(EndsWithFn::new()));
+        #[cfg(feature = "eval")]
+        self.register_function("eval", Box::new(EvalFn::new()));
+        self.register_function("flatten_keys", Box::new(FlattenKeysFn::new()));
         self.register_function("floor", Box::new(FloorFn::new()));
+        self.register_function("from_entries", Box::new(FromEntriesFn::new()));
+        self.register_function("if", Box::new(IfFn::new()));
         self.register_function("join", Box::new(JoinFn::new()));
         self.register_function("keys", Box::new(KeysFn::new()));
         self.register_function("length", Box::new(LengthFn::new()));
+        self.register_function("lower", Box::new(LowerFn::new()));
         self.register_function("map", Box::new(MapFn::new()));
+        self.register_function("map_keys", Box::new(MapKeysFn::new()));
+        self.register_function("match_keys", Box::new(MatchKeysFn::new()));
         self.register_function("min", Box::new(MinFn::new()));
         self.register_function("max", Box::new(MaxFn::new()));
         self.register_function("max_by", Box::new(MaxByFn::new()));
         self.register_function("min_by", Box::new(MinByFn::new()));
         self.register_function("merge", Box::new(MergeFn::new()));
+        self.register_function("merge_patch", Box::new(MergePatchFn::new()));
         self.register_function("not_null", Box::new(NotNullFn::new()));
+        self.register_function("omit_empty", Box::new(OmitEmptyFn::new()));
+        self.register_function("paths", Box::new(PathsFn::new()));
+        self.register_function("product", Box::new(ProductFn::new()));
+        self.register_function("range", Box::new(RangeFn::new()));
+        self.register_function("recursive_search", Box::new(RecursiveSearchFn::new()));
+        self.register_function("reduce", Box::new(ReduceFn::new()));
         self.register_function("reverse", Box::new(ReverseFn::new()));
+        self.register_function("sliding_window", Box::new(SlidingWindowFn::new()));
+        self.register_function("snake_case", Box::new(SnakeCaseFn::new()));
         self.register_function("sort", Box::new(SortFn::new()));
         self.register_function("sort_by", Box::new(SortByFn::new()));
+        self.register_function("sort_keys", Box::new(SortKeysFn::new()));
         self.register_function("starts_with", Box::new(StartsWithFn::new()));
         self.register_function("sum", Box::new(SumFn::new()));
         self.register_function("to_array", Box::new(ToArrayFn::new()));
+        #[cfg(feature = "decimal")]
+        self.register_function("to_decimal", Box::new(ToDecimalFn::new()));
+        self.register_function("to_entries", Box::new(ToEntriesFn::new()));
         self.register_function("to_number", Box::new(ToNumberFn::new()));
         self.register_function("to_string", Box::new(ToStringFn::new()));
         self.register_function("type", Box::new(TypeFn::new()));
+        self.register_function("unflatten_keys", Box::new(UnflattenKeysFn::new()));
+        self.register_function("upper", Box::new(UpperFn::new()));
         self.register_function("values", Box::new(ValuesFn::new()));
+        #[cfg(feature = "community-functions")]
+        self.register_community_functions();
+        #[cfg(feature = "datetime-functions")]
+        self.register_datetime_functions();
+        #[cfg(feature = "hash-functions")]
+        self.register_hash_functions();
+        #[cfg(feature = "math-functions")]
+        self.register_math_functions();
+    }
+
+    /// Registers the `env(name)` function, which returns the value of an
+    /// environment variable of the current process.
+    ///
+    /// This is not called by `register_builtin_functions`: any expression
+    /// compiled with the runtime can read every environment variable,
+    /// including secrets such as credentials. Only register it in runtimes
+    /// used to evaluate trusted expressions.
+    #[cfg(feature = "env")]
+    pub fn register_env_function(&mut self) {
+        self.register_function("env", Box::new(EnvFn::new()));
+    }
+
+    /// Registers the functions for parsing, formatting and converting
+    /// datetimes.
+    ///
+    /// This is called by `register_builtin_functions`, so it only needs to
+    /// be called directly when building a runtime without the standard
+    /// functions.
+    #[cfg(feature = "datetime-functions")]
+    pub fn register_datetime_functions(&mut self) {
+        self.register_function("format_datetime", Box::new(FormatDatetimeFn::new()));
+        self.register_function("from_epoch", Box::new(FromEpochFn::new()));
+        self.register_function("now", Box::new(NowFn::new()));
+        self.register_function("parse_datetime", Box::new(ParseDatetimeFn::new()));
+        self.register_function("to_epoch", Box::new(ToEpochFn::new()));
+    }
+
+    /// Registers the functions for hashing strings and generating
+    /// identifiers.
+    ///
+    /// This is called by `register_builtin_functions`, so it only needs to
+    /// be called directly when building a runtime without the standard
+    /// functions.
+    #[cfg(feature = "hash-functions")]
+    pub fn register_hash_functions(&mut self) {
+        self.register_function("md5", Box::new(Md5Fn::new()));
+        self.register_function("sha256", Box::new(Sha256Fn::new()));
+        self.register_function("uuid", Box::new(UuidFn::new()));
+    }
+
+    /// Registers the extended math functions.
+    ///
+    /// This is called by `register_builtin_functions`, so it only needs to
+    /// be called directly when building a runtime without the standard
+    /// functions.
+    #[cfg(feature = "math-functions")]
+    pub fn register_math_functions(&mut self) {
+        self.register_function("div", Box::new(DivFn::new()));
+        self.register_function("log", Box::new(LogFn::new()));
+        self.register_function("mod", Box::new(ModFn::new()));
+        self.register_function("pow", Box::new(PowFn::new()));
+        self.register_function("round", Box::new(RoundFn::new()));
+        self.register_function("sign", Box::new(SignFn::new()));
+        self.register_function("sqrt", Box::new(SqrtFn::new()));
+        self.register_function("trunc", Box::new(TruncFn::new()));
+    }
+
+    /// Registers the functions from the JMESPath community specification.
+    ///
+    /// This is called by `register_builtin_functions`, so it only needs to
+    /// be called directly when building a runtime without the standard
+    /// functions.
+    #[cfg(feature = "community-functions")]
+    pub fn register_community_functions(&mut self) {
+        self.register_function("find_first", Box::new(FindFirstFn::new()));
+        self.register_function("find_last", Box::new(FindLastFn::new()));
+        self.register_function("from_items", Box::new(FromItemsFn::new()));
+        self.register_function("group_by", Box::new(GroupByFn::new()));
+        self.register_function("items", Box::new(ItemsFn::new()));
+        self.register_function("pad_left", Box::new(PadLeftFn::new()));
+        self.register_function("pad_right", Box::new(PadRightFn::new()));
+        self.register_function("replace", Box::new(ReplaceFn::new()));
+        self.register_function("split", Box::new(SplitFn::new()));
+        self.register_function("trim", Box::new(TrimFn::new()));
+        self.register_function("trim_left", Box::new(TrimLeftFn::new()));
+        self.register_function("trim_right", Box::new(TrimRightFn::new()));
+        self.register_function("zip", Box::new(ZipFn::new()));
+    }
+}
+
+/// A `Runtime` that is either borrowed or shared using an `Arc`.
+#[derive(Clone)]
+pub(crate) enum RuntimeRef<'a> {
+    Borrowed(&'a Runtime),
+    Shared(Arc<Runtime>),
+}
+
+impl<'a> Deref for RuntimeRef<'a> {
+    type Target = Runtime;
+
+    #[inline]
+    fn deref(&self) -> &Runtime {
+        match *self {
+            RuntimeRef::Borrowed(runtime) => runtime,
+            RuntimeRef::Shared(ref runtime) => runtime,
+        }
     }
 }
@@ -0,0 +1,190 @@
+//! Named collections of compiled expressions.
+
+use std::collections::BTreeMap;
+
+use crate::interpreter::SearchResult;
+use crate::{Expression, JmespathError, Rcvar, Runtime, SearchOptions, ToJmespath};
+
+/// A collection of compiled expressions registered under names.
+///
+/// Useful for rules engines and similar applications that load many
+/// expressions from configuration: the expressions are compiled and
+/// validated together, then evaluated by name.
+///
+/// ```
+/// use jmespath::ExpressionSet;
+///
+/// let rules = ExpressionSet::compile(vec![
+///     ("failed", "length(jobs[?status == 'failed'])"),
+///     ("names", "jobs[*].name"),
+/// ])
+/// .unwrap();
+/// let data = jmespath::Variable::from_json(r#"{"jobs": [{"name": "a", "status": "failed"}]}"#)
+///     .unwrap();
+/// assert_eq!(Some(1.0), rules.search("failed", &data).unwrap().unwrap().as_number());
+/// assert!(rules.search("missing", &data).is_none());
+/// ```
+pub struct ExpressionSet<'a> {
+    runtime: &'a Runtime,
+    expressions: BTreeMap<String, Expression<'a>>,
+}
+
+impl ExpressionSet<'static> {
+    /// Creates an empty set that compiles expressions with the default
+    /// `Runtime`.
+    pub fn new() -> ExpressionSet<'static> {
+        ExpressionSet::with_runtime(&crate::DEFAULT_RUNTIME)
+    }
+
+    /// Compiles every `(name, expression)` pair with the default `Runtime`.
+    ///
+    /// Returns the errors of every expression that failed to compile, along
+    /// with its name, rather than stopping at the first one.
+    pub fn compile<I, N, E>(
+        expressions: I,
+    ) -> Result<ExpressionSet<'static>, Vec<(String, JmespathError)>>
+    where
+        I: IntoIterator<Item = (N, E)>,
+        N: Into<String>,
+        E: AsRef<str>,
+    {
+        ExpressionSet::new().insert_all(expressions)
+    }
+}
+
+impl Default for ExpressionSet<'static> {
+    fn default() -> Self {
+        ExpressionSet::new()
+    }
+}
+
+impl<'a> ExpressionSet<'a> {
+    /// Creates an empty set that compiles expressions with `runtime`.
+    pub fn with_runtime(runtime: &'a Runtime) -> ExpressionSet<'a> {
+        ExpressionSet {
+            runtime,
+            expressions: BTreeMap::new(),
+        }
+    }
+
+    /// Compiles `expression` and registers it under `name`, replacing any
+    /// expression previously registered under that name.
+    pub fn insert<N: Into<String>>(
+        &mut self,
+        name: N,
+        expression: &str,
+    ) -> Result<(), JmespathError> {
+        let expr = self.runtime.compile(expression)?;
+        self.expressions.insert(name.into(), expr);
+        Ok(())
+    }
+
+    /// Compiles and registers every `(name, expression)` pair.
+    ///
+    /// Either every expression is registered or, if any fails to compile,
+    /// none are and the errors of every failing expression are returned.
+    pub fn insert_all<I, N, E>(
+        mut self,
+        expressions: I,
+    ) -> Result<ExpressionSet<'a>, Vec<(String, JmespathError)>>
+    where
+        I: IntoIterator<Item = (N, E)>,
+        N: Into<String>,
+        E: AsRef<str>,
+    {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+        for (name, expression) in expressions {
+            let name = name.into();
+            match self.runtime.compile(expression.as_ref()) {
+                Ok(expr) => compiled.push((name, expr)),
+                Err(e) => errors.push((name, e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.expressions.extend(compiled);
+        Ok(self)
+    }
+
+    /// Removes the expression registered under `name`.
+    pub fn remove(&mut self, name: &str) -> Option<Expression<'a>> {
+        self.expressions.remove(name)
+    }
+
+    /// Returns the expression registered under `name`.
+    pub fn get(&self, name: &str) -> Option<&Expression<'a>> {
+        self.expressions.get(name)
+    }
+
+    /// Returns the registered names in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.expressions.keys().map(String::as_str)
+    }
+
+    /// Returns the number of registered expressions.
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    /// Returns true if no expressions are registered.
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+
+    /// Searches `data` with the expression registered under `name`.
+    ///
+    /// Returns `None` if no expression is registered under `name`.
+    pub fn search<T: ToJmespath>(&self, name: &str, data: T) -> Option<SearchResult> {
+        self.get(name).map(|expr| expr.search(data))
+    }
+
+    /// Searches `data` with every registered expression, returning the
+    /// results by name.
+    ///
+    /// `data` is converted to a `Variable` once and shared by every search.
+    pub fn search_all<T: ToJmespath>(
+        &self,
+        data: T,
+        options: &SearchOptions,
+    ) -> Result<BTreeMap<&str, SearchResult>, JmespathError> {
+        let data: Rcvar = data.to_jmespath()?;
+        Ok(self
+            .expressions
+            .iter()
+            .map(|(name, expr)| (name.as_str(), expr.search_variable(data.clone(), options)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Variable;
+
+    #[test]
+    fn reports_every_invalid_expression() {
+        let errors = ExpressionSet::compile(vec![("a", "a["), ("b", "b"), ("c", "c{")])
+            .err()
+            .unwrap();
+        let names: Vec<_> = errors.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(vec!["a", "c"], names);
+        assert_eq!("c{", errors[1].1.expression);
+    }
+
+    #[test]
+    fn searches_every_expression() {
+        let mut set = ExpressionSet::compile(vec![("a", "a"), ("b", "abs(b)")]).unwrap();
+        set.insert("c", "`1`").unwrap();
+        assert!(set.insert("d", "[").is_err());
+        assert_eq!(vec!["a", "b", "c"], set.names().collect::<Vec<_>>());
+        let data = Variable::from_json(r#"{"a": true, "b": "x"}"#).unwrap();
+        let results = set.search_all(data, &SearchOptions::default()).unwrap();
+        assert_eq!("true", results["a"].as_ref().unwrap().to_string());
+        assert!(results["b"].is_err());
+        assert_eq!("1", results["c"].as_ref().unwrap().to_string());
+        assert!(set.remove("a").is_some());
+        assert_eq!(2, set.len());
+    }
+}
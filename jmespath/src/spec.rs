@@ -0,0 +1,219 @@
+//! Selection of the JMESPath grammar and semantics an expression targets.
+
+use crate::ast::Ast;
+use crate::{ErrorReason, JmespathError};
+
+/// Version of the JMESPath specification accepted when compiling expressions.
+///
+/// Pinning a version ensures that an expression is only accepted if every
+/// JMESPath implementation supporting that version evaluates it the same way.
+///
+/// Versions are ordered from oldest to newest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SpecVersion {
+    /// The original JMESPath specification. Extension operators (such as
+    /// `//` and `%`) and extension functions (such as `concat()` and the
+    /// community functions) are rejected when the expression is compiled,
+    /// and slicing a string returns null.
+    Original,
+    /// The original specification along with the extensions supported by
+    /// this crate.
+    #[default]
+    Community,
+}
+
+/// Functions provided by this crate that are not in the original specification.
+const EXTENSION_FUNCTIONS: &[&str] = &[
+    "base64_decode",
+    "base64_encode",
+    "camel_case",
+    "capitalize",
+    "cartesian_product",
+    "compact",
+    "concat",
+    "deep_merge",
+    "default",
+    "div",
+    "env",
+    "eval",
+    "find_first",
+    "find_last",
+    "flatten_keys",
+    "format_datetime",
+    "from_entries",
+    "from_epoch",
+    "from_items",
+    "group_by",
+    "if",
+    "items",
+    "log",
+    "lower",
+    "map_keys",
+    "match_keys",
+    "md5",
+    "merge_patch",
+    "mod",
+    "now",
+    "omit_empty",
+    "pad_left",
+    "pad_right",
+    "parse_datetime",
+    "paths",
+    "pow",
+    "product",
+    "range",
+    "recursive_search",
+    "reduce",
+    "replace",
+    "round",
+    "sha256",
+    "sign",
+    "sliding_window",
+    "snake_case",
+    "sort_keys",
+    "split",
+    "sqrt",
+    "to_decimal",
+    "to_entries",
+    "to_epoch",
+    "trim",
+    "trim_left",
+    "trim_right",
+    "trunc",
+    "unflatten_keys",
+    "upper",
+    "uuid",
+    "zip",
+];
+
+impl SpecVersion {
+    /// Returns an error if the AST uses a feature not supported by the version.
+    pub(crate) fn check(self, expression: &str, ast: &Ast) -> Result<(), JmespathError> {
+        match self {
+            SpecVersion::Community => Ok(()),
+            SpecVersion::Original => match find_extension(ast) {
+                None => Ok(()),
+                Some((offset, feature)) => Err(JmespathError::new(
+                    expression,
+                    offset,
+                    ErrorReason::Parse(format!(
+                        "{} is not part of the original JMESPath specification",
+                        feature
+                    )),
+                )),
+            },
+        }
+    }
+}
+
+/// Finds the first node that uses an extension to the original specification.
+fn find_extension(node: &Ast) -> Option<(usize, String)> {
+    match *node {
+        Ast::Field { .. }
+        | Ast::Identity { .. }
+        | Ast::Index { .. }
+        | Ast::Literal { .. }
+        | Ast::Slice { .. } => None,
+        Ast::Arithmetic {
+            offset,
+            ref operator,
+            ..
+        } => Some((offset, format!("The {} operator", operator))),
+        Ast::Unary {
+            offset,
+            ref operator,
+            ..
+        } => Some((offset, format!("The unary {} operator", operator))),
+        Ast::Root { offset } => Some((offset, "The root node reference $".to_owned())),
+        Ast::Let { offset, .. } => Some((offset, "The let expression".to_owned())),
+        Ast::VariableRef { offset, ref name } => {
+            Some((offset, format!("The variable reference ${}", name)))
+        }
+        Ast::Function {
+            ref name,
+            ref args,
+            offset,
+        } => {
+            if EXTENSION_FUNCTIONS.contains(&name.as_str()) {
+                Some((offset, format!("Function {}()", name)))
+            } else {
+                args.iter().find_map(find_extension)
+            }
+        }
+        Ast::Expref { ref ast, .. } => find_extension(ast),
+        Ast::Flatten { ref node, .. }
+        | Ast::Not { ref node, .. }
+        | Ast::ObjectValues { ref node, .. } => find_extension(node),
+        Ast::MultiList { ref elements, .. } => elements.iter().find_map(find_extension),
+        Ast::MultiHash { ref elements, .. } => {
+            elements.iter().find_map(|kvp| find_extension(&kvp.value))
+        }
+        Ast::Condition {
+            ref predicate,
+            ref then,
+            ..
+        } => find_extension(predicate).or_else(|| find_extension(then)),
+        Ast::Comparison {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Projection {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::And {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Or {
+            ref lhs, ref rhs, ..
+        }
+        | Ast::Subexpr {
+            ref lhs, ref rhs, ..
+        } => find_extension(lhs).or_else(|| find_extension(rhs)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse;
+
+    fn check(version: SpecVersion, expr: &str) -> Result<(), JmespathError> {
+        version.check(expr, &parse(expr).unwrap())
+    }
+
+    #[test]
+    fn community_accepts_extensions() {
+        assert!(check(SpecVersion::Community, "a % `2`").is_ok());
+        assert!(check(SpecVersion::Community, "concat(a, b)").is_ok());
+        assert!(check(SpecVersion::Community, "let $x = a in b[?c == $x]").is_ok());
+    }
+
+    #[test]
+    fn original_rejects_extensions() {
+        assert!(check(SpecVersion::Original, "foo[?a == `1`].length(@)").is_ok());
+        let err = check(SpecVersion::Original, "foo[?a // `2` == `1`]").unwrap_err();
+        assert_eq!(7, err.offset);
+        assert_eq!(
+            ErrorReason::Parse(
+                "The // operator is not part of the original JMESPath specification".to_owned()
+            ),
+            err.reason
+        );
+        let err = check(SpecVersion::Original, "a | {b: concat(b, c)}").unwrap_err();
+        assert_eq!(
+            ErrorReason::Parse(
+                "Function concat() is not part of the original JMESPath specification".to_owned()
+            ),
+            err.reason
+        );
+        let err = check(SpecVersion::Original, "[a, -b]").unwrap_err();
+        assert_eq!(
+            ErrorReason::Parse(
+                "The unary - operator is not part of the original JMESPath specification"
+                    .to_owned()
+            ),
+            err.reason
+        );
+        let err = check(SpecVersion::Original, "a | let $x = b in $x").unwrap_err();
+        assert_eq!(4, err.offset);
+    }
+}
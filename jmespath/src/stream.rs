@@ -0,0 +1,168 @@
+//! Searches streams of newline-delimited JSON documents.
+//!
+//! `search_lines` reads one JSON document per line from a `BufRead` and
+//! yields the result of searching each document as soon as its line has
+//! been read, so the input never has to be buffered as a whole.
+//!
+//! ```
+//! use jmespath::stream::search_lines;
+//!
+//! let expr = jmespath::compile("level").unwrap();
+//! let input = "{\"level\": \"info\"}\n\n{\"level\": \"warn\"}\n";
+//! let levels: Vec<String> = search_lines(input.as_bytes(), &expr)
+//!     .map(|result| result.unwrap().to_string())
+//!     .collect();
+//! assert_eq!(vec![r#""info""#, r#""warn""#], levels);
+//! ```
+//!
+//! Blank lines are skipped. A line that is not valid JSON or that fails to
+//! be searched yields a `StreamError::Line` error and the stream moves on to
+//! the next line; a failure to read from the input yields a
+//! `StreamError::Io` error and ends the stream.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::variable::Variable;
+use crate::{Expression, JmespathError, Rcvar, SearchOptions};
+
+/// Error encountered while searching a stream of documents.
+#[derive(Debug)]
+pub enum StreamError {
+    /// Reading from the input failed.
+    Io(io::Error),
+    /// A line could not be parsed as JSON or searched.
+    Line {
+        /// Line number of the document, starting at 1.
+        line: usize,
+        /// Error raised while parsing or searching the document.
+        error: JmespathError,
+    },
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match *self {
+            StreamError::Io(ref e) => write!(fmt, "Error reading input: {}", e),
+            StreamError::Line { line, ref error } => write!(fmt, "Line {}: {}", line, error),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            StreamError::Io(ref e) => Some(e),
+            StreamError::Line { ref error, .. } => Some(error),
+        }
+    }
+}
+
+/// Iterator returned by `search_lines`.
+pub struct SearchLines<'e, 'a, R> {
+    reader: R,
+    expr: &'e Expression<'a>,
+    options: SearchOptions,
+    buffer: String,
+    line: usize,
+    done: bool,
+}
+
+/// Lazily searches each line of `reader` as a JSON document.
+pub fn search_lines<'e, 'a, R: BufRead>(
+    reader: R,
+    expr: &'e Expression<'a>,
+) -> SearchLines<'e, 'a, R> {
+    SearchLines {
+        reader,
+        expr,
+        options: SearchOptions::default(),
+        buffer: String::new(),
+        line: 0,
+        done: false,
+    }
+}
+
+impl<'e, 'a, R> SearchLines<'e, 'a, R> {
+    /// Searches the documents using the given `SearchOptions`.
+    pub fn with_options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<'e, 'a, R: BufRead> Iterator for SearchLines<'e, 'a, R> {
+    type Item = Result<Rcvar, StreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let document = self.buffer.trim();
+                    if document.is_empty() {
+                        continue;
+                    }
+                    let line = self.line;
+                    let result = serde_json::from_str::<Variable>(document)
+                        .map_err(JmespathError::from)
+                        .and_then(|data| self.expr.search_variable(Rcvar::new(data), &self.options))
+                        .map_err(|error| StreamError::Line { line, error });
+                    return Some(result);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(StreamError::Io(e)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::compile;
+
+    #[test]
+    fn reports_errors_per_line() {
+        let expr = compile("abs(a)").unwrap();
+        let input = "{\"a\": -1}\n{\"a\": \n\r\n{\"a\": \"x\"}\n{\"a\": 2}";
+        let results: Vec<_> = search_lines(input.as_bytes(), &expr).collect();
+        assert_eq!(4, results.len());
+        assert_eq!("1.0", results[0].as_ref().unwrap().to_string());
+        match results[1] {
+            Err(StreamError::Line { line, .. }) => assert_eq!(2, line),
+            ref other => panic!("unexpected result: {:?}", other),
+        }
+        match results[2] {
+            Err(StreamError::Line { line, ref error }) => {
+                assert_eq!(4, line);
+                assert_eq!("abs(a)", error.expression);
+            }
+            ref other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!("2.0", results[3].as_ref().unwrap().to_string());
+    }
+
+    #[test]
+    fn stops_after_io_errors() {
+        struct Failing;
+
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken pipe"))
+            }
+        }
+
+        let expr = compile("@").unwrap();
+        let mut results = search_lines(io::BufReader::new(Failing), &expr);
+        assert!(matches!(results.next(), Some(Err(StreamError::Io(_)))));
+        assert!(results.next().is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! Reads CSV and TSV data with a header row into JMESPath variables.
+//!
+//! Each record becomes an object keyed by the header row, and the records
+//! are collected into an array, so expressions can filter rows directly.
+//! Requires the `tabular` feature.
+//!
+//! ```
+//! use jmespath::tabular::{from_str, TabularOptions};
+//!
+//! let csv = "id,status\n1,ok\n2,failed\n";
+//! let options = TabularOptions { infer_types: true, ..TabularOptions::default() };
+//! let data = from_str(csv, &options).unwrap();
+//! let expr = jmespath::compile("[?status=='failed'].id").unwrap();
+//! assert_eq!("[2]", expr.search(data).unwrap().to_string());
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde_json::Number;
+
+use crate::{Rcvar, Variable};
+
+/// Options used when reading tabular data.
+#[derive(Clone, Debug)]
+pub struct TabularOptions {
+    /// Byte that separates fields. Defaults to `b','`; use `b'\t'` for TSV.
+    pub delimiter: u8,
+    /// Converts fields that look like numbers or booleans to numbers and
+    /// booleans, and empty fields to null. When false, every field is read
+    /// as a string.
+    pub infer_types: bool,
+}
+
+impl Default for TabularOptions {
+    fn default() -> Self {
+        TabularOptions {
+            delimiter: b',',
+            infer_types: false,
+        }
+    }
+}
+
+/// Reads tabular data with a header row from `reader` into an array of
+/// objects.
+pub fn from_reader<R: io::Read>(reader: R, options: &TabularOptions) -> Result<Variable, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .from_reader(reader);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let row: BTreeMap<String, Rcvar> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(header, field)| (header.to_owned(), Rcvar::new(field_value(field, options))))
+            .collect();
+        rows.push(Rcvar::new(Variable::Object(row)));
+    }
+    Ok(Variable::Array(rows))
+}
+
+/// Reads tabular data with a header row from a string into an array of
+/// objects.
+pub fn from_str(s: &str, options: &TabularOptions) -> Result<Variable, String> {
+    from_reader(s.as_bytes(), options)
+}
+
+fn field_value(field: &str, options: &TabularOptions) -> Variable {
+    if !options.infer_types {
+        return Variable::String(field.to_owned());
+    }
+    match field {
+        "" => Variable::Null,
+        "true" => Variable::Bool(true),
+        "false" => Variable::Bool(false),
+        _ => {
+            let number = if let Ok(i) = field.parse::<i64>() {
+                Some(Number::from(i))
+            } else if let Ok(u) = field.parse::<u64>() {
+                Some(Number::from(u))
+            } else {
+                field.parse::<f64>().ok().and_then(Number::from_f64)
+            };
+            number.map_or_else(|| Variable::String(field.to_owned()), Variable::Number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_fields_as_strings_by_default() {
+        let data = from_str("a,b\n1,\ntrue,x\n", &TabularOptions::default()).unwrap();
+        assert_eq!(
+            r#"[{"a":"1","b":""},{"a":"true","b":"x"}]"#,
+            data.to_string()
+        );
+    }
+
+    #[test]
+    fn infers_types() {
+        let options = TabularOptions {
+            delimiter: b'\t',
+            infer_types: true,
+        };
+        let data = from_str("a\tb\tc\n-1\t2.5\t\nfalse\tNaN\t007\n", &options).unwrap();
+        assert_eq!(
+            r#"[{"a":-1,"b":2.5,"c":null},{"a":false,"b":"NaN","c":7}]"#,
+            data.to_string()
+        );
+        assert!(from_str("a,b\n1,2,3\n", &TabularOptions::default()).is_err());
+    }
+}
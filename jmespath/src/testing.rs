@@ -0,0 +1,201 @@
+//! Utilities for table-driven tests of JMESPath expressions.
+//!
+//! Each `Case` consists of an expression, the input JSON document, and the
+//! expected JSON result (or an expected error). Cases are run against a
+//! `Runtime`, which makes it possible to regression-test custom functions
+//! the same way the builtin functions are tested.
+//!
+//! ```
+//! use jmespath::Runtime;
+//! use jmespath::testing::{assert_cases, Case};
+//!
+//! let mut runtime = Runtime::new();
+//! runtime.register_builtin_functions();
+//!
+//! assert_cases(
+//!     &[
+//!         Case::new("length(foo)", r#"{"foo": [1, 2]}"#, "2"),
+//!         Case::new("foo.bar", "{}", "null"),
+//!         Case::error("length(`1`)", "{}"),
+//!     ],
+//!     &runtime,
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::{JmespathError, Runtime, Variable};
+
+/// The outcome expected from evaluating a `Case`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected {
+    /// The expression evaluates to the given value.
+    Result(Variable),
+    /// The expression fails to compile or evaluate.
+    Error,
+}
+
+/// A single table-driven test case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    /// Expression to compile.
+    pub expression: String,
+    /// Data the expression is evaluated against.
+    pub given: Variable,
+    /// Expected outcome of the evaluation.
+    pub expected: Expected,
+}
+
+impl Case {
+    /// Creates a case that expects `expression` to evaluate `given` to
+    /// `expected`. Both `given` and `expected` are JSON documents.
+    ///
+    /// Panics if either document is not valid JSON.
+    pub fn new(expression: &str, given: &str, expected: &str) -> Case {
+        Case {
+            expression: expression.to_owned(),
+            given: parse_json(given),
+            expected: Expected::Result(parse_json(expected)),
+        }
+    }
+
+    /// Creates a case that expects `expression` to fail against `given`.
+    ///
+    /// Panics if `given` is not valid JSON.
+    pub fn error(expression: &str, given: &str) -> Case {
+        Case {
+            expression: expression.to_owned(),
+            given: parse_json(given),
+            expected: Expected::Error,
+        }
+    }
+
+    /// Compiles and evaluates the case using the given runtime.
+    pub fn evaluate(&self, runtime: &Runtime) -> Result<Variable, JmespathError> {
+        let expr = runtime.compile(&self.expression)?;
+        expr.search(self.given.clone())
+            .map(|result| (*result).clone())
+    }
+}
+
+fn parse_json(json: &str) -> Variable {
+    Variable::from_json(json).unwrap_or_else(|e| panic!("Invalid JSON {}: {}", json, e))
+}
+
+/// A case whose outcome did not match the expectation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Failure<'a> {
+    /// Position of the case in the list of cases that were run.
+    pub index: usize,
+    /// The case that failed.
+    pub case: &'a Case,
+    /// The actual outcome of evaluating the case.
+    pub actual: Result<Variable, JmespathError>,
+}
+
+/// Pretty-prints the expected and actual outcomes of the case.
+impl<'a> fmt::Display for Failure<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        writeln!(fmt, "case {} failed: {}", self.index, self.case.expression)?;
+        writeln!(fmt, "given:\n{}", pretty(&self.case.given))?;
+        match self.case.expected {
+            Expected::Result(ref v) => writeln!(fmt, "expected:\n{}", pretty(v))?,
+            Expected::Error => writeln!(fmt, "expected: an error")?,
+        }
+        match self.actual {
+            Ok(ref v) => write!(fmt, "actual:\n{}", pretty(v)),
+            Err(ref e) => write!(fmt, "actual error:\n{}", e),
+        }
+    }
+}
+
+fn pretty(value: &Variable) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// Runs each case against the runtime and returns the cases that failed.
+pub fn run_cases<'a>(cases: &'a [Case], runtime: &Runtime) -> Vec<Failure<'a>> {
+    cases
+        .iter()
+        .enumerate()
+        .filter_map(|(index, case)| {
+            let actual = case.evaluate(runtime);
+            let passed = match (&case.expected, &actual) {
+                (Expected::Result(expected), Ok(actual)) => expected == actual,
+                (Expected::Error, Err(_)) => true,
+                _ => false,
+            };
+            if passed {
+                None
+            } else {
+                Some(Failure {
+                    index,
+                    case,
+                    actual,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Runs each case against the runtime and panics if any case fails.
+///
+/// The panic message contains every failing case.
+pub fn assert_cases(cases: &[Case], runtime: &Runtime) {
+    let failures = run_cases(cases, runtime);
+    if !failures.is_empty() {
+        let report = failures
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        panic!(
+            "{} of {} cases failed\n\n{}",
+            failures.len(),
+            cases.len(),
+            report
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn runtime() -> Runtime {
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        runtime
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let runtime = runtime();
+        let cases = [
+            Case::new("a", r#"{"a": 1}"#, "1"),
+            Case::new("a", r#"{"a": 1}"#, "2"),
+            Case::error("a", "{}"),
+            Case::new("length(a)", "{}", "0"),
+        ];
+        let failures = run_cases(&cases, &runtime);
+        assert_eq!(
+            vec![1, 2, 3],
+            failures.iter().map(|f| f.index).collect::<Vec<_>>()
+        );
+        assert_eq!(Ok(Variable::Null), failures[1].actual);
+        assert!(failures[2].actual.is_err());
+        assert_eq!(
+            "case 1 failed: a\ngiven:\n{\n  \"a\": 1\n}\nexpected:\n2\nactual:\n1",
+            failures[0].to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "1 of 2 cases failed")]
+    fn assert_cases_panics_on_failure() {
+        assert_cases(
+            &[Case::new("a", "{}", "null"), Case::new("a", "{}", "1")],
+            &runtime(),
+        );
+    }
+}
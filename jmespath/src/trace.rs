@@ -0,0 +1,281 @@
+//! Instrumentation of compilation and searches.
+//!
+//! When the `tracing` feature is enabled, compiling an expression and
+//! searching data each run inside a `tracing` span, and an event is emitted
+//! when they complete. Spans include the expression, its fingerprint and
+//! the outcome (`ok` or `error`), and events include the number of AST
+//! nodes and the elapsed duration.
+//! Without the feature, these functions only call the wrapped closure.
+//!
+//! When the `log` feature is enabled, searches that exceed the thresholds
+//! in `SearchOptions::slow_search` are logged as warnings.
+
+use crate::ast::Ast;
+use crate::interpreter::SearchResult;
+use crate::JmespathError;
+
+/// Returns a fingerprint of an expression string.
+///
+/// The fingerprint is computed using 64-bit FNV-1a, so it is stable across
+/// processes and releases and can be used to correlate traces.
+#[cfg_attr(not(any(feature = "tracing", feature = "log")), allow(dead_code))]
+pub(crate) fn fingerprint(expression: &str) -> u64 {
+    expression
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Returns the value of the `outcome` field of a span.
+#[cfg(feature = "tracing")]
+fn outcome<T>(result: &Result<T, JmespathError>) -> &'static str {
+    match *result {
+        Ok(_) => "ok",
+        Err(_) => "error",
+    }
+}
+
+/// Runs `parse` inside a compile span.
+#[cfg(feature = "tracing")]
+pub(crate) fn compile<F>(expression: &str, parse: F) -> Result<Ast, JmespathError>
+where
+    F: FnOnce() -> Result<Ast, JmespathError>,
+{
+    let span = tracing::debug_span!(
+        "jmespath.compile",
+        expression = expression,
+        fingerprint = fingerprint(expression),
+        outcome = tracing::field::Empty
+    );
+    let _enter = span.enter();
+    let start = std::time::Instant::now();
+    let result = parse();
+    let duration_us = start.elapsed().as_micros() as u64;
+    span.record("outcome", outcome(&result));
+    match result {
+        Ok(ref ast) => {
+            tracing::debug!(nodes = ast.node_count(), duration_us, "compiled expression")
+        }
+        Err(ref e) => tracing::debug!(error = %e, duration_us, "failed to compile expression"),
+    }
+    result
+}
+
+/// Runs `parse` inside a compile span.
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn compile<F>(_expression: &str, parse: F) -> Result<Ast, JmespathError>
+where
+    F: FnOnce() -> Result<Ast, JmespathError>,
+{
+    parse()
+}
+
+/// Runs `search` inside a search span.
+#[cfg(feature = "tracing")]
+pub(crate) fn search<F>(expression: &str, ast: &Ast, search: F) -> SearchResult
+where
+    F: FnOnce() -> SearchResult,
+{
+    let span = tracing::debug_span!(
+        "jmespath.search",
+        expression = expression,
+        fingerprint = fingerprint(expression),
+        outcome = tracing::field::Empty
+    );
+    let _enter = span.enter();
+    let start = std::time::Instant::now();
+    let result = search();
+    let duration_us = start.elapsed().as_micros() as u64;
+    span.record("outcome", outcome(&result));
+    match result {
+        Ok(_) => tracing::debug!(nodes = ast.node_count(), duration_us, "searched data"),
+        Err(ref e) => tracing::debug!(
+            nodes = ast.node_count(),
+            error = %e,
+            duration_us,
+            "failed to search data"
+        ),
+    }
+    result
+}
+
+/// Runs `search` inside a search span.
+#[cfg(not(feature = "tracing"))]
+#[inline]
+pub(crate) fn search<F>(_expression: &str, _ast: &Ast, search: F) -> SearchResult
+where
+    F: FnOnce() -> SearchResult,
+{
+    search()
+}
+
+/// Logs a search that exceeded the configured slow search thresholds.
+#[cfg(feature = "log")]
+pub(crate) fn log_slow_search(ctx: &crate::Context<'_>, duration: std::time::Duration) {
+    if let Some(threshold) = ctx.options.slow_search {
+        let nodes = ctx.evaluated_nodes();
+        if threshold.is_exceeded(duration, nodes) {
+            log::warn!(
+                "Slow JMESPath search: expression={:?} fingerprint={:016x} duration={:?} nodes={}",
+                ctx.expression,
+                fingerprint(ctx.expression),
+                duration,
+                nodes
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    use crate::Variable;
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn logs_slow_searches() {
+        use crate::{compile, SearchOptions, SlowSearchThreshold};
+        use std::sync::Mutex;
+
+        static MESSAGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        struct TestLogger;
+
+        impl log::Log for TestLogger {
+            fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record<'_>) {
+                MESSAGES.lock().unwrap().push(record.args().to_string());
+            }
+
+            fn flush(&self) {}
+        }
+
+        log::set_logger(&TestLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+        let expr = compile("a[*].b").unwrap();
+        let data = Variable::from_json(r#"{"a": [{"b": 1}, {"b": 2}]}"#).unwrap();
+        let threshold = |nodes| SearchOptions {
+            slow_search: Some(SlowSearchThreshold {
+                duration: None,
+                nodes: Some(nodes),
+            }),
+            ..SearchOptions::default()
+        };
+        expr.search_with_options(data.clone(), &threshold(4))
+            .unwrap();
+        assert!(MESSAGES.lock().unwrap().is_empty());
+        expr.search_with_options(data, &threshold(3)).unwrap();
+        let messages = MESSAGES.lock().unwrap();
+        assert_eq!(1, messages.len());
+        assert!(
+            messages[0].contains("expression=\"a[*].b\""),
+            "{}",
+            messages[0]
+        );
+        assert!(messages[0].contains("nodes=4"), "{}", messages[0]);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_compiles_and_searches() {
+        use crate::Runtime;
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Name and fields of a span.
+        type Span = (&'static str, Vec<(String, String)>);
+
+        /// Records the name and fields of every span.
+        #[derive(Default)]
+        struct Recorder {
+            spans: Mutex<Vec<Span>>,
+        }
+
+        struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+        impl Visit for Fields<'_> {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.push((field.name().to_owned(), value.to_owned()));
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .push((field.name().to_owned(), format!("{:?}", value)));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = vec![];
+                span.record(&mut Fields(&mut fields));
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata().name(), fields));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, span: &Id, values: &Record<'_>) {
+                let mut spans = self.spans.lock().unwrap();
+                let index = span.into_u64() as usize - 1;
+                values.record(&mut Fields(&mut spans[index].1));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let expr = runtime.compile("abs(a)").unwrap();
+            expr.search(Variable::from_json(r#"{"a": -1}"#).unwrap())
+                .unwrap();
+            expr.search(()).unwrap_err();
+            runtime.compile("abs(").unwrap_err();
+        });
+        let span = |name, expression: &str, outcome: &str| {
+            let fields = vec![
+                ("expression".to_owned(), expression.to_owned()),
+                (
+                    "fingerprint".to_owned(),
+                    fingerprint(expression).to_string(),
+                ),
+                ("outcome".to_owned(), outcome.to_owned()),
+            ];
+            (name, fields)
+        };
+        assert_eq!(
+            vec![
+                span("jmespath.compile", "abs(a)", "ok"),
+                span("jmespath.search", "abs(a)", "ok"),
+                span("jmespath.search", "abs(a)", "error"),
+                span("jmespath.compile", "abs(", "error"),
+            ],
+            *recorder.spans.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fingerprint(""));
+        assert_eq!(fingerprint("foo.bar"), fingerprint("foo.bar"));
+        assert_ne!(fingerprint("foo.bar"), fingerprint("foo.baz"));
+    }
+}
@@ -0,0 +1,817 @@
+//! Evaluates expressions over application-defined document types.
+//!
+//! The `ValueLike` trait describes a tree of JSON-like values (e.g., a
+//! `serde_json::Value`, a configuration tree, or a DOM) that
+//! `Expression::search_value` can walk directly, without converting the
+//! whole document into a `Variable` first.
+//!
+//! `serde_json::Value` implements `ValueLike`, as does `simd_json::BorrowedValue`
+//! when the `simd-json` feature is enabled, so documents too large for
+//! `serde_json` to parse quickly can be parsed with `simd-json` and searched
+//! in place.
+//!
+//! Results that are part of the searched document are borrowed from it
+//! rather than copied. Values are only converted to `Variable`s where the
+//! interpreter needs them: function arguments, comparison and arithmetic
+//! operands, and results compared by `dedup_projections`. Arrays and objects
+//! built by the expression (projections, slices, multi-selects) are new
+//! values, so the elements they collect are cloned.
+//!
+//! ```
+//! use std::borrow::Cow;
+//! use std::collections::BTreeMap;
+//! use jmespath::value::ValueLike;
+//! use jmespath::{JmespathError, JmespathType, Variable};
+//!
+//! /// A configuration tree whose leaves are strings.
+//! #[derive(Clone, Debug, PartialEq)]
+//! enum Config {
+//!     Leaf(String),
+//!     Section(BTreeMap<String, Config>),
+//!     List(Vec<Config>),
+//!     Missing,
+//! }
+//!
+//! impl ValueLike for Config {
+//!     fn jmespath_type(&self) -> JmespathType {
+//!         match self {
+//!             Config::Leaf(_) => JmespathType::String,
+//!             Config::Section(_) => JmespathType::Object,
+//!             Config::List(_) => JmespathType::Array,
+//!             Config::Missing => JmespathType::Null,
+//!         }
+//!     }
+//!
+//!     fn is_truthy(&self) -> bool {
+//!         match self {
+//!             Config::Leaf(s) => !s.is_empty(),
+//!             Config::Section(m) => !m.is_empty(),
+//!             Config::List(l) => !l.is_empty(),
+//!             Config::Missing => false,
+//!         }
+//!     }
+//!
+//!     fn get_field(&self, name: &str) -> Option<&Config> {
+//!         match self {
+//!             Config::Section(m) => m.get(name),
+//!             _ => None,
+//!         }
+//!     }
+//!
+//!     fn elements(&self) -> Option<Vec<&Config>> {
+//!         match self {
+//!             Config::List(l) => Some(l.iter().collect()),
+//!             _ => None,
+//!         }
+//!     }
+//!
+//!     fn object_values(&self) -> Option<Vec<&Config>> {
+//!         match self {
+//!             Config::Section(m) => Some(m.values().collect()),
+//!             _ => None,
+//!         }
+//!     }
+//!
+//!     fn to_variable(&self) -> Result<Variable, JmespathError> {
+//!         Ok(match self {
+//!             Config::Leaf(s) => Variable::String(s.clone()),
+//!             Config::Section(m) => Variable::Object(
+//!                 m.iter()
+//!                     .map(|(k, v)| Ok((k.clone(), v.to_variable()?.into())))
+//!                     .collect::<Result<_, JmespathError>>()?,
+//!             ),
+//!             Config::List(l) => Variable::Array(
+//!                 l.iter()
+//!                     .map(|v| Ok(v.to_variable()?.into()))
+//!                     .collect::<Result<_, JmespathError>>()?,
+//!             ),
+//!             Config::Missing => Variable::Null,
+//!         })
+//!     }
+//!
+//!     fn from_variable(value: &Variable) -> Result<Config, JmespathError> {
+//!         Ok(match value {
+//!             Variable::Null => Config::Missing,
+//!             Variable::String(s) => Config::Leaf(s.clone()),
+//!             Variable::Array(a) => Config::List(
+//!                 a.iter().map(|v| Config::from_variable(v)).collect::<Result<_, _>>()?,
+//!             ),
+//!             Variable::Object(m) => Config::Section(
+//!                 m.iter()
+//!                     .map(|(k, v)| Ok((k.clone(), Config::from_variable(v)?)))
+//!                     .collect::<Result<_, JmespathError>>()?,
+//!             ),
+//!             other => Config::Leaf(other.to_string()),
+//!         })
+//!     }
+//!
+//!     fn from_array(elements: Vec<Config>) -> Config {
+//!         Config::List(elements)
+//!     }
+//!
+//!     fn from_object(entries: Vec<(String, Config)>) -> Config {
+//!         Config::Section(entries.into_iter().collect())
+//!     }
+//!
+//!     fn null() -> Config {
+//!         Config::Missing
+//!     }
+//! }
+//!
+//! let mut hosts = BTreeMap::new();
+//! hosts.insert("name".to_owned(), Config::Leaf("db".to_owned()));
+//! let mut root = BTreeMap::new();
+//! root.insert("hosts".to_owned(), Config::List(vec![Config::Section(hosts)]));
+//! let config = Config::Section(root);
+//!
+//! let expr = jmespath::compile("hosts[0].name").unwrap();
+//! let result = expr.search_value(&config).unwrap();
+//! assert!(matches!(result, Cow::Borrowed(Config::Leaf(name)) if name == "db"));
+//! let expr = jmespath::compile("length(hosts)").unwrap();
+//! assert_eq!(Config::Leaf("1".to_owned()), expr.search_value(&config).unwrap().into_owned());
+//! ```
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use serde_json::{Map, Value};
+
+use crate::ast::Ast;
+use crate::interpreter::{
+    arithmetic, call_function, check_array, check_field, check_index, check_not_null, compare,
+    enter, unary, variable,
+};
+use crate::variable::{find_ignore_case, slice, slice_str, JmespathType, Variable};
+use crate::{Context, ErrorReason, JmespathError, Rcvar, RuntimeError};
+
+/// A JSON-like value that expressions can be evaluated over.
+///
+/// Implementations only need to expose how to navigate the tree and how to
+/// convert values to and from `Variable`s.
+pub trait ValueLike: Clone {
+    /// Returns the JMESPath type of the value.
+    fn jmespath_type(&self) -> JmespathType;
+
+    /// Returns true if the value is null.
+    fn is_null(&self) -> bool {
+        self.jmespath_type() == JmespathType::Null
+    }
+
+    /// Returns true if the value is truthy: not null, false, or an empty
+    /// string, array, or object.
+    fn is_truthy(&self) -> bool;
+
+    /// Returns the value of a field if the value is an object.
+    fn get_field(&self, name: &str) -> Option<&Self>;
+
+    /// Returns the value of a field if the value is an object, falling back
+    /// to the first field in key order whose name is equal to `name`
+    /// ignoring case.
+    ///
+    /// Used when `SearchOptions::case_insensitive_fields` is enabled. The
+    /// default implementation only finds exact matches.
+    fn get_field_ignore_case(&self, name: &str) -> Option<&Self> {
+        self.get_field(name)
+    }
+
+    /// Returns the elements of the value if it is an array.
+    fn elements(&self) -> Option<Vec<&Self>>;
+
+    /// Returns the values of the value, ordered by key, if it is an object.
+    fn object_values(&self) -> Option<Vec<&Self>>;
+
+    /// Converts the value to a `Variable`.
+    fn to_variable(&self) -> Result<Variable, JmespathError>;
+
+    /// Converts a `Variable` produced by the interpreter to a value.
+    fn from_variable(value: &Variable) -> Result<Self, JmespathError>;
+
+    /// Creates an array value.
+    fn from_array(elements: Vec<Self>) -> Self;
+
+    /// Creates an object value.
+    fn from_object(entries: Vec<(String, Self)>) -> Self;
+
+    /// Creates a null value.
+    fn null() -> Self;
+}
+
+impl ValueLike for Value {
+    fn jmespath_type(&self) -> JmespathType {
+        match *self {
+            Value::Null => JmespathType::Null,
+            Value::Bool(_) => JmespathType::Boolean,
+            Value::Number(_) => JmespathType::Number,
+            Value::String(_) => JmespathType::String,
+            Value::Array(_) => JmespathType::Array,
+            Value::Object(_) => JmespathType::Object,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        Value::is_null(self)
+    }
+
+    fn is_truthy(&self) -> bool {
+        match *self {
+            Value::Null => false,
+            Value::Bool(b) => b,
+            Value::Number(_) => true,
+            Value::String(ref s) => !s.is_empty(),
+            Value::Array(ref a) => !a.is_empty(),
+            Value::Object(ref o) => !o.is_empty(),
+        }
+    }
+
+    fn get_field(&self, name: &str) -> Option<&Value> {
+        self.as_object()?.get(name)
+    }
+
+    fn get_field_ignore_case(&self, name: &str) -> Option<&Value> {
+        let map = self.as_object()?;
+        map.get(name).or_else(|| find_ignore_case(map, name))
+    }
+
+    fn elements(&self) -> Option<Vec<&Value>> {
+        self.as_array().map(|array| array.iter().collect())
+    }
+
+    fn object_values(&self) -> Option<Vec<&Value>> {
+        let mut entries: Vec<_> = self.as_object()?.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        Some(entries.into_iter().map(|(_, value)| value).collect())
+    }
+
+    fn to_variable(&self) -> Result<Variable, JmespathError> {
+        Variable::try_from(self)
+    }
+
+    fn from_variable(value: &Variable) -> Result<Value, JmespathError> {
+        serde_json::to_value(value).map_err(|e| {
+            JmespathError::new("", 0, ErrorReason::Parse(format!("Serde error: {}", e)))
+        })
+    }
+
+    fn from_array(elements: Vec<Value>) -> Value {
+        Value::Array(elements)
+    }
+
+    fn from_object(entries: Vec<(String, Value)>) -> Value {
+        Value::Object(entries.into_iter().collect::<Map<String, Value>>())
+    }
+
+    fn null() -> Value {
+        Value::Null
+    }
+}
+
+/// Searches documents parsed by `simd_json::to_borrowed_value`, whose strings
+/// borrow from the parsed buffer. Requires the `simd-json` feature.
+#[cfg(feature = "simd-json")]
+impl<'a> ValueLike for simd_json::BorrowedValue<'a> {
+    fn jmespath_type(&self) -> JmespathType {
+        use simd_json::{BorrowedValue, StaticNode};
+        match *self {
+            BorrowedValue::Static(StaticNode::Null) => JmespathType::Null,
+            BorrowedValue::Static(StaticNode::Bool(_)) => JmespathType::Boolean,
+            BorrowedValue::Static(_) => JmespathType::Number,
+            BorrowedValue::String(_) => JmespathType::String,
+            BorrowedValue::Array(_) => JmespathType::Array,
+            BorrowedValue::Object(_) => JmespathType::Object,
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        use simd_json::{BorrowedValue, StaticNode};
+        match *self {
+            BorrowedValue::Static(StaticNode::Null) => false,
+            BorrowedValue::Static(StaticNode::Bool(b)) => b,
+            BorrowedValue::Static(_) => true,
+            BorrowedValue::String(ref s) => !s.is_empty(),
+            BorrowedValue::Array(ref a) => !a.is_empty(),
+            BorrowedValue::Object(ref o) => !o.is_empty(),
+        }
+    }
+
+    fn get_field(&self, name: &str) -> Option<&Self> {
+        match *self {
+            simd_json::BorrowedValue::Object(ref o) => o.get(name),
+            _ => None,
+        }
+    }
+
+    fn get_field_ignore_case(&self, name: &str) -> Option<&Self> {
+        match *self {
+            simd_json::BorrowedValue::Object(ref o) => {
+                o.get(name).or_else(|| find_ignore_case(o.iter(), name))
+            }
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Vec<&Self>> {
+        match *self {
+            simd_json::BorrowedValue::Array(ref a) => Some(a.iter().collect()),
+            _ => None,
+        }
+    }
+
+    fn object_values(&self) -> Option<Vec<&Self>> {
+        match *self {
+            simd_json::BorrowedValue::Object(ref o) => {
+                let mut entries: Vec<_> = o.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                Some(entries.into_iter().map(|(_, value)| value).collect())
+            }
+            _ => None,
+        }
+    }
+
+    fn to_variable(&self) -> Result<Variable, JmespathError> {
+        use simd_json::{BorrowedValue, StaticNode};
+        let var = match *self {
+            BorrowedValue::Static(StaticNode::Null) => Variable::Null,
+            BorrowedValue::Static(StaticNode::Bool(b)) => Variable::Bool(b),
+            BorrowedValue::Static(StaticNode::I64(n)) => Variable::Number(n.into()),
+            BorrowedValue::Static(StaticNode::U64(n)) => Variable::Number(n.into()),
+            BorrowedValue::Static(StaticNode::F64(n)) => {
+                serde_json::Number::from_f64(n).map_or(Variable::Null, Variable::Number)
+            }
+            BorrowedValue::String(ref s) => Variable::String(s.to_string()),
+            BorrowedValue::Array(ref a) => Variable::Array(
+                a.iter()
+                    .map(|v| v.to_variable().map(Rcvar::new))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+            BorrowedValue::Object(ref o) => Variable::Object(
+                o.iter()
+                    .map(|(k, v)| Ok((k.to_string(), Rcvar::new(v.to_variable()?))))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+        };
+        Ok(var)
+    }
+
+    fn from_variable(value: &Variable) -> Result<Self, JmespathError> {
+        simd_json::serde::to_borrowed_value(value).map_err(|e| {
+            JmespathError::new("", 0, ErrorReason::Parse(format!("Serde error: {}", e)))
+        })
+    }
+
+    fn from_array(elements: Vec<Self>) -> Self {
+        simd_json::BorrowedValue::Array(Box::new(elements))
+    }
+
+    fn from_object(entries: Vec<(String, Self)>) -> Self {
+        entries.into_iter().collect()
+    }
+
+    fn null() -> Self {
+        simd_json::BorrowedValue::Static(simd_json::StaticNode::Null)
+    }
+}
+
+/// Result of searching a `ValueLike` document.
+pub(crate) type ValueResult<'v, V> = Result<Cow<'v, V>, JmespathError>;
+
+/// Evaluates `node` against `data`.
+pub(crate) fn search<'v, V: ValueLike>(
+    node: &Ast,
+    data: &Cow<'v, V>,
+    ctx: &mut Context<'_>,
+) -> ValueResult<'v, V> {
+    enter(node, ctx)?;
+    let result = search_node(node, data, ctx);
+    ctx.depth -= 1;
+    result
+}
+
+fn search_node<'v, V: ValueLike>(
+    node: &Ast,
+    data: &Cow<'v, V>,
+    ctx: &mut Context<'_>,
+) -> ValueResult<'v, V> {
+    match *node {
+        Ast::Field { ref name, offset } => {
+            let ignore_case = ctx.options.case_insensitive_fields;
+            if ctx.options.strict {
+                let found = get_field(&**data, name, ignore_case).is_some();
+                check_field(data.jmespath_type(), found, name, offset, ctx)?;
+            }
+            Ok(child(data, |value| get_field(value, name, ignore_case)))
+        }
+        Ast::Subexpr {
+            ref lhs,
+            ref rhs,
+            offset,
+        } => {
+            let left = search(lhs, data, ctx)?;
+            if left.is_null() {
+                check_not_null(&Variable::Null, offset, ctx)?;
+            }
+            search(rhs, &left, ctx)
+        }
+        Ast::Identity { .. } => Ok(data.clone()),
+        Ast::Root { .. } => from_variable(&ctx.root.clone(), ctx),
+        Ast::Literal { ref value, .. } => from_variable(value, ctx),
+        Ast::Index { idx, offset } => {
+            let data = project_object(data.clone(), ctx);
+            if ctx.options.strict {
+                let length = data.elements().map_or(0, |elements| elements.len());
+                check_index(data.jmespath_type(), length, idx, offset, ctx)?;
+            }
+            Ok(child(&data, |value| {
+                let array = value.elements()?;
+                if idx >= 0 {
+                    array.get(idx as usize).copied()
+                } else {
+                    let i = array.len().checked_sub((-idx) as usize)?;
+                    Some(array[i])
+                }
+            }))
+        }
+        Ast::Or {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = search(lhs, data, ctx)?;
+            if left.is_truthy() {
+                Ok(left)
+            } else {
+                search(rhs, data, ctx)
+            }
+        }
+        Ast::And {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = search(lhs, data, ctx)?;
+            if !left.is_truthy() {
+                Ok(left)
+            } else {
+                search(rhs, data, ctx)
+            }
+        }
+        Ast::Not { ref node, .. } => {
+            let result = search(node, data, ctx)?;
+            from_variable(&Variable::Bool(!result.is_truthy()), ctx)
+        }
+        Ast::Condition {
+            ref predicate,
+            ref then,
+            ..
+        } => {
+            let condition = search(predicate, data, ctx)?;
+            if condition.is_truthy() {
+                search(then, data, ctx)
+            } else {
+                Ok(Cow::Owned(V::null()))
+            }
+        }
+        Ast::Comparison {
+            ref comparator,
+            ref lhs,
+            ref rhs,
+            ..
+        } => {
+            let left = search(lhs, data, ctx)?.to_variable()?;
+            let right = search(rhs, data, ctx)?.to_variable()?;
+            from_variable(&compare(comparator, &left, &right, ctx), ctx)
+        }
+        Ast::Arithmetic {
+            ref operator,
+            ref lhs,
+            ref rhs,
+            offset,
+        } => {
+            let left = Rcvar::new(search(lhs, data, ctx)?.to_variable()?);
+            let right = Rcvar::new(search(rhs, data, ctx)?.to_variable()?);
+            ctx.offset = offset;
+            from_variable(&*arithmetic(operator, &left, &right, ctx)?, ctx)
+        }
+        Ast::Let {
+            ref bindings,
+            ref body,
+            ..
+        } => {
+            let mut values = Vec::with_capacity(bindings.len());
+            for binding in bindings {
+                let value = search(&binding.value, data, ctx)?.to_variable()?;
+                values.push((binding.name.clone(), Rcvar::new(value)));
+            }
+            let len = ctx.scope.len();
+            ctx.scope.extend(values);
+            let result = search(body, data, ctx);
+            ctx.scope.truncate(len);
+            result
+        }
+        Ast::VariableRef { ref name, offset } => from_variable(&*variable(name, offset, ctx)?, ctx),
+        Ast::Unary {
+            ref operator,
+            ref node,
+            offset,
+        } => {
+            let value = Rcvar::new(search(node, data, ctx)?.to_variable()?);
+            ctx.offset = offset;
+            from_variable(&*unary(operator, &value, ctx)?, ctx)
+        }
+        Ast::ObjectValues { ref node, .. } => {
+            let object = search(node, data, ctx)?;
+            Ok(Cow::Owned(match object_values(&object) {
+                Some(values) => V::from_array(into_owned(values)),
+                None => V::null(),
+            }))
+        }
+        Ast::Projection {
+            ref lhs, ref rhs, ..
+        } => {
+            let left = search(lhs, data, ctx)?;
+            // A sliced string is not projected; the rhs applies to the substring.
+            if left.jmespath_type() == JmespathType::String && matches!(**lhs, Ast::Slice { .. }) {
+                return search(rhs, &left, ctx);
+            }
+            let left = project_object(left, ctx);
+            let elements = match elements(&left) {
+                Some(elements) => elements,
+                None => return Ok(Cow::Owned(V::null())),
+            };
+            let mut collected = vec![];
+            let mut seen = vec![];
+            for element in elements {
+                let current = search(rhs, &element, ctx)?;
+                if current.is_null() {
+                    continue;
+                }
+                if ctx.options.dedup_projections {
+                    let variable = current.to_variable()?;
+                    if seen.contains(&variable) {
+                        continue;
+                    }
+                    seen.push(variable);
+                }
+                collected.push(current);
+            }
+            Ok(Cow::Owned(V::from_array(into_owned(collected))))
+        }
+        Ast::Flatten { ref node, .. } => {
+            let value = project_object(search(node, data, ctx)?, ctx);
+            let outer = match elements(&value) {
+                Some(outer) => outer,
+                None => return Ok(Cow::Owned(V::null())),
+            };
+            let mut collected = vec![];
+            for element in outer {
+                match elements(&element) {
+                    Some(nested) => collected.extend(nested),
+                    None => collected.push(element),
+                }
+            }
+            Ok(Cow::Owned(V::from_array(into_owned(collected))))
+        }
+        Ast::MultiList { ref elements, .. } => {
+            if data.is_null() {
+                return Ok(Cow::Owned(V::null()));
+            }
+            let mut collected = vec![];
+            for node in elements {
+                collected.push(search(node, data, ctx)?.into_owned());
+            }
+            Ok(Cow::Owned(V::from_array(collected)))
+        }
+        Ast::MultiHash { ref elements, .. } => {
+            if data.is_null() {
+                return Ok(Cow::Owned(V::null()));
+            }
+            let mut collected = vec![];
+            for kvp in elements {
+                let value = search(&kvp.value, data, ctx)?.into_owned();
+                collected.push((kvp.key.clone(), value));
+            }
+            Ok(Cow::Owned(V::from_object(collected)))
+        }
+        Ast::Function {
+            ref name,
+            ref args,
+            offset,
+        } => {
+            let mut fn_args = vec![];
+            let mut current = None;
+            for arg in args {
+                let arg = match *arg {
+                    Ast::Expref { ref ast, .. } => {
+                        // Only functions taking expression references have
+                        // a use for the current value, so it is only
+                        // converted for them.
+                        if current.is_none() {
+                            current = Some(Rcvar::new(data.to_variable()?));
+                        }
+                        Variable::Expref(*ast.clone())
+                    }
+                    _ => search(arg, data, ctx)?.to_variable()?,
+                };
+                fn_args.push(Rcvar::new(arg));
+            }
+            let current = current.unwrap_or_else(|| Rcvar::new(Variable::Null));
+            from_variable(&*call_function(name, &fn_args, &current, offset, ctx)?, ctx)
+        }
+        Ast::Expref { ref ast, .. } => from_variable(&Variable::Expref(*ast.clone()), ctx),
+        Ast::Slice {
+            start,
+            stop,
+            step,
+            offset,
+        } => {
+            if step == 0 {
+                ctx.offset = offset;
+                let reason = ErrorReason::Runtime(RuntimeError::InvalidSlice);
+                return Err(JmespathError::from_ctx(ctx, reason));
+            }
+            if ctx.slices_strings() && data.jmespath_type() == JmespathType::String {
+                if let Variable::String(s) = data.to_variable()? {
+                    let sliced = Variable::String(slice_str(&s, start, stop, step));
+                    return from_variable(&sliced, ctx);
+                }
+            }
+            let data = project_object(data.clone(), ctx);
+            if ctx.options.strict {
+                check_array(data.jmespath_type(), offset, ctx)?;
+            }
+            Ok(Cow::Owned(match elements(&data) {
+                Some(elements) => V::from_array(into_owned(slice(&elements, start, stop, step))),
+                None => V::null(),
+            }))
+        }
+    }
+}
+
+/// Gets a field of a value, ignoring case when `ignore_case` is set.
+fn get_field<'x, V: ValueLike>(value: &'x V, name: &str, ignore_case: bool) -> Option<&'x V> {
+    if ignore_case {
+        value.get_field_ignore_case(name)
+    } else {
+        value.get_field(name)
+    }
+}
+
+/// Returns a child of `data`, borrowing it when `data` is borrowed.
+fn child<'v, V, F>(data: &Cow<'v, V>, get: F) -> Cow<'v, V>
+where
+    V: ValueLike,
+    F: for<'x> Fn(&'x V) -> Option<&'x V>,
+{
+    match *data {
+        Cow::Borrowed(value) => get(value).map_or(Cow::Owned(V::null()), Cow::Borrowed),
+        Cow::Owned(ref value) => Cow::Owned(get(value).cloned().unwrap_or_else(V::null)),
+    }
+}
+
+/// Returns the elements of an array, or None if the value is not an array.
+fn elements<'v, V: ValueLike>(value: &Cow<'v, V>) -> Option<Vec<Cow<'v, V>>> {
+    match *value {
+        Cow::Borrowed(value) => Some(value.elements()?.into_iter().map(Cow::Borrowed).collect()),
+        Cow::Owned(ref value) => Some(owned(value.elements()?)),
+    }
+}
+
+/// Returns the values of an object ordered by key, or None if the value
+/// is not an object.
+fn object_values<'v, V: ValueLike>(value: &Cow<'v, V>) -> Option<Vec<Cow<'v, V>>> {
+    match *value {
+        Cow::Borrowed(value) => Some(
+            value
+                .object_values()?
+                .into_iter()
+                .map(Cow::Borrowed)
+                .collect(),
+        ),
+        Cow::Owned(ref value) => Some(owned(value.object_values()?)),
+    }
+}
+
+fn owned<'v, V: ValueLike>(values: Vec<&V>) -> Vec<Cow<'v, V>> {
+    values
+        .into_iter()
+        .map(|value| Cow::Owned(value.clone()))
+        .collect()
+}
+
+/// Converts an object into an array of its values when
+/// `SearchOptions::project_objects` is enabled.
+fn project_object<'v, V: ValueLike>(value: Cow<'v, V>, ctx: &Context<'_>) -> Cow<'v, V> {
+    if !ctx.options.project_objects {
+        return value;
+    }
+    match object_values(&value) {
+        Some(values) => Cow::Owned(V::from_array(into_owned(values))),
+        None => value,
+    }
+}
+
+fn into_owned<V: ValueLike>(values: Vec<Cow<'_, V>>) -> Vec<V> {
+    values.into_iter().map(Cow::into_owned).collect()
+}
+
+/// Converts a value produced by the interpreter, reporting errors at the
+/// node being evaluated.
+fn from_variable<'v, V: ValueLike>(value: &Variable, ctx: &Context<'_>) -> ValueResult<'v, V> {
+    V::from_variable(value)
+        .map(Cow::Owned)
+        .map_err(|e| JmespathError::from_ctx(ctx, e.reason))
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::convert::TryFrom;
+
+    use serde_json::{json, Value};
+
+    use crate::{compile, SearchOptions, Variable};
+
+    #[test]
+    fn matches_the_interpreter() {
+        let data = json!({
+            "a": [{"b": 1, "c": "x"}, {"b": 2}, {"b": 2, "c": ""}, [3, [4]]],
+            "o": {"y": 2, "x": 1},
+            "s": "abc"
+        });
+        let expressions = [
+            "a[*].b",
+            "a[?b == `2`].c",
+            "a[-1][]",
+            "a[::-2]",
+            "o.*",
+            "o.x || o.z",
+            "!s && a[0]",
+            "{x: o.x, l: length(a)}",
+            "[s, `true`]",
+            "sort_by(a[:3], &b)[*].b",
+            "a[*].b | [0] // `2`",
+            "a[3][1][0]",
+            "o | keys(@)",
+            "a[?c]",
+            "let $y = o.y in a[?b == $y].c",
+            "a[?b == $.o.y].c",
+            "[s[1:], s[::-1]]",
+        ];
+        let variable = Variable::try_from(&data).unwrap();
+        for expression in expressions.iter() {
+            let expr = compile(expression).unwrap();
+            let expected = expr.search(variable.clone()).unwrap().to_string();
+            let actual = expr.search_json(&data).unwrap();
+            assert_eq!(
+                expected,
+                Variable::try_from(&*actual).unwrap().to_string(),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn borrows_results_from_the_document() {
+        let data = json!({"a": {"b": [1, 2, 3]}});
+        let expr = compile("a.b").unwrap();
+        match expr.search_json(&data).unwrap() {
+            Cow::Borrowed(value) => assert!(std::ptr::eq(value, &data["a"]["b"])),
+            Cow::Owned(value) => panic!("expected a borrowed result: {}", value),
+        }
+        let result = compile("a.b[*]").unwrap().search_json(&data).unwrap();
+        assert!(matches!(result, Cow::Owned(Value::Array(_))));
+    }
+
+    #[test]
+    fn honors_search_options() {
+        let data = json!({"a": [{"b": 1}, {"b": 1}, {"b": 2}], "o": {"k": 1}});
+        let options = SearchOptions {
+            dedup_projections: true,
+            project_objects: true,
+            ..SearchOptions::default()
+        };
+        let search = |expression: &str| {
+            compile(expression)
+                .unwrap()
+                .search_json_with_options(&data, &options)
+                .unwrap()
+                .into_owned()
+        };
+        assert_eq!(json!([1, 2]), search("a[*].b"));
+        assert_eq!(json!([1]), search("o[*]"));
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn searches_simd_json_documents() {
+        let mut bytes =
+            br#"{"a": [{"b": 1, "c": "x"}, {"b": -2.5}], "o": {"y": 2, "x": 1}}"#.to_vec();
+        let data = simd_json::to_borrowed_value(&mut bytes).unwrap();
+        let search = |expression: &str| {
+            let result = compile(expression).unwrap().search_value(&data).unwrap();
+            crate::value::ValueLike::to_variable(&*result)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!("[1,-2.5]", search("a[*].b"));
+        assert_eq!("[1,2]", search("o.*"));
+        assert_eq!(r#""x""#, search("a[?b > `0`].c | [0]"));
+        assert_eq!(r#"{"n":2}"#, search("{n: length(a)}"));
+    }
+}
@@ -18,7 +18,11 @@ use serde_json::Number;
 use std::convert::TryFrom;
 
 /// JMESPath types.
-#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
+///
+/// Crate features add variants, so matches outside this crate need a
+/// wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[non_exhaustive]
 pub enum JmespathType {
     Null,
     String,
@@ -27,6 +31,15 @@ pub enum JmespathType {
     Array,
     Object,
     Expref,
+    /// A point in time. Requires the `datetime` feature.
+    #[cfg(feature = "datetime")]
+    DateTime,
+    /// Binary data. Requires the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    Bytes,
+    /// A value provided by the embedding application, identified by the
+    /// name returned by `JmesExternal::type_name`.
+    External(&'static str),
 }
 
 impl fmt::Display for JmespathType {
@@ -42,13 +55,112 @@ impl fmt::Display for JmespathType {
                 JmespathType::Array => "array",
                 JmespathType::Object => "object",
                 JmespathType::Expref => "expref",
+                #[cfg(feature = "datetime")]
+                JmespathType::DateTime => "datetime",
+                #[cfg(feature = "bytes")]
+                JmespathType::Bytes => "bytes",
+                JmespathType::External(name) => name,
             }
         )
     }
 }
 
+/// A domain value provided by an embedding application.
+///
+/// External values flow through expressions like any other value, so they
+/// can be produced and consumed by custom functions (e.g., decimals, IP
+/// addresses, or handles) without converting them to JSON.
+///
+/// ```
+/// use std::any::Any;
+/// use jmespath::{JmesExternal, Variable};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Port(u16);
+///
+/// impl JmesExternal for Port {
+///     fn type_name(&self) -> &'static str {
+///         "port"
+///     }
+///
+///     fn eq_external(&self, other: &dyn JmesExternal) -> bool {
+///         other.as_any().downcast_ref::<Port>() == Some(self)
+///     }
+///
+///     fn to_json(&self) -> serde_json::Value {
+///         self.0.into()
+///     }
+///
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+/// }
+///
+/// let port = Variable::external(Port(8080));
+/// assert_eq!("port", port.get_type().to_string());
+/// assert_eq!(port, Variable::external(Port(8080)));
+/// assert_eq!("8080", port.to_string());
+/// ```
+pub trait JmesExternal: fmt::Debug + Send + Sync {
+    /// Name returned by the `type()` function for the value. Values that
+    /// return the same name are considered to be of the same type.
+    fn type_name(&self) -> &'static str;
+
+    /// Returns true if the value is equal to another external value.
+    fn eq_external(&self, other: &dyn JmesExternal) -> bool;
+
+    /// Returns true if the value is considered truthy. Defaults to true.
+    fn is_truthy(&self) -> bool {
+        true
+    }
+
+    /// Converts the value to JSON when it is serialized.
+    fn to_json(&self) -> Value;
+
+    /// Returns the value as `Any` so that it can be downcast.
+    fn as_any(&self) -> &dyn std::any::Any;
+}
+
+/// `Rc` reference counted external value.
+#[cfg(not(feature = "sync"))]
+pub type RcExternal = std::rc::Rc<dyn JmesExternal>;
+/// `Arc` reference counted external value.
+#[cfg(feature = "sync")]
+pub type RcExternal = std::sync::Arc<dyn JmesExternal>;
+
+/// A step of a path from a Variable to one of its descendants.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathSegment {
+    /// The value of a key of an object.
+    Key(String),
+    /// An element of an array.
+    Index(usize),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_owned())
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
 /// JMESPath variable.
+///
+/// Crate features add variants, so matches outside this crate need a
+/// wildcard arm.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Variable {
     Null,
     String(String),
@@ -57,6 +169,39 @@ pub enum Variable {
     Array(Vec<Rcvar>),
     Object(BTreeMap<String, Rcvar>),
     Expref(Ast),
+    /// A point in time with a UTC offset. Requires the `datetime` feature.
+    ///
+    /// Datetimes have the type `datetime`, are always truthy, and are
+    /// serialized as RFC 3339 strings. Two datetimes are equal and ordered
+    /// by the instant they represent, regardless of their offsets.
+    #[cfg(feature = "datetime")]
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+    /// Binary data. Requires the `bytes` feature.
+    ///
+    /// Bytes have the type `bytes` and are truthy unless empty. They are
+    /// serialized as base64 strings by human-readable formats such as JSON,
+    /// and as byte strings by binary formats such as MessagePack and CBOR.
+    #[cfg(feature = "bytes")]
+    Bytes(Vec<u8>),
+    /// An exact decimal number. Requires the `decimal` feature.
+    ///
+    /// Decimals have the type `number` and can be passed to any function
+    /// that accepts numbers, which see them as an `f64`. Comparisons and
+    /// arithmetic involving a decimal are exact: the other operand is
+    /// converted to a decimal from its shortest decimal representation.
+    /// Decimals are serialized as strings so that no precision is lost.
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+    /// An integer outside the range of `i64` and `u64`. Requires the
+    /// `big-integers` feature.
+    ///
+    /// Big integers have the type `number`. Integers that fit in an `i64` or
+    /// a `u64` are always stored as a `Variable::Number`; use
+    /// `Variable::from_i128` to choose the representation automatically.
+    #[cfg(feature = "big-integers")]
+    BigInt(i128),
+    /// A value provided by the embedding application.
+    External(RcExternal),
 }
 
 impl Eq for Variable {}
@@ -90,7 +235,19 @@ impl PartialEq for Variable {
         if self.get_type() != other.get_type() {
             false
         } else {
+            #[cfg(feature = "big-integers")]
+            if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+                return a == b;
+            }
+            #[cfg(feature = "decimal")]
+            if let Some(ordering) = self.cmp_decimal(other) {
+                return ordering == Ordering::Equal;
+            }
             match self {
+                #[cfg(feature = "decimal")]
+                Variable::Decimal(_) => false,
+                #[cfg(feature = "big-integers")]
+                Variable::BigInt(a) => other.as_number().is_some_and(|b| float_eq(*a as f64, b)),
                 Variable::Number(a) => {
                     if let (Some(a), Some(b)) = (a.as_f64(), other.as_number()) {
                         float_eq(a, b)
@@ -103,6 +260,13 @@ impl PartialEq for Variable {
                 Variable::Array(ref a) => Some(a) == other.as_array(),
                 Variable::Object(ref o) => Some(o) == other.as_object(),
                 Variable::Expref(ref e) => Some(e) == other.as_expref(),
+                #[cfg(feature = "datetime")]
+                Variable::DateTime(ref d) => Some(d) == other.as_datetime(),
+                #[cfg(feature = "bytes")]
+                Variable::Bytes(ref b) => Some(b.as_slice()) == other.as_bytes(),
+                Variable::External(ref e) => other
+                    .as_external()
+                    .is_some_and(|other| e.eq_external(&**other)),
                 Variable::Null => true,
             }
         }
@@ -150,12 +314,36 @@ impl Ord for Variable {
                     }
                 }
                 JmespathType::Number => {
+                    #[cfg(feature = "big-integers")]
+                    if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+                        return a.cmp(&b);
+                    }
+                    #[cfg(feature = "decimal")]
+                    if let Some(ordering) = self.cmp_decimal(other) {
+                        return ordering;
+                    }
                     if let (Some(a), Some(b)) = (self.as_number(), other.as_number()) {
                         a.partial_cmp(&b).unwrap_or(Ordering::Less)
                     } else {
                         Ordering::Equal
                     }
                 }
+                #[cfg(feature = "datetime")]
+                JmespathType::DateTime => {
+                    if let (Some(a), Some(b)) = (self.as_datetime(), other.as_datetime()) {
+                        a.cmp(b)
+                    } else {
+                        Ordering::Equal
+                    }
+                }
+                #[cfg(feature = "bytes")]
+                JmespathType::Bytes => {
+                    if let (Some(a), Some(b)) = (self.as_bytes(), other.as_bytes()) {
+                        a.cmp(b)
+                    } else {
+                        Ordering::Equal
+                    }
+                }
                 _ => Ordering::Equal,
             }
         }
@@ -231,6 +419,255 @@ impl TryFrom<Value> for Variable {
     }
 }
 
+/// Convert a borrowed YAML value to a Variable.
+///
+/// Tags are ignored and scalar mapping keys are converted to strings.
+/// Requires the `yaml` feature.
+#[cfg(feature = "yaml")]
+impl<'a> TryFrom<&'a serde_yaml::Value> for Variable {
+    type Error = JmespathError;
+
+    fn try_from(value: &'a serde_yaml::Value) -> Result<Self, Self::Error> {
+        use serde_yaml::Value as Yaml;
+        let error = |message: String| JmespathError::new("", 0, crate::ErrorReason::Parse(message));
+        let var = match *value {
+            Yaml::Null => Variable::Null,
+            Yaml::Bool(b) => Variable::Bool(b),
+            Yaml::Number(ref n) => {
+                let number = if let Some(i) = n.as_i64() {
+                    Some(Number::from(i))
+                } else if let Some(u) = n.as_u64() {
+                    Some(Number::from(u))
+                } else {
+                    n.as_f64().and_then(Number::from_f64)
+                };
+                Variable::Number(
+                    number.ok_or_else(|| error(format!("Cannot represent {} as a number", n)))?,
+                )
+            }
+            Yaml::String(ref s) => Variable::String(s.to_owned()),
+            Yaml::Sequence(ref values) => Variable::Array(
+                values
+                    .iter()
+                    .map(|v| Variable::try_from(v).map(Rcvar::new))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+            Yaml::Mapping(ref values) => {
+                let mut map = BTreeMap::new();
+                for (key, value) in values {
+                    let key = match *key {
+                        Yaml::String(ref s) => s.to_owned(),
+                        Yaml::Number(ref n) => n.to_string(),
+                        Yaml::Bool(b) => b.to_string(),
+                        Yaml::Null => "null".to_owned(),
+                        _ => return Err(error("Mapping keys must be scalars".to_owned())),
+                    };
+                    map.insert(key, Rcvar::new(Variable::try_from(value)?));
+                }
+                Variable::Object(map)
+            }
+            Yaml::Tagged(ref tagged) => Variable::try_from(&tagged.value)?,
+        };
+        Ok(var)
+    }
+}
+
+/// Convert a borrowed TOML value to a Variable.
+///
+/// Tables become objects and datetimes become strings in TOML's own format.
+/// Requires the `toml` feature.
+#[cfg(feature = "toml")]
+impl<'a> TryFrom<&'a toml::Value> for Variable {
+    type Error = JmespathError;
+
+    fn try_from(value: &'a toml::Value) -> Result<Self, Self::Error> {
+        use toml::Value as Toml;
+        let var = match *value {
+            Toml::String(ref s) => Variable::String(s.to_owned()),
+            Toml::Integer(i) => Variable::Number(Number::from(i)),
+            Toml::Float(f) => Variable::Number(Number::from_f64(f).ok_or_else(|| {
+                JmespathError::new(
+                    "",
+                    0,
+                    crate::ErrorReason::Parse(format!("Cannot represent {} as a number", f)),
+                )
+            })?),
+            Toml::Boolean(b) => Variable::Bool(b),
+            Toml::Datetime(ref d) => Variable::String(d.to_string()),
+            Toml::Array(ref values) => Variable::Array(
+                values
+                    .iter()
+                    .map(|v| Variable::try_from(v).map(Rcvar::new))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+            Toml::Table(ref values) => Variable::Object(
+                values
+                    .iter()
+                    .map(|(k, v)| Ok((k.to_owned(), Rcvar::new(Variable::try_from(v)?))))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+        };
+        Ok(var)
+    }
+}
+
+/// Convert a borrowed BSON value to a Variable.
+///
+/// ObjectIds become hex strings, binary data becomes a base64 string, and
+/// datetimes become `Variable::DateTime` values when the `datetime` feature
+/// is enabled and RFC 3339 strings otherwise. Types with no JSON equivalent,
+/// such as regular expressions and timestamps, become their relaxed Extended
+/// JSON objects. Requires the `bson` feature.
+#[cfg(feature = "bson")]
+impl<'a> TryFrom<&'a bson::Bson> for Variable {
+    type Error = JmespathError;
+
+    fn try_from(value: &'a bson::Bson) -> Result<Self, Self::Error> {
+        use bson::Bson;
+        let var = match *value {
+            Bson::Null | Bson::Undefined => Variable::Null,
+            Bson::Boolean(b) => Variable::Bool(b),
+            Bson::Int32(i) => Variable::Number(Number::from(i)),
+            Bson::Int64(i) => Variable::Number(Number::from(i)),
+            Bson::Double(f) => Variable::Number(Number::from_f64(f).ok_or_else(|| {
+                JmespathError::new(
+                    "",
+                    0,
+                    crate::ErrorReason::Parse(format!("Cannot represent {} as a number", f)),
+                )
+            })?),
+            Bson::Decimal128(ref d) => {
+                let s = d.to_string();
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map_or(Variable::String(s), Variable::Number)
+            }
+            Bson::String(ref s) | Bson::Symbol(ref s) | Bson::JavaScriptCode(ref s) => {
+                Variable::String(s.to_owned())
+            }
+            Bson::ObjectId(ref id) => Variable::String(id.to_hex()),
+            #[cfg(feature = "datetime")]
+            Bson::DateTime(d) => {
+                match chrono::DateTime::from_timestamp_millis(d.timestamp_millis()) {
+                    Some(d) => Variable::DateTime(d.fixed_offset()),
+                    None => Variable::Number(Number::from(d.timestamp_millis())),
+                }
+            }
+            #[cfg(not(feature = "datetime"))]
+            Bson::DateTime(d) => match d.try_to_rfc3339_string() {
+                Ok(s) => Variable::String(s),
+                Err(_) => Variable::Number(Number::from(d.timestamp_millis())),
+            },
+            #[cfg(feature = "bytes")]
+            Bson::Binary(ref b) => Variable::Bytes(b.bytes.clone()),
+            #[cfg(not(feature = "bytes"))]
+            Bson::Binary(ref b) => {
+                let json = Bson::Binary(b.clone()).into_relaxed_extjson();
+                Variable::String(json["$binary"]["base64"].as_str().unwrap_or("").to_owned())
+            }
+            Bson::Array(ref values) => Variable::Array(
+                values
+                    .iter()
+                    .map(|v| Variable::try_from(v).map(Rcvar::new))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+            Bson::Document(ref document) => Variable::try_from(document)?,
+            _ => Variable::try_from(value.clone().into_relaxed_extjson())?,
+        };
+        Ok(var)
+    }
+}
+
+/// Convert a borrowed BSON document to a Variable object.
+///
+/// Requires the `bson` feature.
+#[cfg(feature = "bson")]
+impl<'a> TryFrom<&'a bson::Document> for Variable {
+    type Error = JmespathError;
+
+    fn try_from(document: &'a bson::Document) -> Result<Self, Self::Error> {
+        Ok(Variable::Object(
+            document
+                .iter()
+                .map(|(k, v)| Ok((k.to_owned(), Rcvar::new(Variable::try_from(v)?))))
+                .collect::<Result<_, JmespathError>>()?,
+        ))
+    }
+}
+
+/// Convert a borrowed Variable to a BSON value.
+///
+/// Integers that fit in an `i32` become `Int32` values, other integers
+/// become `Int64` values, and datetimes become BSON datetimes. Requires the
+/// `bson` feature.
+#[cfg(feature = "bson")]
+impl<'a> TryFrom<&'a Variable> for bson::Bson {
+    type Error = JmespathError;
+
+    fn try_from(value: &'a Variable) -> Result<Self, Self::Error> {
+        use bson::Bson;
+        let bson = match *value {
+            Variable::Null => Bson::Null,
+            Variable::Bool(b) => Bson::Boolean(b),
+            Variable::String(ref s) => Bson::String(s.to_owned()),
+            Variable::Number(ref n) => match n.as_i64() {
+                Some(i) => i32::try_from(i).map_or(Bson::Int64(i), Bson::Int32),
+                None => Bson::Double(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Variable::Array(ref values) => Bson::Array(
+                values
+                    .iter()
+                    .map(|v| Bson::try_from(&**v))
+                    .collect::<Result<_, JmespathError>>()?,
+            ),
+            Variable::Object(_) => Bson::Document(bson::Document::try_from(value)?),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(d) => {
+                Bson::DateTime(bson::DateTime::from_millis(d.timestamp_millis()))
+            }
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) => Bson::Binary(bson::Binary {
+                subtype: bson::spec::BinarySubtype::Generic,
+                bytes: b.clone(),
+            }),
+            _ => bson::to_bson(value).map_err(|e| {
+                JmespathError::new(
+                    "",
+                    0,
+                    crate::ErrorReason::Parse(format!("BSON error: {}", e)),
+                )
+            })?,
+        };
+        Ok(bson)
+    }
+}
+
+/// Convert a borrowed Variable object to a BSON document.
+///
+/// Requires the `bson` feature.
+#[cfg(feature = "bson")]
+impl<'a> TryFrom<&'a Variable> for bson::Document {
+    type Error = JmespathError;
+
+    fn try_from(value: &'a Variable) -> Result<Self, Self::Error> {
+        match *value {
+            Variable::Object(ref values) => values
+                .iter()
+                .map(|(k, v)| Ok((k.to_owned(), bson::Bson::try_from(&**v)?)))
+                .collect(),
+            _ => Err(JmespathError::new(
+                "",
+                0,
+                crate::ErrorReason::Parse(format!(
+                    "Cannot convert {} to a BSON document",
+                    value.get_type()
+                )),
+            )),
+        }
+    }
+}
+
 impl Variable {
     /// Shortcut function to encode a `T` into a JMESPath `Variable`
     pub fn from_serializable<T>(value: T) -> Result<Variable, JmespathError>
@@ -245,6 +682,141 @@ impl Variable {
         serde_json::from_str::<Variable>(s).map_err(|e| e.to_string())
     }
 
+    /// Create a JMESPath Variable from a YAML encoded string.
+    ///
+    /// A stream of several YAML documents produces an array with one element
+    /// per document. Requires the `yaml` feature.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_yaml("a: [1, b]").unwrap();
+    /// assert_eq!(Variable::from_json(r#"{"a": [1, "b"]}"#).unwrap(), value);
+    /// let value = Variable::from_yaml("kind: Service\n---\nkind: Deployment\n").unwrap();
+    /// assert_eq!(r#"[{"kind":"Service"},{"kind":"Deployment"}]"#, value.to_string());
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(s: &str) -> Result<Self, String> {
+        let mut documents = serde_yaml::Deserializer::from_str(s)
+            .map(|document| {
+                let value = serde_yaml::Value::deserialize(document).map_err(|e| e.to_string())?;
+                Variable::try_from(&value).map_err(|e| e.to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(match documents.len() {
+            0 => Variable::Null,
+            1 => documents.remove(0),
+            _ => Variable::Array(documents.into_iter().map(Rcvar::new).collect()),
+        })
+    }
+
+    /// Create a JMESPath Variable from a TOML document.
+    ///
+    /// The document's root table becomes an object. Requires the `toml`
+    /// feature.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_toml("[package]\nname = \"jmespath\"\n").unwrap();
+    /// assert_eq!(r#"{"package":{"name":"jmespath"}}"#, value.to_string());
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> Result<Self, String> {
+        let table = toml::from_str::<toml::Table>(s).map_err(|e| e.to_string())?;
+        Variable::try_from(&toml::Value::Table(table)).map_err(|e| e.to_string())
+    }
+
+    /// Serializes the Variable as a YAML document.
+    ///
+    /// Requires the `yaml` feature.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_json(r#"{"a": [1, "b"]}"#).unwrap();
+    /// assert_eq!("a:\n- 1\n- b\n", value.to_yaml_string().unwrap());
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml_string(&self) -> Result<String, String> {
+        serde_yaml::to_string(self).map_err(|e| e.to_string())
+    }
+
+    /// Create a JMESPath Variable from a MessagePack encoded value.
+    ///
+    /// Requires the `msgpack` feature.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, String> {
+        rmp_serde::from_slice(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Serializes the Variable as MessagePack.
+    ///
+    /// Requires the `msgpack` feature.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_json(r#"{"a": [1, "b"]}"#).unwrap();
+    /// let bytes = value.to_msgpack().unwrap();
+    /// assert_eq!(value, Variable::from_msgpack(&bytes).unwrap());
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, String> {
+        rmp_serde::to_vec(self).map_err(|e| e.to_string())
+    }
+
+    /// Create a JMESPath Variable from a CBOR encoded value.
+    ///
+    /// Requires the `cbor` feature.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, String> {
+        ciborium::from_reader(bytes).map_err(|e| e.to_string())
+    }
+
+    /// Serializes the Variable as CBOR.
+    ///
+    /// Requires the `cbor` feature.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_json(r#"{"a": [1, "b"]}"#).unwrap();
+    /// let bytes = value.to_cbor().unwrap();
+    /// assert_eq!(value, Variable::from_cbor(&bytes).unwrap());
+    /// ```
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes).map_err(|e| e.to_string())?;
+        Ok(bytes)
+    }
+
+    /// Writes the Variable to `writer` as JSON Lines.
+    ///
+    /// Each element of an array is written as JSON followed by a newline,
+    /// one element at a time, so the array is never serialized as a whole.
+    /// Any other value is written as a single line.
+    ///
+    /// ```
+    /// use jmespath::Variable;
+    ///
+    /// let value = Variable::from_json(r#"[{"a": 1}, "b"]"#).unwrap();
+    /// let mut out = Vec::new();
+    /// value.write_json_lines(&mut out).unwrap();
+    /// assert_eq!("{\"a\":1}\n\"b\"\n", String::from_utf8(out).unwrap());
+    /// ```
+    pub fn write_json_lines<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let mut write_line = |value: &Variable| -> std::io::Result<()> {
+            serde_json::to_writer(&mut writer, value)?;
+            writer.write_all(b"\n")
+        };
+        match self {
+            Variable::Array(ref array) => array.iter().try_for_each(|v| write_line(v)),
+            value => write_line(value),
+        }
+    }
+
     /// Returns true if the Variable is an Array. Returns false otherwise.
     pub fn is_array(&self) -> bool {
         self.as_array().is_some()
@@ -289,7 +861,14 @@ impl Variable {
 
     /// Returns true if the value is a Number. Returns false otherwise.
     pub fn is_number(&self) -> bool {
-        matches!(self, Variable::Number(_))
+        match self {
+            Variable::Number(_) => true,
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(_) => true,
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(_) => true,
+            _ => false,
+        }
     }
 
     /// If the value is a number, return or cast it to a f64.
@@ -297,10 +876,81 @@ impl Variable {
     pub fn as_number(&self) -> Option<f64> {
         match self {
             Variable::Number(f) => f.as_f64(),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(d) => rust_decimal::prelude::ToPrimitive::to_f64(d),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// Creates an integer Variable.
+    ///
+    /// Returns a `Variable::Number` if the value fits in an `i64` or a `u64`,
+    /// and a `Variable::BigInt` otherwise.
+    #[cfg(feature = "big-integers")]
+    pub fn from_i128(value: i128) -> Variable {
+        if let Ok(i) = i64::try_from(value) {
+            Variable::Number(Number::from(i))
+        } else if let Ok(u) = u64::try_from(value) {
+            Variable::Number(Number::from(u))
+        } else {
+            Variable::BigInt(value)
+        }
+    }
+
+    /// If the value is an integer, returns it as an `i128`.
+    ///
+    /// Returns None if the value is not a number or is a number with a
+    /// fractional part or exponent (e.g., `1.0`).
+    #[cfg(feature = "big-integers")]
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Variable::Number(n) => n
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from)),
+            Variable::BigInt(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the value is a Decimal. Returns false otherwise.
+    #[cfg(feature = "decimal")]
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Variable::Decimal(_))
+    }
+
+    /// If the value is a number, returns it as a decimal.
+    ///
+    /// Numbers that are not decimals are converted from their shortest
+    /// decimal representation, so `0.1` becomes exactly `0.1`. Returns None
+    /// if the value is not a number or is out of the range of a decimal.
+    #[cfg(feature = "decimal")]
+    pub fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        match self {
+            Variable::Decimal(d) => Some(*d),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(i) => rust_decimal::Decimal::try_from_i128_with_scale(*i, 0).ok(),
+            Variable::Number(n) => n
+                .as_i64()
+                .map(rust_decimal::Decimal::from)
+                .or_else(|| n.to_string().parse().ok())
+                .or_else(|| rust_decimal::Decimal::from_scientific(&n.to_string()).ok()),
             _ => None,
         }
     }
 
+    /// Compares two numbers exactly if at least one of them is a decimal.
+    #[cfg(feature = "decimal")]
+    fn cmp_decimal(&self, other: &Variable) -> Option<Ordering> {
+        if self.is_decimal() || other.is_decimal() {
+            Some(self.as_decimal()?.cmp(&other.as_decimal()?))
+        } else {
+            None
+        }
+    }
+
     /// Returns true if the value is a Boolean. Returns false otherwise.
     pub fn is_boolean(&self) -> bool {
         self.as_boolean().is_some()
@@ -329,6 +979,61 @@ impl Variable {
         }
     }
 
+    /// Returns true if the value is a DateTime. Returns false otherwise.
+    #[cfg(feature = "datetime")]
+    pub fn is_datetime(&self) -> bool {
+        self.as_datetime().is_some()
+    }
+
+    /// If the value is a DateTime, returns the associated datetime.
+    /// Returns None otherwise.
+    #[cfg(feature = "datetime")]
+    pub fn as_datetime(&self) -> Option<&chrono::DateTime<chrono::FixedOffset>> {
+        match self {
+            Variable::DateTime(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the value is Bytes. Returns false otherwise.
+    #[cfg(feature = "bytes")]
+    pub fn is_bytes(&self) -> bool {
+        self.as_bytes().is_some()
+    }
+
+    /// If the value is Bytes, returns the associated bytes.
+    /// Returns None otherwise.
+    #[cfg(feature = "bytes")]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Variable::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Creates a Variable that wraps an external value.
+    pub fn external<T: JmesExternal + 'static>(value: T) -> Variable {
+        #[cfg(not(feature = "sync"))]
+        let value = std::rc::Rc::new(value);
+        #[cfg(feature = "sync")]
+        let value = std::sync::Arc::new(value);
+        Variable::External(value)
+    }
+
+    /// Returns true if the value is an External value. Returns false otherwise.
+    pub fn is_external(&self) -> bool {
+        self.as_external().is_some()
+    }
+
+    /// If the value is an External value, returns the associated value.
+    /// Returns None otherwise.
+    pub fn as_external(&self) -> Option<&RcExternal> {
+        match self {
+            Variable::External(e) => Some(e),
+            _ => None,
+        }
+    }
+
     /// Returns true if the value is an expression reference.
     /// Returns false otherwise.
     pub fn is_expref(&self) -> bool {
@@ -390,6 +1095,15 @@ impl Variable {
             Variable::Array(ref a) => !a.is_empty(),
             Variable::Object(ref o) => !o.is_empty(),
             Variable::Number(_) => true,
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(_) => true,
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) => !b.is_empty(),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(_) => true,
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(_) => true,
+            Variable::External(ref e) => e.is_truthy(),
             _ => false,
         }
     }
@@ -404,16 +1118,27 @@ impl Variable {
             Variable::Object(_) => JmespathType::Object,
             Variable::Null => JmespathType::Null,
             Variable::Expref(_) => JmespathType::Expref,
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(_) => JmespathType::DateTime,
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(_) => JmespathType::Bytes,
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(_) => JmespathType::Number,
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(_) => JmespathType::Number,
+            Variable::External(ref e) => JmespathType::External(e.type_name()),
         }
     }
 
     /// Compares two Variable values using a comparator.
     pub fn compare(&self, cmp: &Comparator, value: &Variable) -> Option<bool> {
-        // Ordering requires numeric values.
-        if !(self.is_number() && value.is_number()
-            || *cmp == Comparator::NotEqual
-            || *cmp == Comparator::Equal)
-        {
+        // Ordering requires numeric (or datetime) values.
+        #[cfg(feature = "datetime")]
+        let ordered =
+            self.is_number() && value.is_number() || self.is_datetime() && value.is_datetime();
+        #[cfg(not(feature = "datetime"))]
+        let ordered = self.is_number() && value.is_number();
+        if !(ordered || *cmp == Comparator::NotEqual || *cmp == Comparator::Equal) {
             return None;
         }
         match *cmp {
@@ -430,6 +1155,43 @@ impl Variable {
     pub fn slice(&self, start: Option<i32>, stop: Option<i32>, step: i32) -> Option<Vec<Rcvar>> {
         self.as_array().map(|a| slice(a, start, stop, step))
     }
+
+    /// Returns a mutable reference to the descendant at `path`.
+    ///
+    /// Every shared value along the path is cloned using `Rcvar::make_mut`
+    /// before descending into it, so only the values on the path are copied
+    /// while their siblings remain shared. Returns None if the path does not
+    /// exist, in which case the values visited so far may have been copied.
+    ///
+    /// ```
+    /// use jmespath::{PathSegment, Variable};
+    ///
+    /// let mut value = Variable::from_json(r#"{"a": [{"b": 1}]}"#).unwrap();
+    /// let path = ["a".into(), 0.into(), "b".into()];
+    /// *value.get_mut_path(&path).unwrap() = Variable::Bool(true);
+    /// assert_eq!(r#"{"a":[{"b":true}]}"#, value.to_string());
+    /// assert!(value.get_mut_path(&[PathSegment::Index(0)]).is_none());
+    /// ```
+    pub fn get_mut_path(&mut self, path: &[PathSegment]) -> Option<&mut Variable> {
+        let mut current = self;
+        for segment in path {
+            let child = match (current, segment) {
+                (Variable::Object(map), PathSegment::Key(key)) => map.get_mut(key)?,
+                (Variable::Array(array), PathSegment::Index(index)) => array.get_mut(*index)?,
+                _ => return None,
+            };
+            current = Rcvar::make_mut(child);
+        }
+        Some(current)
+    }
+
+    /// Returns a mutable reference to the descendant of a shared value at
+    /// `path`, cloning `root` first if it is shared.
+    ///
+    /// See `Variable::get_mut_path`.
+    pub fn make_mut_at<'a>(root: &'a mut Rcvar, path: &[PathSegment]) -> Option<&'a mut Variable> {
+        Rcvar::make_mut(root).get_mut_path(path)
+    }
 }
 
 impl Variable {
@@ -442,15 +1204,52 @@ impl Variable {
             Variable::Array(_) => de::Unexpected::Seq,
             Variable::Object(_) => de::Unexpected::Map,
             Variable::Expref(_) => de::Unexpected::Other("expression"),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(_) => de::Unexpected::Other("datetime"),
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(b) => de::Unexpected::Bytes(b),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(_) => de::Unexpected::Other("decimal"),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(_) => de::Unexpected::Other("big integer"),
+            Variable::External(_) => de::Unexpected::Other("external value"),
         }
     }
 }
 
+/// Returns the value of the smallest key that is equal to `name` ignoring
+/// case.
+pub(crate) fn find_ignore_case<'a, K, V, I>(entries: I, name: &str) -> Option<&'a V>
+where
+    K: AsRef<str> + 'a,
+    V: 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    let name = name.to_lowercase();
+    entries
+        .into_iter()
+        .filter(|(key, _)| key.as_ref().to_lowercase() == name)
+        .min_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()))
+        .map(|(_, value)| value)
+}
+
 // ------------------------------------------
 // Variable slicing implementation
 // ------------------------------------------
 
-fn slice(array: &[Rcvar], start: Option<i32>, stop: Option<i32>, step: i32) -> Vec<Rcvar> {
+/// Slices the characters of a string, using the same start, stop, and step
+/// semantics as arrays.
+pub(crate) fn slice_str(s: &str, start: Option<i32>, stop: Option<i32>, step: i32) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    slice(&chars, start, stop, step).into_iter().collect()
+}
+
+pub(crate) fn slice<T: Clone>(
+    array: &[T],
+    start: Option<i32>,
+    stop: Option<i32>,
+    step: i32,
+) -> Vec<T> {
     let mut result = vec![];
     let len = array.len() as i32;
     if len == 0 {
@@ -549,6 +1348,21 @@ impl<'de> de::Deserialize<'de> for Variable {
                 Ok(Variable::Number(value.into()))
             }
 
+            #[cfg(feature = "big-integers")]
+            #[inline]
+            fn visit_i128<E>(self, value: i128) -> Result<Variable, E> {
+                Ok(Variable::from_i128(value))
+            }
+
+            #[cfg(feature = "big-integers")]
+            #[inline]
+            fn visit_u128<E>(self, value: u128) -> Result<Variable, E> {
+                Ok(i128::try_from(value).map_or_else(
+                    |_| Number::from_f64(value as f64).map_or(Variable::Null, Variable::Number),
+                    Variable::from_i128,
+                ))
+            }
+
             #[inline]
             fn visit_f64<E>(self, value: f64) -> Result<Variable, E> {
                 Ok(Number::from_f64(value).map_or(Variable::Null, Variable::Number))
@@ -567,6 +1381,18 @@ impl<'de> de::Deserialize<'de> for Variable {
                 Ok(Variable::String(value))
             }
 
+            #[cfg(feature = "bytes")]
+            #[inline]
+            fn visit_bytes<E>(self, value: &[u8]) -> Result<Variable, E> {
+                Ok(Variable::Bytes(value.to_vec()))
+            }
+
+            #[cfg(feature = "bytes")]
+            #[inline]
+            fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Variable, E> {
+                Ok(Variable::Bytes(value))
+            }
+
             #[inline]
             fn visit_none<E>(self) -> Result<Variable, E> {
                 Ok(Variable::Null)
@@ -643,6 +1469,15 @@ impl<'de> de::Deserializer<'de> for Variable {
                 value: None,
             }),
             Variable::Expref(v) => visitor.visit_string(format!("<expression: {:?}>", v)),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(v) => visitor.visit_string(v.to_rfc3339()),
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(v) => visitor.visit_byte_buf(v),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(v) => visitor.visit_string(v.to_string()),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(v) => visitor.visit_i128(v),
+            Variable::External(v) => v.to_json().deserialize_any(visitor),
         }
     }
 
@@ -720,6 +1555,11 @@ impl<'de> de::Deserializer<'de> for Variable {
         unit seq bytes byte_buf map unit_struct tuple_struct struct
         identifier tuple ignored_any
     }
+
+    #[cfg(feature = "big-integers")]
+    forward_to_deserialize_any! {
+        i128 u128
+    }
 }
 
 struct VariantDeserializer {
@@ -924,12 +1764,282 @@ impl<'de> de::Deserializer<'de> for MapDeserializer {
     }
 }
 
-// Serde Variable serialization
-impl ser::Serialize for Variable {
+/// Deserializes borrowed Variables without cloning them.
+///
+/// Strings are borrowed when the visitor supports it. Expression
+/// references cannot be deserialized and return an error.
+///
+/// ```
+/// use jmespath::Variable;
+/// use serde::Deserialize;
+///
+/// let value = Variable::from_json(r#"{"a": ["x", "y"]}"#).unwrap();
+/// let a = value.get_field("a");
+/// let a: Vec<&str> = Deserialize::deserialize(&*a).unwrap();
+/// assert_eq!(vec!["x", "y"], a);
+/// ```
+impl<'de> de::Deserializer<'de> for &'de Variable {
+    type Error = Error;
+
     #[inline]
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
     where
-        S: ser::Serializer,
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Variable::Null => visitor.visit_unit(),
+            Variable::Bool(v) => visitor.visit_bool(*v),
+            Variable::Number(v) => v.clone().deserialize_any(visitor),
+            Variable::String(v) => visitor.visit_borrowed_str(v),
+            Variable::Array(v) => visitor.visit_seq(SeqRefDeserializer { iter: v.iter() }),
+            Variable::Object(v) => visitor.visit_map(MapRefDeserializer {
+                iter: v.iter(),
+                value: None,
+            }),
+            Variable::Expref(_) => Err(de::Error::custom(
+                "expression references cannot be deserialized",
+            )),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(v) => visitor.visit_string(v.to_rfc3339()),
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(v) => visitor.visit_bytes(v),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(v) => visitor.visit_string(v.to_string()),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(v) => visitor.visit_i128(*v),
+            Variable::External(v) => v.to_json().deserialize_any(visitor),
+        }
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Variable::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        _name: &str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let (variant, value) = match self {
+            Variable::Object(value) => {
+                // enums are encoded in json as maps with a single key:value pair
+                let mut iter = value.iter();
+                match (iter.next(), iter.next()) {
+                    (Some((variant, value)), None) => (variant.as_str(), Some(&**value)),
+                    _ => {
+                        return Err(de::Error::invalid_value(
+                            de::Unexpected::Map,
+                            &"map with a single key",
+                        ))
+                    }
+                }
+            }
+            Variable::String(variant) => (variant.as_str(), None),
+            other => {
+                return Err(de::Error::invalid_type(
+                    other.unexpected(),
+                    &"string or map",
+                ));
+            }
+        };
+
+        visitor.visit_enum(EnumRefDeserializer {
+            val: value,
+            variant,
+        })
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string
+        unit seq bytes byte_buf map unit_struct tuple_struct struct
+        identifier tuple ignored_any
+    }
+
+    #[cfg(feature = "big-integers")]
+    forward_to_deserialize_any! {
+        i128 u128
+    }
+}
+
+struct VariantRefDeserializer<'de> {
+    val: Option<&'de Variable>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantRefDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.val {
+            Some(value) => de::Deserialize::deserialize(value),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.val {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.val {
+            Some(Variable::Array(fields)) => visitor.visit_seq(SeqRefDeserializer {
+                iter: fields.iter(),
+            }),
+            Some(other) => Err(de::Error::invalid_type(
+                other.unexpected(),
+                &"tuple variant",
+            )),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.val {
+            Some(Variable::Object(fields)) => visitor.visit_map(MapRefDeserializer {
+                iter: fields.iter(),
+                value: None,
+            }),
+            Some(other) => Err(de::Error::invalid_type(
+                other.unexpected(),
+                &"struct variant",
+            )),
+            _ => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+struct EnumRefDeserializer<'de> {
+    variant: &'de str,
+    val: Option<&'de Variable>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumRefDeserializer<'de> {
+    type Error = Error;
+    type Variant = VariantRefDeserializer<'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantRefDeserializer<'de>), Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = de::value::BorrowedStrDeserializer::new(self.variant);
+        let visitor = VariantRefDeserializer { val: self.val };
+        seed.deserialize(variant).map(|v| (v, visitor))
+    }
+}
+
+struct SeqRefDeserializer<'de> {
+    iter: std::slice::Iter<'de, Rcvar>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqRefDeserializer<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(&**value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapRefDeserializer<'de> {
+    iter: std::collections::btree_map::Iter<'de, String, Rcvar>,
+    value: Option<&'de Variable>,
+}
+
+impl<'de> de::MapAccess<'de> for MapRefDeserializer<'de> {
+    type Error = Error;
+
+    fn next_key_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(de::value::BorrowedStrDeserializer::new(key))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// Serde Variable serialization
+impl ser::Serialize for Variable {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
     {
         match self {
             Variable::Null => serializer.serialize_unit(),
@@ -939,6 +2049,20 @@ impl ser::Serialize for Variable {
             Variable::Array(ref v) => v.serialize(serializer),
             Variable::Object(ref v) => v.serialize(serializer),
             Variable::Expref(ref e) => serializer.serialize_str(&format!("<expression: {:?}>", e)),
+            #[cfg(feature = "datetime")]
+            Variable::DateTime(ref d) => serializer.serialize_str(&d.to_rfc3339()),
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) if serializer.is_human_readable() => {
+                use base64::Engine;
+                serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(b))
+            }
+            #[cfg(feature = "bytes")]
+            Variable::Bytes(ref b) => serializer.serialize_bytes(b),
+            #[cfg(feature = "decimal")]
+            Variable::Decimal(ref d) => serializer.serialize_str(&d.to_string()),
+            #[cfg(feature = "big-integers")]
+            Variable::BigInt(i) => serializer.serialize_i128(*i),
+            Variable::External(ref e) => e.to_json().serialize(serializer),
         }
     }
 }
@@ -1029,6 +2153,21 @@ impl ser::Serializer for Serializer {
         self.serialize_f64(value as f64)
     }
 
+    #[cfg(feature = "big-integers")]
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<Variable, Error> {
+        Ok(Variable::from_i128(value))
+    }
+
+    #[cfg(feature = "big-integers")]
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<Variable, Error> {
+        match i128::try_from(value) {
+            Ok(i) => Ok(Variable::from_i128(i)),
+            Err(_) => self.serialize_f64(value as f64),
+        }
+    }
+
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<Variable, Error> {
         Ok(Number::from_f64(value).map_or(Variable::Null, Variable::Number))
@@ -1047,11 +2186,16 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Variable, Error> {
-        let vec = value
-            .iter()
-            .map(|&b| Rcvar::new(Variable::Number(Number::from(b))))
-            .collect();
-        Ok(Variable::Array(vec))
+        #[cfg(feature = "bytes")]
+        return Ok(Variable::Bytes(value.to_vec()));
+        #[cfg(not(feature = "bytes"))]
+        {
+            let vec = value
+                .iter()
+                .map(|&b| Rcvar::new(Variable::Number(Number::from(b))))
+                .collect();
+            Ok(Variable::Array(vec))
+        }
     }
 
     #[inline]
@@ -1075,18 +2219,14 @@ impl ser::Serializer for Serializer {
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        value: &T,
-    ) -> Result<Variable, Error>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Variable, Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -1094,10 +2234,10 @@ impl ser::Serializer for Serializer {
         value: &T,
     ) -> Result<Variable, Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         let mut values = BTreeMap::new();
-        values.insert(String::from(variant), Rcvar::new(to_variable(&value)?));
+        values.insert(String::from(variant), Rcvar::new(to_variable(value)?));
         Ok(Variable::Object(values))
     }
 
@@ -1107,9 +2247,9 @@ impl ser::Serializer for Serializer {
     }
 
     #[inline]
-    fn serialize_some<V: ?Sized>(self, value: &V) -> Result<Variable, Error>
+    fn serialize_some<V>(self, value: &V) -> Result<Variable, Error>
     where
-        V: ser::Serialize,
+        V: ?Sized + ser::Serialize,
     {
         value.serialize(self)
     }
@@ -1168,9 +2308,9 @@ impl ser::SerializeSeq for SeqState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         self.0.push(Rcvar::new(to_variable(value)?));
         Ok(())
@@ -1185,9 +2325,9 @@ impl ser::SerializeTuple for SeqState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         ser::SerializeSeq::serialize_element(self, value)
     }
@@ -1201,9 +2341,9 @@ impl ser::SerializeTupleStruct for SeqState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         ser::SerializeSeq::serialize_element(self, value)
     }
@@ -1217,11 +2357,11 @@ impl ser::SerializeTupleVariant for TupleVariantState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
-        self.vec.push(Rcvar::new(to_variable(&value)?));
+        self.vec.push(Rcvar::new(to_variable(value)?));
         Ok(())
     }
 
@@ -1236,9 +2376,9 @@ impl ser::SerializeMap for MapState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         match to_variable(key)? {
             Variable::String(s) => self.next_key = Some(s),
@@ -1247,9 +2387,9 @@ impl ser::SerializeMap for MapState {
         Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         let key = self
             .next_key
@@ -1268,9 +2408,9 @@ impl ser::SerializeStruct for MapState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         ser::SerializeMap::serialize_key(self, key)?;
         ser::SerializeMap::serialize_value(self, value)
@@ -1285,9 +2425,9 @@ impl ser::SerializeStructVariant for StructVariantState {
     type Ok = Variable;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         self.map
             .insert(String::from(key), Rcvar::new(to_variable(value)?));
@@ -1309,6 +2449,255 @@ mod tests {
     use serde_json::{self, Number, Value};
     use std::collections::BTreeMap;
 
+    #[cfg(feature = "datetime")]
+    fn datetime(s: &str) -> Variable {
+        Variable::DateTime(chrono::DateTime::parse_from_rfc3339(s).unwrap())
+    }
+
+    #[cfg(feature = "datetime")]
+    #[test]
+    fn datetimes_compare_by_instant() {
+        let a = datetime("2020-01-01T00:00:00Z");
+        let b = datetime("2020-01-01T01:00:00+01:00");
+        let c = datetime("2021-06-15T12:30:00Z");
+        assert_eq!(JmespathType::DateTime, a.get_type());
+        assert_eq!("datetime", a.get_type().to_string());
+        assert!(a.is_truthy());
+        assert_eq!(a, b);
+        assert_ne!(a, Variable::String("2020-01-01T00:00:00+00:00".to_owned()));
+        assert_eq!(Some(true), a.compare(&Comparator::LessThan, &c));
+        assert_eq!(Some(false), c.compare(&Comparator::LessThanEqual, &a));
+        assert_eq!(None, a.compare(&Comparator::LessThan, &Variable::Null));
+    }
+
+    #[test]
+    fn make_mut_at_copies_only_the_path() {
+        let original =
+            Rcvar::new(Variable::from_json(r#"{"a": {"b": [1, 2]}, "c": {"d": 3}}"#).unwrap());
+        let mut modified = original.clone();
+        let path = ["a".into(), "b".into(), PathSegment::Index(1)];
+        *Variable::make_mut_at(&mut modified, &path).unwrap() = Variable::Null;
+        assert_eq!(r#"{"a":{"b":[1,2]},"c":{"d":3}}"#, original.to_string());
+        assert_eq!(r#"{"a":{"b":[1,null]},"c":{"d":3}}"#, modified.to_string());
+        // Siblings of the modified path are still shared.
+        let shared = |v: &Rcvar, key: &str| v.as_object().unwrap()[key].clone();
+        assert!(Rcvar::ptr_eq(
+            &shared(&original, "c"),
+            &shared(&modified, "c")
+        ));
+        assert!(!Rcvar::ptr_eq(
+            &shared(&original, "a"),
+            &shared(&modified, "a")
+        ));
+        assert!(Variable::make_mut_at(&mut modified, &["a".into(), "x".into()]).is_none());
+        assert!(Variable::make_mut_at(&mut modified, &["c".into(), 0.into()]).is_none());
+    }
+
+    #[test]
+    fn writes_json_lines() {
+        let lines = |json: &str| {
+            let mut out = Vec::new();
+            Variable::from_json(json)
+                .unwrap()
+                .write_json_lines(&mut out)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            "1\n[2,3]\n{\"a\":null}\n",
+            lines(r#"[1, [2, 3], {"a": null}]"#)
+        );
+        assert_eq!("", lines("[]"));
+        assert_eq!("{\"a\":1}\n", lines(r#"{"a": 1}"#));
+    }
+
+    #[cfg(feature = "big-integers")]
+    #[test]
+    fn big_integers_round_trip() {
+        let big = Variable::from_i128(-(1 << 100));
+        assert!(matches!(big, Variable::BigInt(_)));
+        assert!(matches!(Variable::from_i128(1 << 63), Variable::Number(_)));
+        assert_eq!(JmespathType::Number, big.get_type());
+        assert_eq!("-1267650600228229401496703205376", big.to_string());
+        assert_eq!(big, Variable::from_serializable(-(1i128 << 100)).unwrap());
+        let value: i128 = serde::Deserialize::deserialize(big.clone()).unwrap();
+        assert_eq!(-(1 << 100), value);
+        assert_eq!(
+            Some(true),
+            big.compare(&Comparator::LessThan, &Variable::from_i128(i64::MIN.into()))
+        );
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimals_compare_exactly() {
+        let d = |s: &str| Variable::Decimal(s.parse().unwrap());
+        let n = |s: &str| Variable::from_json(s).unwrap();
+        assert_eq!(JmespathType::Number, d("1.5").get_type());
+        assert!(d("0").is_truthy());
+        assert_eq!(Some(1.5), d("1.5").as_number());
+        assert_eq!(d("0.30"), d("0.3"));
+        assert_eq!(d("0.3"), n("0.3"));
+        assert_ne!(d("0.3"), n("0.30000000000000004"));
+        assert_eq!(n("0.3"), n("0.30000000000000004"));
+        assert_eq!(
+            Some(true),
+            d("10.01").compare(&Comparator::GreaterThan, &n("10"))
+        );
+        assert_eq!(
+            Some(false),
+            n("10").compare(&Comparator::GreaterThanEqual, &d("10.01"))
+        );
+        assert_eq!("\"10.01\"", d("10.01").to_string());
+    }
+
+    #[cfg(feature = "datetime")]
+    #[test]
+    fn datetimes_serialize_as_rfc3339_strings() {
+        use crate::functions::{ArgumentType, CustomFunction, Signature};
+        use crate::Runtime;
+
+        let d = datetime("2020-01-01T01:00:00+01:00");
+        assert_eq!("\"2020-01-01T01:00:00+01:00\"", d.to_string());
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        runtime.register_function(
+            "dt",
+            Box::new(CustomFunction::new(
+                Signature::new(vec![ArgumentType::String], None),
+                Box::new(|args, _| Ok(Rcvar::new(datetime(args[0].as_string().unwrap())))),
+            )),
+        );
+        let eval = |expr: &str| {
+            runtime
+                .compile(expr)
+                .unwrap()
+                .search(())
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            "true",
+            eval("dt('2020-01-01T01:00:00+01:00') < dt('2020-01-02T00:00:00Z')")
+        );
+        assert_eq!("\"datetime\"", eval("type(dt('2020-01-01T00:00:00Z'))"));
+        assert_eq!(
+            "\"2020-01-01T01:00:00+01:00\"",
+            eval("to_string(dt('2020-01-01T01:00:00+01:00'))")
+        );
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_serialize_as_base64_in_json() {
+        let b = Variable::Bytes(vec![0, 159, 146, 150]);
+        assert_eq!(JmespathType::Bytes, b.get_type());
+        assert_eq!("\"AJ+Slg==\"", b.to_string());
+        assert!(b.is_truthy());
+        assert!(!Variable::Bytes(vec![]).is_truthy());
+        assert_eq!(b, Variable::Bytes(vec![0, 159, 146, 150]));
+        assert!(Variable::Bytes(vec![1]) < Variable::Bytes(vec![1, 0]));
+        #[cfg(feature = "msgpack")]
+        assert_eq!(b, Variable::from_msgpack(&b.to_msgpack().unwrap()).unwrap());
+        #[cfg(feature = "cbor")]
+        assert_eq!(b, Variable::from_cbor(&b.to_cbor().unwrap()).unwrap());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Port(u16);
+
+    impl JmesExternal for Port {
+        fn type_name(&self) -> &'static str {
+            "port"
+        }
+
+        fn eq_external(&self, other: &dyn JmesExternal) -> bool {
+            other.as_any().downcast_ref::<Port>() == Some(self)
+        }
+
+        fn is_truthy(&self) -> bool {
+            self.0 != 0
+        }
+
+        fn to_json(&self) -> Value {
+            Value::from(self.0)
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+    }
+
+    #[test]
+    fn external_values_use_trait_behavior() {
+        let port = Variable::external(Port(80));
+        assert_eq!(JmespathType::External("port"), port.get_type());
+        assert_eq!(port, Variable::external(Port(80)));
+        assert_ne!(port, Variable::external(Port(81)));
+        assert_ne!(port, Variable::Number(Number::from(80)));
+        assert!(port.is_truthy());
+        assert!(!Variable::external(Port(0)).is_truthy());
+        assert_eq!("80", port.to_string());
+        assert_eq!(
+            80u16,
+            serde_json::from_str::<u16>(&port.to_string()).unwrap()
+        );
+        let value: u16 = de::Deserialize::deserialize(port).unwrap();
+        assert_eq!(80, value);
+    }
+
+    #[test]
+    fn external_values_flow_through_functions() {
+        use crate::functions::{ArgumentType, CustomFunction, Signature};
+        use crate::{ErrorReason, Runtime, RuntimeError};
+
+        let mut runtime = Runtime::new();
+        runtime.register_builtin_functions();
+        runtime.register_function(
+            "port",
+            Box::new(CustomFunction::new(
+                Signature::new(vec![ArgumentType::Number], None),
+                Box::new(|args, _| {
+                    let port = Port(args[0].as_number().unwrap() as u16);
+                    Ok(Rcvar::new(Variable::external(port)))
+                }),
+            )),
+        );
+        runtime.register_function(
+            "port_number",
+            Box::new(CustomFunction::new(
+                Signature::new(vec![ArgumentType::External("port")], None),
+                Box::new(|args, _| {
+                    let port = args[0].as_external().unwrap();
+                    let port = port.as_any().downcast_ref::<Port>().unwrap();
+                    Ok(Rcvar::new(Variable::Number(Number::from(port.0))))
+                }),
+            )),
+        );
+        let eval = |expr: &str| runtime.compile(expr).unwrap().search(());
+        assert_eq!("\"port\"", eval("type(port(`80`))").unwrap().to_string());
+        assert_eq!(
+            "true",
+            eval("port(`80`) == port(`80`)").unwrap().to_string()
+        );
+        assert_eq!("443", eval("port_number(port(`443`))").unwrap().to_string());
+        assert_eq!(
+            "[80]",
+            eval("`1` | [port(`80`), port(`0`)][?@]")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            ErrorReason::Runtime(RuntimeError::InvalidType {
+                function: Some("port_number".to_owned()),
+                expected: ArgumentType::External("port"),
+                actual: JmespathType::Number,
+                position: 0,
+            }),
+            eval("port_number(`1`)").unwrap_err().reason
+        );
+    }
+
     #[test]
     fn creates_variable_from_str() {
         assert_eq!(Ok(Variable::Bool(true)), Variable::from_json("true"));
@@ -1322,13 +2711,13 @@ mod tests {
     fn test_determines_types() {
         assert_eq!(
             JmespathType::Object,
-            Variable::from_json(&"{\"foo\": \"bar\"}")
+            Variable::from_json("{\"foo\": \"bar\"}")
                 .unwrap()
                 .get_type()
         );
         assert_eq!(
             JmespathType::Array,
-            Variable::from_json(&"[\"foo\"]").unwrap().get_type()
+            Variable::from_json("[\"foo\"]").unwrap().get_type()
         );
         assert_eq!(JmespathType::Null, Variable::Null.get_type());
         assert_eq!(JmespathType::Boolean, Variable::Bool(true).get_type());
@@ -1344,28 +2733,56 @@ mod tests {
 
     #[test]
     fn test_is_truthy() {
+        assert!(Variable::from_json("{\"foo\": \"bar\"}")
+            .unwrap()
+            .is_truthy());
+        assert!(!Variable::from_json("{}").unwrap().is_truthy());
+        assert!(Variable::from_json("[\"foo\"]").unwrap().is_truthy());
+        assert!(!Variable::from_json("[]").unwrap().is_truthy());
+        assert!(!Variable::Null.is_truthy());
+        assert!(Variable::Bool(true).is_truthy());
+        assert!(!Variable::Bool(false).is_truthy());
+        assert!(Variable::String("foo".to_string()).is_truthy());
+        assert!(!Variable::String("".to_string()).is_truthy());
+        assert!(Variable::Number(Number::from_f64(10.0).unwrap()).is_truthy());
+        assert!(Variable::Number(Number::from_f64(0.0).unwrap()).is_truthy());
+    }
+
+    #[test]
+    fn deserializes_borrowed_variables() {
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        enum Shape {
+            Point,
+            Circle(u32),
+            Rect { w: u32, h: u32 },
+        }
+
+        #[derive(Debug, PartialEq, serde_derive::Deserialize)]
+        struct Doc<'a> {
+            name: &'a str,
+            tags: Vec<String>,
+            shapes: Vec<Shape>,
+            missing: Option<bool>,
+        }
+
+        let value = Variable::from_json(
+            r#"{"name": "n", "tags": [], "missing": null,
+                "shapes": ["Point", {"Circle": 2}, {"Rect": {"w": 1, "h": 3}}]}"#,
+        )
+        .unwrap();
         assert_eq!(
-            true,
-            Variable::from_json(&"{\"foo\": \"bar\"}")
-                .unwrap()
-                .is_truthy()
-        );
-        assert_eq!(false, Variable::from_json(&"{}").unwrap().is_truthy());
-        assert_eq!(true, Variable::from_json(&"[\"foo\"]").unwrap().is_truthy());
-        assert_eq!(false, Variable::from_json(&"[]").unwrap().is_truthy());
-        assert_eq!(false, Variable::Null.is_truthy());
-        assert_eq!(true, Variable::Bool(true).is_truthy());
-        assert_eq!(false, Variable::Bool(false).is_truthy());
-        assert_eq!(true, Variable::String("foo".to_string()).is_truthy());
-        assert_eq!(false, Variable::String("".to_string()).is_truthy());
-        assert_eq!(
-            true,
-            Variable::Number(Number::from_f64(10.0).unwrap()).is_truthy()
-        );
-        assert_eq!(
-            true,
-            Variable::Number(Number::from_f64(0.0).unwrap()).is_truthy()
+            Doc {
+                name: "n",
+                tags: vec![],
+                shapes: vec![Shape::Point, Shape::Circle(2), Shape::Rect { w: 1, h: 3 }],
+                missing: None,
+            },
+            Doc::deserialize(&value).unwrap()
         );
+        let expref = Variable::Expref(Ast::Identity { offset: 0 });
+        assert!(String::deserialize(&expref).is_err());
     }
 
     #[test]
@@ -1411,8 +2828,8 @@ mod tests {
 
     #[test]
     fn determines_if_null() {
-        assert_eq!(false, Variable::Bool(true).is_null());
-        assert_eq!(true, Variable::Null.is_null());
+        assert!(!Variable::Bool(true).is_null());
+        assert!(Variable::Null.is_null());
     }
 
     #[test]
@@ -1423,8 +2840,8 @@ mod tests {
 
     #[test]
     fn determines_if_boolean() {
-        assert_eq!(true, Variable::Bool(true).is_boolean());
-        assert_eq!(false, Variable::Null.is_boolean());
+        assert!(Variable::Bool(true).is_boolean());
+        assert!(!Variable::Null.is_boolean());
     }
 
     #[test]
@@ -1435,8 +2852,8 @@ mod tests {
 
     #[test]
     fn determines_if_string() {
-        assert_eq!(false, Variable::Bool(true).is_string());
-        assert_eq!(true, Variable::String("foo".to_string()).is_string());
+        assert!(!Variable::Bool(true).is_string());
+        assert!(Variable::String("foo".to_string()).is_string());
     }
 
     #[test]
@@ -1488,10 +2905,7 @@ mod tests {
 
     #[test]
     fn test_is_expref() {
-        assert_eq!(
-            true,
-            Variable::Expref(Ast::Identity { offset: 0 }).is_expref()
-        );
+        assert!(Variable::Expref(Ast::Identity { offset: 0 }).is_expref());
         assert_eq!(
             &Ast::Identity { offset: 0 },
             Variable::Expref(Ast::Identity { offset: 0 })
@@ -1651,4 +3065,98 @@ mod tests {
 
         assert_eq!(r#"{"num":41.0}"#, json_string);
     }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn converts_yaml_values() {
+        let value = Variable::from_yaml("1: !Ref name\ntrue: [~, 2.5]\n").unwrap();
+        assert_eq!(r#"{"1":"name","true":[null,2.5]}"#, value.to_string());
+        assert_eq!(Variable::Null, Variable::from_yaml("").unwrap());
+        assert!(Variable::from_yaml("? [a]\n: b\n").is_err());
+        assert!(Variable::from_yaml("a: [").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn converts_toml_values() {
+        let value = Variable::from_toml(
+            "released = 2024-05-01T12:00:00Z\nversions = [1, 2.5]\n[[bin]]\nname = \"jp\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            r#"{"bin":[{"name":"jp"}],"released":"2024-05-01T12:00:00Z","versions":[1,2.5]}"#,
+            value.to_string()
+        );
+        assert!(Variable::from_toml("x = nan").is_err());
+        assert!(Variable::from_toml("x = ").is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn decodes_msgpack_values() {
+        // {"a": [1, -2, 1.5, nil, true]}
+        let bytes = [
+            0x81, 0xa1, b'a', 0x95, 0x01, 0xfe, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xc0, 0xc3,
+        ];
+        let value = Variable::from_msgpack(&bytes).unwrap();
+        assert_eq!(r#"{"a":[1,-2,1.5,null,true]}"#, value.to_string());
+        assert!(Variable::from_msgpack(&bytes[..4]).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn decodes_cbor_values() {
+        // {"a": [1, -2, 1.5 (half precision), null, true]}
+        let bytes = [
+            0xa1, 0x61, b'a', 0x85, 0x01, 0x21, 0xf9, 0x3e, 0x00, 0xf6, 0xf5,
+        ];
+        let value = Variable::from_cbor(&bytes).unwrap();
+        assert_eq!(r#"{"a":[1,-2,1.5,null,true]}"#, value.to_string());
+        assert!(Variable::from_cbor(&bytes[..4]).is_err());
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn converts_bson_values() {
+        use bson::{doc, oid::ObjectId, Binary, Bson, Document};
+
+        let id = ObjectId::parse_str("65f1c0ffee0000000000abcd").unwrap();
+        let document = doc! {
+            "_id": id,
+            "n": 1,
+            "big": 5_000_000_000i64,
+            "data": Binary { subtype: bson::spec::BinarySubtype::Generic, bytes: b"hi".to_vec() },
+            "re": bson::Regex { pattern: "^a".to_owned(), options: "i".to_owned() },
+            "tags": ["a", Bson::Null],
+        };
+        let value = Variable::try_from(&document).unwrap();
+        assert_eq!(
+            r#"{"_id":"65f1c0ffee0000000000abcd","big":5000000000,"data":"aGk=","n":1,"re":{"$regularExpression":{"options":"i","pattern":"^a"}},"tags":["a",null]}"#,
+            value.to_string()
+        );
+        let value =
+            Variable::from_json(r#"{"n": 1, "big": 5000000000, "f": 1.5, "a": [true]}"#).unwrap();
+        assert_eq!(
+            doc! { "a": [true], "big": 5_000_000_000i64, "f": 1.5, "n": 1 },
+            Document::try_from(&value).unwrap()
+        );
+        assert!(Document::try_from(&Variable::Bool(true)).is_err());
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn converts_bson_datetimes() {
+        let datetime = bson::DateTime::from_millis(1_700_000_000_000);
+        let value = Variable::try_from(&bson::Bson::DateTime(datetime)).unwrap();
+        #[cfg(feature = "datetime")]
+        {
+            assert_eq!(r#""2023-11-14T22:13:20+00:00""#, value.to_string());
+            assert_eq!(
+                bson::Bson::DateTime(datetime),
+                bson::Bson::try_from(&value).unwrap()
+            );
+        }
+        #[cfg(not(feature = "datetime"))]
+        assert_eq!(r#""2023-11-14T22:13:20Z""#, value.to_string());
+    }
 }
@@ -0,0 +1,76 @@
+//! `wasm-bindgen` bindings for running expressions in JavaScript.
+//!
+//! Documents and results are passed across the boundary as JSON strings,
+//! and errors are thrown as JavaScript `Error`s carrying the same message as
+//! `JmespathError`'s `Display` output. Requires the `wasm` feature.
+//!
+//! ```js
+//! import { Expression, search } from "jmespath";
+//!
+//! const expr = new Expression("people[?age > `20`].name");
+//! expr.search('{"people": [{"name": "a", "age": 30}]}'); // '["a"]'
+//! search("length(@)", "[1, 2]"); // "2"
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::{compile, Variable};
+
+/// A compiled expression that can search JSON documents.
+#[wasm_bindgen(js_name = Expression)]
+pub struct WasmExpression {
+    inner: crate::Expression<'static>,
+}
+
+#[wasm_bindgen(js_class = Expression)]
+impl WasmExpression {
+    /// Compiles an expression using the default runtime.
+    #[wasm_bindgen(constructor)]
+    pub fn new(expression: &str) -> Result<WasmExpression, JsError> {
+        compile(expression)
+            .map(|inner| WasmExpression { inner })
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Searches a JSON document and returns the result as JSON.
+    pub fn search(&self, json: &str) -> Result<String, JsError> {
+        search_json(&self.inner, json).map_err(|e| JsError::new(&e))
+    }
+
+    /// Returns the original expression string.
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.inner.as_str().to_owned()
+    }
+}
+
+/// Compiles `expression` and searches a JSON document with it, returning
+/// the result as JSON.
+#[wasm_bindgen]
+pub fn search(expression: &str, json: &str) -> Result<String, JsError> {
+    WasmExpression::new(expression)?.search(json)
+}
+
+fn search_json(expr: &crate::Expression<'_>, json: &str) -> Result<String, String> {
+    let data = Variable::from_json(json)?;
+    expr.search(data)
+        .map(|result| result.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn searches_json_strings() {
+        let expr = WasmExpression::new("a[?b > `1`].b").unwrap();
+        assert_eq!(
+            "[2]",
+            expr.search(r#"{"a": [{"b": 1}, {"b": 2}]}"#).unwrap()
+        );
+        assert_eq!("a[?b > `1`].b", expr.to_js_string());
+        assert_eq!("2", search("length(@)", "[1, 2]").unwrap());
+        assert!(search_json(&expr.inner, "{").is_err());
+    }
+}
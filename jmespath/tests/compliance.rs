@@ -4,7 +4,9 @@
 //! as a library (leading to possibilities like a compliance test runner CLI).
 
 use serde_json::Value;
+use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
 
 use jmespath::{compile, Expression, Rcvar, RuntimeError, Variable};
 
@@ -29,17 +31,17 @@ impl BenchType {
                 "parse" => Ok(BenchType::Parse),
                 "interpret" => Ok(BenchType::Interpret),
                 "full" => Ok(BenchType::Full),
-                s @ _ => Err(TestCaseError::UnknownBenchType(s.to_string())),
+                s => Err(TestCaseError::UnknownBenchType(s.to_string())),
             })
     }
 }
 
 impl fmt::Display for BenchType {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        match self {
-            &BenchType::Parse => write!(fmt, "parse"),
-            &BenchType::Interpret => write!(fmt, "interpret"),
-            &BenchType::Full => write!(fmt, "full"),
+        match *self {
+            BenchType::Parse => write!(fmt, "parse"),
+            BenchType::Interpret => write!(fmt, "interpret"),
+            BenchType::Full => write!(fmt, "full"),
         }
     }
 }
@@ -70,7 +72,7 @@ impl ErrorType {
                 "invalid-value" => Ok(ErrorType::InvalidSlice),
                 "invalid-arity" => Ok(ErrorType::InvalidArity),
                 "unknown-function" => Ok(ErrorType::UnknownFunction),
-                e @ _ => Err(TestCaseError::UnknownErrorType(e.to_string())),
+                e => Err(TestCaseError::UnknownErrorType(e.to_string())),
             })
     }
 }
@@ -78,12 +80,12 @@ impl ErrorType {
 impl fmt::Display for ErrorType {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         use self::ErrorType::*;
-        match self {
-            &InvalidArity => write!(fmt, "invalid-arity"),
-            &InvalidType => write!(fmt, "invalid-type"),
-            &InvalidSlice => write!(fmt, "invalid-value"),
-            &UnknownFunction => write!(fmt, "unknown-function"),
-            &SyntaxError => write!(fmt, "syntax"),
+        match *self {
+            InvalidArity => write!(fmt, "invalid-arity"),
+            InvalidType => write!(fmt, "invalid-type"),
+            InvalidSlice => write!(fmt, "invalid-value"),
+            UnknownFunction => write!(fmt, "unknown-function"),
+            SyntaxError => write!(fmt, "syntax"),
         }
     }
 }
@@ -103,52 +105,68 @@ impl Assertion {
     pub fn assert(&self, suite: &str, case: &TestCase, given: Rcvar) -> Result<(), String> {
         match self {
             &Assertion::Bench(_) => Ok(()),
-            &Assertion::ValidResult(ref expected_result) => {
+            Assertion::ValidResult(expected_result) => {
                 let expr = self.try_parse(suite, case)?;
-                match expr.search(given) {
+                // The bytecode VM must agree with the tree-walking interpreter.
+                let compiled = expr.clone().compile();
+                for expr in &[expr.clone(), compiled] {
+                    match expr.search(given.clone()) {
+                        Err(e) => return Err(self.err_message(suite, case, format!("{}", e))),
+                        Ok(r) => {
+                            if *r != **expected_result {
+                                return Err(self.err_message(
+                                    suite,
+                                    case,
+                                    format!("{:?}, {}", r, expr.as_ast()),
+                                ));
+                            }
+                        }
+                    }
+                }
+                // Searching the JSON directly must agree as well.
+                let json = serde_json::to_value(&*given).unwrap();
+                match expr.search_json(&json) {
                     Err(e) => Err(self.err_message(suite, case, format!("{}", e))),
                     Ok(r) => {
-                        if *r == **expected_result {
-                            Ok(())
+                        let r = Variable::try_from(&*r).unwrap();
+                        if r != **expected_result {
+                            Err(self.err_message(suite, case, format!("{:?} (search_json)", r)))
                         } else {
-                            Err(self.err_message(
-                                suite,
-                                case,
-                                format!("{:?}, {}", r, expr.as_ast()),
-                            ))
+                            Ok(())
                         }
                     }
                 }
             }
-            &Assertion::Error(ref error_type) => {
+            Assertion::Error(error_type) => {
                 use jmespath::ErrorReason::*;
                 let result = self.try_parse(suite, case);
-                match error_type {
-                    &ErrorType::InvalidArity => match result?.search(given).map_err(|e| e.reason) {
+                match *error_type {
+                    ErrorType::InvalidArity => match result?.search(given).map_err(|e| e.reason) {
                         Err(Runtime(RuntimeError::NotEnoughArguments { .. })) => Ok(()),
                         Err(Runtime(RuntimeError::TooManyArguments { .. })) => Ok(()),
                         Err(e) => Err(self.err_message(suite, case, format!("{}", e))),
                         Ok(r) => Err(self.err_message(suite, case, r.to_string())),
                     },
-                    &ErrorType::InvalidType => match result?.search(given).map_err(|e| e.reason) {
+                    ErrorType::InvalidType => match result?.search(given).map_err(|e| e.reason) {
                         Err(Runtime(RuntimeError::InvalidType { .. })) => Ok(()),
                         Err(Runtime(RuntimeError::InvalidReturnType { .. })) => Ok(()),
+                        Err(Runtime(RuntimeError::InvalidOperand { .. })) => Ok(()),
                         Err(e) => Err(self.err_message(suite, case, format!("{}", e))),
                         Ok(r) => Err(self.err_message(suite, case, r.to_string())),
                     },
-                    &ErrorType::InvalidSlice => match result?.search(given).map_err(|e| e.reason) {
+                    ErrorType::InvalidSlice => match result?.search(given).map_err(|e| e.reason) {
                         Err(Runtime(RuntimeError::InvalidSlice)) => Ok(()),
                         Err(e) => Err(self.err_message(suite, case, format!("{}", e))),
                         Ok(r) => Err(self.err_message(suite, case, r.to_string())),
                     },
-                    &ErrorType::UnknownFunction => {
+                    ErrorType::UnknownFunction => {
                         match result?.search(given).map_err(|e| e.reason) {
                             Err(Runtime(RuntimeError::UnknownFunction(_))) => Ok(()),
                             Err(e) => Err(self.err_message(suite, case, format!("{}", e))),
                             Ok(r) => Err(self.err_message(suite, case, r.to_string())),
                         }
                     }
-                    &ErrorType::SyntaxError => match result {
+                    ErrorType::SyntaxError => match result {
                         Err(_) => Ok(()),
                         Ok(expr) => {
                             Err(self.err_message(suite, case, format!("Parsed {:?}", expr)))
@@ -180,9 +198,9 @@ impl Assertion {
 impl fmt::Display for Assertion {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
-            &Assertion::Error(ref e) => write!(fmt, "expects error({})", e),
-            &Assertion::Bench(ref b) => write!(fmt, "expects bench({})", b),
-            &Assertion::ValidResult(ref r) => write!(fmt, "expects result({:?})", r),
+            Assertion::Error(e) => write!(fmt, "expects error({})", e),
+            Assertion::Bench(b) => write!(fmt, "expects bench({})", b),
+            Assertion::ValidResult(r) => write!(fmt, "expects result({:?})", r),
         }
     }
 }
@@ -223,9 +241,9 @@ impl<'a> TestSuite<'a> {
             .clone();
         let given = serde_json::from_value::<Variable>(value).map_err(|e| format!("{}", e))?;
         Ok(TestSuite {
-            filename: filename,
+            filename,
             given: Rcvar::new(given),
-            cases: cases,
+            cases,
         })
     }
 }
@@ -248,16 +266,16 @@ impl fmt::Display for TestCaseError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         use self::TestCaseError::*;
         match self {
-            &InvalidJSON(ref msg) => write!(fmt, "invalid test case JSON: {}", msg),
+            InvalidJSON(msg) => write!(fmt, "invalid test case JSON: {}", msg),
             &NoCaseType => write!(fmt, "case has no result, error, or bench"),
             &NoResult => write!(fmt, "test case has no result key"),
             &ResultCannotToString => write!(fmt, "result could not be cast to string"),
             &NoExpression => write!(fmt, "test case has no expression key"),
             &ExpressionIsNotString => write!(fmt, "test case expression is not a string"),
             &ErrorIsNotString => write!(fmt, "test case error value is not a string"),
-            &UnknownErrorType(ref t) => write!(fmt, "unknown error type: {}", t),
+            UnknownErrorType(t) => write!(fmt, "unknown error type: {}", t),
             &BenchIsNotString => write!(fmt, "bench value is not a string"),
-            &UnknownBenchType(ref bench) => write!(
+            UnknownBenchType(bench) => write!(
                 fmt,
                 "unknown bench value: {}, expected one of of parse|full",
                 bench
@@ -280,14 +298,18 @@ pub struct TestCase {
     pub assertion: Assertion,
 }
 
-impl TestCase {
+impl FromStr for TestCase {
+    type Err = TestCaseError;
+
     /// Creates a test case from a JSON encoded string.
-    pub fn from_str(case: &str) -> Result<TestCase, TestCaseError> {
+    fn from_str(case: &str) -> Result<TestCase, TestCaseError> {
         serde_json::from_str::<Value>(case)
             .map_err(|e| TestCaseError::InvalidJSON(e.to_string()))
             .and_then(|json| TestCase::from_json(&json))
     }
+}
 
+impl TestCase {
     /// Creates a test case from parsed JSON data.
     fn from_json(case: &Value) -> Result<TestCase, TestCaseError> {
         use crate::TestCaseError::*;